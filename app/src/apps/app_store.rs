@@ -1,5 +1,8 @@
 use crate::{
-  apps::{AppAction, MenuApp, MenuAppContext, MenuAppInput, common::AppName},
+  apps::{
+    AppAction, MenuApp, MenuAppContext, MenuAppInput,
+    common::{AppName, app_metadata_lines},
+  },
   platform::{HttpEventChannel, Platform},
   protocol::{HttpEvent, HttpRequest},
   types::*,
@@ -12,6 +15,7 @@ use alloc::{
 };
 use embassy_futures::join::join;
 use serde::{Deserialize, Serialize};
+use wasm_protocol::AppMetadata;
 
 pub struct AppStoreApp<P: Platform> {
   ctx: MenuAppContext<P>,
@@ -35,6 +39,29 @@ enum Screen {
 struct AppEntry {
   name: String,
   size: u32,
+  /// From the app's metadata section, for apps that declare one.
+  #[serde(default)]
+  app: Option<AppMetadata>,
+}
+
+impl AppEntry {
+  fn display_name(&self) -> &str {
+    match &self.app {
+      Some(app) if !app.name.is_empty() => &app.name,
+      _ => &self.name,
+    }
+  }
+
+  fn info_lines(&self) -> Vec<MenuLine> {
+    let mut lines = vec![
+      MenuLine(Icon20::Info, format!("Name: {}", self.name)),
+      MenuLine(Icon20::Info, format!("Size: {}", self.size)),
+    ];
+    if let Some(app) = &self.app {
+      lines.extend(app_metadata_lines(app));
+    }
+    lines
+  }
 }
 
 type AppList = Vec<AppEntry>;
@@ -167,7 +194,12 @@ impl<P: Platform> MenuApp for AppStoreApp<P> {
           .state
           .app_list
           .as_ref()
-          .map(|apps| apps.iter().map(|app| MenuLine(Icon20::File, app.name.clone())).collect())
+          .map(|apps| {
+            apps
+              .iter()
+              .map(|app| MenuLine(Icon20::File, app.display_name().to_string()))
+              .collect()
+          })
           .unwrap_or_default(),
         selected: self.state.cursor as u32,
         animation: if self.state.back_nav {
@@ -175,18 +207,19 @@ impl<P: Platform> MenuApp for AppStoreApp<P> {
         } else {
           MenuAnimation::FromRight
         },
+        header: None,
       },
       Screen::AppInfo => {
         if let Some(app) = self.state.current_app() {
+          let mut menu = app.info_lines();
+          let info_lines = menu.len() as u32;
+          menu.push(MenuLine(Icon20::Info, "Download".to_string()));
+          menu.push(MenuLine(Icon20::Info, "Back".to_string()));
           LcdScreen::Menu {
-            menu: vec![
-              MenuLine(Icon20::Info, format!("Name: {}", app.name)),
-              MenuLine(Icon20::Info, format!("Size: {}", app.size)),
-              MenuLine(Icon20::Info, "Download".to_string()),
-              MenuLine(Icon20::Info, "Back".to_string()),
-            ],
-            selected: self.state.cursor as u32 + 2,
+            menu,
+            selected: self.state.cursor as u32 + info_lines,
             animation: MenuAnimation::FromRight,
+            header: None,
           }
         } else {
           LcdScreen::Headline(Icon40::Error, "App not found".to_string())
@@ -13,11 +13,14 @@
 
 use crate::platform::{display::DisplayHandle, Platform};
 use crate::protocol::HostIpcSender;
-use crate::types::{DeviceEvent, HexButton, HexpansionEvent, Icon40};
+use crate::types::{DeviceEvent, HexButton, HexpansionEvent, Icon20, Icon40, MenuLine};
 use crate::utils::sleep;
+use alloc::format;
 use alloc::string::{String, ToString};
+use alloc::vec::Vec;
 use core::fmt;
 use display_types::LcdScreen;
+use wasm_protocol::{AppMetadata, Capabilities, HOST_ABI_VERSION};
 
 /// How long a `ctx.notify` toast stays up. Sourced from `display_types` (the
 /// single source of truth for the `LcdScreen::Notification` timing, also used
@@ -173,3 +176,37 @@ pub enum MenuAppInput {
   Button(HexButton),
   Stop,
 }
+
+/// Detail-screen lines describing a `.wsm` app from its metadata, shared by
+/// Files and the App Store.
+pub fn app_metadata_lines(app: &AppMetadata) -> Vec<MenuLine> {
+  let mut lines = Vec::new();
+  if !app.name.is_empty() {
+    let title = match app.version.as_str() {
+      "" => app.name.clone(),
+      version => format!("{} v{version}", app.name),
+    };
+    lines.push(MenuLine(Icon20::File, title));
+  }
+  if !app.author.is_empty() {
+    lines.push(MenuLine(Icon20::Info, format!("By {}", app.author)));
+  }
+  if !app.description.is_empty() {
+    lines.push(MenuLine(Icon20::Info, app.description.clone()));
+  }
+  let abi = if app.abi_version > HOST_ABI_VERSION {
+    format!("Needs firmware ABI v{}+", app.abi_version)
+  } else {
+    format!("ABI v{}", app.abi_version)
+  };
+  lines.push(MenuLine(Icon20::Config, abi));
+  let permissions: Vec<&str> = Capabilities::NAMED
+    .iter()
+    .filter(|(capability, _)| app.permissions().contains(*capability))
+    .map(|(_, name)| *name)
+    .collect();
+  if !permissions.is_empty() {
+    lines.push(MenuLine(Icon20::Config, format!("Uses {}", permissions.join(", "))));
+  }
+  lines
+}
@@ -132,6 +132,7 @@ impl<P: Platform> MenuApp for ConfigApp<P> {
       ],
       selected: self.state.cursor as u32,
       animation: MenuAnimation::FromRight,
+      header: None,
     }
   }
 
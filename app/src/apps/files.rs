@@ -1,11 +1,15 @@
 use crate::{
-  apps::{AppAction, MenuApp, MenuAppContext, MenuAppInput, common::AppName},
+  apps::{
+    AppAction, MenuApp, MenuAppContext, MenuAppInput,
+    common::{AppName, app_metadata_lines},
+  },
   platform::Platform,
   types::*,
 };
 use alloc::{format, string::ToString, vec, vec::Vec};
 use embedded_tools::local_fs::DirEntry;
 use log::{info, warn};
+use wasm_protocol::AppMetadata;
 
 pub struct FilesApp<P: Platform> {
   ctx: MenuAppContext<P>,
@@ -23,11 +27,18 @@ enum Screen {
   FileDetail,
 }
 
+#[derive(Clone)]
+struct FileItem {
+  entry: DirEntry,
+  /// What a `.wsm` app says about itself, if it says anything.
+  app: Option<AppMetadata>,
+}
+
 struct AppState {
   screen: Screen,
-  files: Vec<DirEntry>,
+  files: Vec<FileItem>,
   cursor: usize,
-  selected_file: Option<DirEntry>,
+  selected_file: Option<FileItem>,
   /// True after navigating back from FileDetail to FileList, so the list
   /// slides in from the left (back direction) instead of from the right.
   back_nav: bool,
@@ -57,9 +68,19 @@ impl AppState {
   fn is_wasm(name: &str) -> bool {
     name.ends_with(".wsm") || name.ends_with(".wasm")
   }
-  fn detail_action_count(file: &DirEntry) -> usize {
+  fn detail_info(file: &FileItem) -> Vec<MenuLine> {
+    let mut lines = vec![
+      MenuLine(Icon20::Info, format!("Name: {}", file.entry.name)),
+      MenuLine(Icon20::Info, format!("Size: {}B", file.entry.size)),
+    ];
+    if let Some(app) = &file.app {
+      lines.extend(app_metadata_lines(app));
+    }
+    lines
+  }
+  fn detail_action_count(file: &FileItem) -> usize {
     let mut count = 0usize;
-    if Self::is_wasm(&file.name) {
+    if Self::is_wasm(&file.entry.name) {
       count += 1;
     }
     count += 1;
@@ -77,7 +98,17 @@ impl<P: Platform> FilesApp<P> {
   }
 
   async fn refresh_files(&mut self) {
-    self.state.files = self.ctx.platform.storage_manager().list_files().await.unwrap_or_default();
+    let storage = self.ctx.platform.storage_manager();
+    let entries = storage.list_files().await.unwrap_or_default();
+    self.state.files.clear();
+    for entry in entries {
+      let app = if AppState::is_wasm(&entry.name) {
+        storage.read_app_metadata(&entry.name).await
+      } else {
+        None
+      };
+      self.state.files.push(FileItem { entry, app });
+    }
   }
 }
 
@@ -89,7 +120,13 @@ impl<P: Platform> MenuApp for FilesApp<P> {
           .state
           .files
           .iter()
-          .map(|f| MenuLine(Icon20::File, format!("{}  {}B", f.name, f.size)))
+          .map(|f| {
+            let name = match &f.app {
+              Some(app) if !app.name.is_empty() => &app.name,
+              _ => &f.entry.name,
+            };
+            MenuLine(Icon20::File, format!("{}  {}B", name, f.entry.size))
+          })
           .collect();
         menu.push(MenuLine(Icon20::Info, "<= Back".to_string()));
         let animation = if self.state.back_nav {
@@ -101,15 +138,13 @@ impl<P: Platform> MenuApp for FilesApp<P> {
           menu,
           selected: self.state.cursor as u32,
           animation,
+          header: None,
         }
       }
       Screen::FileDetail => {
         if let Some(file) = &self.state.selected_file {
-          let mut items = vec![
-            MenuLine(Icon20::Info, format!("Name: {}", file.name)),
-            MenuLine(Icon20::Info, format!("Size: {}B", file.size)),
-          ];
-          if AppState::is_wasm(&file.name) {
+          let mut items = AppState::detail_info(file);
+          if AppState::is_wasm(&file.entry.name) {
             items.push(MenuLine(Icon20::Config, "Execute".to_string()));
           }
           items.push(MenuLine(Icon20::Config, "Delete".to_string()));
@@ -118,6 +153,7 @@ impl<P: Platform> MenuApp for FilesApp<P> {
             menu: items,
             selected: self.state.cursor as u32,
             animation: MenuAnimation::FromRight,
+            header: None,
           }
         } else {
           LcdScreen::Headline(Icon40::Error, "File not found".to_string())
@@ -166,7 +202,7 @@ impl<P: Platform> FilesApp<P> {
       Some(f) => f.clone(),
       None => return AppAction::Stop,
     };
-    let info_lines = 2;
+    let info_lines = AppState::detail_info(&file).len();
     let max = AppState::detail_action_count(&file);
     match input {
       HexButton::Up => self.state.move_cursor_up(),
@@ -176,14 +212,14 @@ impl<P: Platform> FilesApp<P> {
           return AppAction::Continue;
         }
         let action_idx = self.state.cursor - info_lines;
-        let has_execute = AppState::is_wasm(&file.name);
+        let has_execute = AppState::is_wasm(&file.entry.name);
         if has_execute && action_idx == 0 {
-          info!("Executing WASM: {}", file.name);
-          return AppAction::LaunchWasm(file.name);
+          info!("Executing WASM: {}", file.entry.name);
+          return AppAction::LaunchWasm(file.entry.name);
         }
         let delete_idx = if has_execute { 1 } else { 0 };
         if action_idx == delete_idx {
-          match self.ctx.platform.storage_manager().delete(file.name).await {
+          match self.ctx.platform.storage_manager().delete(file.entry.name).await {
             Ok(()) => {
               self.state.screen = Screen::FileList;
               self.state.cursor = 0;
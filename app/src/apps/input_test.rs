@@ -98,6 +98,7 @@ fn render_state(pressed: u32) -> LcdScreen {
     menu: lines,
     selected: 0,
     animation: MenuAnimation::None,
+    header: None,
   }
 }
 
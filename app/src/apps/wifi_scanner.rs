@@ -102,6 +102,7 @@ impl<P: Platform> MenuApp for WifiScannerApp<P> {
           menu,
           selected: self.state.cursor as u32,
           animation: MenuAnimation::FromRight,
+          header: None,
         }
       }
     }
@@ -0,0 +1,38 @@
+use super::common::*;
+use crate::platform::CrashLogHandle;
+use alloc::format;
+use picoserve::{
+  ResponseSent,
+  io::Read,
+  request::Request,
+  response::{IntoResponse, ResponseWriter},
+  routing::RequestHandlerService,
+};
+
+/// Serves the last [`CrashReport`](crate::platform::CrashReport) as JSON, or
+/// `null` if no app has crashed since boot.
+pub struct GetCrashReportHandler {
+  crash_log: CrashLogHandle,
+}
+
+impl GetCrashReportHandler {
+  pub fn new(crash_log: CrashLogHandle) -> Self {
+    Self { crash_log }
+  }
+}
+
+impl RequestHandlerService<()> for GetCrashReportHandler {
+  async fn call_request_handler_service<R: Read, W: ResponseWriter<Error = R::Error>>(
+    &self,
+    (): &(),
+    (): (),
+    request: Request<'_, R>,
+    response_writer: W,
+  ) -> Result<ResponseSent, W::Error> {
+    let json = match serde_json::to_string(&self.crash_log.last()) {
+      Ok(json) => json,
+      Err(err) => return format_response!(request, response_writer, "Error writing JSON: {err:?}"),
+    };
+    json_response!(request, response_writer, json.as_str())
+  }
+}
@@ -9,9 +9,16 @@ use picoserve::{
   routing::RequestHandlerService,
 };
 use serde::Serialize;
+use wasm_protocol::AppMetadata;
 
 #[derive(Serialize)]
-struct FileEntry { pub name: String, pub size: u32 }
+struct FileEntry {
+  pub name: String,
+  pub size: u32,
+  /// Present for `.wsm` apps that declare metadata.
+  #[serde(skip_serializing_if = "Option::is_none")]
+  pub app: Option<AppMetadata>,
+}
 
 pub struct HandleFileList {
   storage: StorageHandle,
@@ -69,7 +76,8 @@ impl Chunks for FileListChunks {
     chunk_writer.write_chunk(b"[").await?;
 
     for (i, entry) in entries.iter().enumerate() {
-      let file_entry = FileEntry { name: entry.name.clone(), size: entry.size };
+      let app = self.storage.read_app_metadata(&entry.name).await;
+      let file_entry = FileEntry { name: entry.name.clone(), size: entry.size, app };
       let json = serde_json::to_string(&file_entry).unwrap();
       chunk_writer.write_chunk(json.as_bytes()).await?;
       if i < entries.len() - 1 {
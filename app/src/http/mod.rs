@@ -1,6 +1,7 @@
 #[macro_use]
 pub mod common;
 pub mod config;
+pub mod crash_report;
 pub mod delete_file;
 pub mod list_files;
 pub mod ota;
@@ -44,6 +45,10 @@ pub fn build_api_router<P: Platform + 'static>(
         .post_service(wifi_join::HandleWifiJoin::new(platform.config_manager(), platform.clone()))
         .options(async || cors_options_response()),
     )
+    .route(
+      "/crash",
+      get_service(crash_report::GetCrashReportHandler::new(platform.crash_log())).options(async || cors_options_response()),
+    )
     .route("/files", get_service(list_files::HandleFileList::new(storage.clone())))
     .route(
       "/file",
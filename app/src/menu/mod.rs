@@ -7,14 +7,20 @@ pub use types::*;
 use crate::{
   apps::*,
   menu::{menus::get_root_menu_options, state::*},
-  platform::Platform,
-  protocol::{HostIpcMessage, HostRuntimeCommand},
+  platform::{Platform, WallClockHandle},
+  protocol::{HostIpcMessage, HostIpcSender, HostRuntimeCommand},
   types::*,
+  utils::sleep,
 };
-use alloc::{string::ToString, vec::Vec};
-use embassy_futures::select::{Either, Either3, Either4, select, select3, select4};
+use alloc::{
+  collections::VecDeque,
+  format,
+  string::{String, ToString},
+  vec::Vec,
+};
+use embassy_futures::select::{Either, Either4, select, select4};
 use log::{debug, info};
-use wasm_protocol::HostIpcMessage as WireHostIpcMessage;
+use wasm_protocol::{HostIpcMessage as WireHostIpcMessage, KeyEvent, KeyModifiers};
 
 pub async fn menu_task<P: Platform + 'static>(mut runner_ctx: MenuRunnerContext<P>) {
   let mut stack: Vec<AppStackEntry<P>> = Vec::new();
@@ -75,6 +81,7 @@ async fn handle_root_menu<P: Platform>(
   stack_signal: &StackSignal,
 ) {
   let mut should_render = true;
+  let wall_clock = runner_ctx.platform.wall_clock();
 
   loop {
     let idx = stack.len() - 1;
@@ -96,6 +103,7 @@ async fn handle_root_menu<P: Platform>(
           // Returning to the root menu is a "back" transition: slide in from
           // the left (content moves rightward) to mirror the forward push.
           animation: MenuAnimation::FromLeft,
+          header: clock_header(&wall_clock),
         },
         _ => unreachable!(),
       };
@@ -103,13 +111,14 @@ async fn handle_root_menu<P: Platform>(
       should_render = false;
     }
 
-    let input = select3(
+    let input = select4(
       runner_ctx.platform.system_manager().next_button(),
       runner_ctx.platform.input_manager().next_button(),
       select(
         runner_ctx.platform.hexpansion_manager().next_event(),
         runner_ctx.platform.hexpansion_manager().next_device_event(),
       ),
+      sleep(ms_until_clock_tick(&wall_clock)),
     )
     .await;
 
@@ -118,11 +127,11 @@ async fn handle_root_menu<P: Platform>(
     let mut new_entry: Option<AppStackEntry<P>> = None;
 
     match input {
-      Either3::First(_system) => {
+      Either4::First(_system) => {
         debug!("handle_root_menu: boot button — return to main loop");
         return;
       }
-      Either3::Second(hex) => {
+      Either4::Second(hex) => {
         should_render = true;
         if let AppStackEntry::RootMenu { menu_options, selected } = &mut stack[idx] {
           match hex {
@@ -181,7 +190,7 @@ async fn handle_root_menu<P: Platform>(
           }
         }
       }
-      Either3::Third(inner) => match inner {
+      Either4::Third(inner) => match inner {
         Either::First(hx_event) => {
           debug!("handle_root_menu: hexpansion event {hx_event:?}");
           if let Some(event) = stack_signal.try_receive() {
@@ -211,6 +220,10 @@ async fn handle_root_menu<P: Platform>(
           }
         }
       },
+      Either4::Fourth(()) => {
+        // Redraw so the clock in the header moves on
+        should_render = true;
+      }
     }
 
     // Apply stack mutations (borrow on stack is released)
@@ -243,6 +256,10 @@ async fn handle_root_menu<P: Platform>(
 /// drain background channels (TCP, HTTP) without waiting for user input.
 pub const APP_TICK_MS: u64 = 50;
 
+/// Most buttons and key events held back for a hosted app that hasn't made
+/// room for them yet. Input beyond this is dropped.
+const PENDING_INPUT_MAX: usize = 32;
+
 /// A single event from the menu's multiplexed input sources, flattened into
 /// one enum so app handling doesn't need to reason about nested `Either`s.
 enum MenuEvent {
@@ -413,31 +430,45 @@ async fn handle_hosted_app<P: Platform>(
   display: &crate::platform::DisplayHandle,
   stack_signal: &StackSignal,
 ) {
+  let mut modifiers = KeyModifiers::NONE;
+  // Buttons and keys wait here, in order, for room in the app's message
+  // queue, which holds one message. A press and its release often arrive
+  // together, and dropping either would leave a button held or garble typed
+  // text, but the menu mustn't stop handling input while the app catches up.
+  let mut pending: VecDeque<WireHostIpcMessage> = VecDeque::new();
   loop {
-    let input = embassy_futures::select::select4(
-      runner_ctx.platform.system_manager().next_button(),
-      runner_ctx.platform.input_manager().next_button(),
-      stack_signal.receive(),
-      select(
-        runner_ctx.platform.hexpansion_manager().next_event(),
-        runner_ctx.platform.hexpansion_manager().next_device_event(),
+    let input = select(
+      send_pending_input(&runner_ctx.host_ipc_sender, &pending),
+      embassy_futures::select::select4(
+        runner_ctx.platform.system_manager().next_button(),
+        runner_ctx.platform.input_manager().next_button(),
+        stack_signal.receive(),
+        select(
+          runner_ctx.platform.hexpansion_manager().next_event(),
+          runner_ctx.platform.hexpansion_manager().next_device_event(),
+        ),
       ),
     )
     .await;
+    let input = match input {
+      Either::First(()) => {
+        pending.pop_front();
+        continue;
+      }
+      Either::Second(input) => input,
+    };
 
     match input {
       Either4::First(_system) => {
+        // Input queued for an app that is stopping no longer matters, and
+        // the Stop itself must not be dropped when the channel is full.
+        pending.clear();
         runner_ctx
           .host_ipc_sender
-          .try_send((0, HostIpcMessage::Runtime(HostRuntimeCommand::Stop)))
-          .ok();
-      }
-      Either4::Second(hex) => {
-        runner_ctx
-          .host_ipc_sender
-          .try_send((0, HostIpcMessage::Wire(WireHostIpcMessage::HexButton(hex))))
-          .ok();
+          .send((0, HostIpcMessage::Runtime(HostRuntimeCommand::Stop)))
+          .await;
       }
+      Either4::Second(hex) => queue_input(&mut pending, WireHostIpcMessage::HexButton(hex)),
       Either4::Third(event) => {
         debug!("hosted_app: stack event {event:?}");
         match event {
@@ -466,11 +497,18 @@ async fn handle_hosted_app<P: Platform>(
                 KeyEventType::Pressed => nav,
                 KeyEventType::Released => nav.released(),
               };
-              runner_ctx
-                .host_ipc_sender
-                .try_send((0, HostIpcMessage::Wire(WireHostIpcMessage::HexButton(button))))
-                .ok();
+              queue_input(&mut pending, WireHostIpcMessage::HexButton(button));
             }
+            // Every key is also sent as a key event, for text entry.
+            let pressed = ke.typ == KeyEventType::Pressed;
+            modifiers = modifiers.update(ke.code, pressed);
+            let key = KeyEvent {
+              port: ke.port,
+              code: ke.code,
+              pressed,
+              modifiers,
+            };
+            queue_input(&mut pending, WireHostIpcMessage::Key(key));
           }
         }
       },
@@ -478,6 +516,25 @@ async fn handle_hosted_app<P: Platform>(
   }
 }
 
+/// Send the oldest of `pending` to the hosted app once it has room. Never
+/// completes while nothing is pending.
+async fn send_pending_input(sender: &HostIpcSender, pending: &VecDeque<WireHostIpcMessage>) {
+  match pending.front() {
+    Some(message) => sender.send((0, HostIpcMessage::Wire(message.clone()))).await,
+    None => core::future::pending().await,
+  }
+}
+
+/// Hold `message` for the hosted app, unless [`PENDING_INPUT_MAX`] are
+/// already waiting because it has stopped reading them.
+fn queue_input(pending: &mut VecDeque<WireHostIpcMessage>, message: WireHostIpcMessage) {
+  if pending.len() >= PENDING_INPUT_MAX {
+    debug!("hosted_app: dropped {message:?}, {PENDING_INPUT_MAX} inputs already waiting");
+    return;
+  }
+  pending.push_back(message);
+}
+
 /// Map a key event's KeyCode to a HexButton for navigation.
 ///
 /// Arrows and Enter are intentionally absent: the platform already surfaces
@@ -490,3 +547,18 @@ fn device_key_to_nav(code: KeyCode) -> Option<HexButton> {
     _ => None,
   }
 }
+
+/// `HH:MM` in local time for the root menu header, once the clock is set.
+fn clock_header(wall_clock: &WallClockHandle) -> Option<String> {
+  let now = wall_clock.local_time()?;
+  Some(format!("{:02}:{:02}", now.hour, now.minute))
+}
+
+/// How long until the root menu's clock shows a different minute. Until the
+/// clock is set, check back every few seconds in case it got synchronized.
+fn ms_until_clock_tick(wall_clock: &WallClockHandle) -> u64 {
+  match wall_clock.unix_micros() {
+    Some(micros) => 60_000 - (micros / 1_000) % 60_000,
+    None => 5_000,
+  }
+}
@@ -0,0 +1,152 @@
+use alloc::sync::Arc;
+use core::{cell::Cell, fmt};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use embassy_time::Instant;
+
+#[derive(Clone, Copy, Default)]
+struct ClockState {
+  /// UNIX time in microseconds when [`Instant`] was zero, once set.
+  boot_time: Option<u64>,
+  utc_offset_minutes: i16,
+}
+
+/// The time of day, once the device has been told it. The desktop build sets
+/// it from the OS at startup; on the badge it stays unset until SNTP
+/// synchronizes it after WiFi connects.
+#[derive(Clone)]
+pub struct WallClockHandle {
+  state: Arc<Mutex<CriticalSectionRawMutex, Cell<ClockState>>>,
+}
+
+impl WallClockHandle {
+  pub fn new() -> Self {
+    Self {
+      state: Arc::new(Mutex::new(Cell::new(ClockState::default()))),
+    }
+  }
+
+  /// Set the clock, `unix_micros` being the current UNIX time in
+  /// microseconds.
+  pub fn set(&self, unix_micros: u64) {
+    let boot_time = unix_micros.saturating_sub(Instant::now().as_micros());
+    self.update(|state| state.boot_time = Some(boot_time));
+  }
+
+  /// The current UNIX time in microseconds, or `None` if the clock was never
+  /// set.
+  pub fn unix_micros(&self) -> Option<u64> {
+    let boot_time = self.state.lock(Cell::get).boot_time?;
+    Some(boot_time + Instant::now().as_micros())
+  }
+
+  /// Set the local timezone, in minutes east of UTC.
+  pub fn set_utc_offset(&self, minutes: i16) {
+    self.update(|state| state.utc_offset_minutes = minutes);
+  }
+
+  pub fn utc_offset_minutes(&self) -> i16 {
+    self.state.lock(Cell::get).utc_offset_minutes
+  }
+
+  /// The current local date and time, or `None` if the clock was never set.
+  pub fn local_time(&self) -> Option<DateTime> {
+    let seconds = (self.unix_micros()? / 1_000_000) as i64;
+    Some(DateTime::from_unix(seconds + self.utc_offset_minutes() as i64 * 60))
+  }
+
+  fn update(&self, f: impl FnOnce(&mut ClockState)) {
+    self.state.lock(|cell| {
+      let mut state = cell.get();
+      f(&mut state);
+      cell.set(state);
+    });
+  }
+}
+
+impl Default for WallClockHandle {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for WallClockHandle {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("WallClockHandle")
+      .field("unix_micros", &self.unix_micros())
+      .field("utc_offset_minutes", &self.utc_offset_minutes())
+      .finish()
+  }
+}
+
+/// A calendar date and time of day (proleptic Gregorian, no leap seconds).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct DateTime {
+  pub year: i32,
+  /// 1 to 12.
+  pub month: u8,
+  /// 1 to 31.
+  pub day: u8,
+  pub hour: u8,
+  pub minute: u8,
+  pub second: u8,
+  /// 0 is Monday, 6 is Sunday.
+  pub weekday: u8,
+}
+
+impl DateTime {
+  /// The date and time `seconds` after 1970-01-01 00:00:00.
+  pub fn from_unix(seconds: i64) -> Self {
+    let days = seconds.div_euclid(86_400);
+    let time = seconds.rem_euclid(86_400);
+
+    // Howard Hinnant's civil_from_days, with eras of 400 years starting on
+    // 0000-03-01 so the leap day falls at the end of each year.
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let mp = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * mp + 2) / 5 + 1;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+    Self {
+      year: year as i32,
+      month: month as u8,
+      day: day as u8,
+      hour: (time / 3_600) as u8,
+      minute: (time / 60 % 60) as u8,
+      second: (time % 60) as u8,
+      // 1970-01-01 was a Thursday.
+      weekday: (days + 3).rem_euclid(7) as u8,
+    }
+  }
+}
+
+/// `YYYY-MM-DD HH:MM:SS`
+impl fmt::Display for DateTime {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(
+      f,
+      "{:04}-{:02}-{:02} {:02}:{:02}:{:02}",
+      self.year, self.month, self.day, self.hour, self.minute, self.second
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::string::ToString;
+
+  #[test]
+  fn converts_unix_time_to_calendar_dates() {
+    assert_eq!(DateTime::from_unix(0).to_string(), "1970-01-01 00:00:00");
+    assert_eq!(DateTime::from_unix(0).weekday, 3);
+    assert_eq!(DateTime::from_unix(951_782_400).to_string(), "2000-02-29 00:00:00");
+    assert_eq!(DateTime::from_unix(1_700_000_000).to_string(), "2023-11-14 22:13:20");
+    assert_eq!(DateTime::from_unix(1_700_000_000).weekday, 1);
+    assert_eq!(DateTime::from_unix(-1).to_string(), "1969-12-31 23:59:59");
+  }
+}
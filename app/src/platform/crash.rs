@@ -0,0 +1,49 @@
+use alloc::{string::String, sync::Arc, vec::Vec};
+use core::{cell::RefCell, fmt};
+use embassy_sync::blocking_mutex::{Mutex, raw::CriticalSectionRawMutex};
+use serde::Serialize;
+
+/// Why the last app stopped abnormally, for the web app's crash report.
+#[derive(Debug, Clone, Serialize)]
+pub struct CrashReport {
+  /// The reason shown on the error screen (trap, limit or panic message).
+  pub reason: String,
+  /// The last lines the app wrote to stdout, oldest first.
+  pub stdout: Vec<String>,
+}
+
+/// Holds the most recent [`CrashReport`]. The WASM runner records into it on
+/// the app core; the API server reads it from the other.
+#[derive(Clone)]
+pub struct CrashLogHandle {
+  inner: Arc<Mutex<CriticalSectionRawMutex, RefCell<Option<CrashReport>>>>,
+}
+
+impl CrashLogHandle {
+  pub fn new() -> Self {
+    Self {
+      inner: Arc::new(Mutex::new(RefCell::new(None))),
+    }
+  }
+
+  /// Replace the stored report with `report`.
+  pub fn record(&self, report: CrashReport) {
+    self.inner.lock(|last| *last.borrow_mut() = Some(report));
+  }
+
+  pub fn last(&self) -> Option<CrashReport> {
+    self.inner.lock(|last| last.borrow().clone())
+  }
+}
+
+impl Default for CrashLogHandle {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for CrashLogHandle {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("CrashLogHandle").finish()
+  }
+}
@@ -5,7 +5,8 @@ use display_types::LcdScreen;
 #[derive(Debug, Clone)]
 pub enum DisplayError {
   SignalBusy,
-  /// The submitted raw frame was not `DISPLAY_WIDTH * DISPLAY_HEIGHT * 2` bytes.
+  /// The submitted raw frame was not `DISPLAY_WIDTH * DISPLAY_HEIGHT * 2` bytes,
+  /// or a partial update's region or buffer did not fit.
   InvalidFrame,
 }
 
@@ -15,6 +16,77 @@ pub const DISPLAY_HEIGHT: usize = 240;
 /// Byte length of one raw RGB565 frame.
 pub const FRAME_BYTES: usize = DISPLAY_WIDTH * DISPLAY_HEIGHT * 2;
 
+/// Widest row spacing accepted for a region's buffer: one screen row.
+pub const MAX_REGION_STRIDE: usize = DISPLAY_WIDTH * 2;
+
+/// A rectangle of the screen, in pixels, for partial frame updates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FrameRegion {
+  pub x: u16,
+  pub y: u16,
+  pub width: u16,
+  pub height: u16,
+}
+
+impl FrameRegion {
+  /// The whole screen.
+  pub const FULL: FrameRegion = FrameRegion {
+    x: 0,
+    y: 0,
+    width: DISPLAY_WIDTH as u16,
+    height: DISPLAY_HEIGHT as u16,
+  };
+
+  /// Whether the region is non-empty and lies within the screen.
+  pub fn is_valid(&self) -> bool {
+    self.width > 0
+      && self.height > 0
+      && self.x as usize + self.width as usize <= DISPLAY_WIDTH
+      && self.y as usize + self.height as usize <= DISPLAY_HEIGHT
+  }
+
+  /// Bytes in one row of the region.
+  pub fn row_bytes(&self) -> usize {
+    self.width as usize * 2
+  }
+
+  /// Length of a buffer holding the region with rows `stride` bytes apart,
+  /// from the first byte of the first row to the last byte of the last.
+  /// `None` for an empty region or if the length doesn't fit in a `usize`.
+  pub fn buffer_len(&self, stride: usize) -> Option<usize> {
+    stride
+      .checked_mul((self.height as usize).checked_sub(1)?)?
+      .checked_add(self.row_bytes())
+  }
+
+  /// The region's rows from a [`check`](Self::check)ed `buffer`, top to
+  /// bottom.
+  pub fn rows<'a>(&self, buffer: &'a [u8], stride: usize) -> impl Iterator<Item = &'a [u8]> {
+    let row_bytes = self.row_bytes();
+    buffer.chunks(stride).take(self.height as usize).map(move |row| &row[..row_bytes])
+  }
+
+  /// Copy the region from `buffer` (rows `stride` bytes apart) into its place
+  /// in `frame`, a whole RGB565 frame.
+  pub fn composite(&self, frame: &mut [u8], buffer: &[u8], stride: usize) {
+    for (row, pixels) in self.rows(buffer, stride).enumerate() {
+      let start = ((self.y as usize + row) * DISPLAY_WIDTH + self.x as usize) * 2;
+      frame[start..start + pixels.len()].copy_from_slice(pixels);
+    }
+  }
+
+  /// Check a guest-supplied region and buffer before they are used.
+  pub fn check(&self, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    if !self.is_valid() || !(self.row_bytes()..=MAX_REGION_STRIDE).contains(&stride) {
+      return Err(DisplayError::InvalidFrame);
+    }
+    match self.buffer_len(stride) {
+      Some(len) if buffer.len() >= len => Ok(()),
+      _ => Err(DisplayError::InvalidFrame),
+    }
+  }
+}
+
 pub trait DisplayManager: Send + Sync + fmt::Debug {
   fn signal(&self, screen: LcdScreen) -> Result<(), DisplayError>;
   fn try_signal(&self, screen: LcdScreen) -> Result<(), DisplayError>;
@@ -31,8 +103,18 @@ pub trait DisplayManager: Send + Sync + fmt::Debug {
   /// Non-blocking: implementations copy the frame and flush it to the physical
   /// display asynchronously (e.g. on a dedicated display task), so callers can
   /// immediately start rendering the next frame. Returns
-  /// [`DisplayError::InvalidFrame`] if `buffer` is not exactly `FRAME_BYTES` long.
+  /// [`DisplayError::InvalidFrame`] if `buffer` is not exactly `FRAME_BYTES` long,
+  /// and [`DisplayError::SignalBusy`] without waiting if the display is drawing
+  /// a screen of its own rather than parked at `LcdScreen::Blank`.
   fn signal_raw_frame(&self, buffer: &[u8]) -> Result<(), DisplayError>;
+
+  /// Update only `region` of the display from `buffer`, which holds the
+  /// region's rows `stride` bytes apart. The rest of the screen keeps what was
+  /// last drawn, and [`frame_buffer`](Self::frame_buffer) shows the update
+  /// composited over it. Returns [`DisplayError::InvalidFrame`] if the region
+  /// is off screen or `buffer` is too short, and [`DisplayError::SignalBusy`]
+  /// as for [`signal_raw_frame`](Self::signal_raw_frame).
+  fn signal_raw_region(&self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError>;
 }
 
 #[derive(Clone, Debug)]
@@ -60,4 +142,49 @@ impl DisplayHandle {
   pub fn signal_raw_frame(&self, buffer: &[u8]) -> Result<(), DisplayError> {
     self.inner.signal_raw_frame(buffer)
   }
+
+  pub fn signal_raw_region(&self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    self.inner.signal_raw_region(region, buffer, stride)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::vec;
+
+  #[test]
+  fn region_rows_are_composited_into_place() {
+    let region = FrameRegion {
+      x: 2,
+      y: 1,
+      width: 2,
+      height: 2,
+    };
+    // Rows 6 bytes apart: 4 bytes of pixels, then 2 bytes of padding.
+    let buffer = [1, 2, 3, 4, 0xEE, 0xEE, 5, 6, 7, 8];
+    region.check(&buffer, 6).unwrap();
+
+    let mut frame = vec![0u8; FRAME_BYTES];
+    region.composite(&mut frame, &buffer, 6);
+    let row1 = DISPLAY_WIDTH * 2;
+    assert_eq!(&frame[row1 + 4..row1 + 8], [1, 2, 3, 4]);
+    assert_eq!(&frame[2 * row1 + 4..2 * row1 + 8], [5, 6, 7, 8]);
+    assert_eq!(frame.iter().filter(|&&byte| byte != 0).count(), 8);
+  }
+
+  #[test]
+  fn regions_off_screen_or_short_of_data_are_rejected() {
+    let region = FrameRegion {
+      x: 230,
+      y: 0,
+      width: 20,
+      height: 1,
+    };
+    assert!(region.check(&[0; 40], 40).is_err());
+    assert!(FrameRegion::FULL.check(&[0; FRAME_BYTES - 1], DISPLAY_WIDTH * 2).is_err());
+    assert!(FrameRegion::FULL.check(&[0; FRAME_BYTES], DISPLAY_WIDTH * 2).is_ok());
+    assert!(FrameRegion::FULL.check(&[0; 2 * FRAME_BYTES], MAX_REGION_STRIDE + 2).is_err());
+    assert_eq!(FrameRegion::FULL.buffer_len(usize::MAX), None);
+  }
 }
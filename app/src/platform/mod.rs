@@ -1,16 +1,22 @@
+pub mod clock;
+pub mod crash;
 pub mod display;
 pub mod hexpansion;
 pub mod http;
 pub mod input;
 pub mod led;
 pub mod power;
+pub mod sntp;
 pub mod storage;
 pub mod system;
 pub mod tcp;
 pub mod traits;
+pub mod udp;
 pub mod wifi;
 
-pub use display::{DisplayError, DisplayHandle, DisplayManager};
+pub use clock::{DateTime, WallClockHandle};
+pub use crash::{CrashLogHandle, CrashReport};
+pub use display::{DisplayError, DisplayHandle, DisplayManager, FrameRegion};
 pub use hexpansion::{HexpansionHandle, HexpansionManager};
 pub use http::{HttpClient, HttpClientHandle, HttpEventChannel};
 pub use input::{InputHandle, InputManager};
@@ -20,4 +26,5 @@ pub use storage::{ConfigHandle, DirEntry, FileType, FsError, LocalFsTrait, State
 pub use system::{SystemHandle, SystemManager};
 pub use tcp::{TcpClient, TcpEvent, TcpEventChannel, TcpHandle};
 pub use traits::Platform;
+pub use udp::{UdpClient, UdpEvent, UdpEventChannel, UdpHandle};
 pub use wifi::{WiFiHandle, WiFiManager, WifiStatus};
@@ -0,0 +1,143 @@
+//! SNTP (RFC 4330) packets, shared by the firmware and desktop time sync.
+//!
+//! Only the packet format lives here; each platform sends the request over its
+//! own UDP stack and feeds the reply back through [`parse_response`].
+
+/// Standard NTP server port.
+pub const NTP_PORT: u16 = 123;
+
+/// Size of an NTP packet without extension fields or authenticator.
+pub const PACKET_LEN: usize = 48;
+
+/// Seconds between the NTP epoch (1900) and the UNIX epoch (1970).
+const NTP_UNIX_OFFSET: u64 = 2_208_988_800;
+
+/// Version 4, client mode.
+const CLIENT_HEADER: u8 = (4 << 3) | 3;
+
+const MODE_SERVER: u8 = 4;
+const LEAP_UNSYNCHRONIZED: u8 = 3;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SntpError {
+  /// Fewer than [`PACKET_LEN`] bytes.
+  Truncated,
+  /// Not a reply from a server.
+  NotServer,
+  /// The server doesn't know the time itself.
+  Unsynchronized,
+  /// The server asked us to go away (stratum 0).
+  KissOfDeath,
+  /// The reply doesn't answer our request.
+  Mismatched,
+}
+
+/// Split `server` into host and port; the port defaults to [`NTP_PORT`]. A
+/// port makes it easy to point the device at a local stand-in server.
+pub fn server_addr(server: &str) -> (&str, u16) {
+  match server.rsplit_once(':') {
+    Some((host, port)) => match port.parse() {
+      Ok(port) => (host, port),
+      Err(_) => (server, NTP_PORT),
+    },
+    None => (server, NTP_PORT),
+  }
+}
+
+/// A client request. `nonce` goes in the transmit timestamp; the server echoes
+/// it back, which is how [`parse_response`] recognises the answer.
+pub fn request(nonce: u64) -> [u8; PACKET_LEN] {
+  let mut packet = [0u8; PACKET_LEN];
+  packet[0] = CLIENT_HEADER;
+  packet[40..48].copy_from_slice(&nonce.to_be_bytes());
+  packet
+}
+
+/// The server's transmit time from a reply to `request(nonce)`, in UNIX
+/// microseconds.
+pub fn parse_response(packet: &[u8], nonce: u64) -> Result<u64, SntpError> {
+  if packet.len() < PACKET_LEN {
+    return Err(SntpError::Truncated);
+  }
+  if packet[0] & 0x07 != MODE_SERVER {
+    return Err(SntpError::NotServer);
+  }
+  if packet[0] >> 6 == LEAP_UNSYNCHRONIZED {
+    return Err(SntpError::Unsynchronized);
+  }
+  match packet[1] {
+    0 => return Err(SntpError::KissOfDeath),
+    1..=15 => {}
+    _ => return Err(SntpError::Unsynchronized),
+  }
+  if timestamp(packet, 24) != nonce {
+    return Err(SntpError::Mismatched);
+  }
+  to_unix_micros(timestamp(packet, 40)).ok_or(SntpError::Unsynchronized)
+}
+
+fn timestamp(packet: &[u8], offset: usize) -> u64 {
+  u64::from_be_bytes(packet[offset..offset + 8].try_into().unwrap())
+}
+
+/// Convert a 32.32 fixed-point NTP timestamp to UNIX microseconds. `None` for
+/// times before 1970.
+fn to_unix_micros(timestamp: u64) -> Option<u64> {
+  let seconds = (timestamp >> 32).checked_sub(NTP_UNIX_OFFSET)?;
+  let micros = ((timestamp & 0xFFFF_FFFF) * 1_000_000) >> 32;
+  Some(seconds * 1_000_000 + micros)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  /// What a server would send back for `request` at `unix_micros`.
+  fn reply(request: &[u8; PACKET_LEN], unix_micros: u64) -> [u8; PACKET_LEN] {
+    let seconds = unix_micros / 1_000_000 + NTP_UNIX_OFFSET;
+    let fraction = ((unix_micros % 1_000_000) << 32) / 1_000_000;
+    let mut packet = [0u8; PACKET_LEN];
+    packet[0] = (4 << 3) | MODE_SERVER;
+    packet[1] = 2;
+    packet[24..32].copy_from_slice(&request[40..48]);
+    packet[40..48].copy_from_slice(&((seconds << 32) | fraction).to_be_bytes());
+    packet
+  }
+
+  #[test]
+  fn parses_a_reply_to_our_request() {
+    let req = request(0x1234_5678_9ABC_DEF0);
+    assert_eq!(req[0], 0x23);
+
+    let now = 1_700_000_000_123_456;
+    let micros = parse_response(&reply(&req, now), 0x1234_5678_9ABC_DEF0).unwrap();
+    assert!(now.abs_diff(micros) <= 1, "{micros}");
+  }
+
+  #[test]
+  fn rejects_bad_replies() {
+    let req = request(7);
+    let good = reply(&req, 1_700_000_000_000_000);
+
+    assert_eq!(parse_response(&good[..40], 7), Err(SntpError::Truncated));
+    assert_eq!(parse_response(&good, 8), Err(SntpError::Mismatched));
+
+    let mut client = good;
+    client[0] = CLIENT_HEADER;
+    assert_eq!(parse_response(&client, 7), Err(SntpError::NotServer));
+
+    let mut kiss = good;
+    kiss[1] = 0;
+    assert_eq!(parse_response(&kiss, 7), Err(SntpError::KissOfDeath));
+
+    let mut unsynced = good;
+    unsynced[0] |= LEAP_UNSYNCHRONIZED << 6;
+    assert_eq!(parse_response(&unsynced, 7), Err(SntpError::Unsynchronized));
+  }
+
+  #[test]
+  fn server_port_is_optional() {
+    assert_eq!(server_addr("pool.ntp.org"), ("pool.ntp.org", 123));
+    assert_eq!(server_addr("127.0.0.1:1123"), ("127.0.0.1", 1123));
+  }
+}
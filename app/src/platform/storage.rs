@@ -1,5 +1,9 @@
 use alloc::sync::Arc;
 use core::ops::Deref;
+use log::warn;
+use wasm_protocol::{
+  APP_METADATA_SECTION, AppMetadata, CUSTOM_SECTION_ID, MAX_METADATA_LEN, MODULE_HEADER, SectionHeader, custom_section_name,
+};
 
 pub use embedded_tools::config::{ConfigFileTrait, StateError};
 pub use embedded_tools::local_fs::{DirEntry, FileType, FsError, LocalFsTrait};
@@ -9,10 +13,61 @@ pub struct StorageHandle {
   inner: Arc<dyn LocalFsTrait>,
 }
 
+/// Bytes read at the start of each section: enough for its header and the
+/// name of [`APP_METADATA_SECTION`].
+const SECTION_PEEK: usize = 32;
+
 impl StorageHandle {
   pub fn new(inner: Arc<dyn LocalFsTrait>) -> Self {
     Self { inner }
   }
+
+  /// The metadata a `.wsm` app declares in its [`APP_METADATA_SECTION`].
+  /// Walks the module a section header at a time, so only a few bytes of
+  /// code and data are read. `None` if the file isn't a module or declares
+  /// no (readable) metadata.
+  pub async fn read_app_metadata(&self, file_name: &str) -> Option<AppMetadata> {
+    let size = self.get_file_size(file_name.into()).await.ok()? as usize;
+    let header = self.read_binary_chunk(file_name.into(), 0, MODULE_HEADER.len() as u32).await.ok()?;
+    if header[..] != MODULE_HEADER {
+      return None;
+    }
+
+    let mut pos = MODULE_HEADER.len();
+    while pos < size {
+      let peek = self
+        .read_binary_chunk(file_name.into(), pos as u32, (size - pos).min(SECTION_PEEK) as u32)
+        .await
+        .ok()?;
+      let section = SectionHeader::parse(&peek)?;
+      let payload = pos + section.header_len;
+      if section.id == CUSTOM_SECTION_ID
+        && let Some((name, name_len)) = custom_section_name(&peek[section.header_len..])
+        && name == APP_METADATA_SECTION
+      {
+        let len = section.size.checked_sub(name_len)?;
+        if len > MAX_METADATA_LEN {
+          warn!("Storage: {file_name}: app metadata too large ({len}B)");
+          return None;
+        }
+        let data = self
+          .read_binary_chunk(file_name.into(), (payload + name_len) as u32, len as u32)
+          .await
+          .ok()?;
+        return AppMetadata::decode(&data)
+          .inspect_err(|err| warn!("Storage: {file_name}: unreadable app metadata: {err:?}"))
+          .ok();
+      }
+      // A section that doesn't move the walk on, or runs past the end of the
+      // file, means the module is corrupt.
+      let next = payload.checked_add(section.size)?;
+      if next <= pos || next > size {
+        return None;
+      }
+      pos = next;
+    }
+    None
+  }
 }
 
 impl Deref for StorageHandle {
@@ -1,3 +1,5 @@
+use super::clock::WallClockHandle;
+use super::crash::CrashLogHandle;
 use super::display::DisplayHandle;
 use super::hexpansion::HexpansionHandle;
 use super::http::HttpClientHandle;
@@ -7,6 +9,7 @@ use super::power::PowerHandle;
 use super::storage::{ConfigHandle, FsError, StorageHandle};
 use super::system::SystemHandle;
 use super::tcp::TcpHandle;
+use super::udp::UdpHandle;
 use super::wifi::WiFiHandle;
 use crate::types::{DeviceConfig, OtaError};
 use core::fmt;
@@ -23,8 +26,14 @@ pub trait Platform: Clone + Send + Sync + fmt::Debug {
   /// A raw TCP stream client (used by the SSH app). `None` on platforms that
   /// cannot open raw sockets.
   fn tcp_client(&self) -> Option<TcpHandle>;
+  /// A raw UDP socket client. `None` on platforms without one.
+  fn udp_client(&self) -> Option<UdpHandle>;
   fn storage_manager(&self) -> StorageHandle;
   fn config_manager(&self) -> ConfigHandle<DeviceConfig>;
+  /// Where the WASM runner keeps the report of the last app crash.
+  fn crash_log(&self) -> CrashLogHandle;
+  /// The time of day, shared by everything that reads or sets it.
+  fn wall_clock(&self) -> WallClockHandle;
   /// The currently running firmware version, baked in at build time.
   fn firmware_version(&self) -> u32;
   /// Fill `dest` with cryptographically-secure random bytes from the device
@@ -0,0 +1,57 @@
+use alloc::{boxed::Box, string::String, sync::Arc, vec::Vec};
+use core::{fmt, future::Future, pin::Pin};
+use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, channel::Channel};
+
+/// Events produced by a bound UDP socket, pumped in the background like
+/// [`TcpEvent`](super::TcpEvent)s.
+#[derive(Debug, Clone)]
+pub enum UdpEvent {
+  /// The socket was bound and the pump is now receiving datagrams.
+  Bound,
+  /// A datagram from `host:port` (`host` is the sender's IP address).
+  Datagram { host: String, port: u16, data: Vec<u8> },
+  /// The socket was closed locally.
+  Closed,
+  /// The socket could not be bound, or failed.
+  Error,
+}
+
+/// Bounded channel carrying [`UdpEvent`]s from the socket pump to the
+/// consumer, leaked to `'static` like [`TcpEventChannel`](super::TcpEventChannel).
+pub type UdpEventChannel = Channel<CriticalSectionRawMutex, UdpEvent, 16>;
+
+/// Raw UDP socket abstraction, mirroring [`TcpClient`](super::TcpClient): one
+/// socket at a time, pumped in the background, with `!Send` futures.
+pub trait UdpClient: Send + Sync + fmt::Debug {
+  /// Bind a socket to local `port` (0 picks any free port) and begin
+  /// streaming received datagrams into `channel`, which carries
+  /// `Bound`/`Error` to signal the outcome.
+  fn bind(&self, port: u16, channel: &'static UdpEventChannel) -> Pin<Box<dyn Future<Output = ()> + 'static>>;
+  /// Send `data` to `host:port` from the current socket.
+  fn send_to(&self, host: String, port: u16, data: Vec<u8>) -> Pin<Box<dyn Future<Output = ()> + 'static>>;
+  /// Close the current socket.
+  fn close(&self) -> Pin<Box<dyn Future<Output = ()> + 'static>>;
+}
+
+#[derive(Clone, Debug)]
+pub struct UdpHandle {
+  inner: Arc<dyn UdpClient>,
+}
+
+impl UdpHandle {
+  pub fn new(client: Arc<dyn UdpClient>) -> Self {
+    Self { inner: client }
+  }
+
+  pub async fn bind(&self, port: u16, channel: &'static UdpEventChannel) {
+    self.inner.bind(port, channel).await
+  }
+
+  pub async fn send_to(&self, host: String, port: u16, data: Vec<u8>) {
+    self.inner.send_to(host, port, data).await
+  }
+
+  pub async fn close(&self) {
+    self.inner.close().await
+  }
+}
@@ -49,10 +49,15 @@ pub enum HostIpcMessage {
   Wire(wasm_protocol::HostIpcMessage),
 }
 
+/// How many guest messages can wait for the IPC handler. A guest that sends
+/// more is told to retry (`extern_try_write_wasm_ipc_message`) or, through
+/// the older import, trapped.
+pub const WASM_IPC_QUEUE_DEPTH: usize = 8;
+
 // Channel type aliases
-pub type WasmIpcChannel = Channel<CriticalSectionRawMutex, (u32, WasmIpcMessage), 1>;
-pub type WasmIpcSender = Sender<'static, CriticalSectionRawMutex, (u32, WasmIpcMessage), 1>;
-pub type WasmIpcReceiver = Receiver<'static, CriticalSectionRawMutex, (u32, WasmIpcMessage), 1>;
+pub type WasmIpcChannel = Channel<CriticalSectionRawMutex, (u32, WasmIpcMessage), WASM_IPC_QUEUE_DEPTH>;
+pub type WasmIpcSender = Sender<'static, CriticalSectionRawMutex, (u32, WasmIpcMessage), WASM_IPC_QUEUE_DEPTH>;
+pub type WasmIpcReceiver = Receiver<'static, CriticalSectionRawMutex, (u32, WasmIpcMessage), WASM_IPC_QUEUE_DEPTH>;
 pub type HostIpcChannel = Channel<CriticalSectionRawMutex, (u32, HostIpcMessage), 1>;
 pub type HostIpcSender = Sender<'static, CriticalSectionRawMutex, (u32, HostIpcMessage), 1>;
 pub type HostIpcReceiver = Receiver<'static, CriticalSectionRawMutex, (u32, HostIpcMessage), 1>;
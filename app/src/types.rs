@@ -25,6 +25,17 @@ pub struct DeviceConfig {
   #[serde(default)]
   pub ap_password: String,
   pub known_wifi_networks: Vec<KnownWifiNetwork>,
+  /// SNTP server the clock is synchronized from in Station mode, as
+  /// `host` or `host:port`
+  #[serde(default = "default_ntp_server")]
+  pub ntp_server: String,
+  /// Local timezone, in minutes east of UTC
+  #[serde(default)]
+  pub utc_offset_minutes: i16,
+}
+
+fn default_ntp_server() -> String {
+  "pool.ntp.org".to_string()
 }
 
 impl Default for DeviceConfig {
@@ -38,6 +49,8 @@ impl Default for DeviceConfig {
       ap_ssid: "Rustagon".to_string(),
       ap_password: "rustagon".to_string(),
       known_wifi_networks: Vec::new(),
+      ntp_server: default_ntp_server(),
+      utc_offset_minutes: 0,
     }
   }
 }
@@ -100,6 +113,8 @@ pub struct LedStates {
 pub enum LedRequest {
   Off,
   Solid(LedState),
+  /// An explicit colour per LED, e.g. set by a WASM guest.
+  Pixels([LedState; NUM_LEDS]),
   Rainbow,
   Breathe(LedState),
   Chase(LedState),
@@ -110,7 +125,7 @@ pub enum LedRequest {
 
 // ================================ Input ================================
 
-pub use wasm_protocol::HexButton;
+pub use wasm_protocol::{HexButton, KeyCode};
 
 // ================================ WebSocket ================================
 
@@ -170,100 +185,3 @@ pub enum KeyEventType {
   Pressed,
   Released,
 }
-
-impl KeyCode {
-  /// Map a directional/confirm key to its unified `HexButton` equivalent so
-  /// keyboard arrow and Enter keys are indistinguishable from physical hex
-  /// buttons. Returns `None` for keys that remain plain keyboard events.
-  pub fn to_hex_button(self) -> Option<HexButton> {
-    match self {
-      KeyCode::Up => Some(HexButton::Up),
-      KeyCode::Down => Some(HexButton::Down),
-      KeyCode::Left => Some(HexButton::Left),
-      KeyCode::Right => Some(HexButton::Right),
-      KeyCode::Enter => Some(HexButton::Fire),
-      _ => None,
-    }
-  }
-}
-
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum KeyCode {
-  A,
-  B,
-  C,
-  D,
-  E,
-  F,
-  G,
-  H,
-  I,
-  J,
-  K,
-  L,
-  M,
-  N,
-  O,
-  P,
-  Q,
-  R,
-  S,
-  T,
-  U,
-  V,
-  W,
-  X,
-  Y,
-  Z,
-  Digit0,
-  Digit1,
-  Digit2,
-  Digit3,
-  Digit4,
-  Digit5,
-  Digit6,
-  Digit7,
-  Digit8,
-  Digit9,
-  Enter,
-  Escape,
-  Backspace,
-  Tab,
-  Space,
-  Delete,
-  Up,
-  Down,
-  Left,
-  Right,
-  F1,
-  F2,
-  F3,
-  F4,
-  F5,
-  F6,
-  F7,
-  F8,
-  F9,
-  F10,
-  F11,
-  F12,
-  Comma,
-  Period,
-  Slash,
-  Semicolon,
-  Quote,
-  Minus,
-  Equals,
-  Backtick,
-  Backslash,
-  LBracket,
-  RBracket,
-  Shift,
-  Ctrl,
-  Alt,
-  CapsLock,
-  Home,
-  End,
-  PageUp,
-  PageDown,
-}
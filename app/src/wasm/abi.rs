@@ -0,0 +1,127 @@
+//! Host ABI checks, run before a guest is instantiated.
+//!
+//! A module importing a host function this firmware does not provide would
+//! otherwise fail in the linker with an error meaningless to the user. Both
+//! the ABI version the guest declares in [`ABI_VERSION_SECTION`] and the
+//! versions of the imports it actually uses are checked against
+//! [`HOST_ABI_VERSION`].
+
+use super::profiler::PROFILE_IMPORT_MODULE;
+use alloc::string::String;
+use core::fmt;
+use wasm_protocol::{ABI_VERSION_SECTION, HOST_ABI_VERSION, import_abi_version};
+use wasmi::Module;
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum AbiMismatch {
+  /// The guest needs at least this host ABI version.
+  NeedsVersion(u32),
+  /// The guest imports a function no firmware version provides.
+  UnknownImport(String),
+  /// The guest's ABI version section is not a `u32`.
+  MalformedVersion,
+}
+
+impl fmt::Display for AbiMismatch {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::NeedsVersion(version) => write!(f, "App needs firmware ABI v{version}+"),
+      Self::UnknownImport(name) => write!(f, "App needs newer firmware ({name})"),
+      Self::MalformedVersion => f.write_str("App has a malformed ABI version"),
+    }
+  }
+}
+
+impl From<AbiMismatch> for wasmi::Error {
+  fn from(mismatch: AbiMismatch) -> Self {
+    wasmi::Error::new(mismatch.to_string())
+  }
+}
+
+/// Check a compiled module against this host's ABI, returning the version the
+/// guest needs.
+pub fn check_module_abi(module: &Module) -> Result<u32, AbiMismatch> {
+  let declared = module
+    .custom_sections()
+    .find(|section| section.name() == ABI_VERSION_SECTION)
+    .map(|section| section.data());
+  let imports = module.imports().filter(|import| is_versioned_import(import.module()));
+  required_abi_version(declared, imports.map(|import| (import.module(), import.name())))
+}
+
+/// Whether imports from `module` are covered by [`HOST_ABI_VERSION`]. The
+/// profiler's hooks are added by the host, not asked for by the guest, and
+/// WASI is versioned by its module name.
+fn is_versioned_import(module: &str) -> bool {
+  #[cfg(feature = "wasi")]
+  if module == super::wasi::WASI_IMPORT_MODULE {
+    return false;
+  }
+  module != PROFILE_IMPORT_MODULE
+}
+
+/// The host ABI version needed by a guest declaring `declared` (the raw
+/// [`ABI_VERSION_SECTION`] contents, if present) and using `imports`.
+pub fn required_abi_version<'a>(
+  declared: Option<&[u8]>,
+  imports: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Result<u32, AbiMismatch> {
+  let mut required = match declared {
+    Some(bytes) => u32::from_le_bytes(bytes.try_into().map_err(|_| AbiMismatch::MalformedVersion)?),
+    // Guests from before the section existed only know the original imports.
+    None => 1,
+  };
+  for (module, name) in imports {
+    let version = import_abi_version(module, name).ok_or_else(|| AbiMismatch::UnknownImport(name.into()))?;
+    required = required.max(version);
+  }
+  if required > HOST_ABI_VERSION {
+    return Err(AbiMismatch::NeedsVersion(required));
+  }
+  Ok(required)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const NO_IMPORTS: [(&str, &str); 0] = [];
+
+  #[test]
+  fn legacy_guest_without_section_is_accepted() {
+    let imports = [("index", "extern_write_stdout"), ("index", "extern_get_millis")];
+    assert_eq!(required_abi_version(None, imports), Ok(1));
+  }
+
+  #[test]
+  fn version_is_raised_by_newer_imports() {
+    let imports = [("index", "extern_write_stdout"), ("index", "extern_set_led")];
+    assert_eq!(required_abi_version(Some(&1u32.to_le_bytes()), imports), Ok(2));
+  }
+
+  #[test]
+  fn guest_built_for_newer_firmware_is_rejected() {
+    let declared = (HOST_ABI_VERSION + 1).to_le_bytes();
+    assert_eq!(
+      required_abi_version(Some(&declared), NO_IMPORTS),
+      Err(AbiMismatch::NeedsVersion(HOST_ABI_VERSION + 1))
+    );
+  }
+
+  #[test]
+  fn unknown_imports_are_rejected() {
+    assert_eq!(
+      required_abi_version(None, [("index", "extern_teleport")]),
+      Err(AbiMismatch::UnknownImport("extern_teleport".into()))
+    );
+    assert_eq!(
+      required_abi_version(None, [("env", "extern_write_stdout")]),
+      Err(AbiMismatch::UnknownImport("extern_write_stdout".into()))
+    );
+  }
+
+  #[test]
+  fn malformed_section_is_rejected() {
+    assert_eq!(required_abi_version(Some(&[1, 0]), NO_IMPORTS), Err(AbiMismatch::MalformedVersion));
+  }
+}
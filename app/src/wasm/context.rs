@@ -1,81 +1,150 @@
+use super::fault::{GuestFault, guest_range};
 use super::host::WasmHost;
+use super::lcd::MissedLcd;
+use super::leds::GuestLeds;
+use super::limits::MyLimiter;
+use super::overlay::{OVERLAY_REGION, Overlay};
+use super::profiler::Profiler;
+use super::stdout::StdoutTail;
+use super::storage::AppStorage;
 use super::timers::TimerRegistry;
+#[cfg(feature = "wasi")]
+use super::wasi::WasiFiles;
+use crate::platform::display::{DisplayError, FrameRegion};
 use crate::protocol::{HostIpcReceiver, WasmIpcSender};
-use alloc::vec::Vec;
-use wasmi::{Caller, ResourceLimiter};
-use wasmi_core::LimiterError;
+use crate::types::LedRequest;
+use alloc::{
+  string::{String, ToString},
+  vec::Vec,
+};
+use log::warn;
+use wasm_protocol::{Capabilities, WireFormat};
+use wasmi::{Caller, Memory};
 
 pub struct WasmCtx<H: WasmHost> {
   pub counter: u32,
   pub last_screen_update: u32,
   pub timer_registry: TimerRegistry,
+  /// Set by `extern_idle` during a tick: the guest has nothing to do until
+  /// one of its timers expires or a host message arrives.
+  pub idle: bool,
+  /// Set while the host draws the guest's `ShowScreen` widgets; its raw
+  /// frames are dropped until it sends `ShowFramebuffer`, and after that
+  /// reach the LCD once the display has parked at the `Blank` it queues,
+  /// being resent each tick until it has.
+  pub widget_mode: bool,
+  pub leds: GuestLeds,
+  /// The app's storage sandbox, if the platform provides one.
+  pub storage: Option<AppStorage>,
+  /// Encoding of IPC messages, as declared by the guest's
+  /// `WIRE_FORMAT_EXPORT`.
+  pub wire_format: WireFormat,
+  /// The guest's recent stdout, for the crash report.
+  pub stdout: StdoutTail,
+  /// Set by `extern_panic`: the guest's panic message and location.
+  pub panic: Option<String>,
   pub wasm_ipc_sender: WasmIpcSender,
   pub host_ipc_receiver: HostIpcReceiver,
   pub limiter: MyLimiter,
+  pub profiler: Profiler,
+  /// Created the first time the profiler's overlay is shown.
+  pub overlay: Option<Overlay>,
+  /// Raw updates the display was too busy to take, sent again next tick.
+  pub missed_lcd: MissedLcd,
+  /// Files opened through WASI's `path_open`.
+  #[cfg(feature = "wasi")]
+  pub wasi: WasiFiles,
   pub host: H,
 }
 
-pub struct MyLimiter;
+impl<H: WasmHost> WasmCtx<H> {
+  /// What this host backs for the guest, as reported by
+  /// `extern_get_capabilities`.
+  pub fn capabilities(&self) -> Capabilities {
+    let capabilities = self.host.capabilities();
+    if self.storage.is_some() {
+      capabilities.union(Capabilities::STORAGE)
+    } else {
+      capabilities
+    }
+  }
 
-impl ResourceLimiter for MyLimiter {
-  fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool, LimiterError> {
-    log::debug!("memory_growing: current={current} desired={desired}");
-    Ok(true)
+  /// Print `text` from the guest, keeping it for the crash report.
+  pub fn write_stdout(&mut self, text: &str) {
+    self.stdout.push(text);
+    self.host.write_stdout(text);
   }
 
-  fn table_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool, LimiterError> {
-    log::debug!("table_growing: current={current} desired={desired}");
-    Ok(true)
+  /// Push any per-LED writes made during the last `tick` to the host. On a
+  /// full LED channel the frame stays pending and is retried next tick.
+  pub fn flush_leds(&mut self) {
+    if let Some(states) = self.leds.pending() {
+      if self.host.set_leds(states).is_ok() {
+        self.leds.mark_flushed();
+      }
+    }
   }
 
-  fn instances(&self) -> usize {
-    100
+  /// Draw the profiler's overlay over the frame the guest just presented.
+  /// Handle the `result` of sending `region` of the guest's frame, from
+  /// guest address `ptr` with rows `stride` apart, to the display.
+  pub fn lcd_presented(&mut self, result: Result<(), DisplayError>, region: FrameRegion, ptr: u32, stride: u32) {
+    match result {
+      Ok(()) => {}
+      Err(DisplayError::SignalBusy) => self.missed_lcd.record(region, ptr, stride),
+      Err(err) => warn!("WASM: display dropped an LCD update of {region:?}: {err:?}"),
+    }
   }
 
-  fn tables(&self) -> usize {
-    100
+  pub fn draw_overlay(&mut self) {
+    let overlay = self.overlay.get_or_insert_with(Overlay::default);
+    let pixels = overlay.render(self.profiler.overlay_text().to_string());
+    // Drawn again with the next frame if the display is busy.
+    let _ = self.host.set_lcd_region(OVERLAY_REGION, pixels, OVERLAY_REGION.row_bytes());
   }
 
-  fn memories(&self) -> usize {
-    100
+  /// Turn the ring off when a session that drove the LEDs ends, so the guest's
+  /// last frame does not linger over the menu.
+  pub fn release_leds(&mut self) {
+    if self.leds.touched() {
+      let _ = self.host.set_led_effect(LedRequest::Off);
+    }
   }
 }
 
 pub trait ReadWasmBuffer {
-  fn read_memory(&self, ptr: u32, len: u32) -> Vec<u8>;
-  fn read_memory_into(&self, ptr: u32, buffer: &mut [u8]);
-  fn write_memory(&mut self, ptr: u32, buf: &[u8]);
+  fn read_memory(&self, ptr: u32, len: u32) -> Result<Vec<u8>, GuestFault>;
+  fn read_memory_into(&self, ptr: u32, buffer: &mut [u8]) -> Result<(), GuestFault>;
+  fn write_memory(&mut self, ptr: u32, buf: &[u8]) -> Result<(), GuestFault>;
 }
 
 impl<H: WasmHost> ReadWasmBuffer for Caller<'_, WasmCtx<H>> {
-  fn read_memory(&self, ptr: u32, len: u32) -> Vec<u8> {
+  fn read_memory(&self, ptr: u32, len: u32) -> Result<Vec<u8>, GuestFault> {
+    let memory = guest_memory(self)?;
+    // Check before allocating so a bogus length cannot exhaust host memory.
+    guest_range(ptr, len as usize, memory.data_size(self))?;
     let mut buffer = Vec::new();
     buffer.resize(len as usize, 0u8);
-    self.read_memory_into(ptr, &mut buffer);
-    buffer
+    self.read_memory_into(ptr, &mut buffer)?;
+    Ok(buffer)
   }
 
-  fn read_memory_into(&self, ptr: u32, buffer: &mut [u8]) {
-    let memory = self
-      .get_export("memory")
-      .and_then(|export| export.into_memory())
-      .ok_or_else(|| wasmi::Error::new("failed to find memory export"))
-      .unwrap();
-    memory
-      .read(&self, ptr as usize, buffer)
-      .map_err(|_| wasmi::Error::new("failed to read memory"))
-      .unwrap();
+  fn read_memory_into(&self, ptr: u32, buffer: &mut [u8]) -> Result<(), GuestFault> {
+    guest_memory(self)?
+      .read(self, ptr as usize, buffer)
+      .map_err(|_| GuestFault::OutOfBounds { ptr, len: buffer.len() })
   }
 
-  fn write_memory(&mut self, ptr: u32, buf: &[u8]) {
-    let memory = self
-      .get_export("memory")
-      .and_then(|export| export.into_memory())
-      .ok_or_else(|| wasmi::Error::new("failed to find memory export"))
-      .unwrap();
-    memory
+  fn write_memory(&mut self, ptr: u32, buf: &[u8]) -> Result<(), GuestFault> {
+    guest_memory(self)?
       .write(self, ptr as usize, buf)
-      .map_err(|_| wasmi::Error::new("failed to write memory"))
-      .unwrap();
+      .map_err(|_| GuestFault::OutOfBounds { ptr, len: buf.len() })
   }
 }
+
+pub fn guest_memory<H: WasmHost>(caller: &Caller<'_, WasmCtx<H>>) -> Result<Memory, GuestFault> {
+  caller
+    .get_export("memory")
+    .and_then(|export| export.into_memory())
+    .ok_or(GuestFault::MissingMemory)
+}
@@ -0,0 +1,280 @@
+//! Validation of guest-supplied data at the host-call boundary.
+//!
+//! Pointers, lengths, message bytes and message ids passed to host imports
+//! all come from the guest and cannot be trusted. The helpers here turn bad
+//! input into a [`GuestFault`], which the imports return as a trap: the
+//! guest is stopped and the reason shown, but the host keeps running.
+
+use super::widgets::widget_screen;
+use crate::platform::display::{FrameRegion, MAX_REGION_STRIDE};
+use crate::protocol::{HostIpcMessage, HostIpcReceiver, WasmIpcMessage, WasmIpcSender};
+use core::{fmt, ops::Range};
+use display_types::LcdScreen;
+use wasm_protocol::{HostIpcMessage as WireHostIpcMessage, WasmIpcMessage as WireWasmIpcMessage, WireFormat};
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum GuestFault {
+  /// The module does not export a `memory`.
+  MissingMemory,
+  /// A pointer/length pair reaches outside guest memory.
+  OutOfBounds { ptr: u32, len: usize },
+  /// A guest IPC message did not decode.
+  MalformedMessage,
+  /// The guest sent a message while the host's queue was full.
+  ChannelFull,
+  /// The guest read a host message that was never delivered to it.
+  NoHostMessage { expected: u32 },
+  /// The guest read a host message with the wrong id.
+  MismatchedHostMessage { expected: u32, actual: u32 },
+  /// A partial LCD update was empty, off screen, or had rows overlapping.
+  BadLcdRegion { x: u32, y: u32, width: u32, height: u32 },
+  /// The guest drove a pin that isn't on a user header.
+  BadGpioPin(u32),
+}
+
+impl fmt::Display for GuestFault {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::MissingMemory => f.write_str("App has no memory export"),
+      Self::OutOfBounds { ptr, len } => write!(f, "Bad memory access ({len} bytes at {ptr:#x})"),
+      Self::MalformedMessage => f.write_str("App sent a malformed message"),
+      Self::ChannelFull => f.write_str("App sent messages too quickly"),
+      Self::NoHostMessage { expected } => write!(f, "App read missing message {expected}"),
+      Self::MismatchedHostMessage { expected, actual } => write!(f, "App read message {expected}, but {actual} was pending"),
+      Self::BadLcdRegion { x, y, width, height } => write!(f, "App drew a bad screen region ({width}x{height} at {x},{y})"),
+      Self::BadGpioPin(pin) => write!(f, "App drove GPIO {pin}, which isn't a header pin"),
+    }
+  }
+}
+
+impl From<GuestFault> for wasmi::Error {
+  fn from(fault: GuestFault) -> Self {
+    wasmi::Error::new(fault.to_string())
+  }
+}
+
+/// The byte range `ptr..ptr + len`, if it lies within `memory_size` bytes of
+/// guest memory.
+pub fn guest_range(ptr: u32, len: usize, memory_size: usize) -> Result<Range<usize>, GuestFault> {
+  let start = ptr as usize;
+  match start.checked_add(len) {
+    Some(end) if end <= memory_size => Ok(start..end),
+    _ => Err(GuestFault::OutOfBounds { ptr, len }),
+  }
+}
+
+/// The screen region a guest asked to update, with the bytes its buffer
+/// spans, if it lies on screen and `stride` leaves room for each of its rows
+/// without being wider than [`MAX_REGION_STRIDE`].
+pub fn lcd_region(x: u32, y: u32, width: u32, height: u32, stride: u32) -> Result<(FrameRegion, usize), GuestFault> {
+  let fault = GuestFault::BadLcdRegion { x, y, width, height };
+  let region = match (u16::try_from(x), u16::try_from(y), u16::try_from(width), u16::try_from(height)) {
+    (Ok(x), Ok(y), Ok(width), Ok(height)) => FrameRegion { x, y, width, height },
+    _ => return Err(fault),
+  };
+  let stride = stride as usize;
+  if !region.is_valid() || !(region.row_bytes()..=MAX_REGION_STRIDE).contains(&stride) {
+    return Err(fault);
+  }
+  let len = region.buffer_len(stride).ok_or(fault)?;
+  Ok((region, len))
+}
+
+pub fn decode_wasm_message(format: WireFormat, bytes: &[u8]) -> Result<WireWasmIpcMessage, GuestFault> {
+  format.decode(bytes).map_err(|_| GuestFault::MalformedMessage)
+}
+
+/// Queue a guest message for the host without blocking the interpreter.
+/// Widget screens go to the host's display as `LcdScreen`, like its own
+/// error screen, so each platform renders them the way it renders the menus.
+pub fn send_wasm_message(sender: &WasmIpcSender, id: u32, msg: WireWasmIpcMessage) -> Result<(), GuestFault> {
+  let msg = match msg {
+    WireWasmIpcMessage::ShowScreen(widget) => WasmIpcMessage::LcdScreen(widget_screen(widget)),
+    // Parks the host renderer so it doesn't draw over the guest's frames.
+    WireWasmIpcMessage::ShowFramebuffer => WasmIpcMessage::LcdScreen(LcdScreen::Blank),
+    msg => WasmIpcMessage::Wire(msg),
+  };
+  sender.try_send((id, msg)).map_err(|_| GuestFault::ChannelFull)
+}
+
+/// Take the host message the runner announced to the guest as `expected`.
+///
+/// The message is only removed once it is known to be the right one, so a
+/// runtime command (e.g. Stop) queued behind a bad read is left for the
+/// runner to act on.
+pub fn take_host_message(receiver: &HostIpcReceiver, expected: u32) -> Result<WireHostIpcMessage, GuestFault> {
+  match receiver.try_peek() {
+    Ok((actual, HostIpcMessage::Wire(_))) if actual == expected => {}
+    Ok((actual, HostIpcMessage::Wire(_))) => return Err(GuestFault::MismatchedHostMessage { expected, actual }),
+    Ok((_, HostIpcMessage::Runtime(_))) | Err(_) => return Err(GuestFault::NoHostMessage { expected }),
+  }
+  match receiver.try_receive() {
+    Ok((_, HostIpcMessage::Wire(msg))) => Ok(msg),
+    _ => Err(GuestFault::NoHostMessage { expected }),
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::protocol::{HostIpcChannel, HostRuntimeCommand, WASM_IPC_QUEUE_DEPTH, WasmIpcChannel};
+  use alloc::boxed::Box;
+  use wasm_protocol::{HexButton, HttpRequest, Widget};
+
+  fn host_channel() -> &'static HostIpcChannel {
+    Box::leak(Box::new(HostIpcChannel::new()))
+  }
+
+  #[test]
+  fn range_inside_memory_is_accepted() {
+    assert_eq!(guest_range(16, 32, 64), Ok(16..48));
+    assert_eq!(guest_range(0, 64, 64), Ok(0..64));
+  }
+
+  #[test]
+  fn range_past_end_of_memory_is_rejected() {
+    assert_eq!(guest_range(48, 32, 64), Err(GuestFault::OutOfBounds { ptr: 48, len: 32 }));
+    assert_eq!(
+      guest_range(u32::MAX, usize::MAX, 64),
+      Err(GuestFault::OutOfBounds {
+        ptr: u32::MAX,
+        len: usize::MAX
+      })
+    );
+  }
+
+  #[test]
+  fn lcd_regions_must_fit_the_screen_and_stride() {
+    assert!(lcd_region(200, 200, 40, 40, 80).is_ok());
+    assert!(lcd_region(0, 0, 240, 240, 480).is_ok());
+    assert_eq!(
+      lcd_region(201, 0, 40, 1, 80),
+      Err(GuestFault::BadLcdRegion {
+        x: 201,
+        y: 0,
+        width: 40,
+        height: 1
+      })
+    );
+    assert!(lcd_region(0, 0, 0, 10, 0).is_err());
+    assert!(lcd_region(0, 0, 10, 10, 19).is_err());
+    assert!(lcd_region(u32::MAX, 0, 1, 1, 2).is_err());
+    assert!(lcd_region(0, 0, 1, 240, 482).is_err());
+    assert_eq!(lcd_region(0, 0, 1, 2, 480).unwrap().1, 482);
+  }
+
+  #[test]
+  fn malformed_message_is_rejected() {
+    assert_eq!(
+      decode_wasm_message(WireFormat::Json, b"{not json").unwrap_err(),
+      GuestFault::MalformedMessage
+    );
+    assert_eq!(
+      decode_wasm_message(WireFormat::Json, b"\"NoSuchMessage\"").unwrap_err(),
+      GuestFault::MalformedMessage
+    );
+    assert_eq!(
+      decode_wasm_message(WireFormat::Json, &[]).unwrap_err(),
+      GuestFault::MalformedMessage
+    );
+    assert_eq!(
+      decode_wasm_message(WireFormat::Postcard, &[0xff, 0xff]).unwrap_err(),
+      GuestFault::MalformedMessage
+    );
+    assert_eq!(
+      decode_wasm_message(WireFormat::Postcard, &[]).unwrap_err(),
+      GuestFault::MalformedMessage
+    );
+  }
+
+  #[test]
+  fn message_in_either_format_is_decoded() {
+    let msg = WireWasmIpcMessage::HttpRequest(HttpRequest::new("http://badge.local/".into()));
+    for format in [WireFormat::Json, WireFormat::Postcard] {
+      let bytes = format.encode(&msg).unwrap();
+      assert!(matches!(
+        decode_wasm_message(format, &bytes),
+        Ok(WireWasmIpcMessage::HttpRequest(req)) if req.url == "http://badge.local/"
+      ));
+    }
+  }
+
+  #[test]
+  fn message_beyond_the_queue_depth_is_rejected() {
+    let channel: &'static WasmIpcChannel = Box::leak(Box::new(WasmIpcChannel::new()));
+    let msg = || WireWasmIpcMessage::HttpRequest(HttpRequest::new("http://badge.local/".into()));
+    for id in 0..WASM_IPC_QUEUE_DEPTH as u32 {
+      assert_eq!(send_wasm_message(&channel.sender(), id + 2, msg()), Ok(()));
+    }
+    assert_eq!(send_wasm_message(&channel.sender(), 99, msg()), Err(GuestFault::ChannelFull));
+  }
+
+  #[test]
+  fn widget_screens_go_to_the_host_display() {
+    let channel: &'static WasmIpcChannel = Box::leak(Box::new(WasmIpcChannel::new()));
+    let widget = Widget::Progress("Loading".into());
+    assert_eq!(
+      send_wasm_message(&channel.sender(), 2, WireWasmIpcMessage::ShowScreen(widget)),
+      Ok(())
+    );
+    assert_eq!(send_wasm_message(&channel.sender(), 3, WireWasmIpcMessage::ShowFramebuffer), Ok(()));
+
+    assert!(matches!(
+      channel.try_receive(),
+      Ok((2, WasmIpcMessage::LcdScreen(LcdScreen::Progress(text)))) if text == "Loading"
+    ));
+    assert!(matches!(
+      channel.try_receive(),
+      Ok((3, WasmIpcMessage::LcdScreen(LcdScreen::Blank)))
+    ));
+  }
+
+  #[test]
+  fn reading_without_a_pending_message_is_rejected() {
+    let channel = host_channel();
+    assert_eq!(
+      take_host_message(&channel.receiver(), 7).unwrap_err(),
+      GuestFault::NoHostMessage { expected: 7 }
+    );
+  }
+
+  #[test]
+  fn reading_the_wrong_id_is_rejected_and_keeps_the_message() {
+    let channel = host_channel();
+    channel
+      .try_send((5, HostIpcMessage::Wire(WireHostIpcMessage::HexButton(HexButton::Fire))))
+      .unwrap();
+    assert_eq!(
+      take_host_message(&channel.receiver(), 7).unwrap_err(),
+      GuestFault::MismatchedHostMessage { expected: 7, actual: 5 }
+    );
+    assert!(channel.receiver().try_peek().is_ok());
+  }
+
+  #[test]
+  fn runtime_commands_are_never_handed_to_the_guest() {
+    let channel = host_channel();
+    channel.try_send((0, HostIpcMessage::Runtime(HostRuntimeCommand::Stop))).unwrap();
+    assert_eq!(
+      take_host_message(&channel.receiver(), 0).unwrap_err(),
+      GuestFault::NoHostMessage { expected: 0 }
+    );
+    assert!(matches!(
+      channel.try_receive(),
+      Ok((_, HostIpcMessage::Runtime(HostRuntimeCommand::Stop)))
+    ));
+  }
+
+  #[test]
+  fn matching_message_is_delivered() {
+    let channel = host_channel();
+    channel
+      .try_send((5, HostIpcMessage::Wire(WireHostIpcMessage::HexButton(HexButton::Fire))))
+      .unwrap();
+    assert!(matches!(
+      take_host_message(&channel.receiver(), 5),
+      Ok(WireHostIpcMessage::HexButton(HexButton::Fire))
+    ));
+    assert!(channel.receiver().try_peek().is_err());
+  }
+}
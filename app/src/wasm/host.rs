@@ -1,10 +1,77 @@
+use crate::platform::{CrashReport, DisplayError, FrameRegion};
+use crate::platform::led::LedError;
+use crate::types::{LedRequest, LedState, NUM_LEDS};
+use crate::wasm::fault::GuestFault;
+use alloc::vec::Vec;
+use core::future::{Future, pending};
+use wasm_protocol::Capabilities;
+
 /// Platform-specific operations needed by the WASM runtime.
 pub trait WasmHost {
     fn write_stdout(&mut self, text: &str);
 
     fn get_millis(&self) -> u64;
 
-    fn set_lcd_buffer(&mut self, buffer: &[u8]);
+    /// Monotonic time since boot, in microseconds.
+    fn get_micros(&self) -> u64;
+
+    /// Wait while the guest is idle: until `ms` more of the host's clock
+    /// has passed, or indefinitely for `None`. The runner stops waiting as
+    /// soon as a host message arrives. The default waits in real time; a
+    /// host with a simulated clock waits for that instead.
+    fn idle(&self, ms: Option<u64>) -> impl Future<Output = ()> {
+        async move {
+            match ms {
+                Some(ms) => crate::utils::sleep(ms).await,
+                None => pending().await,
+            }
+        }
+    }
+
+    /// The current UNIX time in microseconds, or `None` until the device
+    /// knows the time of day.
+    fn unix_time_micros(&self) -> Option<u64>;
+
+    /// Fill `dest` from the platform's entropy source.
+    fn fill_random(&mut self, dest: &mut [u8]);
+
+    /// Show a whole raw frame. Fails with [`DisplayError::SignalBusy`] while
+    /// the display is drawing a screen of its own; the runner sends the
+    /// frame again before the next tick.
+    fn set_lcd_buffer(&mut self, buffer: &[u8]) -> Result<(), DisplayError>;
+
+    /// Update only `region` of the screen. `buffer` holds the region's rows,
+    /// `stride` bytes apart; it has already been checked to be long enough.
+    /// Busy displays are retried as for [`set_lcd_buffer`](Self::set_lcd_buffer).
+    fn set_lcd_region(&mut self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError>;
+
+    /// Drive `pin` low for a `state` of 0, high otherwise. A host with real
+    /// pins only lets the guest drive the user header pins, and fails with
+    /// [`GuestFault::BadGpioPin`] for any other, which traps the guest.
+    fn set_gpio(&mut self, pin: u32, state: u32) -> Result<(), GuestFault>;
+
+    /// Show an explicit colour on each ring LED.
+    fn set_leds(&mut self, leds: &[LedState; NUM_LEDS]) -> Result<(), LedError>;
+
+    /// Switch the ring to one of the built-in LED effects.
+    fn set_led_effect(&mut self, request: LedRequest) -> Result<(), LedError>;
+
+    /// Hardware features this host backs. Storage is added by the runner
+    /// when the app is given a sandbox.
+    fn capabilities(&self) -> Capabilities;
+
+    /// Keep `report` for the web app after the guest stops abnormally.
+    fn report_crash(&mut self, report: CrashReport);
+
+    /// A chance to rewrite the module before it is compiled, e.g. to
+    /// instrument it for profiling. The default runs it unchanged.
+    fn prepare_module(&mut self, module: Vec<u8>) -> Vec<u8> {
+        module
+    }
+
+    /// An instrumented guest entered function `index` of its original module.
+    fn enter_function(&mut self, _index: u32) {}
 
-    fn set_gpio(&mut self, pin: u32, state: u32);
+    /// An instrumented guest left the function it last entered.
+    fn leave_function(&mut self) {}
 }
@@ -0,0 +1,155 @@
+//! Host side of guest HTTP requests.
+//!
+//! A guest may have up to [`MAX_HTTP_REQUESTS`] requests in flight. Each one
+//! runs as its own future that forwards the response to the guest, tagged
+//! with the id of the `HttpRequest` message; the IPC handler drives them all
+//! through [`GuestHttp::run`] alongside its other work. Requests over the
+//! limit are answered with `Busy`.
+
+use crate::platform::{HttpClientHandle, HttpEventChannel};
+use crate::protocol::{HostIpcMessage, HostIpcSender, HttpEvent, HttpRequest};
+use alloc::{boxed::Box, vec::Vec};
+use core::future::{Future, pending};
+use core::pin::Pin;
+use embassy_futures::{join::join, select::select_slice};
+use wasm_protocol::HostIpcMessage as WireHostIpcMessage;
+
+/// Requests a guest may have in flight at once.
+pub const MAX_HTTP_REQUESTS: usize = 4;
+
+type RequestFuture = Pin<Box<dyn Future<Output = ()>>>;
+
+pub struct GuestHttp {
+  client: Option<HttpClientHandle>,
+  host_ipc_sender: HostIpcSender,
+  in_flight: Vec<RequestFuture>,
+}
+
+impl GuestHttp {
+  pub fn new(client: Option<HttpClientHandle>, host_ipc_sender: HostIpcSender) -> Self {
+    Self {
+      client,
+      host_ipc_sender,
+      in_flight: Vec::new(),
+    }
+  }
+
+  /// Start `req`, sent by the guest as message `id`. Returns a reply to send
+  /// straight away if the request cannot be started.
+  pub fn start(&mut self, id: u32, req: HttpRequest) -> Option<(u32, WireHostIpcMessage)> {
+    let Some(client) = self.client.clone() else {
+      return Some((id, WireHostIpcMessage::HttpError));
+    };
+    if self.in_flight.len() >= MAX_HTTP_REQUESTS {
+      return Some((id, WireHostIpcMessage::Busy));
+    }
+    self
+      .in_flight
+      .push(Box::pin(forward_request(client, self.host_ipc_sender, id, req)));
+    None
+  }
+
+  /// Drive the requests in flight until one of them finishes. Never
+  /// completes while none are in flight. Dropping the future loses nothing:
+  /// each request keeps its progress for the next call.
+  pub async fn run(&mut self) {
+    if self.in_flight.is_empty() {
+      pending::<()>().await;
+    }
+    let ((), finished) = select_slice(Pin::new(&mut self.in_flight[..])).await;
+    self.in_flight.swap_remove(finished);
+  }
+
+  /// Abandon the requests still in flight. Called when the session ends.
+  pub fn cancel_all(&mut self) {
+    self.in_flight.clear();
+  }
+}
+
+/// Make request `id` and forward its response to the guest.
+async fn forward_request(client: HttpClientHandle, host_ipc_sender: HostIpcSender, id: u32, req: HttpRequest) {
+  let channel = HttpEventChannel::new();
+  let forward = async {
+    loop {
+      let msg = http_event_message(channel.receive().await);
+      let last = is_final(&msg);
+      host_ipc_sender.send((id, HostIpcMessage::Wire(msg))).await;
+      if last {
+        break;
+      }
+    }
+  };
+  join(client.request(req, &channel), forward).await;
+}
+
+fn http_event_message(event: HttpEvent) -> WireHostIpcMessage {
+  match event {
+    HttpEvent::Meta(meta) => WireHostIpcMessage::HttpResponseMeta(meta),
+    HttpEvent::Chunk(chunk) => WireHostIpcMessage::HttpResponseBody(chunk),
+    HttpEvent::Done => WireHostIpcMessage::HttpResponseComplete,
+    HttpEvent::Error => WireHostIpcMessage::HttpError,
+  }
+}
+
+/// Whether `msg` is the last one a request produces.
+fn is_final(msg: &WireHostIpcMessage) -> bool {
+  matches!(msg, WireHostIpcMessage::HttpResponseComplete | WireHostIpcMessage::HttpError)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use crate::platform::HttpClient;
+  use crate::protocol::HostIpcChannel;
+  use alloc::sync::Arc;
+  use core::fmt;
+
+  /// A client whose requests never finish.
+  struct StalledClient;
+
+  impl fmt::Debug for StalledClient {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+      f.write_str("StalledClient")
+    }
+  }
+
+  impl HttpClient for StalledClient {
+    fn request<'a>(&'a self, _req: HttpRequest, _channel: &'a HttpEventChannel) -> Pin<Box<dyn Future<Output = ()> + 'a>> {
+      Box::pin(pending())
+    }
+  }
+
+  fn sender() -> HostIpcSender {
+    Box::leak(Box::new(HostIpcChannel::new())).sender()
+  }
+
+  fn request() -> HttpRequest {
+    HttpRequest::new("http://badge.local/weather.json".into())
+  }
+
+  #[test]
+  fn request_without_a_client_fails() {
+    let mut http = GuestHttp::new(None, sender());
+    assert!(matches!(http.start(2, request()), Some((2, WireHostIpcMessage::HttpError))));
+  }
+
+  #[test]
+  fn requests_over_the_limit_are_refused() {
+    let client = HttpClientHandle::new(Arc::new(StalledClient));
+    let mut http = GuestHttp::new(Some(client), sender());
+    for id in 0..MAX_HTTP_REQUESTS as u32 {
+      assert!(http.start(id + 2, request()).is_none());
+    }
+    assert!(matches!(http.start(9, request()), Some((9, WireHostIpcMessage::Busy))));
+
+    http.cancel_all();
+    assert!(http.start(10, request()).is_none());
+  }
+
+  #[test]
+  fn done_and_error_events_end_the_request() {
+    assert!(is_final(&http_event_message(HttpEvent::Done)));
+    assert!(is_final(&http_event_message(HttpEvent::Error)));
+    assert!(!is_final(&http_event_message(HttpEvent::Chunk(alloc::vec![1, 2]))));
+  }
+}
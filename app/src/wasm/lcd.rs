@@ -0,0 +1,148 @@
+use crate::platform::display::FrameRegion;
+
+/// Raw updates the display refused while it was busy drawing a screen of its
+/// own, to send again before the next tick. Only where they came from is
+/// kept: the guest's frame stays in its memory, so sending the same area
+/// again shows what it presented, or anything it drew over it since.
+#[derive(Debug, Default)]
+pub struct MissedLcd {
+  missed: Option<Missed>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Missed {
+  /// Guest address the screen's top-left pixel would have, with rows
+  /// `stride` apart. Wraps for a buffer holding just the region.
+  origin: u32,
+  stride: u32,
+  region: FrameRegion,
+}
+
+impl MissedLcd {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  /// Remember an update of `region` from guest address `ptr`, rows `stride`
+  /// bytes apart. Updates from the same buffer merge into the area covering
+  /// both; one from another buffer replaces what was missed before.
+  pub fn record(&mut self, region: FrameRegion, ptr: u32, stride: u32) {
+    let origin = ptr.wrapping_sub(offset(region, stride));
+    self.missed = Some(match self.missed {
+      Some(missed) if missed.origin == origin && missed.stride == stride => Missed {
+        region: bounding_box(missed.region, region),
+        ..missed
+      },
+      _ => Missed { origin, stride, region },
+    });
+  }
+
+  /// The area to send again, the guest address of its first pixel and its
+  /// stride, forgetting it.
+  pub fn take(&mut self) -> Option<(FrameRegion, u32, u32)> {
+    let missed = self.missed.take()?;
+    Some((
+      missed.region,
+      missed.origin.wrapping_add(offset(missed.region, missed.stride)),
+      missed.stride,
+    ))
+  }
+}
+
+/// Bytes from the screen's top-left pixel to `region`'s in a buffer with
+/// rows `stride` apart.
+fn offset(region: FrameRegion, stride: u32) -> u32 {
+  (region.y as u32).wrapping_mul(stride).wrapping_add(region.x as u32 * 2)
+}
+
+fn bounding_box(a: FrameRegion, b: FrameRegion) -> FrameRegion {
+  let x = a.x.min(b.x);
+  let y = a.y.min(b.y);
+  FrameRegion {
+    x,
+    y,
+    width: (a.x + a.width).max(b.x + b.width) - x,
+    height: (a.y + a.height).max(b.y + b.height) - y,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn updates_from_one_buffer_merge() {
+    let mut missed = MissedLcd::new();
+    let frame = 0x1000;
+    let at = |x: u32, y: u32| frame + y * 480 + x * 2;
+    missed.record(
+      FrameRegion {
+        x: 10,
+        y: 20,
+        width: 5,
+        height: 5,
+      },
+      at(10, 20),
+      480,
+    );
+    missed.record(
+      FrameRegion {
+        x: 100,
+        y: 4,
+        width: 10,
+        height: 2,
+      },
+      at(100, 4),
+      480,
+    );
+    assert_eq!(
+      missed.take(),
+      Some((
+        FrameRegion {
+          x: 10,
+          y: 4,
+          width: 100,
+          height: 21
+        },
+        at(10, 4),
+        480
+      ))
+    );
+    assert_eq!(missed.take(), None);
+
+    // A buffer holding just the region, elsewhere, replaces them.
+    missed.record(
+      FrameRegion {
+        x: 10,
+        y: 20,
+        width: 5,
+        height: 5,
+      },
+      at(10, 20),
+      480,
+    );
+    missed.record(
+      FrameRegion {
+        x: 200,
+        y: 200,
+        width: 8,
+        height: 8,
+      },
+      0x40,
+      16,
+    );
+    assert_eq!(
+      missed.take(),
+      Some((
+        FrameRegion {
+          x: 200,
+          y: 200,
+          width: 8,
+          height: 8
+        },
+        0x40,
+        16
+      ))
+    );
+  }
+}
@@ -0,0 +1,75 @@
+use crate::types::{LedRequest, LedState, NUM_LEDS};
+use wasm_protocol::{LedColour, LedEffect};
+
+/// Guest-side view of the LED ring. Individual LED writes only update this
+/// buffer; the runner flushes it to the host once per `tick` so a guest
+/// setting all twelve LEDs costs one LED request, not twelve.
+pub struct GuestLeds {
+  states: [LedState; NUM_LEDS],
+  dirty: bool,
+  touched: bool,
+}
+
+impl GuestLeds {
+  pub fn new() -> Self {
+    Self {
+      states: [LedState::new(0, 0, 0); NUM_LEDS],
+      dirty: false,
+      touched: false,
+    }
+  }
+
+  /// Set one LED. Out-of-range indices are ignored.
+  pub fn set(&mut self, index: u32, colour: LedColour) {
+    if let Some(state) = self.states.get_mut(index as usize) {
+      *state = LedState::new(colour.r, colour.g, colour.b);
+      self.dirty = true;
+      self.touched = true;
+    }
+  }
+
+  /// Set every LED from `r, g, b` byte triples.
+  pub fn set_all(&mut self, rgb: &[u8]) {
+    for (state, chunk) in self.states.iter_mut().zip(rgb.chunks_exact(3)) {
+      *state = LedState::new(chunk[0], chunk[1], chunk[2]);
+    }
+    self.dirty = true;
+    self.touched = true;
+  }
+
+  /// Record that the guest picked a built-in effect, which supersedes any
+  /// pending per-LED writes.
+  pub fn set_effect(&mut self) {
+    self.dirty = false;
+    self.touched = true;
+  }
+
+  /// The pending per-LED frame, if any was written since the last flush.
+  pub fn pending(&self) -> Option<&[LedState; NUM_LEDS]> {
+    if self.dirty { Some(&self.states) } else { None }
+  }
+
+  pub fn mark_flushed(&mut self) {
+    self.dirty = false;
+  }
+
+  /// Whether the guest changed the LEDs at all during this session.
+  pub fn touched(&self) -> bool {
+    self.touched
+  }
+}
+
+/// Map a guest effect selection onto the host's `LedRequest`.
+pub fn led_effect_request(effect: LedEffect, colour: LedColour) -> LedRequest {
+  let colour = LedState::new(colour.r, colour.g, colour.b);
+  match effect {
+    LedEffect::Off => LedRequest::Off,
+    LedEffect::Solid => LedRequest::Solid(colour),
+    LedEffect::Rainbow => LedRequest::Rainbow,
+    LedEffect::Breathe => LedRequest::Breathe(colour),
+    LedEffect::Chase => LedRequest::Chase(colour),
+    LedEffect::Sparkle => LedRequest::Sparkle(colour),
+    LedEffect::TheaterChase => LedRequest::TheaterChase(colour),
+    LedEffect::Fire => LedRequest::Fire,
+  }
+}
@@ -0,0 +1,253 @@
+use core::fmt;
+use log::warn;
+use wasm_protocol::{APP_METADATA_SECTION, AppLimits, AppMetadata, find_custom_section};
+use wasmi::ResourceLimiter;
+use wasmi_core::LimiterError;
+
+/// Default cap on a guest's linear memory. Comfortably fits a couple of
+/// full-screen framebuffers plus game state while leaving most of PSRAM to
+/// the rest of the firmware.
+pub const DEFAULT_MAX_MEMORY_BYTES: usize = 2 * 1024 * 1024;
+
+/// Default cap on the element count of any guest table.
+pub const DEFAULT_MAX_TABLE_ELEMENTS: usize = 4 * 1024;
+
+/// Default fuel per `tick` (and for `wasm_main`). One unit is roughly one
+/// interpreted instruction, so this allows a few seconds of work on the
+/// badge before the guest is considered stuck.
+pub const DEFAULT_FUEL_PER_TICK: u64 = 20_000_000;
+
+/// Default cap on the size of a module file. The whole module is held in
+/// PSRAM while the app runs, next to its linear memory.
+pub const DEFAULT_MAX_MODULE_BYTES: usize = 1024 * 1024;
+
+/// The most linear memory an app can ask for in its [`AppLimits`]. Half of
+/// PSRAM, so the module and the menu still fit.
+pub const MAX_APP_MEMORY_BYTES: usize = 4 * 1024 * 1024;
+
+/// The most table elements an app can ask for.
+pub const MAX_APP_TABLE_ELEMENTS: usize = 16 * 1024;
+
+/// The most fuel per `tick` an app can ask for: ten times the default, so a
+/// stuck app is still stopped within a minute or so.
+pub const MAX_APP_FUEL_PER_TICK: u64 = 10 * DEFAULT_FUEL_PER_TICK;
+
+/// Resource limits applied to one launched WASM app.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct WasmLimits {
+  pub max_memory_bytes: usize,
+  pub max_table_elements: usize,
+  /// Fuel refilled before every call into the guest. `None` disables fuel
+  /// metering, which makes the interpreter slightly faster.
+  pub fuel_per_tick: Option<u64>,
+  /// Largest module file that will be loaded from storage.
+  pub max_module_bytes: usize,
+}
+
+impl Default for WasmLimits {
+  fn default() -> Self {
+    Self {
+      max_memory_bytes: DEFAULT_MAX_MEMORY_BYTES,
+      max_table_elements: DEFAULT_MAX_TABLE_ELEMENTS,
+      fuel_per_tick: Some(DEFAULT_FUEL_PER_TICK),
+      max_module_bytes: DEFAULT_MAX_MODULE_BYTES,
+    }
+  }
+}
+
+impl WasmLimits {
+  /// The limits for an app asking for `requested` in its metadata: the
+  /// defaults, with each non-zero request in place of its default, up to the
+  /// `MAX_APP_*` ceilings.
+  pub fn for_app(requested: &AppLimits) -> Self {
+    let pick = |requested: u32, default: usize, max: usize| match requested {
+      0 => default,
+      requested => (requested as usize).min(max),
+    };
+    let fuel_per_tick = match requested.fuel_per_tick {
+      0 => DEFAULT_FUEL_PER_TICK,
+      fuel => (fuel as u64).min(MAX_APP_FUEL_PER_TICK),
+    };
+    Self {
+      max_memory_bytes: pick(
+        requested.memory_kb.saturating_mul(1024),
+        DEFAULT_MAX_MEMORY_BYTES,
+        MAX_APP_MEMORY_BYTES,
+      ),
+      max_table_elements: pick(requested.table_elements, DEFAULT_MAX_TABLE_ELEMENTS, MAX_APP_TABLE_ELEMENTS),
+      fuel_per_tick: Some(fuel_per_tick),
+      ..Self::default()
+    }
+  }
+
+  /// [`for_app`](Self::for_app) with the limits in `metadata`, or the
+  /// defaults for an app that declares none.
+  pub fn for_metadata(metadata: Option<&AppMetadata>) -> Self {
+    metadata.map(|metadata| Self::for_app(&metadata.limits)).unwrap_or_default()
+  }
+
+  /// The limits for a module already in memory, from the metadata it
+  /// declares.
+  pub fn for_module(module: &[u8]) -> Self {
+    let metadata = find_custom_section(module, APP_METADATA_SECTION).and_then(|section| AppMetadata::decode(section).ok());
+    Self::for_metadata(metadata.as_ref())
+  }
+
+  pub fn with_max_memory(mut self, bytes: usize) -> Self {
+    self.max_memory_bytes = bytes;
+    self
+  }
+
+  pub fn with_max_table_elements(mut self, elements: usize) -> Self {
+    self.max_table_elements = elements;
+    self
+  }
+
+  pub fn with_fuel_per_tick(mut self, fuel: Option<u64>) -> Self {
+    self.fuel_per_tick = fuel;
+    self
+  }
+
+  pub fn with_max_module(mut self, bytes: usize) -> Self {
+    self.max_module_bytes = bytes;
+    self
+  }
+}
+
+/// Which limit stopped a guest.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum LimitExceeded {
+  Memory { desired: usize, max: usize },
+  Table { desired: usize, max: usize },
+  Fuel,
+}
+
+impl fmt::Display for LimitExceeded {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Memory { desired, max } => write!(f, "Out of memory ({}/{} KB)", desired / 1024, max / 1024),
+      Self::Table { desired, max } => write!(f, "Table too large ({desired}/{max})"),
+      Self::Fuel => f.write_str("App stopped responding"),
+    }
+  }
+}
+
+/// `ResourceLimiter` enforcing [`WasmLimits`]. Denied growth makes the
+/// guest's `memory.grow` fail, which ends in a trap; the denial is recorded
+/// so the runner can tell the user why the app stopped.
+pub struct MyLimiter {
+  limits: WasmLimits,
+  exceeded: Option<LimitExceeded>,
+}
+
+impl MyLimiter {
+  pub fn new(limits: WasmLimits) -> Self {
+    Self { limits, exceeded: None }
+  }
+
+  pub fn limits(&self) -> &WasmLimits {
+    &self.limits
+  }
+
+  /// The first limit the guest ran into, if any.
+  pub fn exceeded(&self) -> Option<LimitExceeded> {
+    self.exceeded
+  }
+
+  pub fn record_out_of_fuel(&mut self) {
+    self.exceeded.get_or_insert(LimitExceeded::Fuel);
+  }
+}
+
+impl ResourceLimiter for MyLimiter {
+  fn memory_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool, LimiterError> {
+    log::debug!("memory_growing: current={current} desired={desired}");
+    let max = self.limits.max_memory_bytes;
+    if desired > max {
+      warn!("memory_growing: denied, {desired} bytes exceeds limit of {max}");
+      self.exceeded.get_or_insert(LimitExceeded::Memory { desired, max });
+      return Ok(false);
+    }
+    Ok(true)
+  }
+
+  fn table_growing(&mut self, current: usize, desired: usize, _maximum: Option<usize>) -> Result<bool, LimiterError> {
+    log::debug!("table_growing: current={current} desired={desired}");
+    let max = self.limits.max_table_elements;
+    if desired > max {
+      warn!("table_growing: denied, {desired} elements exceeds limit of {max}");
+      self.exceeded.get_or_insert(LimitExceeded::Table { desired, max });
+      return Ok(false);
+    }
+    Ok(true)
+  }
+
+  // A launch is always a single module with one memory and one table.
+  fn instances(&self) -> usize {
+    1
+  }
+
+  fn tables(&self) -> usize {
+    1
+  }
+
+  fn memories(&self) -> usize {
+    1
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn growth_within_limits_is_allowed() {
+    let mut limiter = MyLimiter::new(WasmLimits::default().with_max_memory(64 * 1024));
+    assert!(matches!(limiter.memory_growing(0, 64 * 1024, None), Ok(true)));
+    assert!(matches!(limiter.table_growing(0, 16, None), Ok(true)));
+    assert_eq!(limiter.exceeded(), None);
+  }
+
+  #[test]
+  fn growth_past_limits_is_denied_and_recorded() {
+    let mut limiter = MyLimiter::new(WasmLimits::default().with_max_memory(64 * 1024));
+    assert!(matches!(limiter.memory_growing(64 * 1024, 128 * 1024, None), Ok(false)));
+    assert_eq!(
+      limiter.exceeded(),
+      Some(LimitExceeded::Memory {
+        desired: 128 * 1024,
+        max: 64 * 1024
+      })
+    );
+  }
+
+  #[test]
+  fn app_requests_replace_defaults_up_to_the_ceilings() {
+    assert_eq!(WasmLimits::for_app(&AppLimits::DEFAULT), WasmLimits::default());
+
+    let limits = WasmLimits::for_app(&AppLimits {
+      memory_kb: 3 * 1024,
+      table_elements: 1_000_000,
+      fuel_per_tick: 0,
+    });
+    assert_eq!(limits.max_memory_bytes, 3 * 1024 * 1024);
+    assert_eq!(limits.max_table_elements, MAX_APP_TABLE_ELEMENTS);
+    assert_eq!(limits.fuel_per_tick, Some(DEFAULT_FUEL_PER_TICK));
+
+    let limits = WasmLimits::for_app(&AppLimits {
+      memory_kb: u32::MAX,
+      fuel_per_tick: u32::MAX,
+      ..AppLimits::DEFAULT
+    });
+    assert_eq!(limits.max_memory_bytes, MAX_APP_MEMORY_BYTES);
+    assert_eq!(limits.fuel_per_tick, Some(MAX_APP_FUEL_PER_TICK));
+  }
+
+  #[test]
+  fn first_exceeded_limit_is_kept() {
+    let mut limiter = MyLimiter::new(WasmLimits::default().with_max_table_elements(8));
+    assert!(matches!(limiter.table_growing(8, 9, None), Ok(false)));
+    limiter.record_out_of_fuel();
+    assert_eq!(limiter.exceeded(), Some(LimitExceeded::Table { desired: 9, max: 8 }));
+  }
+}
@@ -0,0 +1,82 @@
+use crate::alloc_ext::external_vec;
+use crate::platform::{FsError, StorageHandle};
+use alloc::string::ToString;
+use alloc::vec::Vec;
+use core::fmt;
+use log::debug;
+
+/// Bytes read from storage at a time while loading a module, so only the
+/// module itself needs a large buffer.
+const LOAD_CHUNK_BYTES: usize = 16 * 1024;
+
+/// Why an app's module couldn't be loaded from storage.
+#[derive(Debug, PartialEq)]
+pub enum ModuleLoadError {
+  Storage(FsError),
+  Empty,
+  TooLarge {
+    size: usize,
+    max: usize,
+  },
+  /// The file got shorter while it was being read.
+  Truncated,
+}
+
+impl fmt::Display for ModuleLoadError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Storage(FsError::NotFound) => f.write_str("App file not found"),
+      Self::Storage(err) => write!(f, "Can't read app ({err:?})"),
+      Self::Empty => f.write_str("App file is empty"),
+      Self::TooLarge { size, max } => write!(f, "App too large ({}/{} KB)", size.div_ceil(1024), max / 1024),
+      Self::Truncated => f.write_str("App file changed while loading"),
+    }
+  }
+}
+
+/// Read the whole module in `file_name` into external memory (PSRAM on the
+/// firmware), a chunk at a time. Files over `max_bytes` are rejected before
+/// anything is read.
+pub async fn load_module(storage: &StorageHandle, file_name: &str, max_bytes: usize) -> Result<Vec<u8>, ModuleLoadError> {
+  let size = storage
+    .get_file_size(file_name.to_string())
+    .await
+    .map_err(ModuleLoadError::Storage)? as usize;
+  if size == 0 {
+    return Err(ModuleLoadError::Empty);
+  }
+  if size > max_bytes {
+    return Err(ModuleLoadError::TooLarge { size, max: max_bytes });
+  }
+
+  let mut module = external_vec(size);
+  let mut pos = 0;
+  while pos < size {
+    let len = (size - pos).min(LOAD_CHUNK_BYTES);
+    let chunk = storage
+      .read_binary_chunk(file_name.to_string(), pos as u32, len as u32)
+      .await
+      .map_err(ModuleLoadError::Storage)?;
+    if chunk.len() != len {
+      return Err(ModuleLoadError::Truncated);
+    }
+    module[pos..pos + len].copy_from_slice(&chunk);
+    pos += len;
+  }
+  debug!("load_module: {file_name}, {size} bytes");
+  Ok(module)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn too_large_rounds_the_size_up() {
+    let err = ModuleLoadError::TooLarge {
+      size: 1024 * 1024 + 1,
+      max: 1024 * 1024,
+    };
+    assert_eq!(err.to_string(), "App too large (1025/1024 KB)");
+  }
+}
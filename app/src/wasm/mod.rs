@@ -1,69 +1,238 @@
+pub mod abi;
 pub mod context;
+pub mod fault;
 pub mod host;
+pub mod http;
+pub mod lcd;
+pub mod leds;
+pub mod limits;
+pub mod loader;
+pub mod overlay;
+pub mod profiler;
+pub mod sockets;
+pub mod stdout;
+pub mod storage;
 pub mod timers;
+#[cfg(feature = "wasi")]
+pub mod wasi;
+pub mod widgets;
 
+pub use abi::*;
 pub use context::*;
+pub use fault::*;
 pub use host::WasmHost;
+pub use http::*;
+pub use lcd::*;
+pub use leds::*;
+pub use limits::*;
+pub use loader::*;
+pub use overlay::*;
+pub use profiler::*;
+pub use sockets::*;
+pub use stdout::*;
+pub use storage::*;
 pub use timers::*;
+#[cfg(feature = "wasi")]
+pub use wasi::*;
+pub use widgets::*;
 
+use crate::platform::display::{FRAME_BYTES, FrameRegion};
+use crate::platform::{CrashReport, StorageHandle};
 use crate::protocol::*;
-use alloc::{boxed::Box, format, string::String, vec::Vec};
-use embassy_futures::yield_now;
-use log::{debug, info};
-use wasm_protocol::{HostIpcMessage as WireHostIpcMessage, WasmIpcMessage as WireWasmIpcMessage};
-use wasmi::{Caller, Engine, Extern, Linker, Module, Store};
+use crate::utils::select_timeout;
+use alloc::{
+  boxed::Box,
+  format,
+  string::{String, ToString},
+  vec::Vec,
+};
+use display_types::{Icon40, LcdScreen};
+use embassy_futures::{block_on, select::select, yield_now};
+use log::{debug, error, info};
+use wasm_protocol::{
+  BUSY_ABI_VERSION, Capabilities, HOST_ABI_VERSION, HostIpcMessage as WireHostIpcMessage, KEY_EVENTS_ABI_VERSION, LedColour, LedEffect,
+  MAX_STORAGE_KEY_LEN, NUM_LEDS, StorageError, WIRE_FORMAT_EXPORT, WasmIpcMessage as WireWasmIpcMessage, WireFormat,
+};
+use wasmi::{CallHook, Caller, Config, Engine, Extern, ExternType, Instance, Linker, Module, Store};
+
+/// Longest panic message or file name read from the guest.
+const PANIC_TEXT_MAX: u32 = 256;
+
+/// How long the "app stopped" error screen stays up if the user does not
+/// dismiss it with a button press.
+const ERROR_SCREEN_TIMEOUT_MS: u64 = 5_000;
+
+/// Fuel a call to a profile hook costs the guest: the base cost of the call
+/// instruction. The `i32.const` and `block` around it compile to nothing.
+const PROFILE_HOOK_FUEL: u64 = 1;
 
 /// Runs a WASM binary through the wasmi interpreter.
 ///
 /// Platform-specific operations are delegated to `host`. IPC communication
 /// with the host system (HTTP requests, button events, lifecycle) happens
 /// through the embassy channel pairs.
+///
+/// The guest runs under `limits`. If it traps, including by hitting one of
+/// those limits, the reason is shown on an error screen until the user
+/// presses a button; `WasmIpcMessage::Stopped` is sent however the guest ends.
 pub async fn wasmi_runner<H: WasmHost>(
   host: H,
   wasm_ipc_sender: WasmIpcSender,
   host_ipc_receiver: HostIpcReceiver,
+  storage: Option<AppStorage>,
+  limits: WasmLimits,
   buf: Vec<u8>,
 ) -> Result<(), wasmi::Error> {
-  debug!("wasmi_runner: creating engine, buf={} bytes", buf.len());
+  debug!("wasmi_runner: creating engine, buf={} bytes, limits={limits:?}", buf.len());
+  let started_us = host.get_micros();
 
-  let engine = Box::new(Engine::default());
+  let mut config = Config::default();
+  config.consume_fuel(limits.fuel_per_tick.is_some());
+  let engine = Box::new(Engine::new(&config));
 
   let wasm_ctx = WasmCtx {
     counter: 1,
     last_screen_update: 0,
     timer_registry: TimerRegistry::new(),
+    idle: false,
+    widget_mode: false,
+    leds: GuestLeds::new(),
+    storage,
+    wire_format: WireFormat::Json,
+    stdout: StdoutTail::new(),
+    panic: None,
     host_ipc_receiver: host_ipc_receiver.clone(),
     wasm_ipc_sender,
-    limiter: MyLimiter,
+    limiter: MyLimiter::new(limits),
+    profiler: Profiler::new(started_us),
+    overlay: None,
+    missed_lcd: MissedLcd::new(),
+    #[cfg(feature = "wasi")]
+    wasi: WasiFiles::new(),
     host,
   };
 
-  let mut linker = <Linker<WasmCtx<H>>>::new(&engine);
+  let mut store = Store::new(&engine, wasm_ctx);
+  store.limiter(|ctx| &mut ctx.limiter);
+
+  let buf = store.data_mut().host.prepare_module(buf);
+  let result = run_guest(&engine, &mut store, &host_ipc_receiver, &buf).await;
+  #[cfg(feature = "wasi")]
+  {
+    let ctx = store.data_mut();
+    ctx.wasi.close_all(ctx.storage.as_ref());
+  }
+  let ctx = store.data();
+  info!("{}", ctx.profiler.summary(ctx.host.get_micros()));
+
+  store.data_mut().release_leds();
+  if let Err(err) = &result {
+    let reason = exit_reason(&mut store, err);
+    error!("WASM: Program stopped: {reason} ({err})");
+    let ctx = store.data_mut();
+    let stdout = ctx.stdout.lines();
+    ctx.host.report_crash(CrashReport {
+      reason: reason.clone(),
+      stdout,
+    });
+    let ctx = store.data();
+    show_error_screen(&ctx.wasm_ipc_sender, &ctx.host_ipc_receiver, reason).await;
+  }
+  store.data().wasm_ipc_sender.send((0, WasmIpcMessage::Stopped)).await;
+  result
+}
+
+/// Loads the module in `file_name` with [`load_module`] and runs it with
+/// [`wasmi_runner`], giving it its own [`AppStorage`].
+///
+/// A module that can't be loaded, for instance because it is bigger than
+/// `limits.max_module_bytes`, is reported like a crash: the reason goes on the
+/// error screen and `WasmIpcMessage::Stopped` is still sent.
+pub async fn wasmi_file_runner<H: WasmHost>(
+  mut host: H,
+  wasm_ipc_sender: WasmIpcSender,
+  host_ipc_receiver: HostIpcReceiver,
+  storage: StorageHandle,
+  file_name: &str,
+  limits: WasmLimits,
+) -> Result<(), wasmi::Error> {
+  match load_module(&storage, file_name, limits.max_module_bytes).await {
+    Ok(buf) => {
+      let app_storage = AppStorage::new(storage, file_name);
+      wasmi_runner(host, wasm_ipc_sender, host_ipc_receiver, Some(app_storage), limits, buf).await
+    }
+    Err(err) => {
+      let reason = err.to_string();
+      error!("WASM: Failed to load {file_name}: {reason}");
+      host.report_crash(CrashReport {
+        reason: reason.clone(),
+        stdout: Vec::new(),
+      });
+      show_error_screen(&wasm_ipc_sender, &host_ipc_receiver, reason.clone()).await;
+      wasm_ipc_sender.send((0, WasmIpcMessage::Stopped)).await;
+      Err(wasmi::Error::new(reason))
+    }
+  }
+}
+
+async fn run_guest<H: WasmHost>(
+  engine: &Engine,
+  store: &mut Store<WasmCtx<H>>,
+  host_ipc_receiver: &HostIpcReceiver,
+  buf: &[u8],
+) -> Result<(), wasmi::Error> {
+  let mut linker = <Linker<WasmCtx<H>>>::new(engine);
   debug!("wasmi_runner: registering host functions");
   register_host_functions(&mut linker)?;
 
   debug!("wasmi_runner: compiling module");
-  let module = Box::new(unsafe { Module::new_unchecked(&engine, &buf) }?);
+  let module = Box::new(unsafe { Module::new_unchecked(engine, buf) }?);
+  let abi_version = check_module_abi(&module)?;
+  debug!("wasmi_runner: guest needs host ABI v{abi_version}, host is v{HOST_ABI_VERSION}");
+  if module.imports().any(|import| import.module() == PROFILE_IMPORT_MODULE) {
+    time_host_calls(store, &module);
+  }
+  #[cfg(feature = "wasi")]
+  stub_unsupported_functions(&mut linker, &module)?;
 
-  debug!("wasmi_runner: creating store and instantiating");
-  let mut store = Store::new(&engine, wasm_ctx);
-  store.limiter(|ctx| &mut ctx.limiter);
+  debug!("wasmi_runner: instantiating");
+  refuel(store)?;
+  let instance = linker.instantiate_and_start(&mut *store, &module)?;
 
-  let instance = linker.instantiate_and_start(&mut store, &module)?;
+  let wire_format = guest_wire_format(&instance, store)?;
+  debug!("wasmi_runner: guest speaks {wire_format:?}");
+  store.data_mut().wire_format = wire_format;
 
-  let wasm_main = instance
-    .get_export(&store, "wasm_main")
-    .and_then(Extern::into_func)
-    .ok_or(wasmi::Error::new("WASM: `wasm_main` not found"))?;
-  debug!("wasmi_runner: calling wasm_main");
+  let export = |name| {
+    instance
+      .get_export(&*store, name)
+      .and_then(Extern::into_func)
+      .map(|func| (name, func))
+  };
+  // WASI reactors set up libc in `_initialize` before anything else runs.
+  let initialize = export("_initialize");
+  let (entry_name, entry) = export("wasm_main")
+    .or_else(|| export("_start"))
+    .ok_or(wasmi::Error::new("WASM: neither `wasm_main` nor `_start` found"))?;
 
-  let mut result = [];
-  wasm_main
-    .call(&mut store, &[], &mut result)
-    .map_err(|err| wasmi::Error::new(format!("WASM: Error calling `wasm_main`: {err}")))?;
+  for (name, func) in initialize.into_iter().chain([(entry_name, entry)]) {
+    debug!("wasmi_runner: calling {name}");
+    refuel(store)?;
+    if let Err(err) = func.call(&mut *store, &[], &mut []) {
+      return guest_call_failed(err, name);
+    }
+  }
 
-  debug!("wasmi_runner: wasm_main completed, entering tick loop");
-  let tick = instance.get_typed_func::<(u32, u32), i32>(&mut store, "tick")?;
+  // A `_start` program without a `tick` has done all its work.
+  let tick = match instance.get_typed_func::<(u32, u32), i32>(&*store, "tick") {
+    Ok(tick) => tick,
+    Err(_) if entry_name == "_start" => {
+      info!("WASM: Program complete after `_start`");
+      return Ok(());
+    }
+    Err(err) => return Err(err),
+  };
+  debug!("wasmi_runner: {entry_name} completed, entering tick loop");
 
   let mut tick_count = 0u64;
   loop {
@@ -71,12 +240,20 @@ pub async fn wasmi_runner<H: WasmHost>(
       Ok((host_msg_id, ref host_ipc_msg)) => {
         if let HostIpcMessage::Runtime(HostRuntimeCommand::Stop) = host_ipc_msg {
           info!("WASM: Program aborted by Stop message");
-          store.data().wasm_ipc_sender.send((0, WasmIpcMessage::Stopped)).await;
+          let _ = host_ipc_receiver.try_receive();
           return Ok(());
         }
         match host_ipc_msg {
+          HostIpcMessage::Wire(WireHostIpcMessage::Key(_)) if abi_version < KEY_EVENTS_ABI_VERSION => {
+            let _ = host_ipc_receiver.try_receive();
+            (0, 0)
+          }
+          HostIpcMessage::Wire(WireHostIpcMessage::Busy) if abi_version < BUSY_ABI_VERSION => {
+            let _ = host_ipc_receiver.try_receive();
+            (0, 0)
+          }
           HostIpcMessage::Wire(host_msg) => {
-            let host_msg_bytes = serde_json::to_vec(host_msg).unwrap();
+            let host_msg_bytes = encode_host_message(store.data().wire_format, host_msg)?;
             debug!("wasmi_runner: delivering host msg id={host_msg_id} len={}", host_msg_bytes.len());
             (host_msg_id, host_msg_bytes.len() as u32)
           }
@@ -89,103 +266,374 @@ pub async fn wasmi_runner<H: WasmHost>(
       Err(_) => (0, 0),
     };
 
-    if tick
-      .call(&mut store, (host_msg_id, host_msg_length))
-      .map_err(|err| wasmi::Error::new(format!("Error calling tick: {err}")))?
-      != 0
-    {
+    resend_missed_lcd(store, &instance);
+    refuel(store)?;
+    store.data_mut().idle = false;
+    let tick_started_us = store.data().host.get_micros();
+    let finished = match tick.call(&mut *store, (host_msg_id, host_msg_length)) {
+      Ok(finished) => finished != 0,
+      Err(err) => return guest_call_failed(err, "tick"),
+    };
+    let ctx = store.data_mut();
+    let presented = ctx.profiler.record_tick(tick_started_us, ctx.host.get_micros());
+    if finished {
       break;
     }
 
+    if presented && ctx.profiler.overlay_enabled() && !ctx.widget_mode {
+      ctx.draw_overlay();
+    }
+    store.data_mut().flush_leds();
+
     tick_count += 1;
     if tick_count % 1000 == 0 {
       debug!("wasmi_runner: {tick_count} ticks completed");
     }
 
-    yield_now().await;
+    if store.data().idle {
+      wait_for_event(store.data(), host_ipc_receiver).await;
+    } else {
+      yield_now().await;
+    }
   }
 
   info!("WASM: Program complete after {tick_count} ticks");
-  store.data().wasm_ipc_sender.send((0, WasmIpcMessage::Stopped)).await;
   Ok(())
 }
 
+/// The outcome of a call into the guest that failed with `err`. A guest
+/// built against WASI ends with `proc_exit`, which unwinds as an error: status
+/// 0 is a normal exit.
+fn guest_call_failed(err: wasmi::Error, name: &str) -> Result<(), wasmi::Error> {
+  match err.i32_exit_status() {
+    Some(0) => {
+      info!("WASM: Program exited from `{name}`");
+      Ok(())
+    }
+    Some(status) => Err(wasmi::Error::new(format!("App exited with status {status}"))),
+    None => Err(wasmi::Error::new(format!("WASM: Error calling `{name}`: {err}"))),
+  }
+}
+
+/// Sleep after a tick in which the guest went idle, until its earliest timer
+/// expires or a host message arrives. A guest with an LED frame the host has
+/// not taken yet is only yielded to, so the frame is retried next tick.
+async fn wait_for_event<H: WasmHost>(ctx: &WasmCtx<H>, host_ipc_receiver: &HostIpcReceiver) {
+  if ctx.leds.pending().is_some() {
+    yield_now().await;
+    return;
+  }
+  let message = host_ipc_receiver.ready_to_receive();
+  let now_ms = ctx.host.get_millis();
+  match ctx.timer_registry.next_expiry().map(|expiry| expiry.saturating_sub(now_ms)) {
+    Some(0) => yield_now().await,
+    wait_ms => {
+      select(message, ctx.host.idle(wait_ms)).await;
+    }
+  }
+}
+
+/// The wire format the guest declares through [`WIRE_FORMAT_EXPORT`], or JSON
+/// for guests that predate it.
+fn guest_wire_format<H: WasmHost>(instance: &Instance, store: &mut Store<WasmCtx<H>>) -> Result<WireFormat, wasmi::Error> {
+  let Ok(declared) = instance.get_typed_func::<(), u32>(&*store, WIRE_FORMAT_EXPORT) else {
+    return Ok(WireFormat::Json);
+  };
+  let value = declared.call(&mut *store, ())?;
+  WireFormat::from_u32(value).ok_or_else(|| wasmi::Error::new(format!("App needs unsupported wire format {value}")))
+}
+
+fn encode_host_message(format: WireFormat, msg: &WireHostIpcMessage) -> Result<Vec<u8>, wasmi::Error> {
+  format
+    .encode(msg)
+    .map_err(|err| wasmi::Error::new(format!("Encoding host message: {err:?}")))
+}
+
+/// Send the raw updates the display refused during the last tick again,
+/// from the guest's memory as it is now. Anything still refused waits for
+/// the next tick.
+fn resend_missed_lcd<H: WasmHost>(store: &mut Store<WasmCtx<H>>, instance: &Instance) {
+  let Some((region, ptr, stride)) = store.data_mut().missed_lcd.take() else {
+    return;
+  };
+  let Some(memory) = instance.get_memory(&*store, "memory") else {
+    return;
+  };
+  let (data, ctx) = memory.data_and_store_mut(&mut *store);
+  if ctx.widget_mode {
+    return;
+  }
+  // The merged area of two updates may not fit the buffer's rows.
+  let Ok((region, len)) = lcd_region(region.x.into(), region.y.into(), region.width.into(), region.height.into(), stride) else {
+    return;
+  };
+  let Ok(pixels) = guest_range(ptr, len, data.len()) else {
+    return;
+  };
+  let result = ctx.host.set_lcd_region(region, &data[pixels], stride as usize);
+  ctx.lcd_presented(result, region, ptr, stride);
+}
+
+/// Top the guest's fuel back up to its per-call budget.
+fn refuel<H: WasmHost>(store: &mut Store<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  match store.data().limiter.limits().fuel_per_tick {
+    Some(fuel) => store.set_fuel(fuel),
+    None => Ok(()),
+  }
+}
+
+/// The error-screen text for a guest panic reported through `extern_panic`.
+/// Guests that avoid `core::fmt` can only pass static messages, so the
+/// message may be empty.
+fn panic_reason(message: &str, file: &str, line: u32) -> String {
+  let location = match file {
+    "" => String::new(),
+    file => format!(" at {file}:{line}"),
+  };
+  match message {
+    "" => format!("App panicked{location}"),
+    message => format!("App panicked{location}: {message}"),
+  }
+}
+
+/// A short, user-facing explanation of why the guest stopped.
+fn exit_reason<H: WasmHost>(store: &mut Store<WasmCtx<H>>, err: &wasmi::Error) -> String {
+  if let Some(panic) = &store.data().panic {
+    return panic.clone();
+  }
+  if store.data().limiter.limits().fuel_per_tick.is_some() && matches!(store.get_fuel(), Ok(0)) {
+    store.data_mut().limiter.record_out_of_fuel();
+  }
+  match store.data().limiter.exceeded() {
+    Some(exceeded) => exceeded.to_string(),
+    None => err.to_string(),
+  }
+}
+
+/// Show `reason` until the user presses a button (or sends Stop), or
+/// [`ERROR_SCREEN_TIMEOUT_MS`] passes.
+async fn show_error_screen(wasm_ipc_sender: &WasmIpcSender, host_ipc_receiver: &HostIpcReceiver, reason: String) {
+  let screen = LcdScreen::Headline(Icon40::Error, reason);
+  wasm_ipc_sender.send((0, WasmIpcMessage::LcdScreen(screen))).await;
+
+  let dismissed = async {
+    loop {
+      match host_ipc_receiver.receive().await {
+        (_, HostIpcMessage::Wire(WireHostIpcMessage::HexButton(_))) | (_, HostIpcMessage::Runtime(HostRuntimeCommand::Stop)) => break,
+        _ => {}
+      }
+    }
+  };
+  select_timeout(dismissed, ERROR_SCREEN_TIMEOUT_MS).await;
+}
+
 fn register_host_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
   linker
     .func_wrap(
       "index",
       "extern_write_stdout",
-      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| {
-        let buffer = ReadWasmBuffer::read_memory(&caller, ptr, len);
-        let string = String::from_utf8_lossy(&buffer);
-        caller.data_mut().host.write_stdout(&string);
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| -> Result<(), wasmi::Error> {
+        let buffer = ReadWasmBuffer::read_memory(&caller, ptr, len)?;
+        caller.data_mut().write_stdout(&String::from_utf8_lossy(&buffer));
+        Ok(())
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_panic",
+      |mut caller: Caller<'_, WasmCtx<H>>,
+       msg_ptr: u32,
+       msg_len: u32,
+       file_ptr: u32,
+       file_len: u32,
+       line: u32|
+       -> Result<(), wasmi::Error> {
+        let message = ReadWasmBuffer::read_memory(&caller, msg_ptr, msg_len.min(PANIC_TEXT_MAX))?;
+        let file = ReadWasmBuffer::read_memory(&caller, file_ptr, file_len.min(PANIC_TEXT_MAX))?;
+        let panic = panic_reason(&String::from_utf8_lossy(&message), &String::from_utf8_lossy(&file), line);
+        caller.data_mut().panic = Some(panic.clone());
+        Err(wasmi::Error::new(panic))
       },
     )?
     .func_wrap(
       "index",
       "extern_set_gpio",
-      |mut caller: Caller<'_, WasmCtx<H>>, pin_number: u32, state: u32| {
-        caller.data_mut().host.set_gpio(pin_number, state);
+      |mut caller: Caller<'_, WasmCtx<H>>, pin_number: u32, state: u32| -> Result<(), wasmi::Error> {
+        Ok(caller.data_mut().host.set_gpio(pin_number, state)?)
       },
     )?
     .func_wrap("index", "extern_get_millis", |caller: Caller<'_, WasmCtx<H>>| -> u32 {
       caller.data().host.get_millis() as u32
     })?
-    .func_wrap("index", "extern_set_lcd_buffer", |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32| {
-      let memory = caller
-        .get_export("memory")
-        .and_then(|export| export.into_memory())
-        .ok_or_else(|| wasmi::Error::new("failed to find memory export"))
-        .unwrap();
-      let wasm_buffer = unsafe { core::slice::from_raw_parts(memory.data_ptr(&caller).add(ptr as usize), 240 * 240 * 2) };
-      caller.data_mut().host.set_lcd_buffer(wasm_buffer);
-    })?
+    .func_wrap(
+      "index",
+      "extern_set_lcd_buffer",
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32| -> Result<(), wasmi::Error> {
+        let memory = guest_memory(&caller)?;
+        let (data, ctx) = memory.data_and_store_mut(&mut caller);
+        let frame = guest_range(ptr, FRAME_BYTES, data.len())?;
+        if !ctx.widget_mode {
+          let started_us = ctx.host.get_micros();
+          let result = ctx.host.set_lcd_buffer(&data[frame]);
+          ctx.lcd_presented(result, FrameRegion::FULL, ptr, FrameRegion::FULL.row_bytes() as u32);
+          ctx.profiler.record_present(ctx.host.get_micros().saturating_sub(started_us));
+        }
+        Ok(())
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_set_lcd_region",
+      |mut caller: Caller<'_, WasmCtx<H>>, x: u32, y: u32, width: u32, height: u32, ptr: u32, stride: u32| -> Result<(), wasmi::Error> {
+        let (region, len) = lcd_region(x, y, width, height, stride)?;
+        let memory = guest_memory(&caller)?;
+        let (data, ctx) = memory.data_and_store_mut(&mut caller);
+        let pixels = guest_range(ptr, len, data.len())?;
+        if !ctx.widget_mode {
+          let started_us = ctx.host.get_micros();
+          let result = ctx.host.set_lcd_region(region, &data[pixels], stride as usize);
+          ctx.lcd_presented(result, region, ptr, stride);
+          ctx.profiler.record_present(ctx.host.get_micros().saturating_sub(started_us));
+        }
+        Ok(())
+      },
+    )?
     .func_wrap(
       "index",
       "extern_write_wasm_ipc_message",
-      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| -> u32 {
-        let wasm_msg_bytes = ReadWasmBuffer::read_memory(&caller, ptr, len);
-        let wasm_msg_id = caller.data().counter + 1;
-
-        let wire_msg: WireWasmIpcMessage = serde_json::from_slice(&wasm_msg_bytes).unwrap();
-
-        debug!("wasmi_runner: guest message received: {wire_msg:?}");
-
-        caller
-          .data()
-          .wasm_ipc_sender
-          .try_send((wasm_msg_id, WasmIpcMessage::Wire(wire_msg)))
-          .unwrap();
-
-        caller.data_mut().counter = wasm_msg_id;
-        wasm_msg_id
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| -> Result<u32, wasmi::Error> {
+        Ok(write_wasm_message(&mut caller, ptr, len)?)
+      },
+    )?
+    // Like `extern_write_wasm_ipc_message`, but a full queue returns 0 (never
+    // a message id) instead of trapping, so the guest can retry next tick.
+    .func_wrap(
+      "index",
+      "extern_try_write_wasm_ipc_message",
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| -> Result<u32, wasmi::Error> {
+        match write_wasm_message(&mut caller, ptr, len) {
+          Err(GuestFault::ChannelFull) => Ok(0),
+          result => Ok(result?),
+        }
       },
     )?
     .func_wrap(
       "index",
       "extern_read_host_ipc_message",
-      |mut caller: Caller<'_, WasmCtx<H>>, host_msg_id_a: u32, ptr: u32| {
-        let (host_msg_id_b, host_ipc_msg) = caller.data().host_ipc_receiver.try_receive().unwrap();
-
-        if host_msg_id_a != host_msg_id_b {
-          panic!("Mismatched host IDs! {host_msg_id_a} {host_msg_id_b}");
+      |mut caller: Caller<'_, WasmCtx<H>>, host_msg_id: u32, ptr: u32| -> Result<(), wasmi::Error> {
+        let host_msg = take_host_message(&caller.data().host_ipc_receiver, host_msg_id)?;
+        if let WireHostIpcMessage::HexButton(button) = &host_msg {
+          caller.data_mut().profiler.on_button(*button);
         }
-
-        let host_msg = match host_ipc_msg {
-          HostIpcMessage::Wire(msg) => msg,
-          HostIpcMessage::Runtime(_) => panic!("Runtime command reached guest read"),
-        };
-
-        let host_msg_bytes = serde_json::to_vec(&host_msg).unwrap();
-        ReadWasmBuffer::write_memory(&mut caller, ptr, &host_msg_bytes);
+        let host_msg_bytes = encode_host_message(caller.data().wire_format, &host_msg)?;
+        ReadWasmBuffer::write_memory(&mut caller, ptr, &host_msg_bytes)?;
+        Ok(())
       },
     )?;
 
+  register_abi_functions(linker)?;
   register_timer_functions(linker)?;
+  register_clock_functions(linker)?;
+  register_led_functions(linker)?;
+  register_storage_functions(linker)?;
+  register_profile_functions(linker)?;
+  #[cfg(feature = "wasi")]
+  register_wasi_functions(linker)?;
+
+  Ok(())
+}
 
+/// Decode the guest message at `ptr..ptr + len` and queue it for the host,
+/// returning the id it is sent under.
+fn write_wasm_message<H: WasmHost>(caller: &mut Caller<'_, WasmCtx<H>>, ptr: u32, len: u32) -> Result<u32, GuestFault> {
+  let wasm_msg_bytes = ReadWasmBuffer::read_memory(&*caller, ptr, len)?;
+  let wasm_msg_id = caller.data().counter + 1;
+
+  let wire_msg = decode_wasm_message(caller.data().wire_format, &wasm_msg_bytes)?;
+
+  debug!("wasmi_runner: guest message received: {wire_msg:?}");
+
+  let widget_mode = match wire_msg {
+    WireWasmIpcMessage::ShowScreen(_) => Some(true),
+    WireWasmIpcMessage::ShowFramebuffer => Some(false),
+    _ => None,
+  };
+
+  send_wasm_message(&caller.data().wasm_ipc_sender, wasm_msg_id, wire_msg)?;
+
+  if let Some(widget_mode) = widget_mode {
+    caller.data_mut().widget_mode = widget_mode;
+  }
+  caller.data_mut().counter = wasm_msg_id;
+  Ok(wasm_msg_id)
+}
+
+fn register_abi_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap("index", "extern_get_abi_version", |_caller: Caller<'_, WasmCtx<H>>| -> u32 {
+      HOST_ABI_VERSION
+    })?
+    .func_wrap("index", "extern_get_capabilities", |caller: Caller<'_, WasmCtx<H>>| -> u32 {
+      caller.data().capabilities().bits()
+    })?;
   Ok(())
 }
 
+/// Time each call an instrumented guest makes into the host, by the
+/// imported function it calls. Only the profile hooks say which one that is,
+/// so ordinary guests don't pay for a call hook.
+fn time_host_calls<H: WasmHost>(store: &mut Store<WasmCtx<H>>, module: &Module) {
+  let imports = module
+    .imports()
+    .filter(|import| matches!(import.ty(), ExternType::Func(_)) && import.module() != PROFILE_IMPORT_MODULE)
+    .map(|import| import.name().to_string())
+    .collect();
+  store.data_mut().profiler.time_host_calls(imports);
+  store.call_hook(|ctx, hook| {
+    match hook {
+      CallHook::CallingHost => ctx.profiler.host_call_started(ctx.host.get_micros()),
+      CallHook::ReturningFromHost => ctx.profiler.host_call_finished(ctx.host.get_micros()),
+      CallHook::CallingWasm | CallHook::ReturningFromWasm => {}
+    }
+    Ok(())
+  });
+}
+
+/// The hooks of a module the host instrumented in
+/// [`WasmHost::prepare_module`]. Unused by ordinary guests.
+fn register_profile_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap(
+      PROFILE_IMPORT_MODULE,
+      PROFILE_ENTER,
+      |mut caller: Caller<'_, WasmCtx<H>>, index: u32| -> Result<(), wasmi::Error> {
+        let ctx = caller.data_mut();
+        ctx.host.enter_function(index);
+        ctx.profiler.enter_function(index);
+        refund_profile_hook(&mut caller)
+      },
+    )?
+    .func_wrap(
+      PROFILE_IMPORT_MODULE,
+      PROFILE_LEAVE,
+      |mut caller: Caller<'_, WasmCtx<H>>| -> Result<(), wasmi::Error> {
+        caller.data_mut().host.leave_function();
+        refund_profile_hook(&mut caller)
+      },
+    )?;
+  Ok(())
+}
+
+/// Give back the fuel the guest spent calling a profile hook, so an
+/// instrumented guest runs out of fuel where the original would.
+fn refund_profile_hook<H: WasmHost>(caller: &mut Caller<'_, WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  match caller.get_fuel() {
+    Ok(fuel) => caller.set_fuel(fuel + PROFILE_HOOK_FUEL),
+    // Fuel isn't metered.
+    Err(_) => Ok(()),
+  }
+}
+
 fn register_timer_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
   linker
     .func_wrap(
@@ -214,7 +662,148 @@ fn register_timer_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Res
       |mut caller: Caller<'_, WasmCtx<H>>, timer_id: i32| {
         caller.data_mut().timer_registry.cancel(timer_id);
       },
+    )?
+    .func_wrap("index", "extern_idle", |mut caller: Caller<'_, WasmCtx<H>>| {
+      caller.data_mut().idle = true;
+    })?;
+
+  Ok(())
+}
+
+/// Time and randomness. The UNIX clock reads 0 until the host knows the time
+/// of day.
+fn register_clock_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap("index", "extern_get_micros", |caller: Caller<'_, WasmCtx<H>>| -> u64 {
+      caller.data().host.get_micros()
+    })?
+    .func_wrap("index", "extern_get_unix_micros", |caller: Caller<'_, WasmCtx<H>>| -> u64 {
+      caller.data().host.unix_time_micros().unwrap_or(0)
+    })?
+    .func_wrap(
+      "index",
+      "extern_fill_random",
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| -> Result<(), wasmi::Error> {
+        let memory = guest_memory(&caller)?;
+        let (data, ctx) = memory.data_and_store_mut(&mut caller);
+        let range = guest_range(ptr, len as usize, data.len())?;
+        ctx.host.fill_random(&mut data[range]);
+        Ok(())
+      },
     )?;
 
   Ok(())
 }
+
+fn register_led_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap(
+      "index",
+      "extern_set_led",
+      |mut caller: Caller<'_, WasmCtx<H>>, index: u32, rgb: u32| {
+        caller.data_mut().leds.set(index, LedColour::from_packed(rgb));
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_set_leds",
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32| -> Result<(), wasmi::Error> {
+        let rgb = ReadWasmBuffer::read_memory(&caller, ptr, (NUM_LEDS * 3) as u32)?;
+        caller.data_mut().leds.set_all(&rgb);
+        Ok(())
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_set_led_effect",
+      |mut caller: Caller<'_, WasmCtx<H>>, effect: u32, rgb: u32| {
+        let Some(effect) = LedEffect::from_u32(effect) else {
+          debug!("wasmi_runner: ignoring unknown LED effect {effect}");
+          return;
+        };
+        let request = led_effect_request(effect, LedColour::from_packed(rgb));
+        let ctx = caller.data_mut();
+        ctx.leds.set_effect();
+        if let Err(err) = ctx.host.set_led_effect(request) {
+          debug!("wasmi_runner: LED effect request dropped: {err:?}");
+        }
+      },
+    )?;
+
+  Ok(())
+}
+
+/// Storage calls block the interpreter until the filesystem answers. Values
+/// are small (bounded by the app's quota) so this is a few milliseconds at
+/// worst, and it keeps the guest API a plain synchronous call.
+fn register_storage_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap(
+      "index",
+      "extern_storage_read",
+      |mut caller: Caller<'_, WasmCtx<H>>, key_ptr: u32, key_len: u32, buf_ptr: u32, buf_len: u32| -> Result<i32, wasmi::Error> {
+        let Some(key) = read_storage_key(&caller, key_ptr, key_len)? else {
+          return Ok(StorageError::InvalidKey as i32);
+        };
+        let Some(storage) = caller.data().storage.clone() else {
+          return Ok(StorageError::Unavailable as i32);
+        };
+        match block_on(storage.read(&key)) {
+          Ok(value) => {
+            let copy_len = value.len().min(buf_len as usize);
+            ReadWasmBuffer::write_memory(&mut caller, buf_ptr, &value[..copy_len])?;
+            Ok(value.len() as i32)
+          }
+          Err(err) => Ok(err as i32),
+        }
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_storage_write",
+      |caller: Caller<'_, WasmCtx<H>>, key_ptr: u32, key_len: u32, buf_ptr: u32, buf_len: u32| -> Result<i32, wasmi::Error> {
+        let Some(key) = read_storage_key(&caller, key_ptr, key_len)? else {
+          return Ok(StorageError::InvalidKey as i32);
+        };
+        let Some(storage) = caller.data().storage.clone() else {
+          return Ok(StorageError::Unavailable as i32);
+        };
+        if buf_len > storage.quota() {
+          return Ok(StorageError::QuotaExceeded as i32);
+        }
+        let value = ReadWasmBuffer::read_memory(&caller, buf_ptr, buf_len)?;
+        match block_on(storage.write(&key, value)) {
+          Ok(()) => Ok(0),
+          Err(err) => Ok(err as i32),
+        }
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_storage_delete",
+      |caller: Caller<'_, WasmCtx<H>>, key_ptr: u32, key_len: u32| -> Result<i32, wasmi::Error> {
+        let Some(key) = read_storage_key(&caller, key_ptr, key_len)? else {
+          return Ok(StorageError::InvalidKey as i32);
+        };
+        let Some(storage) = caller.data().storage.clone() else {
+          return Ok(StorageError::Unavailable as i32);
+        };
+        match block_on(storage.delete(&key)) {
+          Ok(()) => Ok(0),
+          Err(err) => Ok(err as i32),
+        }
+      },
+    )?;
+
+  Ok(())
+}
+
+/// Read a storage key out of guest memory. Over-long or non-UTF-8 keys are
+/// `None` (rejected before anything is copied); an out-of-bounds key is a
+/// fault.
+fn read_storage_key<H: WasmHost>(caller: &Caller<'_, WasmCtx<H>>, ptr: u32, len: u32) -> Result<Option<String>, GuestFault> {
+  if len as usize > MAX_STORAGE_KEY_LEN {
+    return Ok(None);
+  }
+  Ok(String::from_utf8(ReadWasmBuffer::read_memory(caller, ptr, len)?).ok())
+}
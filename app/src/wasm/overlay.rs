@@ -0,0 +1,81 @@
+use crate::platform::display::{DISPLAY_WIDTH, FrameRegion};
+use alloc::{string::String, vec, vec::Vec};
+use core::convert::Infallible;
+use embedded_graphics::mono_font::{MonoTextStyle, ascii::FONT_6X10};
+use embedded_graphics::pixelcolor::{Rgb565, raw::RawU16};
+use embedded_graphics::prelude::*;
+use embedded_graphics::text::{Baseline, Text};
+
+/// Room for [`Profiler::overlay_text`](super::Profiler::overlay_text) in
+/// 6-pixel-wide characters.
+const OVERLAY_WIDTH: u16 = 18 * 6;
+const OVERLAY_HEIGHT: u16 = 12;
+
+/// Where the overlay is drawn: centred near the top, low enough to clear the
+/// round screen's edge.
+pub const OVERLAY_REGION: FrameRegion = FrameRegion {
+  x: (DISPLAY_WIDTH as u16 - OVERLAY_WIDTH) / 2,
+  y: 24,
+  width: OVERLAY_WIDTH,
+  height: OVERLAY_HEIGHT,
+};
+
+/// A line of white-on-black text in [`OVERLAY_REGION`], drawn over the guest's
+/// frames after each one it presents.
+pub struct Overlay {
+  /// The region's RGB565 pixels, rows packed.
+  pixels: Vec<u8>,
+  /// The text the pixels show.
+  text: Option<String>,
+}
+
+impl Default for Overlay {
+  fn default() -> Self {
+    Self {
+      pixels: vec![0; OVERLAY_REGION.row_bytes() * OVERLAY_REGION.height as usize],
+      text: None,
+    }
+  }
+}
+
+impl Overlay {
+  /// Render `text`, cut to fit, returning the region's pixels with rows
+  /// [`FrameRegion::row_bytes`] apart. Unchanged text isn't redrawn.
+  pub fn render(&mut self, text: String) -> &[u8] {
+    if self.text.as_ref() != Some(&text) {
+      self.pixels.fill(0);
+      let style = MonoTextStyle::new(&FONT_6X10, Rgb565::WHITE);
+      let _ = Text::with_baseline(&text, Point::new(0, 1), style, Baseline::Top).draw(&mut Pixels(&mut self.pixels));
+      self.text = Some(text);
+    }
+    &self.pixels
+  }
+}
+
+/// Draw target over the overlay's pixels.
+struct Pixels<'a>(&'a mut [u8]);
+
+impl OriginDimensions for Pixels<'_> {
+  fn size(&self) -> Size {
+    Size::new(OVERLAY_WIDTH as u32, OVERLAY_HEIGHT as u32)
+  }
+}
+
+impl DrawTarget for Pixels<'_> {
+  type Color = Rgb565;
+  type Error = Infallible;
+
+  fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+  where
+    I: IntoIterator<Item = Pixel<Self::Color>>,
+  {
+    for Pixel(point, color) in pixels {
+      if point.x < 0 || point.y < 0 || point.x >= OVERLAY_WIDTH as i32 || point.y >= OVERLAY_HEIGHT as i32 {
+        continue;
+      }
+      let i = (point.y as usize * OVERLAY_WIDTH as usize + point.x as usize) * 2;
+      self.0[i..i + 2].copy_from_slice(&RawU16::from(color).into_inner().to_be_bytes());
+    }
+    Ok(())
+  }
+}
@@ -0,0 +1,335 @@
+use alloc::{collections::BTreeMap, string::String, vec::Vec};
+use core::cmp::Reverse;
+use core::fmt;
+use wasm_protocol::HexButton;
+
+/// Import module of the hooks a host-instrumented guest calls on entering and
+/// leaving each of its functions. Guests never import these themselves.
+pub const PROFILE_IMPORT_MODULE: &str = "profile";
+/// `enter(function_index: i32)`, with the index in the uninstrumented module.
+/// Also called with an imported function's index just before a call to it.
+pub const PROFILE_ENTER: &str = "enter";
+/// `leave()`, pairing with the last `enter`.
+pub const PROFILE_LEAVE: &str = "leave";
+
+/// Holding both buttons toggles the frame-time overlay. The presses still
+/// reach the guest.
+pub const OVERLAY_CHORD: [HexButton; 2] = [HexButton::HexA, HexButton::HexF];
+
+/// The overlay shows figures averaged over this long.
+const OVERLAY_WINDOW_US: u64 = 1_000_000;
+
+/// Count, total and worst case of something timed repeatedly.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct Timing {
+  pub count: u64,
+  pub total_us: u64,
+  pub max_us: u64,
+}
+
+impl Timing {
+  pub fn record(&mut self, us: u64) {
+    self.count += 1;
+    self.total_us += us;
+    self.max_us = self.max_us.max(us);
+  }
+
+  pub fn mean_us(&self) -> u64 {
+    self.total_us.checked_div(self.count).unwrap_or(0)
+  }
+}
+
+/// What the overlay shows, from the last complete window.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+struct WindowStats {
+  fps_tenths: u64,
+  tick_mean_us: u64,
+  tick_max_us: u64,
+}
+
+/// Per-session timing of the guest: its ticks, its calls into the host and
+/// the frames it presents. Always collected, cheap next to the interpreter,
+/// except for host calls, which are only timed for an instrumented guest.
+#[derive(Debug, Default)]
+pub struct Profiler {
+  /// Each call to the guest's `tick`.
+  pub ticks: Timing,
+  /// Time spent in `extern_set_lcd_buffer` and `extern_set_lcd_region`.
+  pub presents: Timing,
+  /// Ticks that presented a frame.
+  pub frames: u64,
+  /// Time between the starts of ticks that presented a frame.
+  pub frame_intervals: Timing,
+  started_us: u64,
+  /// Names of the guest's imported functions, by function index.
+  imports: Vec<String>,
+  /// Calls into the host, by the index of the imported function.
+  host_calls: BTreeMap<u32, Timing>,
+  /// The import the profile hooks said the guest is about to call.
+  next_import: Option<u32>,
+  /// The import being called and when the call started.
+  host_call: Option<(u32, u64)>,
+  /// Start of the last tick that presented a frame.
+  last_frame_us: Option<u64>,
+  presented: bool,
+  window_started_us: u64,
+  window_frames: u64,
+  window_ticks: Timing,
+  window: WindowStats,
+  /// Which [`OVERLAY_CHORD`] buttons are held.
+  chord_held: [bool; 2],
+  overlay: bool,
+}
+
+impl Profiler {
+  pub fn new(now_us: u64) -> Self {
+    Self {
+      started_us: now_us,
+      window_started_us: now_us,
+      ..Self::default()
+    }
+  }
+
+  /// Time the host calls of an instrumented guest whose imported functions
+  /// are `imports`, in function index order.
+  pub fn time_host_calls(&mut self, imports: Vec<String>) {
+    self.imports = imports;
+  }
+
+  /// The guest entered function `index`, or is about to call it if it's an
+  /// import.
+  pub fn enter_function(&mut self, index: u32) {
+    if (index as usize) < self.imports.len() {
+      self.next_import = Some(index);
+    }
+  }
+
+  /// A call into the host starting. Only a call to the import the guest
+  /// entered last is timed, which leaves out the profile hooks themselves.
+  pub fn host_call_started(&mut self, now_us: u64) {
+    self.host_call = self.next_import.take().map(|index| (index, now_us));
+  }
+
+  pub fn host_call_finished(&mut self, now_us: u64) {
+    if let Some((index, started_us)) = self.host_call.take() {
+      self.host_calls.entry(index).or_default().record(now_us.saturating_sub(started_us));
+    }
+  }
+
+  /// The timed host calls by import name.
+  pub fn host_calls(&self) -> impl Iterator<Item = (&str, &Timing)> {
+    self
+      .host_calls
+      .iter()
+      .map(|(&index, timing)| (self.imports[index as usize].as_str(), timing))
+  }
+
+  /// A frame (or part of one) taking `us` to hand to the display.
+  pub fn record_present(&mut self, us: u64) {
+    self.presents.record(us);
+    self.presented = true;
+  }
+
+  /// A tick that ran from `started_us` to `finished_us`. Returns whether it
+  /// presented a frame.
+  pub fn record_tick(&mut self, started_us: u64, finished_us: u64) -> bool {
+    let us = finished_us.saturating_sub(started_us);
+    self.ticks.record(us);
+    self.window_ticks.record(us);
+
+    let presented = core::mem::take(&mut self.presented);
+    if presented {
+      if let Some(last) = self.last_frame_us.replace(started_us) {
+        self.frame_intervals.record(started_us.saturating_sub(last));
+      }
+      self.frames += 1;
+      self.window_frames += 1;
+    }
+
+    let elapsed = finished_us.saturating_sub(self.window_started_us);
+    if elapsed >= OVERLAY_WINDOW_US {
+      self.window = WindowStats {
+        fps_tenths: self.window_frames * 10_000_000 / elapsed,
+        tick_mean_us: self.window_ticks.mean_us(),
+        tick_max_us: self.window_ticks.max_us,
+      };
+      self.window_started_us = finished_us;
+      self.window_frames = 0;
+      self.window_ticks = Timing::default();
+    }
+    presented
+  }
+
+  /// Track the [`OVERLAY_CHORD`] buttons, toggling the overlay when the
+  /// chord completes.
+  pub fn on_button(&mut self, button: HexButton) {
+    let Some(slot) = OVERLAY_CHORD.iter().position(|chord| chord.index() == button.index()) else {
+      return;
+    };
+    let pressed = !button.is_release();
+    if pressed && !self.chord_held[slot] && self.chord_held.iter().enumerate().all(|(i, held)| i == slot || *held) {
+      self.overlay = !self.overlay;
+    }
+    self.chord_held[slot] = pressed;
+  }
+
+  pub fn overlay_enabled(&self) -> bool {
+    self.overlay
+  }
+
+  /// One line for the overlay: frames per second, then the mean and worst
+  /// tick in milliseconds, e.g. `29.8 fps 4.1/12.0 ms`.
+  pub fn overlay_text(&self) -> impl fmt::Display + '_ {
+    OverlayText(&self.window)
+  }
+
+  /// A report of the whole session, for the log when the app exits.
+  pub fn summary(&self, now_us: u64) -> impl fmt::Display + '_ {
+    Summary {
+      profiler: self,
+      elapsed_us: now_us.saturating_sub(self.started_us),
+    }
+  }
+}
+
+/// Microseconds as milliseconds with one decimal.
+struct Millis(u64);
+
+impl fmt::Display for Millis {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let tenths = (self.0 + 50) / 100;
+    write!(f, "{}.{}", tenths / 10, tenths % 10)
+  }
+}
+
+struct OverlayText<'a>(&'a WindowStats);
+
+impl fmt::Display for OverlayText<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let window = self.0;
+    write!(
+      f,
+      "{}.{} fps {}/{} ms",
+      window.fps_tenths / 10,
+      window.fps_tenths % 10,
+      Millis(window.tick_mean_us),
+      Millis(window.tick_max_us)
+    )
+  }
+}
+
+struct Summary<'a> {
+  profiler: &'a Profiler,
+  elapsed_us: u64,
+}
+
+impl fmt::Display for Summary<'_> {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    let Profiler {
+      ticks,
+      presents,
+      frames,
+      frame_intervals,
+      ..
+    } = self.profiler;
+    let fps_tenths = (frames * 10_000_000).checked_div(self.elapsed_us).unwrap_or(0);
+    writeln!(f, "Profile: {} ticks in {} ms", ticks.count, Millis(self.elapsed_us))?;
+    writeln!(
+      f,
+      "  tick:       mean {} ms, max {} ms",
+      Millis(ticks.mean_us()),
+      Millis(ticks.max_us)
+    )?;
+    if !self.profiler.imports.is_empty() {
+      let mut host_calls: Vec<_> = self.profiler.host_calls().collect();
+      host_calls.sort_unstable_by_key(|(_, timing)| Reverse(timing.total_us));
+      let count: u64 = host_calls.iter().map(|(_, timing)| timing.count).sum();
+      let total_us: u64 = host_calls.iter().map(|(_, timing)| timing.total_us).sum();
+      writeln!(f, "  host calls: {count} ({} ms total)", Millis(total_us))?;
+      for (name, timing) in host_calls {
+        writeln!(
+          f,
+          "    {name}: {} ({} ms total), mean {} ms, max {} ms",
+          timing.count,
+          Millis(timing.total_us),
+          Millis(timing.mean_us()),
+          Millis(timing.max_us)
+        )?;
+      }
+    }
+    writeln!(
+      f,
+      "  lcd:        {} updates, mean {} ms, max {} ms",
+      presents.count,
+      Millis(presents.mean_us()),
+      Millis(presents.max_us)
+    )?;
+    write!(
+      f,
+      "  frames:     {} ({}.{} fps), interval mean {} ms, max {} ms",
+      frames,
+      fps_tenths / 10,
+      fps_tenths % 10,
+      Millis(frame_intervals.mean_us()),
+      Millis(frame_intervals.max_us)
+    )
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::{string::ToString, vec};
+
+  #[test]
+  fn overlay_shows_the_last_window() {
+    let mut profiler = Profiler::new(0);
+    for frame in 0..30 {
+      let started = frame * 33_333;
+      profiler.record_present(500);
+      profiler.record_tick(started, started + 4_000 + frame * 100);
+    }
+    profiler.record_tick(1_000_000, 1_000_000);
+    assert_eq!(profiler.overlay_text().to_string(), "30.0 fps 5.3/6.9 ms");
+    assert_eq!(profiler.frame_intervals.count, 29);
+    assert_eq!(profiler.frame_intervals.max_us, 33_333);
+  }
+
+  #[test]
+  fn host_calls_are_timed_by_import() {
+    let mut profiler = Profiler::new(0);
+    profiler.time_host_calls(vec!["extern_get_millis".to_string(), "extern_set_lcd_buffer".to_string()]);
+    for (index, started_us, finished_us) in [(1, 0, 900), (0, 1_000, 1_010), (1, 2_000, 2_500)] {
+      // The hook's own call isn't timed, only the call it announces.
+      profiler.host_call_started(started_us);
+      profiler.enter_function(index);
+      profiler.host_call_finished(started_us);
+      profiler.host_call_started(started_us);
+      profiler.host_call_finished(finished_us);
+    }
+    // Calls into defined functions don't announce a host call.
+    profiler.enter_function(2);
+    profiler.host_call_started(3_000);
+    profiler.host_call_finished(3_100);
+
+    let host_calls: Vec<_> = profiler
+      .host_calls()
+      .map(|(name, timing)| (name, timing.count, timing.max_us))
+      .collect();
+    assert_eq!(host_calls, [("extern_get_millis", 1, 10), ("extern_set_lcd_buffer", 2, 900)]);
+  }
+
+  #[test]
+  fn chord_toggles_the_overlay() {
+    let mut profiler = Profiler::new(0);
+    profiler.on_button(HexButton::HexA);
+    profiler.on_button(HexButton::HexF);
+    assert!(profiler.overlay_enabled());
+    profiler.on_button(HexButton::HexFReleased);
+    profiler.on_button(HexButton::HexF);
+    assert!(!profiler.overlay_enabled());
+    profiler.on_button(HexButton::HexAReleased);
+    profiler.on_button(HexButton::HexF);
+    assert!(!profiler.overlay_enabled());
+  }
+}
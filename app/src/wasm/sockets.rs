@@ -0,0 +1,208 @@
+//! Host side of the guest socket messages.
+//!
+//! Guests open TCP streams and UDP sockets over IPC; the IPC handler keeps a
+//! [`GuestSockets`] per session, hands it socket requests and forwards what
+//! [`GuestSockets::next_event`] produces to the guest. Each socket is named by
+//! the id of the message that opened it. The platform clients drive one
+//! connection at a time, so a guest may have one TCP stream and one UDP
+//! socket open.
+
+use crate::platform::{TcpEvent, TcpEventChannel, TcpHandle, UdpEvent, UdpEventChannel, UdpHandle};
+use alloc::boxed::Box;
+use core::future::pending;
+use embassy_futures::select::{Either, select};
+use wasm_protocol::{Datagram, HostIpcMessage as WireHostIpcMessage, WasmIpcMessage as WireWasmIpcMessage};
+
+pub struct GuestSockets {
+  tcp: Option<TcpHandle>,
+  udp: Option<UdpHandle>,
+  tcp_events: &'static TcpEventChannel,
+  udp_events: &'static UdpEventChannel,
+  /// Id of the open TCP stream.
+  tcp_socket: Option<u32>,
+  /// Id of the bound UDP socket.
+  udp_socket: Option<u32>,
+}
+
+impl GuestSockets {
+  /// The event channels are leaked once and reused for every socket.
+  pub fn new(tcp: Option<TcpHandle>, udp: Option<UdpHandle>) -> Self {
+    Self {
+      tcp,
+      udp,
+      tcp_events: Box::leak(Box::new(TcpEventChannel::new())),
+      udp_events: Box::leak(Box::new(UdpEventChannel::new())),
+      tcp_socket: None,
+      udp_socket: None,
+    }
+  }
+
+  /// Carry out socket request `msg`, sent by the guest as message `id`.
+  /// Returns a reply to send straight away, if there is one; everything else
+  /// arrives through [`GuestSockets::next_event`].
+  pub async fn handle(&mut self, id: u32, msg: WireWasmIpcMessage) -> Option<(u32, WireHostIpcMessage)> {
+    match msg {
+      WireWasmIpcMessage::TcpConnect { host, port } => {
+        let Some(tcp) = self.tcp.as_ref().filter(|_| self.tcp_socket.is_none()) else {
+          return Some((id, WireHostIpcMessage::SocketError));
+        };
+        self.tcp_events.clear();
+        self.tcp_socket = Some(id);
+        tcp.connect(host, port, self.tcp_events).await;
+        None
+      }
+      WireWasmIpcMessage::UdpBind { port } => {
+        let Some(udp) = self.udp.as_ref().filter(|_| self.udp_socket.is_none()) else {
+          return Some((id, WireHostIpcMessage::SocketError));
+        };
+        self.udp_events.clear();
+        self.udp_socket = Some(id);
+        udp.bind(port, self.udp_events).await;
+        None
+      }
+      WireWasmIpcMessage::SocketSend { socket, data } => match &self.tcp {
+        Some(tcp) if self.tcp_socket == Some(socket) => {
+          tcp.send(data).await;
+          None
+        }
+        _ => Some((socket, WireHostIpcMessage::SocketError)),
+      },
+      WireWasmIpcMessage::SocketSendTo { socket, datagram } => match &self.udp {
+        Some(udp) if self.udp_socket == Some(socket) => {
+          udp.send_to(datagram.host, datagram.port, datagram.data).await;
+          None
+        }
+        _ => Some((socket, WireHostIpcMessage::SocketError)),
+      },
+      WireWasmIpcMessage::SocketClose { socket } => {
+        if self.tcp_socket == Some(socket) {
+          self.close_tcp().await;
+        } else if self.udp_socket == Some(socket) {
+          self.close_udp().await;
+        } else {
+          return None;
+        }
+        Some((socket, WireHostIpcMessage::SocketClosed))
+      }
+      WireWasmIpcMessage::HttpRequest(_) | WireWasmIpcMessage::ShowScreen(_) | WireWasmIpcMessage::ShowFramebuffer => None,
+    }
+  }
+
+  /// Wait for the next message for the guest from any open socket. Never
+  /// completes while no socket is open.
+  pub async fn next_event(&mut self) -> (u32, WireHostIpcMessage) {
+    let tcp = async {
+      match self.tcp_socket {
+        Some(id) => (id, self.tcp_events.receive().await),
+        None => pending().await,
+      }
+    };
+    let udp = async {
+      match self.udp_socket {
+        Some(id) => (id, self.udp_events.receive().await),
+        None => pending().await,
+      }
+    };
+    match select(tcp, udp).await {
+      Either::First((id, event)) => {
+        let msg = tcp_event_message(event);
+        if is_final(&msg) {
+          self.tcp_socket = None;
+        }
+        (id, msg)
+      }
+      Either::Second((id, event)) => {
+        let msg = udp_event_message(event);
+        if is_final(&msg) {
+          self.udp_socket = None;
+        }
+        (id, msg)
+      }
+    }
+  }
+
+  /// Close everything the guest left open. Called when the session ends.
+  pub async fn close_all(&mut self) {
+    self.close_tcp().await;
+    self.close_udp().await;
+  }
+
+  async fn close_tcp(&mut self) {
+    if let (Some(tcp), Some(_)) = (&self.tcp, self.tcp_socket.take()) {
+      tcp.close().await;
+    }
+  }
+
+  async fn close_udp(&mut self) {
+    if let (Some(udp), Some(_)) = (&self.udp, self.udp_socket.take()) {
+      udp.close().await;
+    }
+  }
+}
+
+fn tcp_event_message(event: TcpEvent) -> WireHostIpcMessage {
+  match event {
+    TcpEvent::Connected => WireHostIpcMessage::SocketConnected,
+    TcpEvent::Data(data) => WireHostIpcMessage::SocketData(data),
+    TcpEvent::Closed => WireHostIpcMessage::SocketClosed,
+    TcpEvent::Error => WireHostIpcMessage::SocketError,
+  }
+}
+
+fn udp_event_message(event: UdpEvent) -> WireHostIpcMessage {
+  match event {
+    UdpEvent::Bound => WireHostIpcMessage::SocketConnected,
+    UdpEvent::Datagram { host, port, data } => WireHostIpcMessage::SocketDatagram(Datagram { host, port, data }),
+    UdpEvent::Closed => WireHostIpcMessage::SocketClosed,
+    UdpEvent::Error => WireHostIpcMessage::SocketError,
+  }
+}
+
+/// Whether `msg` is the last one a socket produces.
+fn is_final(msg: &WireHostIpcMessage) -> bool {
+  matches!(msg, WireHostIpcMessage::SocketClosed | WireHostIpcMessage::SocketError)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use embassy_futures::block_on;
+
+  #[test]
+  fn connect_without_a_client_fails() {
+    let mut sockets = GuestSockets::new(None, None);
+    let connect = WireWasmIpcMessage::TcpConnect {
+      host: "irc.libera.chat".into(),
+      port: 6667,
+    };
+    assert!(matches!(
+      block_on(sockets.handle(4, connect)),
+      Some((4, WireHostIpcMessage::SocketError))
+    ));
+    assert!(matches!(
+      block_on(sockets.handle(5, WireWasmIpcMessage::UdpBind { port: 0 })),
+      Some((5, WireHostIpcMessage::SocketError))
+    ));
+  }
+
+  #[test]
+  fn requests_on_unknown_sockets_fail() {
+    let mut sockets = GuestSockets::new(None, None);
+    let send = WireWasmIpcMessage::SocketSend {
+      socket: 9,
+      data: b"PING\r\n".to_vec(),
+    };
+    assert!(matches!(
+      block_on(sockets.handle(10, send)),
+      Some((9, WireHostIpcMessage::SocketError))
+    ));
+    assert!(block_on(sockets.handle(11, WireWasmIpcMessage::SocketClose { socket: 9 })).is_none());
+  }
+
+  #[test]
+  fn closed_and_error_events_end_the_socket() {
+    assert!(is_final(&tcp_event_message(TcpEvent::Closed)));
+    assert!(is_final(&udp_event_message(UdpEvent::Error)));
+    assert!(!is_final(&tcp_event_message(TcpEvent::Data(alloc::vec![1, 2]))));
+  }
+}
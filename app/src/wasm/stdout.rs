@@ -0,0 +1,90 @@
+use alloc::{collections::VecDeque, string::String, vec::Vec};
+
+/// Lines of guest stdout kept for the crash report.
+pub const STDOUT_TAIL_LINES: usize = 32;
+/// Longer lines are cut short, so one runaway print cannot use up the heap.
+pub const STDOUT_LINE_MAX: usize = 160;
+
+/// The last [`STDOUT_TAIL_LINES`] lines a guest wrote to stdout.
+pub struct StdoutTail {
+  lines: VecDeque<String>,
+  /// The line being written, up to its newline.
+  partial: String,
+}
+
+impl StdoutTail {
+  pub fn new() -> Self {
+    Self {
+      lines: VecDeque::with_capacity(STDOUT_TAIL_LINES),
+      partial: String::new(),
+    }
+  }
+
+  pub fn push(&mut self, text: &str) {
+    let mut rest = text;
+    while let Some(newline) = rest.find('\n') {
+      self.append(&rest[..newline]);
+      let line = core::mem::take(&mut self.partial);
+      if self.lines.len() == STDOUT_TAIL_LINES {
+        self.lines.pop_front();
+      }
+      self.lines.push_back(line);
+      rest = &rest[newline + 1..];
+    }
+    self.append(rest);
+  }
+
+  /// The kept lines, oldest first, including any unfinished last line.
+  pub fn lines(&self) -> Vec<String> {
+    let mut lines: Vec<String> = self.lines.iter().cloned().collect();
+    if !self.partial.is_empty() {
+      lines.push(self.partial.clone());
+    }
+    lines
+  }
+
+  fn append(&mut self, text: &str) {
+    let room = STDOUT_LINE_MAX.saturating_sub(self.partial.len());
+    let mut end = text.len().min(room);
+    while !text.is_char_boundary(end) {
+      end -= 1;
+    }
+    self.partial.push_str(&text[..end]);
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::format;
+
+  #[test]
+  fn lines_are_split_across_writes() {
+    let mut tail = StdoutTail::new();
+    tail.push("score: ");
+    tail.push("12\nlevel 2\nboss");
+    assert_eq!(tail.lines(), ["score: 12", "level 2", "boss"]);
+  }
+
+  #[test]
+  fn only_the_last_lines_are_kept() {
+    let mut tail = StdoutTail::new();
+    for i in 0..STDOUT_TAIL_LINES + 5 {
+      tail.push(&format!("line {i}\n"));
+    }
+    let lines = tail.lines();
+    assert_eq!(lines.len(), STDOUT_TAIL_LINES);
+    assert_eq!(lines[0], "line 5");
+  }
+
+  #[test]
+  fn long_lines_are_truncated_on_a_char_boundary() {
+    let mut tail = StdoutTail::new();
+    tail.push("a");
+    tail.push(&"é".repeat(STDOUT_LINE_MAX));
+    tail.push("\n");
+    let line = &tail.lines()[0];
+    assert!(line.len() <= STDOUT_LINE_MAX);
+    assert!(line.starts_with("aé"));
+  }
+}
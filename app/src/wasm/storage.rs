@@ -0,0 +1,218 @@
+//! Per-app persistent storage for WASM guests.
+//!
+//! Each app gets its own directory under [`APP_DATA_DIR`] on the device
+//! filesystem, named after the `.wsm` file it was launched from (see
+//! [`app_dir_name`]). Guests only
+//! ever supply a key, which must be a single path component (see
+//! [`is_valid_storage_key`]), so they cannot reach outside their directory or
+//! into another app's data.
+
+use crate::platform::{FileType, FsError, StorageHandle};
+use alloc::{
+  format,
+  string::{String, ToString},
+  vec::Vec,
+};
+use wasm_protocol::{DEFAULT_STORAGE_QUOTA, MAX_STORAGE_KEYS, StorageError, is_valid_storage_key};
+
+/// Directory holding one sandbox directory per app.
+pub const APP_DATA_DIR: &str = "/appdata";
+
+/// Sandbox used by apps launched from a buffer (uploads, desktop CLI), which
+/// have no file name to key their data by. It is wiped on every such launch
+/// so one uploaded app never sees another's data. No [`app_dir_name`] can
+/// match it.
+pub const SCRATCH_APP_NAME: &str = "_scratch";
+
+/// Characters of an app's file name kept in its directory name, leaving room
+/// for the `-` and eight hex digits of its hash.
+const APP_DIR_NAME_PREFIX: usize = wasm_protocol::MAX_STORAGE_KEY_LEN - 9;
+
+#[derive(Clone, Debug)]
+pub struct AppStorage {
+  storage: StorageHandle,
+  dir: String,
+  quota: u32,
+}
+
+impl AppStorage {
+  /// Storage for the app launched from `app_file` (e.g. `"snake.wsm"`).
+  pub fn new(storage: StorageHandle, app_file: &str) -> Self {
+    Self::in_dir(storage, &app_dir_name(app_file))
+  }
+
+  /// Storage for an app launched from a buffer. Call [`AppStorage::clear`]
+  /// before handing it to the guest.
+  pub fn scratch(storage: StorageHandle) -> Self {
+    Self::in_dir(storage, SCRATCH_APP_NAME)
+  }
+
+  fn in_dir(storage: StorageHandle, name: &str) -> Self {
+    Self {
+      storage,
+      dir: format!("{APP_DATA_DIR}/{name}"),
+      quota: DEFAULT_STORAGE_QUOTA,
+    }
+  }
+
+  pub fn with_quota(mut self, quota: u32) -> Self {
+    self.quota = quota;
+    self
+  }
+
+  pub fn quota(&self) -> u32 {
+    self.quota
+  }
+
+  pub async fn read(&self, key: &str) -> Result<Vec<u8>, StorageError> {
+    let path = self.path(key)?;
+    let size = self.storage.get_file_size(path.clone()).await.map_err(map_fs_error)?;
+    self.storage.read_binary_chunk(path, 0, size).await.map_err(map_fs_error)
+  }
+
+  pub async fn write(&self, key: &str, value: Vec<u8>) -> Result<(), StorageError> {
+    let path = self.path(key)?;
+    self.ensure_dir().await?;
+
+    let (used, keys) = self.usage(key).await?;
+    if keys >= MAX_STORAGE_KEYS || used.saturating_add(value.len() as u32) > self.quota {
+      return Err(StorageError::QuotaExceeded);
+    }
+
+    self.storage.write_binary_chunk(path, 0, value, true).await.map_err(map_fs_error)
+  }
+
+  pub async fn delete(&self, key: &str) -> Result<(), StorageError> {
+    let path = self.path(key)?;
+    self.storage.delete(path).await.map_err(map_fs_error)
+  }
+
+  /// Remove every value stored by this app.
+  pub async fn clear(&self) -> Result<(), StorageError> {
+    let entries = match self.storage.list_dir(self.dir.clone()).await {
+      Ok(entries) => entries,
+      Err(FsError::NotFound) => return Ok(()),
+      Err(err) => return Err(map_fs_error(err)),
+    };
+    for entry in entries.iter().filter(|entry| entry.file_type == FileType::File) {
+      self
+        .storage
+        .delete(format!("{}/{}", self.dir, entry.name))
+        .await
+        .map_err(map_fs_error)?;
+    }
+    Ok(())
+  }
+
+  fn path(&self, key: &str) -> Result<String, StorageError> {
+    if !is_valid_storage_key(key) {
+      return Err(StorageError::InvalidKey);
+    }
+    Ok(format!("{}/{key}", self.dir))
+  }
+
+  async fn ensure_dir(&self) -> Result<(), StorageError> {
+    for dir in [APP_DATA_DIR.to_string(), self.dir.clone()] {
+      match self.storage.mkdir(dir).await {
+        Ok(()) | Err(FsError::AlreadyExists) => {}
+        Err(err) => return Err(map_fs_error(err)),
+      }
+    }
+    Ok(())
+  }
+
+  /// Bytes and keys in use, not counting `key` (which is about to be
+  /// overwritten).
+  async fn usage(&self, key: &str) -> Result<(u32, usize), StorageError> {
+    let entries = self.storage.list_dir(self.dir.clone()).await.map_err(map_fs_error)?;
+    Ok(
+      entries
+        .iter()
+        .filter(|entry| entry.file_type == FileType::File && entry.name != key)
+        .fold((0u32, 0usize), |(bytes, keys), entry| (bytes.saturating_add(entry.size), keys + 1)),
+    )
+  }
+}
+
+/// Directory name for an app: the start of its file name, without the
+/// directory or `.wsm` extension and with anything that is not a valid
+/// storage key character replaced by `_`, then a hash of the whole path.
+///
+/// The readable part is only for someone browsing the filesystem. The hash
+/// keeps apps whose names sanitise or truncate to the same thing, or that
+/// share a name in different directories, apart. A leading `/` is ignored,
+/// so `snake.wsm` and `/snake.wsm` share data.
+pub fn app_dir_name(app_file: &str) -> String {
+  let path = app_file.trim_start_matches('/');
+  let name = path.rsplit('/').next().unwrap_or(path);
+  let name = name.strip_suffix(".wsm").unwrap_or(name);
+  let name: String = name
+    .chars()
+    .take(APP_DIR_NAME_PREFIX)
+    .map(|ch| if ch.is_ascii_alphanumeric() || ch == '-' || ch == '_' { ch } else { '_' })
+    .collect();
+  format!("{name}-{:08x}", fnv1a(path.as_bytes()))
+}
+
+/// 32-bit FNV-1a: stable across builds and platforms, unlike `core::hash`.
+fn fnv1a(bytes: &[u8]) -> u32 {
+  bytes
+    .iter()
+    .fold(0x811c_9dc5, |hash, byte| (hash ^ *byte as u32).wrapping_mul(0x0100_0193))
+}
+
+fn map_fs_error(err: FsError) -> StorageError {
+  match err {
+    FsError::NotFound => StorageError::NotFound,
+    FsError::NoSpace => StorageError::QuotaExceeded,
+    _ => StorageError::Io,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn dir_name_strips_path_and_extension() {
+    assert!(app_dir_name("snake.wsm").starts_with("snake-"));
+    assert!(app_dir_name("/apps/tetris.wsm").starts_with("tetris-"));
+    assert_eq!(app_dir_name("snake.wsm"), app_dir_name("/snake.wsm"));
+  }
+
+  #[test]
+  fn dir_names_are_valid_and_distinct() {
+    let long = "a".repeat(40);
+    let files = [
+      "my app.wsm".to_string(),
+      "my_app.wsm".to_string(),
+      "apps/snake.wsm".to_string(),
+      "games/snake.wsm".to_string(),
+      format!("{long}1.wsm"),
+      format!("{long}2.wsm"),
+      "_scratch.wsm".to_string(),
+      "../flappy.wsm".to_string(),
+      "..".to_string(),
+      String::new(),
+    ];
+    let names: Vec<String> = files.iter().map(|file| app_dir_name(file)).collect();
+    for (i, name) in names.iter().enumerate() {
+      assert!(is_valid_storage_key(name), "{name}");
+      assert_ne!(name, SCRATCH_APP_NAME);
+      assert!(!names[..i].contains(name), "{} collides", files[i]);
+    }
+  }
+
+  #[test]
+  fn keys_are_single_components() {
+    assert!(is_valid_storage_key("high_score"));
+    assert!(is_valid_storage_key("save-1.bin"));
+    assert!(!is_valid_storage_key(""));
+    assert!(!is_valid_storage_key(".."));
+    assert!(!is_valid_storage_key(".hidden"));
+    assert!(!is_valid_storage_key("../snake/high_score"));
+    assert!(!is_valid_storage_key("a/b"));
+    assert!(!is_valid_storage_key("a\\b"));
+    assert!(!is_valid_storage_key(&"k".repeat(wasm_protocol::MAX_STORAGE_KEY_LEN + 1)));
+  }
+}
@@ -33,4 +33,9 @@ impl TimerRegistry {
     pub fn cancel(&mut self, timer_id: i32) {
         self.timers.remove(&timer_id);
     }
+
+    /// When the earliest registered timer expires, if there are any.
+    pub fn next_expiry(&self) -> Option<u64> {
+        self.timers.values().copied().min()
+    }
 }
@@ -0,0 +1,711 @@
+//! A WASI preview1 subset, so programs built with standard toolchains (C, Zig,
+//! TinyGo, `wasm32-wasip1` Rust) run alongside guests written against the
+//! `"index"` imports.
+//!
+//! - stdout and stderr go to the guest's console, like `extern_write_stdout`;
+//!   stdin is always at end of file.
+//! - The clocks and `random_get` come from the [`WasmHost`].
+//! - There are no arguments or environment variables.
+//! - Files live in the app's [`AppStorage`] sandbox, preopened as `/`, beside
+//!   its `extern_storage_*` values and under the same quota. A file is read
+//!   whole when opened and written back when closed or synced, so names must
+//!   be valid storage keys: there are no subdirectories.
+//!
+//! Any other preview1 function the program imports fails with `ENOSYS` (see
+//! [`stub_unsupported_functions`]).
+
+use super::context::{ReadWasmBuffer, WasmCtx, guest_memory};
+use super::fault::{GuestFault, guest_range};
+use super::host::WasmHost;
+use super::storage::AppStorage;
+use alloc::{
+  collections::BTreeMap,
+  format,
+  string::{String, ToString},
+  vec::Vec,
+};
+use embassy_futures::block_on;
+use log::debug;
+use wasm_protocol::{StorageError, is_valid_storage_key};
+use wasmi::{Caller, ExternType, Linker, Module, Val};
+use wasmi_core::ValType;
+
+/// Import module of the WASI preview1 functions.
+pub const WASI_IMPORT_MODULE: &str = "wasi_snapshot_preview1";
+
+/// The functions implemented here; see [`stub_unsupported_functions`] for
+/// the rest.
+pub const WASI_FUNCTIONS: [&str; 22] = [
+  "args_get",
+  "args_sizes_get",
+  "environ_get",
+  "environ_sizes_get",
+  "clock_res_get",
+  "clock_time_get",
+  "random_get",
+  "fd_close",
+  "fd_datasync",
+  "fd_fdstat_get",
+  "fd_filestat_get",
+  "fd_prestat_dir_name",
+  "fd_prestat_get",
+  "fd_read",
+  "fd_seek",
+  "fd_sync",
+  "fd_tell",
+  "fd_write",
+  "path_open",
+  "path_unlink_file",
+  "proc_exit",
+  "sched_yield",
+];
+
+/// The descriptor of the app's sandbox, preopened as `/`.
+const PREOPEN_FD: u32 = 3;
+const PREOPEN_NAME: &str = "/";
+/// Descriptors from here on are open files.
+const FIRST_FILE_FD: u32 = 4;
+/// Files an app may have open at once.
+const MAX_OPEN_FILES: usize = 8;
+
+type Errno = u32;
+
+/// The preview1 `errno` values used here.
+mod errno {
+  use super::Errno;
+
+  pub const SUCCESS: Errno = 0;
+  pub const BADF: Errno = 8;
+  pub const EXIST: Errno = 20;
+  pub const INVAL: Errno = 28;
+  pub const IO: Errno = 29;
+  pub const MFILE: Errno = 33;
+  pub const NOENT: Errno = 44;
+  pub const NOSPC: Errno = 51;
+  pub const NOSYS: Errno = 52;
+  pub const NOTDIR: Errno = 54;
+  pub const NOTSUP: Errno = 58;
+  pub const SPIPE: Errno = 70;
+  pub const NOTCAPABLE: Errno = 76;
+}
+
+const FILETYPE_CHARACTER_DEVICE: u8 = 2;
+const FILETYPE_DIRECTORY: u8 = 3;
+const FILETYPE_REGULAR_FILE: u8 = 4;
+
+const CLOCK_REALTIME: u32 = 0;
+const CLOCK_MONOTONIC: u32 = 1;
+/// Resolution of both clocks, in nanoseconds.
+const CLOCK_RESOLUTION_NS: u64 = 1_000;
+
+const OFLAGS_CREAT: u32 = 1 << 0;
+const OFLAGS_DIRECTORY: u32 = 1 << 1;
+const OFLAGS_EXCL: u32 = 1 << 2;
+const OFLAGS_TRUNC: u32 = 1 << 3;
+const FDFLAGS_APPEND: u32 = 1 << 0;
+
+const WHENCE_SET: u32 = 0;
+const WHENCE_CUR: u32 = 1;
+const WHENCE_END: u32 = 2;
+
+/// Every right: descriptors aren't restricted beyond what each call checks.
+const ALL_RIGHTS: u64 = (1 << 30) - 1;
+
+/// A file the guest has open, held in memory until it is closed.
+#[derive(Debug, PartialEq)]
+struct OpenFile {
+  /// Its storage key in the app's sandbox.
+  name: String,
+  data: Vec<u8>,
+  pos: usize,
+  append: bool,
+  /// Written since it was opened or last synced.
+  dirty: bool,
+}
+
+impl OpenFile {
+  fn read(&mut self, len: usize) -> &[u8] {
+    let start = self.pos.min(self.data.len());
+    let end = start + len.min(self.data.len() - start);
+    self.pos = end;
+    &self.data[start..end]
+  }
+
+  /// Write `bytes` at the file position, or at the end in append mode,
+  /// refusing to grow the file beyond `max_len`.
+  fn write(&mut self, bytes: &[u8], max_len: usize) -> Result<(), Errno> {
+    if self.append {
+      self.pos = self.data.len();
+    }
+    let end = self
+      .pos
+      .checked_add(bytes.len())
+      .filter(|end| *end <= max_len)
+      .ok_or(errno::NOSPC)?;
+    if end > self.data.len() {
+      self.data.resize(end, 0);
+    }
+    self.data[self.pos..end].copy_from_slice(bytes);
+    self.pos = end;
+    self.dirty |= !bytes.is_empty();
+    Ok(())
+  }
+
+  fn seek(&mut self, offset: i64, whence: u32) -> Result<u64, Errno> {
+    let base = match whence {
+      WHENCE_SET => 0,
+      WHENCE_CUR => self.pos as i64,
+      WHENCE_END => self.data.len() as i64,
+      _ => return Err(errno::INVAL),
+    };
+    let pos = base.checked_add(offset).filter(|pos| *pos >= 0).ok_or(errno::INVAL)?;
+    self.pos = usize::try_from(pos).map_err(|_| errno::INVAL)?;
+    Ok(pos as u64)
+  }
+
+  /// Write the file back to `storage` if it changed.
+  fn sync(&mut self, storage: &AppStorage) -> Errno {
+    if !self.dirty {
+      return errno::SUCCESS;
+    }
+    match block_on(storage.write(&self.name, self.data.clone())) {
+      Ok(()) => {
+        self.dirty = false;
+        errno::SUCCESS
+      }
+      Err(err) => storage_errno(err),
+    }
+  }
+}
+
+/// The guest's open files, by descriptor.
+#[derive(Debug, Default)]
+pub struct WasiFiles {
+  files: BTreeMap<u32, OpenFile>,
+}
+
+impl WasiFiles {
+  pub fn new() -> Self {
+    Self::default()
+  }
+
+  fn open(&mut self, file: OpenFile) -> Result<u32, Errno> {
+    if self.files.len() >= MAX_OPEN_FILES {
+      return Err(errno::MFILE);
+    }
+    let fd = (FIRST_FILE_FD..).find(|fd| !self.files.contains_key(fd)).ok_or(errno::MFILE)?;
+    self.files.insert(fd, file);
+    Ok(fd)
+  }
+
+  /// Write back every file the guest left open.
+  pub fn close_all(&mut self, storage: Option<&AppStorage>) {
+    let files = core::mem::take(&mut self.files);
+    for (fd, mut file) in files {
+      let result = storage.map_or(errno::NOTCAPABLE, |storage| file.sync(storage));
+      if result != errno::SUCCESS {
+        debug!("wasi: lost writes to {} (fd {fd}), errno {result}", file.name);
+      }
+    }
+  }
+}
+
+/// The storage key `path` names in the sandbox: relative to [`PREOPEN_NAME`]
+/// (a leading `/` or `./` is ignored) and a single component.
+fn sandbox_key(path: &str) -> Option<&str> {
+  let key = path.trim_start_matches('/');
+  let key = key.strip_prefix("./").unwrap_or(key);
+  is_valid_storage_key(key).then_some(key)
+}
+
+fn storage_errno(err: StorageError) -> Errno {
+  match err {
+    StorageError::NotFound => errno::NOENT,
+    StorageError::QuotaExceeded => errno::NOSPC,
+    StorageError::InvalidKey | StorageError::Unavailable => errno::NOTCAPABLE,
+    StorageError::Io => errno::IO,
+  }
+}
+
+/// The `(buf, len)` pairs of the `iovec` array at `ptr`.
+fn read_iovecs<H: WasmHost>(caller: &Caller<'_, WasmCtx<H>>, ptr: u32, count: u32) -> Result<Vec<(u32, u32)>, GuestFault> {
+  let bytes = ReadWasmBuffer::read_memory(caller, ptr, count.saturating_mul(8))?;
+  let (iovecs, _) = bytes.as_chunks::<8>();
+  Ok(
+    iovecs
+      .iter()
+      .map(|[b0, b1, b2, b3, l0, l1, l2, l3]| (u32::from_le_bytes([*b0, *b1, *b2, *b3]), u32::from_le_bytes([*l0, *l1, *l2, *l3])))
+      .collect(),
+  )
+}
+
+/// Give the guest the descriptor's `fdstat`: its file type and rights.
+fn write_fdstat<H: WasmHost>(caller: &mut Caller<'_, WasmCtx<H>>, ptr: u32, filetype: u8, flags: u16) -> Result<(), GuestFault> {
+  let mut fdstat = [0u8; 24];
+  fdstat[0] = filetype;
+  fdstat[2..4].copy_from_slice(&flags.to_le_bytes());
+  fdstat[8..16].copy_from_slice(&ALL_RIGHTS.to_le_bytes());
+  fdstat[16..24].copy_from_slice(&ALL_RIGHTS.to_le_bytes());
+  ReadWasmBuffer::write_memory(caller, ptr, &fdstat)
+}
+
+fn fd_write<H: WasmHost>(
+  caller: &mut Caller<'_, WasmCtx<H>>,
+  fd: u32,
+  iovs: u32,
+  iovs_len: u32,
+  nwritten: u32,
+) -> Result<Errno, GuestFault> {
+  let mut bytes = Vec::new();
+  for (buf, len) in read_iovecs(caller, iovs, iovs_len)? {
+    bytes.extend(ReadWasmBuffer::read_memory(&*caller, buf, len)?);
+  }
+  let ctx = caller.data_mut();
+  match fd {
+    1 | 2 => ctx.write_stdout(&String::from_utf8_lossy(&bytes)),
+    _ => {
+      let max_len = ctx.storage.as_ref().map_or(0, |storage| storage.quota() as usize);
+      let Some(file) = ctx.wasi.files.get_mut(&fd) else {
+        return Ok(errno::BADF);
+      };
+      if let Err(errno) = file.write(&bytes, max_len) {
+        return Ok(errno);
+      }
+    }
+  }
+  ReadWasmBuffer::write_memory(caller, nwritten, &(bytes.len() as u32).to_le_bytes())?;
+  Ok(errno::SUCCESS)
+}
+
+fn fd_read<H: WasmHost>(caller: &mut Caller<'_, WasmCtx<H>>, fd: u32, iovs: u32, iovs_len: u32, nread: u32) -> Result<Errno, GuestFault> {
+  let iovecs = read_iovecs(caller, iovs, iovs_len)?;
+  let mut total = 0u32;
+  if fd != 0 {
+    if !caller.data().wasi.files.contains_key(&fd) {
+      return Ok(errno::BADF);
+    }
+    for (buf, len) in iovecs {
+      let chunk = caller
+        .data_mut()
+        .wasi
+        .files
+        .get_mut(&fd)
+        .map(|file| file.read(len as usize).to_vec());
+      let chunk = chunk.unwrap_or_default();
+      ReadWasmBuffer::write_memory(caller, buf, &chunk)?;
+      total += chunk.len() as u32;
+      if (chunk.len() as u32) < len {
+        break;
+      }
+    }
+  }
+  ReadWasmBuffer::write_memory(caller, nread, &total.to_le_bytes())?;
+  Ok(errno::SUCCESS)
+}
+
+fn path_open<H: WasmHost>(
+  caller: &mut Caller<'_, WasmCtx<H>>,
+  dir_fd: u32,
+  path_ptr: u32,
+  path_len: u32,
+  oflags: u32,
+  fdflags: u32,
+  fd_out: u32,
+) -> Result<Errno, GuestFault> {
+  let Some(storage) = caller.data().storage.clone() else {
+    return Ok(errno::BADF);
+  };
+  if dir_fd != PREOPEN_FD {
+    return Ok(if caller.data().wasi.files.contains_key(&dir_fd) {
+      errno::NOTDIR
+    } else {
+      errno::BADF
+    });
+  }
+  let path = String::from_utf8(ReadWasmBuffer::read_memory(&*caller, path_ptr, path_len)?).unwrap_or_default();
+  if matches!(path.trim_start_matches('/'), "" | ".") {
+    // The sandbox itself, e.g. for `opendir`: listing it isn't supported.
+    return Ok(errno::NOTSUP);
+  }
+  let Some(key) = sandbox_key(&path) else {
+    return Ok(errno::NOTCAPABLE);
+  };
+  if oflags & OFLAGS_DIRECTORY != 0 {
+    return Ok(errno::NOTDIR);
+  }
+
+  let (data, created) = match block_on(storage.read(key)) {
+    Ok(_) if oflags & (OFLAGS_CREAT | OFLAGS_EXCL) == OFLAGS_CREAT | OFLAGS_EXCL => return Ok(errno::EXIST),
+    Ok(data) if oflags & OFLAGS_TRUNC != 0 => (Vec::new(), !data.is_empty()),
+    Ok(data) => (data, false),
+    Err(StorageError::NotFound) if oflags & OFLAGS_CREAT != 0 => (Vec::new(), true),
+    Err(err) => return Ok(storage_errno(err)),
+  };
+  let file = OpenFile {
+    name: key.to_string(),
+    data,
+    pos: 0,
+    append: fdflags & FDFLAGS_APPEND != 0,
+    dirty: created,
+  };
+  match caller.data_mut().wasi.open(file) {
+    Ok(fd) => {
+      ReadWasmBuffer::write_memory(caller, fd_out, &fd.to_le_bytes())?;
+      Ok(errno::SUCCESS)
+    }
+    Err(errno) => Ok(errno),
+  }
+}
+
+/// Write back, then forget, open file `fd`. Closing stdio or the sandbox
+/// does nothing.
+fn fd_close<H: WasmHost>(ctx: &mut WasmCtx<H>, fd: u32) -> Errno {
+  if fd <= PREOPEN_FD {
+    return errno::SUCCESS;
+  }
+  let Some(mut file) = ctx.wasi.files.remove(&fd) else {
+    return errno::BADF;
+  };
+  ctx.storage.as_ref().map_or(errno::NOTCAPABLE, |storage| file.sync(storage))
+}
+
+fn fd_sync<H: WasmHost>(ctx: &mut WasmCtx<H>, fd: u32) -> Errno {
+  let Some(file) = ctx.wasi.files.get_mut(&fd) else {
+    return if fd <= PREOPEN_FD { errno::INVAL } else { errno::BADF };
+  };
+  ctx.storage.as_ref().map_or(errno::NOTCAPABLE, |storage| file.sync(storage))
+}
+
+/// Write `value` at `ptr`, for a call whose only out parameter it is.
+fn write_u32<H: WasmHost>(caller: &mut Caller<'_, WasmCtx<H>>, ptr: u32, value: u32) -> Result<Errno, GuestFault> {
+  ReadWasmBuffer::write_memory(caller, ptr, &value.to_le_bytes())?;
+  Ok(errno::SUCCESS)
+}
+
+pub fn register_wasi_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  register_process_functions(linker)?;
+  register_fd_functions(linker)?;
+  register_path_functions(linker)?;
+  Ok(())
+}
+
+/// Arguments, environment, clocks, randomness and exit.
+fn register_process_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "args_sizes_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, count: u32, buf_size: u32| -> Result<u32, wasmi::Error> {
+        write_u32(&mut caller, count, 0)?;
+        Ok(write_u32(&mut caller, buf_size, 0)?)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "args_get",
+      |_caller: Caller<'_, WasmCtx<H>>, _argv: u32, _buf: u32| -> u32 { errno::SUCCESS },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "environ_sizes_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, count: u32, buf_size: u32| -> Result<u32, wasmi::Error> {
+        write_u32(&mut caller, count, 0)?;
+        Ok(write_u32(&mut caller, buf_size, 0)?)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "environ_get",
+      |_caller: Caller<'_, WasmCtx<H>>, _environ: u32, _buf: u32| -> u32 { errno::SUCCESS },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "clock_res_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, id: u32, resolution: u32| -> Result<u32, wasmi::Error> {
+        if id != CLOCK_REALTIME && id != CLOCK_MONOTONIC {
+          return Ok(errno::INVAL);
+        }
+        ReadWasmBuffer::write_memory(&mut caller, resolution, &CLOCK_RESOLUTION_NS.to_le_bytes())?;
+        Ok(errno::SUCCESS)
+      },
+    )?
+    // The realtime clock reads 0 until the host knows the time of day, like
+    // `extern_get_unix_micros`.
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "clock_time_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, id: u32, _precision: u64, time: u32| -> Result<u32, wasmi::Error> {
+        let host = &caller.data().host;
+        let micros = match id {
+          CLOCK_REALTIME => host.unix_time_micros().unwrap_or(0),
+          CLOCK_MONOTONIC => host.get_micros(),
+          _ => return Ok(errno::INVAL),
+        };
+        ReadWasmBuffer::write_memory(&mut caller, time, &micros.saturating_mul(1_000).to_le_bytes())?;
+        Ok(errno::SUCCESS)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "random_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, ptr: u32, len: u32| -> Result<u32, wasmi::Error> {
+        let memory = guest_memory(&caller)?;
+        let (data, ctx) = memory.data_and_store_mut(&mut caller);
+        let range = guest_range(ptr, len as usize, data.len())?;
+        ctx.host.fill_random(&mut data[range]);
+        Ok(errno::SUCCESS)
+      },
+    )?
+    // `proc_exit(0)` ends the app normally; see `guest_call_failed`.
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "proc_exit",
+      |_caller: Caller<'_, WasmCtx<H>>, status: u32| -> Result<(), wasmi::Error> { Err(wasmi::Error::i32_exit(status as i32)) },
+    )?
+    .func_wrap(WASI_IMPORT_MODULE, "sched_yield", |_caller: Caller<'_, WasmCtx<H>>| -> u32 {
+      errno::SUCCESS
+    })?;
+  Ok(())
+}
+
+fn register_fd_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_write",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, iovs: u32, iovs_len: u32, nwritten: u32| -> Result<u32, wasmi::Error> {
+        Ok(fd_write(&mut caller, fd, iovs, iovs_len, nwritten)?)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_read",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, iovs: u32, iovs_len: u32, nread: u32| -> Result<u32, wasmi::Error> {
+        Ok(fd_read(&mut caller, fd, iovs, iovs_len, nread)?)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_seek",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, offset: i64, whence: u32, new_offset: u32| -> Result<u32, wasmi::Error> {
+        let Some(file) = caller.data_mut().wasi.files.get_mut(&fd) else {
+          return Ok(if fd <= PREOPEN_FD { errno::SPIPE } else { errno::BADF });
+        };
+        match file.seek(offset, whence) {
+          Ok(pos) => {
+            ReadWasmBuffer::write_memory(&mut caller, new_offset, &pos.to_le_bytes())?;
+            Ok(errno::SUCCESS)
+          }
+          Err(errno) => Ok(errno),
+        }
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_tell",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, offset: u32| -> Result<u32, wasmi::Error> {
+        let Some(file) = caller.data().wasi.files.get(&fd) else {
+          return Ok(if fd <= PREOPEN_FD { errno::SPIPE } else { errno::BADF });
+        };
+        let pos = file.pos as u64;
+        ReadWasmBuffer::write_memory(&mut caller, offset, &pos.to_le_bytes())?;
+        Ok(errno::SUCCESS)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_close",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32| -> u32 { fd_close(caller.data_mut(), fd) },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_sync",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32| -> u32 { fd_sync(caller.data_mut(), fd) },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_datasync",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32| -> u32 { fd_sync(caller.data_mut(), fd) },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_fdstat_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, fdstat: u32| -> Result<u32, wasmi::Error> {
+        let ctx = caller.data();
+        let (filetype, flags) = match fd {
+          0..=2 => (FILETYPE_CHARACTER_DEVICE, 0),
+          PREOPEN_FD if ctx.storage.is_some() => (FILETYPE_DIRECTORY, 0),
+          _ => match ctx.wasi.files.get(&fd) {
+            Some(file) => (FILETYPE_REGULAR_FILE, if file.append { FDFLAGS_APPEND as u16 } else { 0 }),
+            None => return Ok(errno::BADF),
+          },
+        };
+        write_fdstat(&mut caller, fdstat, filetype, flags)?;
+        Ok(errno::SUCCESS)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_filestat_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, filestat: u32| -> Result<u32, wasmi::Error> {
+        let ctx = caller.data();
+        let (filetype, size) = match fd {
+          0..=2 => (FILETYPE_CHARACTER_DEVICE, 0),
+          PREOPEN_FD if ctx.storage.is_some() => (FILETYPE_DIRECTORY, 0),
+          _ => match ctx.wasi.files.get(&fd) {
+            Some(file) => (FILETYPE_REGULAR_FILE, file.data.len() as u64),
+            None => return Ok(errno::BADF),
+          },
+        };
+        // Device, inode and timestamps stay zero.
+        let mut stat = [0u8; 64];
+        stat[16] = filetype;
+        stat[24..32].copy_from_slice(&1u64.to_le_bytes());
+        stat[32..40].copy_from_slice(&size.to_le_bytes());
+        ReadWasmBuffer::write_memory(&mut caller, filestat, &stat)?;
+        Ok(errno::SUCCESS)
+      },
+    )?
+    // libc asks for the preopens from descriptor 3 up until it gets `EBADF`.
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_prestat_get",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, prestat: u32| -> Result<u32, wasmi::Error> {
+        if fd != PREOPEN_FD || caller.data().storage.is_none() {
+          return Ok(errno::BADF);
+        }
+        let mut bytes = [0u8; 8];
+        bytes[4..].copy_from_slice(&(PREOPEN_NAME.len() as u32).to_le_bytes());
+        ReadWasmBuffer::write_memory(&mut caller, prestat, &bytes)?;
+        Ok(errno::SUCCESS)
+      },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "fd_prestat_dir_name",
+      |mut caller: Caller<'_, WasmCtx<H>>, fd: u32, path: u32, path_len: u32| -> Result<u32, wasmi::Error> {
+        if fd != PREOPEN_FD || caller.data().storage.is_none() {
+          return Ok(errno::BADF);
+        }
+        let name = PREOPEN_NAME.as_bytes();
+        ReadWasmBuffer::write_memory(&mut caller, path, &name[..name.len().min(path_len as usize)])?;
+        Ok(errno::SUCCESS)
+      },
+    )?;
+  Ok(())
+}
+
+/// Files are opened and removed by path; there is no `path_filestat_get`,
+/// so `stat` on a path fails with `ENOSYS`.
+fn register_path_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>) -> Result<(), wasmi::Error> {
+  linker
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "path_open",
+      |mut caller: Caller<'_, WasmCtx<H>>,
+       dir_fd: u32,
+       _lookup_flags: u32,
+       path: u32,
+       path_len: u32,
+       oflags: u32,
+       _rights_base: u64,
+       _rights_inheriting: u64,
+       fdflags: u32,
+       fd_out: u32|
+       -> Result<u32, wasmi::Error> { Ok(path_open(&mut caller, dir_fd, path, path_len, oflags, fdflags, fd_out)?) },
+    )?
+    .func_wrap(
+      WASI_IMPORT_MODULE,
+      "path_unlink_file",
+      |caller: Caller<'_, WasmCtx<H>>, dir_fd: u32, path: u32, path_len: u32| -> Result<u32, wasmi::Error> {
+        let Some(storage) = caller.data().storage.clone() else {
+          return Ok(errno::BADF);
+        };
+        if dir_fd != PREOPEN_FD {
+          return Ok(errno::BADF);
+        }
+        let path = String::from_utf8(ReadWasmBuffer::read_memory(&caller, path, path_len)?).unwrap_or_default();
+        let Some(key) = sandbox_key(&path) else {
+          return Ok(errno::NOTCAPABLE);
+        };
+        match block_on(storage.delete(key)) {
+          Ok(()) => Ok(errno::SUCCESS),
+          Err(err) => Ok(storage_errno(err)),
+        }
+      },
+    )?;
+  Ok(())
+}
+
+/// Link every preview1 function `module` imports that isn't in
+/// [`WASI_FUNCTIONS`] to a stub returning `ENOSYS`, so a program that merely
+/// links one (libc pulls in more than most programs call) still runs. Stubs
+/// for functions without an `errno` result trap when called.
+pub fn stub_unsupported_functions<H: WasmHost>(linker: &mut Linker<WasmCtx<H>>, module: &Module) -> Result<(), wasmi::Error> {
+  for import in module.imports() {
+    if import.module() != WASI_IMPORT_MODULE || WASI_FUNCTIONS.contains(&import.name()) {
+      continue;
+    }
+    let ExternType::Func(ty) = import.ty() else {
+      continue;
+    };
+    let name = import.name().to_string();
+    let returns_errno = ty.results() == [ValType::I32];
+    linker.func_new(
+      WASI_IMPORT_MODULE,
+      import.name(),
+      ty.clone(),
+      move |_caller: Caller<'_, WasmCtx<H>>, _params: &[Val], results: &mut [Val]| -> Result<(), wasmi::Error> {
+        debug!("wasi: unsupported call to {name}");
+        if !returns_errno {
+          return Err(wasmi::Error::new(format!("App called unsupported WASI function {name}")));
+        }
+        results[0] = Val::I32(errno::NOSYS as i32);
+        Ok(())
+      },
+    )?;
+  }
+  Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  fn file(data: &[u8]) -> OpenFile {
+    OpenFile {
+      name: "save.txt".into(),
+      data: data.to_vec(),
+      pos: 0,
+      append: false,
+      dirty: false,
+    }
+  }
+
+  #[test]
+  fn paths_stay_in_the_sandbox() {
+    assert_eq!(sandbox_key("/save.txt"), Some("save.txt"));
+    assert_eq!(sandbox_key("./save.txt"), Some("save.txt"));
+    assert_eq!(sandbox_key("save.txt"), Some("save.txt"));
+    assert_eq!(sandbox_key("../snake/high_score"), None);
+    assert_eq!(sandbox_key("/levels/1.txt"), None);
+    assert_eq!(sandbox_key(".."), None);
+  }
+
+  #[test]
+  fn files_read_write_and_seek_in_memory() {
+    let mut save = file(b"hello");
+    assert_eq!(save.read(3), b"hel");
+    assert_eq!(save.seek(-1, WHENCE_END), Ok(4));
+    save.write(b"p!", 16).unwrap();
+    assert_eq!(save.data, b"hellp!");
+    assert!(save.dirty);
+    assert_eq!(save.seek(-1, WHENCE_SET), Err(errno::INVAL));
+    assert_eq!(save.write(&[0; 11], 16), Err(errno::NOSPC));
+
+    let mut log = file(b"a");
+    log.append = true;
+    log.write(b"b", 16).unwrap();
+    assert_eq!(log.data, b"ab");
+  }
+}
@@ -0,0 +1,62 @@
+//! Conversion of the screens guests send with `ShowScreen` into the
+//! renderer's own [`LcdScreen`].
+
+use display_types::{Icon20, Icon40, LcdScreen, MenuAnimation, MenuLine, TextBufferLine};
+use wasm_protocol as wire;
+
+/// The screen the host draws for `widget`.
+pub fn widget_screen(widget: wire::Widget) -> LcdScreen {
+  match widget {
+    wire::Widget::Splash => LcdScreen::Splash,
+    wire::Widget::Headline(icon, text) => LcdScreen::Headline(icon40(icon), text),
+    wire::Widget::Progress(text) => LcdScreen::Progress(text),
+    wire::Widget::BoundedProgress(step, steps) => LcdScreen::BoundedProgress(step, steps),
+    wire::Widget::Menu {
+      menu,
+      selected,
+      animation,
+      header,
+    } => LcdScreen::Menu {
+      menu: menu
+        .into_iter()
+        .map(|wire::MenuLine(icon, text)| MenuLine(icon20(icon), text))
+        .collect(),
+      selected,
+      animation: match animation {
+        wire::MenuAnimation::FromLeft => MenuAnimation::FromLeft,
+        wire::MenuAnimation::FromRight => MenuAnimation::FromRight,
+        wire::MenuAnimation::None => MenuAnimation::None,
+      },
+      header,
+    },
+    wire::Widget::TextBuffer { lines } => LcdScreen::TextBuffer {
+      lines: lines
+        .into_iter()
+        .map(|line| TextBufferLine {
+          text: line.text,
+          cursor: line.cursor,
+        })
+        .collect(),
+    },
+    wire::Widget::Notification(icon, text) => LcdScreen::Notification(icon40(icon), text),
+  }
+}
+
+fn icon20(icon: wire::Icon20) -> Icon20 {
+  match icon {
+    wire::Icon20::Home => Icon20::Home,
+    wire::Icon20::Config => Icon20::Config,
+    wire::Icon20::Wifi => Icon20::Wifi,
+    wire::Icon20::File => Icon20::File,
+    wire::Icon20::Info => Icon20::Info,
+  }
+}
+
+fn icon40(icon: wire::Icon40) -> Icon40 {
+  match icon {
+    wire::Icon40::Info => Icon40::Info,
+    wire::Icon40::Warn => Icon40::Warn,
+    wire::Icon40::Error => Icon40::Error,
+    wire::Icon40::Wifi => Icon40::Wifi,
+  }
+}
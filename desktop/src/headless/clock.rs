@@ -0,0 +1,182 @@
+//! The simulated clock headless runs are timed by.
+//!
+//! The guest only sees time move when the runner advances the clock, a frame
+//! at a time up to each script step. After each advance, and after each
+//! input, the runner waits for the session to settle: for the guest to go
+//! idle with nothing due and for everything it sent to be handled. So a
+//! script replays with the same ticks at the same times on every run, however
+//! fast the machine is. Apps that never go idle can't be waited for, and are
+//! only given a frame of real time per frame.
+
+use std::pin::{Pin, pin};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::task::{Context, Poll, Wake, Waker};
+use std::time::Duration;
+
+#[derive(Default)]
+pub struct VirtualClock {
+  state: Mutex<State>,
+  changed: Condvar,
+}
+
+#[derive(Default)]
+struct State {
+  now_us: u64,
+  /// While the guest is idle: the time it waits for, if any.
+  idle_until: Option<Option<u64>>,
+  waker: Option<Waker>,
+  /// Whether the session has been woken since it was last polled.
+  woken: bool,
+  /// Whether the session is waiting to be woken.
+  parked: bool,
+  /// Whether the session has finished.
+  stopped: bool,
+}
+
+impl State {
+  /// Whether the session is parked with the guest idle and nothing due at the
+  /// current time, or has finished.
+  fn settled(&self) -> bool {
+    let idle = self
+      .idle_until
+      .is_some_and(|until| until.is_none_or(|until_us| until_us > self.now_us));
+    self.stopped || self.parked && idle
+  }
+}
+
+impl VirtualClock {
+  fn state(&self) -> MutexGuard<'_, State> {
+    self.state.lock().unwrap()
+  }
+
+  pub fn now_us(&self) -> u64 {
+    self.state().now_us
+  }
+
+  pub fn now_ms(&self) -> u64 {
+    self.now_us() / 1000
+  }
+
+  /// Move the clock on to `ms`, waking the guest if it waits for it.
+  pub fn advance_to_ms(&self, ms: u64) {
+    let waker = {
+      let mut state = self.state();
+      state.now_us = state.now_us.max(ms * 1000);
+      state.waker.take()
+    };
+    if let Some(waker) = waker {
+      waker.wake();
+    }
+  }
+
+  /// Wait, for up to `timeout` of real time, for the session to settle.
+  /// `false` if it didn't.
+  pub fn wait_settled(&self, timeout: Duration) -> bool {
+    let state = self.state();
+    let (state, _) = self.changed.wait_timeout_while(state, timeout, |state| !state.settled()).unwrap();
+    state.settled()
+  }
+
+  /// Wait for `ms` of the clock to pass, or for ever for `None`.
+  pub fn idle(&self, ms: Option<u64>) -> Idle<'_> {
+    Idle {
+      clock: self,
+      until_us: ms.map(|ms| self.now_us() + ms * 1000),
+      registered: false,
+    }
+  }
+
+  /// Run the session on this thread, parking it whenever it waits, so the
+  /// runner can tell when it has settled.
+  pub fn block_on<F: Future>(self: &Arc<Self>, future: F) -> F::Output {
+    let waker = Waker::from(self.clone());
+    let mut cx = Context::from_waker(&waker);
+    let mut future = pin!(future);
+    loop {
+      self.state().woken = false;
+      if let Poll::Ready(output) = future.as_mut().poll(&mut cx) {
+        self.state().stopped = true;
+        self.changed.notify_all();
+        return output;
+      }
+      let mut state = self.state();
+      if !state.woken {
+        state.parked = true;
+        self.changed.notify_all();
+        state = self.changed.wait_while(state, |state| !state.woken).unwrap();
+      }
+      state.parked = false;
+    }
+  }
+}
+
+impl Wake for VirtualClock {
+  fn wake(self: Arc<Self>) {
+    let mut state = self.state();
+    state.woken = true;
+    state.parked = false;
+    self.changed.notify_all();
+  }
+}
+
+/// The guest waiting on the clock. It stops being idle when this completes
+/// or is dropped, e.g. because a host message arrived.
+pub struct Idle<'a> {
+  clock: &'a VirtualClock,
+  until_us: Option<u64>,
+  registered: bool,
+}
+
+impl Future for Idle<'_> {
+  type Output = ();
+
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
+    let clock = self.clock;
+    let mut state = clock.state();
+    if self.until_us.is_some_and(|until_us| state.now_us >= until_us) {
+      state.idle_until = None;
+      return Poll::Ready(());
+    }
+    state.idle_until = Some(self.until_us);
+    state.waker = Some(cx.waker().clone());
+    drop(state);
+    self.registered = true;
+    Poll::Pending
+  }
+}
+
+impl Drop for Idle<'_> {
+  fn drop(&mut self) {
+    if self.registered {
+      self.clock.state().idle_until = None;
+    }
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn sessions_settle_until_the_clock_reaches_the_guests_time() {
+    let clock = Arc::new(VirtualClock::default());
+    std::thread::spawn({
+      let clock = clock.clone();
+      move || {
+        clock.block_on(async {
+          clock.idle(Some(100)).await;
+          clock.idle(None).await;
+        })
+      }
+    });
+
+    assert!(clock.wait_settled(Duration::from_secs(5)));
+    clock.advance_to_ms(99);
+    assert!(clock.wait_settled(Duration::from_secs(5)));
+    assert_eq!(clock.state().idle_until, Some(Some(100_000)));
+
+    clock.advance_to_ms(100);
+    assert!(clock.wait_settled(Duration::from_secs(5)));
+    assert_eq!(clock.state().idle_until, Some(None));
+  }
+}
@@ -0,0 +1,116 @@
+//! Captured screens, saved as PNG and compared against golden images.
+
+use std::io::Cursor;
+
+/// An RGB888 image, row by row.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Frame {
+  pub width: u32,
+  pub height: u32,
+  pub rgb: Vec<u8>,
+}
+
+/// How far a frame is from its golden image.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Difference {
+  /// Pixels with a channel further off than the tolerance.
+  pub pixels: usize,
+  /// The largest difference in any one channel.
+  pub max_delta: u8,
+}
+
+impl Frame {
+  /// Convert a frame as the render loop draws it (RGB565, high byte first).
+  pub fn from_rgb565(width: u32, height: u32, rgb565: &[u8]) -> Self {
+    let rgb = rgb565
+      .as_chunks::<2>()
+      .0
+      .iter()
+      .flat_map(|&pixel| crate::rgb565_to_rgb888(u16::from_be_bytes(pixel)))
+      .collect();
+    Self { width, height, rgb }
+  }
+
+  pub fn encode_png(&self) -> Result<Vec<u8>, png::EncodingError> {
+    let mut out = Vec::new();
+    let mut encoder = png::Encoder::new(&mut out, self.width, self.height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.write_header()?.write_image_data(&self.rgb)?;
+    Ok(out)
+  }
+
+  /// Decode a PNG of any colour type. Alpha is ignored.
+  pub fn decode_png(bytes: &[u8]) -> Result<Self, png::DecodingError> {
+    let mut decoder = png::Decoder::new(Cursor::new(bytes));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info()?;
+    let mut buf = vec![0; reader.output_buffer_size().unwrap_or_default()];
+    let info = reader.next_frame(&mut buf)?;
+    let pixels = &buf[..info.buffer_size()];
+
+    let rgb = match info.color_type {
+      png::ColorType::Rgb => pixels.to_vec(),
+      png::ColorType::Rgba => pixels.as_chunks::<4>().0.iter().flat_map(|&[r, g, b, _]| [r, g, b]).collect(),
+      png::ColorType::GrayscaleAlpha => pixels.as_chunks::<2>().0.iter().flat_map(|&[value, _]| [value; 3]).collect(),
+      png::ColorType::Grayscale | png::ColorType::Indexed => pixels.iter().flat_map(|&value| [value; 3]).collect(),
+    };
+    Ok(Self {
+      width: info.width,
+      height: info.height,
+      rgb,
+    })
+  }
+
+  /// Compare with `golden`, allowing each channel to be up to `tolerance`
+  /// off. `None` if the sizes differ.
+  pub fn compare(&self, golden: &Frame, tolerance: u8) -> Option<Difference> {
+    if (self.width, self.height) != (golden.width, golden.height) {
+      return None;
+    }
+    let mut difference = Difference { pixels: 0, max_delta: 0 };
+    for (pixel, expected) in self.rgb.as_chunks::<3>().0.iter().zip(golden.rgb.as_chunks::<3>().0) {
+      let delta = pixel.iter().zip(expected).map(|(a, b)| a.abs_diff(*b)).max().unwrap_or_default();
+      difference.max_delta = difference.max_delta.max(delta);
+      if delta > tolerance {
+        difference.pixels += 1;
+      }
+    }
+    Some(difference)
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn round_trips_through_png() {
+    let frame = Frame::from_rgb565(2, 1, &[0xF8, 0x00, 0x07, 0xFF]);
+    assert_eq!(frame.rgb, [255, 0, 0, 0, 255, 255]);
+    assert_eq!(Frame::decode_png(&frame.encode_png().unwrap()).unwrap(), frame);
+  }
+
+  #[test]
+  fn counts_pixels_beyond_the_tolerance() {
+    let golden = Frame {
+      width: 3,
+      height: 1,
+      rgb: vec![10, 10, 10, 100, 100, 100, 0, 0, 0],
+    };
+    let frame = Frame {
+      rgb: vec![12, 10, 10, 100, 90, 100, 0, 0, 0],
+      ..golden.clone()
+    };
+    assert_eq!(frame.compare(&golden, 0), Some(Difference { pixels: 2, max_delta: 10 }));
+    assert_eq!(frame.compare(&golden, 2), Some(Difference { pixels: 1, max_delta: 10 }));
+    assert_eq!(frame.compare(&golden, 10), Some(Difference { pixels: 0, max_delta: 10 }));
+
+    let smaller = Frame {
+      width: 1,
+      rgb: vec![0; 3],
+      ..golden.clone()
+    };
+    assert_eq!(frame.compare(&smaller, 255), None);
+  }
+}
@@ -0,0 +1,460 @@
+//! Headless mode: run a WASM app with no window, feed it input from a script
+//! and check its screen against golden images, so CI can regression-test apps.
+//!
+//! ```text
+//! desktop --headless sdk/wasm/snake.wsm snake.script --golden tests/snake --tolerance 8
+//! ```
+//!
+//! Each `capture` step writes `<name>.png` to the `--out` directory
+//! (`target/headless` by default). With `--golden` the capture is compared
+//! with the image of the same name there, and a frame fails if more than
+//! `--max-diff` pixels (default 0) have a channel more than `--tolerance`
+//! (default 0) off. `--update` writes the captures to the golden directory
+//! instead, to record new golden images. See [`script`] for the script format.
+//!
+//! The app runs on a simulated clock rather than the real one. The runner
+//! moves it on a frame at a time up to each step, and lets the app finish
+//! what it does at each of those times (and with each input) before moving
+//! on, so a script gives the same frames however fast the machine is. See
+//! [`clock`].
+//!
+//! Exits with 0 if every frame matched, 1 if one differed or had no golden
+//! image, 2 for bad arguments or a bad script, and 3 if the app trapped.
+
+mod clock;
+mod frame;
+mod script;
+
+use crate::platform::display::LCD_BUFFER;
+use crate::platform::{DesktopDisplayManager, DesktopLedManager, DesktopLocalFs};
+use crate::tasks::wasm::{DesktopWasmHost, WasmSource, run_session};
+use crate::{DesktopFrameBuffer, HEIGHT, WIDTH};
+use app::platform::{
+  CrashLogHandle, CrashReport, DisplayError, DisplayHandle, DisplayManager, FrameRegion, LedHandle, LedManager, LocalFsTrait,
+  StorageHandle, WallClockHandle,
+};
+use app::protocol::{HostIpcChannel, HostIpcMessage, HostIpcSender, HostRuntimeCommand};
+use app::types::{LedRequest, LedState, NUM_LEDS};
+use app::wasm::fault::GuestFault;
+use app::wasm::host::WasmHost;
+use app::wasm::{AppStorage, GuestSockets, WasmLimits};
+use clock::VirtualClock;
+use display_renderer::LcdState;
+use display_types::LcdScreen;
+use frame::Frame;
+use script::{Action, Script};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, mpsc};
+use std::time::Duration;
+use wasm_protocol::{Capabilities, HostIpcMessage as WireHostIpcMessage, KeyEvent, KeyModifiers};
+
+const USAGE: &str =
+  "usage: desktop --headless <app.wsm> <script> [--out DIR] [--golden DIR] [--update] [--tolerance N] [--max-diff PIXELS]";
+
+const EXIT_MISMATCH: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_TRAPPED: i32 = 3;
+
+/// How often, in simulated milliseconds, the screen is redrawn while waiting
+/// for the next step, as in the window's render loop. Notifications and menu
+/// animations advance with it.
+const FRAME_INTERVAL_MS: u64 = 33;
+
+/// How much real time the app gets to go idle after its clock moves or it is
+/// sent a message. An app that doesn't is treated as always busy from then
+/// on, and only gets [`BUSY_TIMEOUT`] each time.
+const SETTLE_TIMEOUT: Duration = Duration::from_secs(1);
+
+/// How much real time an app that never goes idle gets per frame.
+const BUSY_TIMEOUT: Duration = Duration::from_millis(FRAME_INTERVAL_MS);
+
+/// How long the app gets to stop after the last step.
+const STOP_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// The time of day apps see at the start of the simulated clock, 2024-01-01
+/// 00:00:00 UTC, so a clock drawn by an app looks the same on every run.
+const START_UNIX_MICROS: u64 = 1_704_067_200_000_000;
+
+struct Options {
+  app: PathBuf,
+  script: PathBuf,
+  out: PathBuf,
+  golden: Option<PathBuf>,
+  update: bool,
+  tolerance: u8,
+  max_diff: usize,
+}
+
+/// Run headless with the arguments after `--headless`, returning the exit
+/// code.
+pub fn main(args: impl Iterator<Item = String>) -> i32 {
+  let args: Vec<String> = args.collect();
+  let options = match parse_args(&args) {
+    Ok(options) => options,
+    Err(err) => {
+      eprintln!("{err}\n{USAGE}");
+      return EXIT_USAGE;
+    }
+  };
+  let (wasm, script) = match load(&options) {
+    Ok(loaded) => loaded,
+    Err(err) => {
+      eprintln!("{err}");
+      return EXIT_USAGE;
+    }
+  };
+  if let Err(err) = fs::create_dir_all(&options.out) {
+    eprintln!("failed to create {}: {err}", options.out.display());
+    return EXIT_USAGE;
+  }
+
+  let data_dir = std::env::temp_dir().join(format!("rustagon-headless-{}", std::process::id()));
+  let code = Runner::new(&options, &data_dir, wasm, script.seed).run(&script);
+  fs::remove_dir_all(&data_dir).ok();
+  code
+}
+
+fn parse_args(args: &[String]) -> Result<Options, String> {
+  let mut paths = Vec::new();
+  let mut out = PathBuf::from("target/headless");
+  let (mut golden, mut update, mut tolerance, mut max_diff) = (None, false, 0, 0);
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+    match arg.as_str() {
+      "--out" => out = PathBuf::from(value()?),
+      "--golden" => golden = Some(PathBuf::from(value()?)),
+      "--update" => update = true,
+      "--tolerance" => tolerance = value()?.parse().map_err(|_| format!("{arg} takes 0 to 255"))?,
+      "--max-diff" => max_diff = value()?.parse().map_err(|_| format!("{arg} takes a pixel count"))?,
+      _ => paths.push(arg),
+    }
+  }
+  if update && golden.is_none() {
+    return Err("--update needs --golden".into());
+  }
+  match paths[..] {
+    [app, script] => Ok(Options {
+      app: crate::resolve_wasm_path(app).ok_or_else(|| format!("no app at '{app}'"))?,
+      script: PathBuf::from(script),
+      out,
+      golden,
+      update,
+      tolerance,
+      max_diff,
+    }),
+    _ => Err("expected an app and a script".into()),
+  }
+}
+
+fn load(options: &Options) -> Result<(Vec<u8>, Script), String> {
+  let wasm = fs::read(&options.app).map_err(|err| format!("failed to read {}: {err}", options.app.display()))?;
+  let text = fs::read_to_string(&options.script).map_err(|err| format!("failed to read {}: {err}", options.script.display()))?;
+  let script = Script::parse(&text).map_err(|err| format!("{}: {err}", options.script.display()))?;
+  Ok((wasm, script))
+}
+
+/// One app session, driven from the main thread while the guest runs on its
+/// own, like the window's render loop.
+struct Runner<'a> {
+  options: &'a Options,
+  host_sender: HostIpcSender,
+  clock: Arc<VirtualClock>,
+  /// Whether the guest has failed to go idle in [`SETTLE_TIMEOUT`].
+  busy: bool,
+  /// The guest's result once it has stopped.
+  finished: mpsc::Receiver<Result<(), String>>,
+  display: Arc<DesktopDisplayManager>,
+  lcd_state: LcdState,
+  rgb565: Vec<u8>,
+  modifiers: KeyModifiers,
+  failures: usize,
+}
+
+impl<'a> Runner<'a> {
+  /// Start the app, with a fresh, empty data directory for its storage.
+  fn new(options: &'a Options, data_dir: &Path, wasm: Vec<u8>, seed: u64) -> Self {
+    // Leaked for static lifetime, as in the windowed build.
+    let host_channel = Box::leak(Box::new(HostIpcChannel::new()));
+    let host_sender = host_channel.sender();
+    let host_receiver = host_channel.receiver();
+
+    let display = Arc::new(DesktopDisplayManager::new());
+    let display_handle = DisplayHandle::new(display.clone() as Arc<dyn DisplayManager>);
+    let clock = Arc::new(VirtualClock::default());
+    let host = HeadlessHost {
+      desktop: DesktopWasmHost::new(
+        display_handle.clone(),
+        LedHandle::new(Arc::new(DesktopLedManager) as Arc<dyn LedManager>),
+        CrashLogHandle::new(),
+        WallClockHandle::new(),
+      ),
+      clock: clock.clone(),
+      rng: seed,
+    };
+    let storage = StorageHandle::new(Arc::new(DesktopLocalFs::new(data_dir)) as Arc<dyn LocalFsTrait>);
+
+    let (done, finished) = mpsc::channel();
+    let session_clock = clock.clone();
+    std::thread::spawn(move || {
+      let limits = WasmLimits::for_module(&wasm);
+      let result = session_clock.block_on(run_session(
+        host,
+        WasmSource::Buffer {
+          buffer: wasm,
+          app_storage: AppStorage::scratch(storage),
+        },
+        limits,
+        host_sender,
+        host_receiver,
+        None,
+        &mut GuestSockets::new(None, None),
+        display_handle,
+      ));
+      done.send(result).ok();
+    });
+
+    Self {
+      options,
+      host_sender,
+      clock,
+      busy: false,
+      finished,
+      display,
+      lcd_state: LcdState::new(LcdScreen::Blank, 0),
+      rgb565: vec![0; WIDTH * HEIGHT * 2],
+      modifiers: KeyModifiers::NONE,
+      failures: 0,
+    }
+  }
+
+  fn run(mut self, script: &Script) -> i32 {
+    // Let the app start up.
+    self.settle();
+    let mut result = self.finished.try_recv().ok();
+
+    for step in &script.steps {
+      while result.is_none() && self.clock.now_ms() < step.at_ms {
+        self.clock.advance_to_ms((self.clock.now_ms() + FRAME_INTERVAL_MS).min(step.at_ms));
+        self.settle();
+        self.render();
+        result = self.finished.try_recv().ok();
+      }
+      if result.is_some() {
+        if let Action::Capture(name) = &step.action {
+          self.fail(format!("{name}: the app stopped before the capture"));
+        }
+        continue;
+      }
+
+      match &step.action {
+        Action::Button(button) => self.send(HostIpcMessage::Wire(WireHostIpcMessage::HexButton(*button))),
+        Action::Key { code, pressed } => {
+          self.modifiers = self.modifiers.update(*code, *pressed);
+          let key = KeyEvent {
+            port: 0,
+            code: *code,
+            pressed: *pressed,
+            modifiers: self.modifiers,
+          };
+          self.send(HostIpcMessage::Wire(WireHostIpcMessage::Key(key)));
+        }
+        Action::Capture(name) => {
+          self.render();
+          self.capture(name);
+        }
+        Action::Stop => break,
+      }
+    }
+
+    let result = result.unwrap_or_else(|| {
+      self.deliver(HostIpcMessage::Runtime(HostRuntimeCommand::Stop));
+      self
+        .finished
+        .recv_timeout(STOP_TIMEOUT)
+        .unwrap_or_else(|_| Err("the app did not stop".into()))
+    });
+
+    match result {
+      Err(reason) => {
+        log::error!("headless: {reason}");
+        EXIT_TRAPPED
+      }
+      Ok(()) if self.failures > 0 => {
+        log::error!("headless: {} frame(s) failed", self.failures);
+        EXIT_MISMATCH
+      }
+      Ok(()) => 0,
+    }
+  }
+
+  /// Send `msg` to the guest and wait for it to settle again, having taken
+  /// it.
+  fn send(&mut self, msg: HostIpcMessage) {
+    if self.deliver(msg) {
+      self.settle();
+    }
+  }
+
+  /// Queue `msg` for the guest. Each message is waited for, so the channel
+  /// is only still full if the app is busy; it gets one more wait to take
+  /// the last one then. Returns whether `msg` was queued.
+  fn deliver(&mut self, msg: HostIpcMessage) -> bool {
+    use embassy_sync::channel::TrySendError::Full;
+    if let Err(Full(unsent)) = self.host_sender.try_send((0, msg)) {
+      self.settle();
+      if let Err(Full((_, unsent))) = self.host_sender.try_send(unsent) {
+        log::warn!("headless: the app is not reading its messages, dropped {unsent:?}");
+        return false;
+      }
+    }
+    true
+  }
+
+  /// Wait for the guest to go idle with nothing due at the current time,
+  /// with everything it sent handled.
+  fn settle(&mut self) {
+    let timeout = if self.busy { BUSY_TIMEOUT } else { SETTLE_TIMEOUT };
+    let settled = self.clock.wait_settled(timeout);
+    if !settled && !self.busy {
+      log::warn!("headless: the app did not go idle, so its frames depend on the machine's speed");
+    }
+    self.busy = !settled;
+  }
+
+  /// Draw the current screen into `rgb565`, as the window's render loop does.
+  fn render(&mut self) {
+    if let Some(raw) = LCD_BUFFER.lock().unwrap().as_deref() {
+      self.rgb565.copy_from_slice(raw);
+      return;
+    }
+    let now = self.clock.now_ms() as i32;
+    let (screen, _) = self.display.get_screen();
+    self.lcd_state.update(screen, now);
+    self.lcd_state.notification_cleanup(now);
+    self.rgb565.fill(0);
+    self
+      .lcd_state
+      .draw(&mut DesktopFrameBuffer(&mut self.rgb565), &self.lcd_state.screen, now);
+  }
+
+  fn capture(&mut self, name: &str) {
+    let options = self.options;
+    let file_name = if name.ends_with(".png") {
+      name.to_string()
+    } else {
+      format!("{name}.png")
+    };
+    let frame = Frame::from_rgb565(WIDTH as u32, HEIGHT as u32, &self.rgb565);
+    let png = frame.encode_png().expect("encoding a PNG in memory");
+    let path = options.out.join(&file_name);
+    if let Err(err) = fs::write(&path, &png) {
+      self.fail(format!("{name}: failed to write {}: {err}", path.display()));
+    }
+
+    let Some(golden_dir) = &options.golden else {
+      log::info!("headless: captured {}", path.display());
+      return;
+    };
+    let golden_path = golden_dir.join(&file_name);
+    if options.update {
+      match fs::create_dir_all(golden_dir).and_then(|()| fs::write(&golden_path, &png)) {
+        Ok(()) => log::info!("headless: recorded {}", golden_path.display()),
+        Err(err) => self.fail(format!("{name}: failed to write {}: {err}", golden_path.display())),
+      }
+      return;
+    }
+
+    let golden = match fs::read(&golden_path) {
+      Ok(bytes) => Frame::decode_png(&bytes).map_err(|err| err.to_string()),
+      Err(err) => Err(err.to_string()),
+    };
+    match golden.map(|golden| frame.compare(&golden, options.tolerance)) {
+      Err(err) => self.fail(format!("{name}: can't read {}: {err}", golden_path.display())),
+      Ok(None) => self.fail(format!("{name}: {} is not {WIDTH}x{HEIGHT}", golden_path.display())),
+      Ok(Some(diff)) if diff.pixels > options.max_diff => self.fail(format!(
+        "{name}: {} pixels differ from {} (by up to {})",
+        diff.pixels,
+        golden_path.display(),
+        diff.max_delta
+      )),
+      Ok(Some(diff)) => log::info!("headless: {name} matches ({} pixels differ)", diff.pixels),
+    }
+  }
+
+  fn fail(&mut self, message: String) {
+    log::error!("headless: {message}");
+    self.failures += 1;
+  }
+}
+
+/// The desktop host with a simulated clock, reproducible random numbers and
+/// no network, so a script gives the same frames on every run.
+struct HeadlessHost {
+  desktop: DesktopWasmHost,
+  clock: Arc<VirtualClock>,
+  /// SplitMix64 state.
+  rng: u64,
+}
+
+impl WasmHost for HeadlessHost {
+  fn write_stdout(&mut self, text: &str) {
+    self.desktop.write_stdout(text);
+  }
+
+  fn get_millis(&self) -> u64 {
+    self.clock.now_ms()
+  }
+
+  fn get_micros(&self) -> u64 {
+    self.clock.now_us()
+  }
+
+  fn idle(&self, ms: Option<u64>) -> impl Future<Output = ()> {
+    self.clock.idle(ms)
+  }
+
+  fn unix_time_micros(&self) -> Option<u64> {
+    Some(START_UNIX_MICROS + self.clock.now_us())
+  }
+
+  fn fill_random(&mut self, dest: &mut [u8]) {
+    for chunk in dest.chunks_mut(8) {
+      self.rng = self.rng.wrapping_add(0x9E37_79B9_7F4A_7C15);
+      let mut z = self.rng;
+      z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+      z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+      z ^= z >> 31;
+      chunk.copy_from_slice(&z.to_le_bytes()[..chunk.len()]);
+    }
+  }
+
+  fn set_lcd_buffer(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+    self.desktop.set_lcd_buffer(buffer)
+  }
+
+  fn set_lcd_region(&mut self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    self.desktop.set_lcd_region(region, buffer, stride)
+  }
+
+  fn set_gpio(&mut self, pin: u32, state: u32) -> Result<(), GuestFault> {
+    self.desktop.set_gpio(pin, state)
+  }
+
+  fn set_leds(&mut self, leds: &[LedState; NUM_LEDS]) -> Result<(), app::platform::led::LedError> {
+    self.desktop.set_leds(leds)
+  }
+
+  fn set_led_effect(&mut self, request: LedRequest) -> Result<(), app::platform::led::LedError> {
+    self.desktop.set_led_effect(request)
+  }
+
+  fn capabilities(&self) -> Capabilities {
+    Capabilities::LEDS
+  }
+
+  fn report_crash(&mut self, report: CrashReport) {
+    self.desktop.report_crash(report);
+  }
+}
@@ -0,0 +1,199 @@
+//! Input scripts for the headless runner: one timed step per line.
+//!
+//! ```text
+//! # Times are milliseconds after the app started.
+//! seed 42
+//! 500   tap Fire
+//! 800   press Left
+//! 1200  release Left
+//! 1500  type hi
+//! 2000  capture menu
+//! 6000  stop
+//! ```
+//!
+//! `press`, `release` and `tap` (press then release) take a [`HexButton`];
+//! `keydown`, `keyup` and `key` take a [`KeyCode`], both by variant name.
+//! `type` taps the keys for lowercase letters, digits and spaces. `capture`
+//! saves the screen as `<name>.png`, and `stop` ends the app; without it the
+//! app is stopped after the last step. `seed` fixes the guest's random
+//! numbers, which otherwise start from 0.
+
+use app::types::{HexButton, KeyCode};
+use core::fmt;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Action {
+  Button(HexButton),
+  Key { code: KeyCode, pressed: bool },
+  Capture(String),
+  Stop,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Step {
+  /// Milliseconds after the app started.
+  pub at_ms: u64,
+  pub action: Action,
+}
+
+#[derive(Debug, Default)]
+pub struct Script {
+  pub seed: u64,
+  /// In time order.
+  pub steps: Vec<Step>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ParseError {
+  pub line: usize,
+  pub message: String,
+}
+
+impl fmt::Display for ParseError {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    write!(f, "line {}: {}", self.line, self.message)
+  }
+}
+
+impl Script {
+  pub fn parse(text: &str) -> Result<Self, ParseError> {
+    let mut script = Script::default();
+    for (index, line) in text.lines().enumerate() {
+      let line_no = index + 1;
+      let error = |message: String| ParseError { line: line_no, message };
+
+      let line = line.split('#').next().unwrap_or_default();
+      let mut words = line.split_whitespace();
+      let Some(first) = words.next() else {
+        continue;
+      };
+      if first == "seed" {
+        let value = words.next().ok_or_else(|| error("missing seed".into()))?;
+        script.seed = value.parse().map_err(|_| error(format!("bad seed {value:?}")))?;
+        continue;
+      }
+
+      let at_ms: u64 = first.parse().map_err(|_| error(format!("expected a time in ms, got {first:?}")))?;
+      if script.steps.last().is_some_and(|step| step.at_ms > at_ms) {
+        return Err(error(format!("{at_ms} ms is earlier than the step before")));
+      }
+      let command = words.next().ok_or_else(|| error("missing command".into()))?;
+      let argument = words.next();
+      let argument = || argument.ok_or_else(|| error(format!("`{command}` needs an argument")));
+
+      let actions = match command {
+        "press" => vec![Action::Button(hex_button(argument()?).map_err(error)?)],
+        "release" => vec![Action::Button(hex_button(argument()?).map_err(error)?.released())],
+        "tap" => {
+          let button = hex_button(argument()?).map_err(error)?;
+          vec![Action::Button(button), Action::Button(button.released())]
+        }
+        "keydown" => vec![Action::Key {
+          code: key_code(argument()?).map_err(error)?,
+          pressed: true,
+        }],
+        "keyup" => vec![Action::Key {
+          code: key_code(argument()?).map_err(error)?,
+          pressed: false,
+        }],
+        "key" => key_taps([key_code(argument()?).map_err(error)?]),
+        "type" => {
+          let text = line.trim_start()[first.len()..].trim_start()[command.len()..].trim();
+          key_taps(
+            text
+              .chars()
+              .map(char_key)
+              .collect::<Option<Vec<_>>>()
+              .ok_or_else(|| error(format!("can't type {text:?}")))?,
+          )
+        }
+        "capture" => vec![Action::Capture(argument()?.to_string())],
+        "stop" => vec![Action::Stop],
+        other => return Err(error(format!("unknown command {other:?}"))),
+      };
+      script.steps.extend(actions.into_iter().map(|action| Step { at_ms, action }));
+    }
+    Ok(script)
+  }
+}
+
+/// Look up a button by its variant name, as spelled in the wire protocol.
+fn hex_button(name: &str) -> Result<HexButton, String> {
+  serde_json::from_value(serde_json::Value::String(name.to_string())).map_err(|_| format!("unknown button {name:?}"))
+}
+
+fn key_code(name: &str) -> Result<KeyCode, String> {
+  serde_json::from_value(serde_json::Value::String(name.to_string())).map_err(|_| format!("unknown key {name:?}"))
+}
+
+fn key_taps(codes: impl IntoIterator<Item = KeyCode>) -> Vec<Action> {
+  codes
+    .into_iter()
+    .flat_map(|code| [Action::Key { code, pressed: true }, Action::Key { code, pressed: false }])
+    .collect()
+}
+
+fn char_key(c: char) -> Option<KeyCode> {
+  match c {
+    ' ' => Some(KeyCode::Space),
+    '0'..='9' => key_code(&format!("Digit{c}")).ok(),
+    'a'..='z' => key_code(&c.to_ascii_uppercase().to_string()).ok(),
+    _ => None,
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn parses_timed_steps() {
+    let script = Script::parse("seed 7\n# comment\n100 tap Fire\n\n250 key A # trailing\n300 capture start\n400 stop\n").unwrap();
+    assert_eq!(script.seed, 7);
+    let steps: Vec<_> = script.steps.iter().map(|step| (step.at_ms, step.action.clone())).collect();
+    assert_eq!(
+      steps,
+      [
+        (100, Action::Button(HexButton::Fire)),
+        (100, Action::Button(HexButton::FireReleased)),
+        (
+          250,
+          Action::Key {
+            code: KeyCode::A,
+            pressed: true
+          }
+        ),
+        (
+          250,
+          Action::Key {
+            code: KeyCode::A,
+            pressed: false
+          }
+        ),
+        (300, Action::Capture("start".into())),
+        (400, Action::Stop),
+      ]
+    );
+  }
+
+  #[test]
+  fn types_text_as_key_taps() {
+    let script = Script::parse("0  type  a 1").unwrap();
+    let codes: Vec<_> = script
+      .steps
+      .iter()
+      .filter_map(|step| match step.action {
+        Action::Key { code, pressed: true } => Some(code),
+        _ => None,
+      })
+      .collect();
+    assert_eq!(codes, [KeyCode::A, KeyCode::Space, KeyCode::Digit1]);
+  }
+
+  #[test]
+  fn reports_the_offending_line() {
+    assert_eq!(Script::parse("100 tap Fire\n50 tap Up").unwrap_err().line, 2);
+    assert_eq!(Script::parse("100 tap Jump").unwrap_err().line, 1);
+    assert_eq!(Script::parse("soon tap Up").unwrap_err().line, 1);
+  }
+}
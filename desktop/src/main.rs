@@ -1,7 +1,9 @@
 #![feature(impl_trait_in_assoc_type)]
 #![recursion_limit = "256"]
 
+mod headless;
 mod platform;
+mod profile;
 mod tasks;
 
 use app::menu::menu_task;
@@ -16,20 +18,33 @@ use app::types::{
 use display_renderer::{FrameBuffer, LcdState};
 use embedded_graphics::prelude::RawData as _;
 use minifb::{Key, KeyRepeat, Window, WindowOptions};
-use platform::{DesktopHexpansionManager, DesktopInputManager, DesktopPlatform, DesktopSystemManager};
+use platform::{DesktopHexpansionManager, DesktopInputManager, DesktopLedManager, DesktopPlatform, DesktopSystemManager};
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 const WIDTH: usize = 240;
 const HEIGHT: usize = 240;
 
+/// Border around the LCD in the desktop window, where the simulated LED ring
+/// is drawn.
+const LED_MARGIN: usize = 20;
+const WINDOW_WIDTH: usize = WIDTH + 2 * LED_MARGIN;
+const WINDOW_HEIGHT: usize = HEIGHT + 2 * LED_MARGIN;
+const LED_RADIUS: i32 = 6;
+
 fn main() {
   env_logger::builder()
     .filter_level(log::LevelFilter::Info)
     .parse_default_env()
     .init();
 
-  let mut args = std::env::args().skip(1);
+  let mut args = std::env::args().skip(1).peekable();
+  if args.next_if_eq("--headless").is_some() {
+    std::process::exit(headless::main(args));
+  }
+  if args.next_if_eq("--profile").is_some() {
+    profile::enable();
+  }
   let (data_dir, wasm_app) = match (args.next(), args.next()) {
     (Some(data), Some(app)) => (PathBuf::from(data), resolve_wasm_path(&app)),
     (Some(arg), None) => match resolve_wasm_path(&arg) {
@@ -38,7 +53,9 @@ fn main() {
     },
     (None, _) => (default_data_dir(), None),
   };
-  let wasm_buffer = wasm_app.map(|path| std::fs::read(&path).unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display())));
+  let wasm_buffer = wasm_app
+    .as_ref()
+    .map(|path| std::fs::read(path).unwrap_or_else(|e| panic!("failed to read {}: {e}", path.display())));
   let platform = Arc::new(DesktopPlatform::new(data_dir));
 
   // IPC channels — leaked for static lifetime (never freed on desktop)
@@ -50,6 +67,7 @@ fn main() {
   let stack_event_handle = app::menu::state::create_stack_event_handle();
   let stack_event_for_wasm = stack_event_handle.clone();
   let stack_event_for_http = stack_event_handle.clone();
+  let stack_event_for_reload = stack_event_handle.clone();
 
   // App loader: sends StartWasm over IPC when user picks a WASM app.
   let app_loader: Option<
@@ -73,13 +91,21 @@ fn main() {
     auto_launch: wasm_buffer,
   };
 
+  // Keep the clock in sync with the configured NTP server
+  platform::spawn_sntp_sync(platform.config_manager(), platform.wall_clock());
+
   // Spawn the WASM runner thread (analogous to firmware's second_core_task)
   tasks::wasm::spawn_wasm_runner(
     host_receiver.clone(),
     host_sender.clone(),
     stack_event_for_wasm,
     platform.http_client().unwrap(),
+    platform.tcp_client(),
+    platform.udp_client(),
     platform.display_manager(),
+    platform.led_manager(),
+    platform.crash_log(),
+    platform.wall_clock(),
     platform.storage_manager(),
   );
 
@@ -106,6 +132,11 @@ fn main() {
     futures::executor::block_on(websocket_input_forwarder(ws_incoming_receiver, ws_platform));
   });
 
+  // Restart the app from the command line when it is rebuilt
+  if let Some(path) = wasm_app {
+    tasks::hot_reload::spawn_watcher(path, host_sender.clone(), stack_event_for_reload);
+  }
+
   // Spawn the menu task on a background thread
   let platform_clone = platform.clone();
   std::thread::spawn(move || {
@@ -113,14 +144,19 @@ fn main() {
   });
 
   // Minifb window on the main thread
-  let mut window = Window::new("Rustagon", WIDTH, HEIGHT, WindowOptions::default()).unwrap_or_else(|e| panic!("{}", e));
+  let mut window =
+    Window::new("Rustagon", WINDOW_WIDTH, WINDOW_HEIGHT, WindowOptions::default()).unwrap_or_else(|e| panic!("{}", e));
 
   window.limit_update_rate(Some(std::time::Duration::from_millis(33)));
 
   let mut fb = vec![0u8; WIDTH * HEIGHT * 2];
-  let mut buf32 = vec![0u32; WIDTH * HEIGHT];
+  let mut buf32 = vec![0u32; WINDOW_WIDTH * WINDOW_HEIGHT];
 
   let mut lcd_state = LcdState::new(display_types::LcdScreen::Splash, now_ms());
+  // Shown over the app for a moment after it was hot-reloaded.
+  let mut reload_toast: Option<(LcdState, i32)> = None;
+  // Monotonic clock for the LED effects, which expect a non-wrapping `u64`.
+  let led_clock = std::time::Instant::now();
 
   while window.is_open() && !window.is_key_down(Key::Escape) {
     // Handle keyboard input: character keys mimic the keyboard hexpansion
@@ -142,9 +178,9 @@ fn main() {
     // Check if the WASM has pushed a raw framebuffer
     let wasm_buffer = platform::display::LCD_BUFFER.lock().unwrap().clone();
 
-    let rgb565: &[u8] = if let Some(raw) = &wasm_buffer {
+    if let Some(raw) = &wasm_buffer {
       // Render raw WASM framebuffer (RGB565 LE) directly
-      raw.as_slice()
+      fb.copy_from_slice(raw);
     } else {
       let (screen, _) = platform_clone.get_screen();
       lcd_state.update(screen, now);
@@ -153,9 +189,22 @@ fn main() {
       fb.fill(0);
       let mut desk_fb = DesktopFrameBuffer(&mut fb);
       lcd_state.draw(&mut desk_fb, &lcd_state.screen, now);
+    }
 
-      &fb
-    };
+    if tasks::hot_reload::take_reloaded() {
+      let toast = display_types::LcdScreen::Notification(display_types::Icon40::Info, "Reloaded".to_string());
+      reload_toast = Some((LcdState::new(toast, now), now));
+    }
+    if reload_toast
+      .as_ref()
+      .is_some_and(|(_, shown_at)| now - shown_at >= display_types::NOTIFICATION_TOTAL_MS as i32)
+    {
+      reload_toast = None;
+    }
+    if let Some((toast, _)) = &reload_toast {
+      toast.draw(&mut DesktopFrameBuffer(&mut fb), &toast.screen, now);
+    }
+    let rgb565: &[u8] = &fb;
 
     // Publish the RGB565 frame so frame_buffer()/WebSocket streaming sees the current screen
     platform_clone.display_raw.update_framebuffer(rgb565);
@@ -163,18 +212,50 @@ fn main() {
     for y in 0..HEIGHT {
       for x in 0..WIDTH {
         let i = (y * WIDTH + x) * 2;
-        let raw = ((rgb565[i] as u16) << 8) | (rgb565[i + 1] as u16);
-        let r5 = (raw >> 11) & 0x1F;
-        let g6 = (raw >> 5) & 0x3F;
-        let b5 = raw & 0x1F;
-        let r = (r5 * 255 + 15) / 31;
-        let g = (g6 * 255 + 31) / 63;
-        let b = (b5 * 255 + 15) / 31;
-        buf32[y * WIDTH + x] = (r as u32) << 16 | (g as u32) << 8 | b as u32;
+        let [r, g, b] = rgb565_to_rgb888(((rgb565[i] as u16) << 8) | (rgb565[i + 1] as u16));
+        buf32[(y + LED_MARGIN) * WINDOW_WIDTH + x + LED_MARGIN] = (r as u32) << 16 | (g as u32) << 8 | b as u32;
       }
     }
 
-    window.update_with_buffer(&buf32, WIDTH, HEIGHT).unwrap();
+    draw_led_ring(&mut buf32, &DesktopLedManager::render(led_clock.elapsed().as_millis() as u64));
+
+    window.update_with_buffer(&buf32, WINDOW_WIDTH, WINDOW_HEIGHT).unwrap();
+  }
+}
+
+fn rgb565_to_rgb888(raw: u16) -> [u8; 3] {
+  let r5 = (raw >> 11) & 0x1F;
+  let g6 = (raw >> 5) & 0x3F;
+  let b5 = raw & 0x1F;
+  [
+    ((r5 * 255 + 15) / 31) as u8,
+    ((g6 * 255 + 31) / 63) as u8,
+    ((b5 * 255 + 15) / 31) as u8,
+  ]
+}
+
+/// Draw the simulated LED ring into the window border: LED 0 at the top,
+/// continuing clockwise.
+fn draw_led_ring(buf32: &mut [u32], leds: &[app::types::LedState]) {
+  let centre_x = (WINDOW_WIDTH / 2) as f32;
+  let centre_y = (WINDOW_HEIGHT / 2) as f32;
+  let ring_radius = (WIDTH / 2 + LED_MARGIN / 2) as f32;
+
+  for (i, led) in leds.iter().enumerate() {
+    let angle = i as f32 / leds.len() as f32 * std::f32::consts::TAU;
+    let led_x = (centre_x + ring_radius * angle.sin()) as i32;
+    let led_y = (centre_y - ring_radius * angle.cos()) as i32;
+    let colour = (led.r as u32) << 16 | (led.g as u32) << 8 | led.b as u32;
+
+    for dy in -LED_RADIUS..=LED_RADIUS {
+      for dx in -LED_RADIUS..=LED_RADIUS {
+        let (x, y) = (led_x + dx, led_y + dy);
+        if dx * dx + dy * dy > LED_RADIUS * LED_RADIUS || x < 0 || y < 0 || x >= WINDOW_WIDTH as i32 || y >= WINDOW_HEIGHT as i32 {
+          continue;
+        }
+        buf32[y as usize * WINDOW_WIDTH + x as usize] = colour;
+      }
+    }
   }
 }
 
@@ -195,13 +276,15 @@ fn resolve_wasm_path(arg: &str) -> Option<PathBuf> {
 /// Map a minifb key to the app's `KeyCode`, mimicking the KeebDeck keyboard
 /// hexpansion. Arrows/Enter/Backspace are intentionally unmapped — they mimic
 /// the badge's hex buttons and boot button instead. Shift is mapped so apps
-/// can track shift state (the app crate decides how to shift characters);
-/// Ctrl is reserved for hex buttons and the remaining modifiers
-/// (Alt/CapsLock) are unmapped — nothing consumes them.
+/// can track shift state (the app crate decides how to shift characters), as
+/// are Alt and CapsLock, which WASM guests see in their key events' modifiers.
+/// Ctrl is reserved for hex buttons.
 fn key_to_keycode(key: Key) -> Option<KeyCode> {
   use KeyCode::*;
   match key {
     Key::LeftShift | Key::RightShift => Some(Shift),
+    Key::LeftAlt | Key::RightAlt => Some(Alt),
+    Key::CapsLock => Some(CapsLock),
     Key::A => Some(A),
     Key::B => Some(B),
     Key::C => Some(C),
@@ -3,16 +3,52 @@ use app::platform::led::{LedError, LedManager};
 use app::platform::power::{PowerManager, PowerStatus};
 use app::platform::system::SystemManager;
 use app::platform::wifi::{WiFiManager, WifiStatus};
-use app::types::{DeviceEvent, HexpansionEvent, HexpansionInfo, LedRequest, SystemMessage, WifiDesiredState, WifiResult};
+use app::types::{
+  DeviceEvent, HexpansionEvent, HexpansionInfo, LedRequest, LedState, NUM_LEDS, SystemMessage, WifiDesiredState, WifiResult,
+};
+use display_renderer::led_effects::{
+  BreatheEffect, ChaseEffect, FireEffect, LedEffect, OffEffect, PixelsEffect, RainbowEffect, SolidEffect, SparkleEffect,
+  TheaterChaseEffect,
+};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::channel::Channel;
 use embassy_sync::signal::Signal;
 use std::pin::Pin;
+use std::sync::Mutex;
+
+/// Effect currently shown on the simulated LED ring. Set by
+/// [`DesktopLedManager::request`] and rendered around the LCD by the minifb
+/// loop — the desktop stand-in for the firmware's `led_work_loop_task`.
+static LED_EFFECT: Mutex<Option<Box<dyn LedEffect + Send>>> = Mutex::new(None);
 
 #[derive(Debug)]
 pub struct DesktopLedManager;
+
+impl DesktopLedManager {
+  /// Advance the current effect and return the colour of each ring LED.
+  pub fn render(now_ms: u64) -> [LedState; NUM_LEDS] {
+    match LED_EFFECT.lock().unwrap().as_mut() {
+      Some(effect) => effect.update_and_render(now_ms),
+      None => [LedState::new(0, 0, 0); NUM_LEDS],
+    }
+  }
+}
+
 impl LedManager for DesktopLedManager {
-  fn request(&self, _: LedRequest) -> Result<(), LedError> {
+  fn request(&self, request: LedRequest) -> Result<(), LedError> {
+    // Same effect parameters as the firmware's LED work loop.
+    let effect: Box<dyn LedEffect + Send> = match request {
+      LedRequest::Off => Box::new(OffEffect),
+      LedRequest::Solid(colour) => Box::new(SolidEffect { colour }),
+      LedRequest::Pixels(states) => Box::new(PixelsEffect { states }),
+      LedRequest::Rainbow => Box::new(RainbowEffect::new(0.1)),
+      LedRequest::Breathe(colour) => Box::new(BreatheEffect::new(colour, 0.001)),
+      LedRequest::Chase(colour) => Box::new(ChaseEffect::new(colour, 5, 50)),
+      LedRequest::Sparkle(colour) => Box::new(SparkleEffect::new(colour, 0.3, 100)),
+      LedRequest::TheaterChase(colour) => Box::new(TheaterChaseEffect::new(colour, 3, 100)),
+      LedRequest::Fire => Box::new(FireEffect::new(55, 120, 30)),
+    };
+    *LED_EFFECT.lock().map_err(|_| LedError::ChannelClosed)? = Some(effect);
     Ok(())
   }
 }
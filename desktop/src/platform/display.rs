@@ -1,4 +1,4 @@
-use app::platform::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH, DisplayError, DisplayManager, FRAME_BYTES};
+use app::platform::display::{DISPLAY_HEIGHT, DISPLAY_WIDTH, DisplayError, DisplayManager, FRAME_BYTES, FrameRegion};
 use core::fmt;
 use display_types::LcdScreen;
 use std::sync::Mutex;
@@ -11,8 +11,9 @@ use std::sync::Mutex;
 pub static FRAMEBUFFER: Mutex<Option<Vec<u8>>> = Mutex::new(None);
 
 /// Latest raw RGB565 frame submitted via [`DisplayManager::signal_raw_frame`]
-/// (e.g. by a WASM guest). While `Some`, the render loop renders this instead
-/// of the menu screen; it is cleared when a WASM session ends.
+/// (e.g. by a WASM guest), with any later [`DisplayManager::signal_raw_region`]
+/// updates composited over it. While `Some`, the render loop renders this
+/// instead of the menu screen; it is cleared when a WASM session ends.
 pub static LCD_BUFFER: Mutex<Option<Vec<u8>>> = Mutex::new(None);
 
 struct Inner {
@@ -87,6 +88,16 @@ impl DisplayManager for DesktopDisplayManager {
     }
     Ok(())
   }
+
+  fn signal_raw_region(&self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    region.check(buffer, stride)?;
+    if let Ok(mut lcd) = LCD_BUFFER.lock() {
+      // A guest that only ever sends regions starts from a black screen, like the LCD.
+      let frame = lcd.get_or_insert_with(|| vec![0u8; FRAME_BYTES]);
+      region.composite(frame, buffer, stride);
+    }
+    Ok(())
+  }
 }
 
 fn now_ms() -> i64 {
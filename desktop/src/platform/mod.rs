@@ -3,14 +3,18 @@ pub mod config;
 pub mod display;
 pub mod fs;
 pub mod input;
+pub mod sntp;
 pub mod tcp;
+pub mod udp;
 
 pub use common::*;
 pub use config::DesktopConfigManager;
 pub use display::DesktopDisplayManager;
 pub use fs::DesktopLocalFs;
 pub use input::DesktopInputManager;
+pub use sntp::spawn_sntp_sync;
 pub use tcp::DesktopTcpClient;
+pub use udp::DesktopUdpClient;
 
 use app::platform::hexpansion::HexpansionHandle;
 use app::platform::storage::ConfigFileTrait;
@@ -107,6 +111,9 @@ pub struct DesktopPlatform {
   config: ConfigHandle<DeviceConfig>,
   http_client: HttpClientHandle,
   tcp_client: TcpHandle,
+  udp_client: UdpHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
 }
 
 impl fmt::Debug for DesktopPlatform {
@@ -130,6 +137,11 @@ impl DesktopPlatform {
     let config = ConfigHandle::new(Arc::new(DesktopConfigManager::new()) as Arc<dyn ConfigFileTrait<DeviceConfig>>);
     let http_client = HttpClientHandle::new(Arc::new(DesktopHttpClient) as Arc<dyn app::platform::HttpClient>);
     let tcp_client = TcpHandle::new(Arc::new(DesktopTcpClient::new()) as Arc<dyn TcpClient>);
+    let udp_client = UdpHandle::new(Arc::new(DesktopUdpClient::new()) as Arc<dyn UdpClient>);
+    // The OS keeps the time of day, so the clock is set from the start.
+    let wall_clock = WallClockHandle::new();
+    let unix_time = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap();
+    wall_clock.set(unix_time.as_micros() as u64);
 
     Self {
       display_raw,
@@ -144,6 +156,9 @@ impl DesktopPlatform {
       config,
       http_client,
       tcp_client,
+      udp_client,
+      crash_log: CrashLogHandle::new(),
+      wall_clock,
     }
   }
 
@@ -152,6 +167,14 @@ impl DesktopPlatform {
   }
 }
 
+/// Fill `dest` from the OS RNG. Also used by the WASM host, which has no
+/// platform handle.
+pub fn fill_entropy(dest: &mut [u8]) {
+  if let Err(err) = getrandom::getrandom(dest) {
+    log::warn!("DesktopPlatform: entropy unavailable: {err:?}");
+  }
+}
+
 impl Platform for DesktopPlatform {
   fn display_manager(&self) -> DisplayHandle {
     self.display.clone()
@@ -180,19 +203,26 @@ impl Platform for DesktopPlatform {
   fn tcp_client(&self) -> Option<TcpHandle> {
     Some(self.tcp_client.clone())
   }
+  fn udp_client(&self) -> Option<UdpHandle> {
+    Some(self.udp_client.clone())
+  }
   fn storage_manager(&self) -> StorageHandle {
     self.storage.clone()
   }
   fn config_manager(&self) -> ConfigHandle<DeviceConfig> {
     self.config.clone()
   }
+  fn crash_log(&self) -> CrashLogHandle {
+    self.crash_log.clone()
+  }
+  fn wall_clock(&self) -> WallClockHandle {
+    self.wall_clock.clone()
+  }
   fn firmware_version(&self) -> u32 {
     option_env!("FIRMWARE_VERSION").unwrap_or("0").parse().unwrap_or(0)
   }
   fn entropy(&self, dest: &mut [u8]) {
-    if let Err(err) = getrandom::getrandom(dest) {
-      log::warn!("DesktopPlatform: entropy unavailable: {err:?}");
-    }
+    fill_entropy(dest);
   }
   async fn format_storage(&self) -> Result<(), FsError> {
     self.storage.format().await
@@ -0,0 +1,91 @@
+//! SNTP client for the simulator. The OS already knows the time, so this is
+//! mostly a way to exercise the badge's sync path, e.g. against a stand-in
+//! server on localhost (`"ntp_server": "127.0.0.1:1123"` in the device config).
+
+use app::platform::{ConfigHandle, WallClockHandle, sntp};
+use app::types::DeviceConfig;
+use std::io;
+use std::net::UdpSocket;
+use std::time::{Duration, Instant};
+
+/// How long to wait for the server to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to resynchronize, as on the badge.
+const RESYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// Ask `server` (`host` or `host:port`) for the current UNIX time in
+/// microseconds.
+pub fn query(server: &str) -> io::Result<u64> {
+  let socket = UdpSocket::bind(("0.0.0.0", 0))?;
+  socket.set_read_timeout(Some(REPLY_TIMEOUT))?;
+  socket.connect(sntp::server_addr(server))?;
+
+  let mut nonce = [0u8; 8];
+  super::fill_entropy(&mut nonce);
+  let nonce = u64::from_le_bytes(nonce);
+
+  let sent_at = Instant::now();
+  socket.send(&sntp::request(nonce))?;
+
+  let mut reply = [0u8; 128];
+  let len = socket.recv(&mut reply)?;
+  let server_time =
+    sntp::parse_response(&reply[..len], nonce).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, format!("{err:?}")))?;
+
+  // Assume the reply spent half the round trip in flight.
+  Ok(server_time + sent_at.elapsed().as_micros() as u64 / 2)
+}
+
+/// Keep `wall_clock` in sync with the configured server on a background
+/// thread. If the server can't be reached the clock keeps the OS time.
+pub fn spawn_sntp_sync(device_config: ConfigHandle<DeviceConfig>, wall_clock: WallClockHandle) {
+  std::thread::spawn(move || {
+    loop {
+      let config = futures::executor::block_on(device_config.get_data());
+      wall_clock.set_utc_offset(config.utc_offset_minutes);
+
+      match query(&config.ntp_server) {
+        Ok(unix_micros) => {
+          wall_clock.set(unix_micros);
+          if let Some(now) = wall_clock.local_time() {
+            log::info!("SNTP: Clock set from {}, local time {now}", config.ntp_server);
+          }
+        }
+        Err(err) => log::warn!("SNTP: Sync with {} failed: {err}", config.ntp_server),
+      }
+
+      std::thread::sleep(RESYNC_INTERVAL);
+    }
+  });
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  #[test]
+  fn syncs_from_a_local_stand_in() {
+    let server = UdpSocket::bind("127.0.0.1:0").unwrap();
+    let port = server.local_addr().unwrap().port();
+
+    std::thread::spawn(move || {
+      let mut request = [0u8; 128];
+      let (_, client) = server.recv_from(&mut request).unwrap();
+
+      // A stratum 2 server answering 2023-11-14 22:13:20 UTC.
+      let mut reply = [0u8; sntp::PACKET_LEN];
+      reply[0] = (4 << 3) | 4;
+      reply[1] = 2;
+      reply[24..32].copy_from_slice(&request[40..48]);
+      reply[40..44].copy_from_slice(&(1_700_000_000u32 + 2_208_988_800).to_be_bytes());
+      server.send_to(&reply, client).unwrap();
+    });
+
+    let unix_micros = query(&format!("127.0.0.1:{port}")).unwrap();
+    assert!(
+      (1_700_000_000_000_000..1_700_000_001_000_000).contains(&unix_micros),
+      "{unix_micros}"
+    );
+  }
+}
@@ -0,0 +1,117 @@
+use app::platform::{UdpClient, UdpEvent, UdpEventChannel};
+use core::{fmt, future::Future, pin::Pin};
+use std::io::ErrorKind;
+use std::net::UdpSocket;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often the reader thread wakes to notice the socket was closed.
+const READ_POLL: Duration = Duration::from_millis(100);
+
+/// Desktop UDP client: `std::net::UdpSocket` with a background reader thread
+/// streaming datagrams into the event channel (mirrors `DesktopTcpClient`).
+pub struct DesktopUdpClient {
+  /// The active socket, shared with `send_to`/`close`, and a generation
+  /// counter bumped on every `bind`. A reader thread stops once the slot no
+  /// longer holds its own socket.
+  socket: Arc<Mutex<(u64, Option<UdpSocket>)>>,
+}
+
+impl DesktopUdpClient {
+  pub fn new() -> Self {
+    Self {
+      socket: Arc::new(Mutex::new((0, None))),
+    }
+  }
+}
+
+impl Default for DesktopUdpClient {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+impl fmt::Debug for DesktopUdpClient {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("DesktopUdpClient").finish()
+  }
+}
+
+impl UdpClient for DesktopUdpClient {
+  fn bind(&self, port: u16, channel: &'static UdpEventChannel) -> Pin<Box<dyn Future<Output = ()> + 'static>> {
+    let shared = self.socket.clone();
+    Box::pin(async move {
+      let socket = match UdpSocket::bind(("0.0.0.0", port)) {
+        Ok(s) => s,
+        Err(_) => {
+          channel.send(UdpEvent::Error).await;
+          return;
+        }
+      };
+      let reader = match socket.try_clone() {
+        Ok(r) => r,
+        Err(_) => {
+          channel.send(UdpEvent::Error).await;
+          return;
+        }
+      };
+      let _ = reader.set_read_timeout(Some(READ_POLL));
+      let generation = {
+        let mut guard = shared.lock().unwrap();
+        guard.0 = guard.0.wrapping_add(1);
+        guard.1 = Some(socket);
+        guard.0
+      };
+
+      // Background read pump. Datagrams are dropped when the consumer falls
+      // behind, as UDP would on a congested link.
+      let open = shared.clone();
+      std::thread::spawn(move || {
+        let mut buf = [0u8; 2048];
+        loop {
+          match reader.recv_from(&mut buf) {
+            Ok((n, from)) => {
+              let _ = channel.try_send(UdpEvent::Datagram {
+                host: from.ip().to_string(),
+                port: from.port(),
+                data: buf[..n].to_vec(),
+              });
+            }
+            Err(e) if matches!(e.kind(), ErrorKind::WouldBlock | ErrorKind::TimedOut | ErrorKind::Interrupted) => {}
+            Err(_) => {
+              let _ = channel.try_send(UdpEvent::Error);
+              break;
+            }
+          }
+          let guard = open.lock().unwrap();
+          if guard.0 != generation {
+            break;
+          }
+          if guard.1.is_none() {
+            let _ = channel.try_send(UdpEvent::Closed);
+            break;
+          }
+        }
+      });
+
+      channel.send(UdpEvent::Bound).await;
+    })
+  }
+
+  fn send_to(&self, host: String, port: u16, data: Vec<u8>) -> Pin<Box<dyn Future<Output = ()> + 'static>> {
+    let shared = self.socket.clone();
+    Box::pin(async move {
+      let guard = shared.lock().unwrap();
+      if let Some(socket) = guard.1.as_ref() {
+        let _ = socket.send_to(&data, (host.as_str(), port));
+      }
+    })
+  }
+
+  fn close(&self) -> Pin<Box<dyn Future<Output = ()> + 'static>> {
+    let shared = self.socket.clone();
+    Box::pin(async move {
+      shared.lock().unwrap().1.take();
+    })
+  }
+}
@@ -0,0 +1,306 @@
+//! Rewrites a guest module so it reports which of its functions is running.
+//!
+//! wasmi doesn't expose the guest's call stack, so every defined function is
+//! wrapped in calls to two extra imports: [`PROFILE_ENTER`] with the function's
+//! index in the original module, and [`PROFILE_LEAVE`] on the way out.
+//!
+//! ```text
+//! i32.const <index>
+//! call $enter
+//! block (result ...)   ;; the original body, `return` turned into `br`
+//! end
+//! call $leave
+//! ```
+//!
+//! Direct calls to the module's own imports are wrapped the same way, with
+//! the import's index, so time spent in the host is put down to the host
+//! function called and the runner can time each one.
+//!
+//! The two imports are added after the module's own, which moves every
+//! defined function up by two; the re-encoder fixes up calls, exports, tables
+//! and the start function to match.
+
+use app::wasm::{PROFILE_ENTER, PROFILE_IMPORT_MODULE, PROFILE_LEAVE};
+use std::collections::HashMap;
+use wasm_encoder::reencode::{self, Reencode};
+use wasm_encoder::{BlockType, CodeSection, EntityType, ImportSection, Instruction, TypeSection, ValType};
+use wasmparser::{KnownCustom, Name, Operator, Parser, Payload, TypeRef};
+
+type Error = reencode::Error<&'static str>;
+
+/// The function names in `module`'s name section, by function index.
+pub fn function_names(module: &[u8]) -> HashMap<u32, String> {
+  let mut names = HashMap::new();
+  for payload in Parser::new(0).parse_all(module).flatten() {
+    let Payload::CustomSection(section) = payload else {
+      continue;
+    };
+    let KnownCustom::Name(reader) = section.as_known() else {
+      continue;
+    };
+    for subsection in reader.flatten() {
+      if let Name::Function(map) = subsection {
+        names.extend(map.into_iter().flatten().map(|naming| (naming.index, naming.name.to_string())));
+      }
+    }
+  }
+  names
+}
+
+/// `module` with every defined function reporting its entry and exit.
+pub fn instrument(module: &[u8]) -> Result<Vec<u8>, Error> {
+  let mut instrumenter = Instrumenter::scan(module)?;
+  let mut encoded = wasm_encoder::Module::new();
+  instrumenter.parse_core_module(&mut encoded, Parser::new(0), module)?;
+  if !instrumenter.imports_added {
+    return Err(Error::UserError("module has no imports"));
+  }
+  Ok(encoded.finish())
+}
+
+#[derive(Default)]
+struct Instrumenter {
+  /// Imported functions in the original module.
+  imported_functions: u32,
+  /// Types in the original module; the new ones go after them.
+  type_count: u32,
+  /// Results of each function type in the original module.
+  type_results: HashMap<u32, Vec<wasmparser::ValType>>,
+  /// Type of each defined function.
+  function_types: Vec<u32>,
+  /// Block types added for functions with several results, by function type.
+  block_types: HashMap<u32, u32>,
+  /// Defined functions rewritten so far.
+  defined: u32,
+  imports_added: bool,
+}
+
+impl Instrumenter {
+  /// Collect what the rewrite needs to know before the sections are encoded.
+  fn scan(module: &[u8]) -> Result<Self, Error> {
+    let mut instrumenter = Self::default();
+    for payload in Parser::new(0).parse_all(module) {
+      match payload? {
+        Payload::TypeSection(reader) => {
+          for group in reader {
+            for ty in group?.types() {
+              if let wasmparser::CompositeInnerType::Func(func) = &ty.composite_type.inner {
+                instrumenter.type_results.insert(instrumenter.type_count, func.results().to_vec());
+              }
+              instrumenter.type_count += 1;
+            }
+          }
+        }
+        Payload::ImportSection(reader) => {
+          for import in reader.into_imports() {
+            if matches!(import?.ty, TypeRef::Func(_) | TypeRef::FuncExact(_)) {
+              instrumenter.imported_functions += 1;
+            }
+          }
+        }
+        Payload::FunctionSection(reader) => {
+          for ty in reader {
+            instrumenter.function_types.push(ty?);
+          }
+        }
+        _ => {}
+      }
+    }
+
+    let mut next_type = instrumenter.type_count + 2;
+    for ty in &instrumenter.function_types {
+      if instrumenter.type_results.get(ty).is_some_and(|results| results.len() > 1) && !instrumenter.block_types.contains_key(ty) {
+        instrumenter.block_types.insert(*ty, next_type);
+        next_type += 1;
+      }
+    }
+    Ok(instrumenter)
+  }
+
+  fn enter_function(&self) -> u32 {
+    self.imported_functions
+  }
+
+  fn leave_function(&self) -> u32 {
+    self.imported_functions + 1
+  }
+
+  /// A block type producing the results of function type `ty`.
+  fn block_type(&mut self, ty: u32) -> Result<BlockType, Error> {
+    if let Some(index) = self.block_types.get(&ty) {
+      return Ok(BlockType::FunctionType(*index));
+    }
+    let results = self
+      .type_results
+      .get(&ty)
+      .ok_or(Error::UserError("function has no function type"))?;
+    Ok(match results.first().copied() {
+      Some(result) => BlockType::Result(self.val_type(result)?),
+      None => BlockType::Empty,
+    })
+  }
+}
+
+impl Reencode for Instrumenter {
+  type Error = &'static str;
+
+  fn function_index(&mut self, func: u32) -> Result<u32, Error> {
+    Ok(if func < self.imported_functions { func } else { func + 2 })
+  }
+
+  fn parse_type_section(&mut self, types: &mut TypeSection, section: wasmparser::TypeSectionReader<'_>) -> Result<(), Error> {
+    reencode::utils::parse_type_section(self, types, section)?;
+    types.ty().function([ValType::I32], []);
+    types.ty().function([], []);
+    let mut block_types: Vec<_> = self.block_types.iter().map(|(&ty, &index)| (index, ty)).collect();
+    block_types.sort_unstable();
+    for (_, ty) in block_types {
+      let results = self.type_results[&ty].clone();
+      let results = results
+        .into_iter()
+        .map(|result| self.val_type(result))
+        .collect::<Result<Vec<_>, _>>()?;
+      types.ty().function([], results);
+    }
+    Ok(())
+  }
+
+  fn parse_import_section(&mut self, imports: &mut ImportSection, section: wasmparser::ImportSectionReader<'_>) -> Result<(), Error> {
+    reencode::utils::parse_import_section(self, imports, section)?;
+    imports.import(PROFILE_IMPORT_MODULE, PROFILE_ENTER, EntityType::Function(self.type_count));
+    imports.import(PROFILE_IMPORT_MODULE, PROFILE_LEAVE, EntityType::Function(self.type_count + 1));
+    self.imports_added = true;
+    Ok(())
+  }
+
+  fn parse_function_body(&mut self, code: &mut CodeSection, func: wasmparser::FunctionBody<'_>) -> Result<(), Error> {
+    let ty = *self
+      .function_types
+      .get(self.defined as usize)
+      .ok_or(Error::UserError("more function bodies than functions"))?;
+    let index = self.imported_functions + self.defined;
+    self.defined += 1;
+
+    let mut function = self.new_function_with_parsed_locals(&func)?;
+    function.instruction(&Instruction::I32Const(index as i32));
+    function.instruction(&Instruction::Call(self.enter_function()));
+    function.instruction(&Instruction::Block(self.block_type(ty)?));
+
+    // Blocks opened inside the body, so `return` can branch to the outermost.
+    let mut depth = 0;
+    let mut reader = func.get_operators_reader()?;
+    while !reader.eof() {
+      let operator = reader.read()?;
+      match operator {
+        Operator::Block { .. } | Operator::Loop { .. } | Operator::If { .. } | Operator::Try { .. } | Operator::TryTable { .. } => {
+          depth += 1
+        }
+        Operator::End | Operator::Delegate { .. } if depth > 0 => depth -= 1,
+        Operator::End => {
+          function.instruction(&Instruction::End);
+          function.instruction(&Instruction::Call(self.leave_function()));
+          function.instruction(&Instruction::End);
+          break;
+        }
+        Operator::Return => {
+          function.instruction(&Instruction::Br(depth));
+          continue;
+        }
+        Operator::Call { function_index } if function_index < self.imported_functions => {
+          function.instruction(&Instruction::I32Const(function_index as i32));
+          function.instruction(&Instruction::Call(self.enter_function()));
+          function.instruction(&Instruction::Call(function_index));
+          function.instruction(&Instruction::Call(self.leave_function()));
+          continue;
+        }
+        Operator::ReturnCall { .. } | Operator::ReturnCallIndirect { .. } | Operator::ReturnCallRef { .. } => {
+          function.instruction(&Instruction::Call(self.leave_function()));
+        }
+        _ => {}
+      }
+      function.instruction(&self.instruction(operator)?);
+    }
+    code.function(&function);
+    Ok(())
+  }
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const GUEST: &str = r#"
+    (module
+      (import "index" "extern_get_millis" (func $millis (result i32)))
+      (func $pick (param i32) (result i32 i32)
+        local.get 0
+        if
+          i32.const 1
+          i32.const 2
+          return
+        end
+        call $millis
+        i32.const 3)
+      (func $tick (export "tick") (result i32)
+        i32.const 1
+        call $pick
+        i32.add)
+      (elem declare func $tick)
+      (start $tick2)
+      (func $tick2 call $tick drop))
+  "#;
+
+  #[test]
+  fn reads_function_names() {
+    let names = function_names(&wat::parse_str(GUEST).unwrap());
+    assert_eq!(names.get(&1).map(String::as_str), Some("pick"));
+    assert_eq!(names.get(&2).map(String::as_str), Some("tick"));
+  }
+
+  #[test]
+  fn instrumented_module_is_valid() {
+    let module = instrument(&wat::parse_str(GUEST).unwrap()).unwrap();
+    wasmparser::Validator::new().validate_all(&module).unwrap();
+
+    let imports: Vec<_> = Parser::new(0)
+      .parse_all(&module)
+      .filter_map(|payload| match payload.unwrap() {
+        Payload::ImportSection(reader) => Some(reader.into_imports().map(|import| import.unwrap().name).collect::<Vec<_>>()),
+        _ => None,
+      })
+      .flatten()
+      .collect();
+    assert_eq!(imports, ["extern_get_millis", PROFILE_ENTER, PROFILE_LEAVE]);
+  }
+
+  #[test]
+  fn calls_to_imports_are_announced() {
+    let module = instrument(&wat::parse_str(GUEST).unwrap()).unwrap();
+    let pick = Parser::new(0)
+      .parse_all(&module)
+      .find_map(|payload| match payload.unwrap() {
+        Payload::CodeSectionEntry(body) => Some(body),
+        _ => None,
+      })
+      .unwrap();
+    let operators = pick
+      .get_operators_reader()
+      .unwrap()
+      .into_iter()
+      .map(Result::unwrap)
+      .collect::<Vec<_>>();
+    let call_millis = operators
+      .iter()
+      .position(|operator| matches!(operator, Operator::Call { function_index: 0 }))
+      .unwrap();
+    assert!(matches!(
+      operators[call_millis - 2..call_millis + 2],
+      [
+        Operator::I32Const { value: 0 },
+        Operator::Call { function_index: 1 },
+        Operator::Call { function_index: 0 },
+        Operator::Call { function_index: 2 },
+      ]
+    ));
+  }
+}
@@ -0,0 +1,116 @@
+//! Sampling profiler for apps run with `--profile`.
+//!
+//! Each app is instrumented as it starts (see [`instrument`]), so the host
+//! always knows which guest function is running. A background thread samples
+//! that every [`SAMPLE_INTERVAL`], and the functions the app spent most of its
+//! time in are logged when it exits.
+
+pub mod instrument;
+
+use log::{info, warn};
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU32, Ordering};
+use std::thread::JoinHandle;
+use std::time::Duration;
+
+const SAMPLE_INTERVAL: Duration = Duration::from_millis(1);
+
+/// Functions listed in the report.
+const REPORT_ROWS: usize = 20;
+
+/// Sampled while no guest function runs: the host between ticks.
+const OUTSIDE_GUEST: u32 = u32::MAX;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Profile every app launched from now on.
+pub fn enable() {
+  ENABLED.store(true, Ordering::Relaxed);
+}
+
+pub fn is_enabled() -> bool {
+  ENABLED.load(Ordering::Relaxed)
+}
+
+/// Samples one running app, logging the report when dropped.
+#[derive(Debug)]
+pub struct Sampler {
+  names: HashMap<u32, String>,
+  /// Functions entered and not yet left, innermost last.
+  stack: Vec<u32>,
+  /// The innermost function, for the sampling thread.
+  current: Arc<AtomicU32>,
+  stop: Arc<AtomicBool>,
+  thread: Option<JoinHandle<HashMap<u32, u64>>>,
+}
+
+impl Sampler {
+  /// Instrument `module` and start sampling. `None`, after logging why, if
+  /// the module can't be instrumented.
+  pub fn start(module: &[u8]) -> Option<(Self, Vec<u8>)> {
+    let instrumented = instrument::instrument(module)
+      .inspect_err(|err| warn!("profile: can't instrument the app, running it unprofiled: {err}"))
+      .ok()?;
+    let current = Arc::new(AtomicU32::new(OUTSIDE_GUEST));
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread = {
+      let (current, stop) = (current.clone(), stop.clone());
+      std::thread::spawn(move || {
+        let mut samples = HashMap::new();
+        while !stop.load(Ordering::Relaxed) {
+          std::thread::sleep(SAMPLE_INTERVAL);
+          *samples.entry(current.load(Ordering::Relaxed)).or_insert(0) += 1;
+        }
+        samples
+      })
+    };
+    let sampler = Self {
+      names: instrument::function_names(module),
+      stack: Vec::new(),
+      current,
+      stop,
+      thread: Some(thread),
+    };
+    Some((sampler, instrumented))
+  }
+
+  pub fn enter(&mut self, index: u32) {
+    self.stack.push(index);
+    self.current.store(index, Ordering::Relaxed);
+  }
+
+  pub fn leave(&mut self) {
+    self.stack.pop();
+    self
+      .current
+      .store(self.stack.last().copied().unwrap_or(OUTSIDE_GUEST), Ordering::Relaxed);
+  }
+
+  fn report(&self, samples: &HashMap<u32, u64>) -> String {
+    let total: u64 = samples.values().sum();
+    let mut rows: Vec<_> = samples.iter().map(|(&index, &count)| (count, index)).collect();
+    rows.sort_unstable_by(|a, b| b.cmp(a));
+
+    let mut report = format!("profile: {total} samples, {} ms apart", SAMPLE_INTERVAL.as_millis());
+    for (count, index) in rows.into_iter().take(REPORT_ROWS) {
+      let percent = count as f64 * 100.0 / total as f64;
+      let _ = match (index, self.names.get(&index)) {
+        (OUTSIDE_GUEST, _) => write!(report, "\n  {percent:5.1}% {count:8}  (outside the guest)"),
+        (_, Some(name)) => write!(report, "\n  {percent:5.1}% {count:8}  {name}"),
+        (_, None) => write!(report, "\n  {percent:5.1}% {count:8}  func[{index}]"),
+      };
+    }
+    report
+  }
+}
+
+impl Drop for Sampler {
+  fn drop(&mut self) {
+    self.stop.store(true, Ordering::Relaxed);
+    if let Some(samples) = self.thread.take().and_then(|thread| thread.join().ok()) {
+      info!("{}", self.report(&samples));
+    }
+  }
+}
@@ -0,0 +1,64 @@
+//! Restart the WASM app given on the command line whenever its file changes,
+//! so an SDK app can be rebuilt without closing the window. Each reload starts
+//! the app afresh from a buffer, so like the first launch it gets the scratch
+//! storage, cleared: anything the previous build saved is gone.
+
+use app::menu::state::StackEventHandle;
+use app::protocol::HostIpcSender;
+use log::{info, warn};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, SystemTime};
+
+/// How often the file is checked for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+static RELOADED: AtomicBool = AtomicBool::new(false);
+
+/// Whether the app was reloaded since the last call, for the window to show a
+/// toast.
+pub fn take_reloaded() -> bool {
+  RELOADED.swap(false, Ordering::AcqRel)
+}
+
+/// Watch `path` on a background thread, reloading the app when it changes.
+pub fn spawn_watcher(path: PathBuf, host_sender: HostIpcSender, stack_event_handle: StackEventHandle) {
+  std::thread::spawn(move || {
+    let mut loaded = stamp(&path);
+    let mut seen = loaded;
+    loop {
+      std::thread::sleep(POLL_INTERVAL);
+
+      // Only load a file that stayed the same for a whole interval, so a build
+      // still writing it isn't picked up half done.
+      let current = stamp(&path);
+      if current != seen {
+        seen = current;
+        continue;
+      }
+      if current.is_none() || current == loaded {
+        continue;
+      }
+
+      let buffer = match std::fs::read(&path) {
+        Ok(buffer) if !buffer.is_empty() => buffer,
+        Ok(_) => continue,
+        Err(err) => {
+          warn!("hot_reload: failed to read {}: {err}", path.display());
+          continue;
+        }
+      };
+      if futures::executor::block_on(super::wasm::reload(buffer, &host_sender, &stack_event_handle)) {
+        info!("hot_reload: reloaded {}", path.display());
+        loaded = current;
+        RELOADED.store(true, Ordering::Release);
+      }
+    }
+  });
+}
+
+/// The file's modification time and size, or `None` while it doesn't exist.
+fn stamp(path: &Path) -> Option<(SystemTime, u64)> {
+  let metadata = std::fs::metadata(path).ok()?;
+  Some((metadata.modified().ok()?, metadata.len()))
+}
@@ -1,2 +1,3 @@
+pub mod hot_reload;
 pub mod http;
 pub mod wasm;
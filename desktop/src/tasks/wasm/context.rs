@@ -1,14 +1,31 @@
-use app::platform::display::DisplayHandle;
+use crate::profile::Sampler;
+use app::platform::display::{DisplayError, DisplayHandle, FrameRegion};
+use app::platform::led::{LedError, LedHandle};
+use app::platform::{CrashLogHandle, CrashReport, WallClockHandle};
+use app::types::{LedRequest, LedState, NUM_LEDS};
+use app::wasm::fault::GuestFault;
 use app::wasm::host::WasmHost;
+use wasm_protocol::Capabilities;
 
 #[derive(Debug)]
 pub struct DesktopWasmHost {
   display: DisplayHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
+  /// Set when the app was instrumented for `--profile`.
+  sampler: Option<Sampler>,
 }
 
 impl DesktopWasmHost {
-  pub fn new(display: DisplayHandle) -> Self {
-    Self { display }
+  pub fn new(display: DisplayHandle, led: LedHandle, crash_log: CrashLogHandle, wall_clock: WallClockHandle) -> Self {
+    Self {
+      display,
+      led,
+      crash_log,
+      wall_clock,
+      sampler: None,
+    }
   }
 }
 
@@ -24,13 +41,71 @@ impl WasmHost for DesktopWasmHost {
       .as_millis() as u64
   }
 
-  fn set_lcd_buffer(&mut self, buffer: &[u8]) {
+  fn get_micros(&self) -> u64 {
+    embassy_time::Instant::now().as_micros()
+  }
+
+  fn unix_time_micros(&self) -> Option<u64> {
+    self.wall_clock.unix_micros()
+  }
+
+  fn fill_random(&mut self, dest: &mut [u8]) {
+    crate::platform::fill_entropy(dest);
+  }
+
+  fn set_lcd_buffer(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
     // The display manager stores the frame in LCD_BUFFER, which the render
     // loop picks up next frame — no blocking on the WASM side.
-    let _ = self.display.signal_raw_frame(buffer);
+    self.display.signal_raw_frame(buffer)
   }
 
-  fn set_gpio(&mut self, pin_number: u32, state: u32) {
+  fn set_lcd_region(&mut self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    self.display.signal_raw_region(region, buffer, stride)
+  }
+
+  fn set_gpio(&mut self, pin_number: u32, state: u32) -> Result<(), GuestFault> {
     log::debug!("set_gpio: pin={pin_number} state={state}");
+    Ok(())
+  }
+
+  fn set_leds(&mut self, leds: &[LedState; NUM_LEDS]) -> Result<(), LedError> {
+    self.led.request(LedRequest::Pixels(*leds))
+  }
+
+  fn set_led_effect(&mut self, request: LedRequest) -> Result<(), LedError> {
+    self.led.request(request)
+  }
+
+  fn capabilities(&self) -> Capabilities {
+    Capabilities::LEDS.union(Capabilities::HTTP).union(Capabilities::SOCKETS)
+  }
+
+  fn report_crash(&mut self, report: CrashReport) {
+    self.crash_log.record(report);
+  }
+
+  fn prepare_module(&mut self, module: Vec<u8>) -> Vec<u8> {
+    if !crate::profile::is_enabled() {
+      return module;
+    }
+    match Sampler::start(&module) {
+      Some((sampler, instrumented)) => {
+        self.sampler = Some(sampler);
+        instrumented
+      }
+      None => module,
+    }
+  }
+
+  fn enter_function(&mut self, index: u32) {
+    if let Some(sampler) = &mut self.sampler {
+      sampler.enter(index);
+    }
+  }
+
+  fn leave_function(&mut self) {
+    if let Some(sampler) = &mut self.sampler {
+      sampler.leave();
+    }
   }
 }
@@ -3,23 +3,65 @@ pub mod context;
 pub use context::*;
 
 use app::menu::state::{StackEntryType, StackEvent, StackEventHandle};
-use app::platform::{HttpClientHandle, display::DisplayHandle};
+use app::platform::{
+  CrashLogHandle, HttpClientHandle, LedHandle, StorageHandle, TcpHandle, UdpHandle, WallClockHandle, display::DisplayHandle,
+};
 use app::protocol::*;
-use app::wasm::wasmi_runner;
+use app::wasm::host::WasmHost;
+use app::wasm::{AppStorage, GuestHttp, GuestSockets, WasmLimits, wasmi_file_runner, wasmi_runner};
+use embassy_futures::select::{Either3, select3};
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
 use embassy_sync::channel::Receiver;
 use futures::future::join;
 use log::{debug, info, warn};
 use std::sync::Arc;
-use wasm_protocol::{HostIpcMessage as WireHostIpcMessage, WasmIpcMessage as WireWasmIpcMessage};
+use std::sync::atomic::{AtomicU8, Ordering};
+use wasm_protocol::WasmIpcMessage as WireWasmIpcMessage;
+
+const SESSION_IDLE: u8 = 0;
+const SESSION_RUNNING: u8 = 1;
+/// Stopped by [`reload`], until the new build starts.
+const SESSION_RELOADING: u8 = 2;
+
+static SESSION: AtomicU8 = AtomicU8::new(SESSION_IDLE);
+
+/// Where a session's module comes from.
+pub enum WasmSource {
+  /// An app file in storage, loaded with [`app::wasm::load_module`] and given
+  /// its own storage.
+  File { storage: StorageHandle, file_name: String },
+  /// A module already in memory, e.g. from the command line.
+  Buffer { buffer: Vec<u8>, app_storage: AppStorage },
+}
+
+/// Replace the running app with `buffer`, or start it if none is running. A
+/// running app is stopped without leaving the menu's hosted-app entry, so the
+/// new one takes over its screen and input. Returns `false`, without doing
+/// anything, while the previous reload is still starting.
+pub async fn reload(buffer: Vec<u8>, host_sender: &HostIpcSender, stack_event_handle: &StackEventHandle) -> bool {
+  match SESSION.compare_exchange(SESSION_RUNNING, SESSION_RELOADING, Ordering::AcqRel, Ordering::Acquire) {
+    Ok(_) => host_sender.send((0, HostIpcMessage::Runtime(HostRuntimeCommand::Stop))).await,
+    Err(SESSION_IDLE) => stack_event_handle.send(StackEvent::Pushed(StackEntryType::HostedApp)),
+    Err(_) => return false,
+  }
+  host_sender
+    .send((0, HostIpcMessage::Runtime(HostRuntimeCommand::StartWasmWithBuffer(buffer))))
+    .await;
+  true
+}
 
 pub fn spawn_wasm_runner(
   host_receiver: Receiver<'static, CriticalSectionRawMutex, (u32, HostIpcMessage), 1>,
   host_sender: HostIpcSender,
   stack_event_handle: StackEventHandle,
   http_client: HttpClientHandle,
+  tcp_client: Option<TcpHandle>,
+  udp_client: Option<UdpHandle>,
   display: DisplayHandle,
-  storage: app::platform::StorageHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
+  storage: StorageHandle,
 ) {
   std::thread::spawn(move || {
     futures::executor::block_on(wasm_host_loop(
@@ -27,7 +69,12 @@ pub fn spawn_wasm_runner(
       host_sender,
       stack_event_handle,
       http_client,
+      tcp_client,
+      udp_client,
       display,
+      led,
+      crash_log,
+      wall_clock,
       storage,
     ));
   });
@@ -38,44 +85,65 @@ async fn wasm_host_loop(
   host_sender: HostIpcSender,
   stack_event_handle: StackEventHandle,
   http_client: HttpClientHandle,
+  tcp_client: Option<TcpHandle>,
+  udp_client: Option<UdpHandle>,
   display: DisplayHandle,
-  storage: app::platform::StorageHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
+  storage: StorageHandle,
 ) {
   info!("Desktop WASM runner loop started");
 
+  // Shared by every session, so their event channels are only leaked once.
+  let mut sockets = GuestSockets::new(tcp_client, udp_client);
+
   loop {
     debug!("wasm_host_loop: waiting for message...");
     let (_msg_id, msg) = host_receiver.receive().await;
     debug!("wasm_host_loop: received msg={msg:?}");
     match msg {
       HostIpcMessage::Runtime(HostRuntimeCommand::StartWasm(filename)) => {
-        debug!("wasm_host_loop: loading wasm file");
-        let buf = storage.read_binary_chunk(filename, 0, 256 * 1024).await.unwrap_or_default();
-        if buf.is_empty() {
-          warn!("wasm_host_loop: wasm file not found or empty");
-          continue;
-        }
-        debug!("wasm_host_loop: file loaded, {} bytes", buf.len());
+        debug!("wasm_host_loop: starting {filename}");
+        let limits = WasmLimits::for_metadata(storage.read_app_metadata(&filename).await.as_ref());
         run_program(
-          buf,
+          WasmSource::File {
+            storage: storage.clone(),
+            file_name: filename,
+          },
+          limits,
           host_sender.clone(),
           host_receiver.clone(),
           stack_event_handle.clone(),
           http_client.clone(),
+          &mut sockets,
           display.clone(),
+          led.clone(),
+          crash_log.clone(),
+          wall_clock.clone(),
         )
         .await;
         debug!("wasm_host_loop: run_program returned");
       }
       HostIpcMessage::Runtime(HostRuntimeCommand::StartWasmWithBuffer(buffer)) => {
         debug!("wasm_host_loop: running from buffer ({} bytes)", buffer.len());
+        let app_storage = AppStorage::scratch(storage.clone());
+        if let Err(err) = app_storage.clear().await {
+          warn!("wasm_host_loop: failed to clear scratch storage: {err:?}");
+        }
+        let limits = WasmLimits::for_module(&buffer);
         run_program(
-          buffer,
+          WasmSource::Buffer { buffer, app_storage },
+          limits,
           host_sender.clone(),
           host_receiver.clone(),
           stack_event_handle.clone(),
           http_client.clone(),
+          &mut sockets,
           display.clone(),
+          led.clone(),
+          crash_log.clone(),
+          wall_clock.clone(),
         )
         .await;
         debug!("wasm_host_loop: run_program returned");
@@ -88,67 +156,112 @@ async fn wasm_host_loop(
 }
 
 async fn run_program(
-  wasm_buffer: Vec<u8>,
+  source: WasmSource,
+  limits: WasmLimits,
   host_sender: HostIpcSender,
   host_receiver: Receiver<'static, CriticalSectionRawMutex, (u32, HostIpcMessage), 1>,
   stack_event_handle: StackEventHandle,
   http_client: HttpClientHandle,
+  sockets: &mut GuestSockets,
   display: DisplayHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
 ) {
+  SESSION.store(SESSION_RUNNING, Ordering::Release);
+  let _ = run_session(
+    DesktopWasmHost::new(display.clone(), led, crash_log, wall_clock),
+    source,
+    limits,
+    host_sender,
+    host_receiver,
+    Some(http_client),
+    sockets,
+    display,
+  )
+  .await;
+
+  if SESSION
+    .compare_exchange(SESSION_RUNNING, SESSION_IDLE, Ordering::AcqRel, Ordering::Acquire)
+    .is_err()
+  {
+    info!("run_program: session stopped for a reload");
+    return;
+  }
+  stack_event_handle.send(StackEvent::Popped);
+  info!("run_program: session complete");
+}
+
+/// Run one guest until it stops, serving its HTTP, socket and screen requests.
+/// `limits` are the app's own, see [`WasmLimits::for_app`]; `sockets` are
+/// left closed for the next session. Fails with the reason if the guest
+/// couldn't be loaded or trapped. Shared with the headless runner, which has
+/// no menu to return to.
+pub async fn run_session<H: WasmHost>(
+  host: H,
+  source: WasmSource,
+  limits: WasmLimits,
+  host_sender: HostIpcSender,
+  host_receiver: Receiver<'static, CriticalSectionRawMutex, (u32, HostIpcMessage), 1>,
+  http_client: Option<HttpClientHandle>,
+  sockets: &mut GuestSockets,
+  display: DisplayHandle,
+) -> Result<(), String> {
   let wasm_channel = Box::leak(Box::new(WasmIpcChannel::new()));
   let wasm_receiver = wasm_channel.receiver();
   let wasm_sender = wasm_channel.sender();
 
-  debug!("run_program: starting wasmi_runner ({} bytes)", wasm_buffer.len());
-
   let ipc_sender = wasm_sender.clone();
-  let wasm_future = wasmi_runner(DesktopWasmHost::new(display.clone()), wasm_sender, host_receiver, wasm_buffer);
+  let wasm_future = async move {
+    match source {
+      WasmSource::File { storage, file_name } => {
+        debug!("run_program: starting wasmi_file_runner ({file_name})");
+        wasmi_file_runner(host, wasm_sender, host_receiver, storage, &file_name, limits).await
+      }
+      WasmSource::Buffer { buffer, app_storage } => {
+        debug!("run_program: starting wasmi_runner ({} bytes)", buffer.len());
+        wasmi_runner(host, wasm_sender, host_receiver, Some(app_storage), limits, buffer).await
+      }
+    }
+  };
 
   let ipc_future = async {
     ipc_sender.try_send((0, WasmIpcMessage::Started)).ok();
 
+    let mut http = GuestHttp::new(http_client, host_sender);
+
     loop {
-      let (wasm_req_id, msg) = wasm_receiver.receive().await;
+      let (wasm_req_id, msg) = match select3(wasm_receiver.receive(), sockets.next_event(), http.run()).await {
+        Either3::First(received) => received,
+        Either3::Second((socket, socket_msg)) => {
+          host_sender.send((socket, HostIpcMessage::Wire(socket_msg))).await;
+          continue;
+        }
+        Either3::Third(()) => continue,
+      };
       debug!("run_program/ipc: received WasmIpcMessage::{msg:?}");
       match msg {
         WasmIpcMessage::Wire(WireWasmIpcMessage::HttpRequest(http_req)) => {
           debug!("run_program/ipc: handling HTTP request to {}", http_req.url);
-          let channel = app::platform::HttpEventChannel::new();
-          join(http_client.request(http_req, &channel), async {
-            loop {
-              match channel.receive().await {
-                app::protocol::HttpEvent::Meta(meta) => {
-                  host_sender
-                    .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpResponseMeta(meta))))
-                    .await;
-                }
-                app::protocol::HttpEvent::Chunk(chunk) => {
-                  host_sender
-                    .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpResponseBody(chunk))))
-                    .await;
-                }
-                app::protocol::HttpEvent::Done => {
-                  host_sender
-                    .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpResponseComplete)))
-                    .await;
-                  break;
-                }
-                app::protocol::HttpEvent::Error => {
-                  host_sender
-                    .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpError)))
-                    .await;
-                  break;
-                }
-              }
-            }
-          })
-          .await;
+          if let Some((id, reply)) = http.start(wasm_req_id, http_req) {
+            host_sender.send((id, HostIpcMessage::Wire(reply))).await;
+          }
+        }
+        WasmIpcMessage::Wire(socket_request) => {
+          if let Some((socket, reply)) = sockets.handle(wasm_req_id, socket_request).await {
+            host_sender.send((socket, HostIpcMessage::Wire(reply))).await;
+          }
         }
         WasmIpcMessage::LcdScreen(screen) => {
+          // Host screens (e.g. the error screen after a trap) replace the
+          // guest's last raw frame.
+          *crate::platform::display::LCD_BUFFER.lock().unwrap() = None;
           let _ = display.signal(screen);
         }
         WasmIpcMessage::Stopped => {
           debug!("run_program/ipc: Received Stopped");
+          http.cancel_all();
+          sockets.close_all().await;
           break;
         }
         WasmIpcMessage::Started | WasmIpcMessage::MenuAppStarted => {}
@@ -157,10 +270,9 @@ async fn run_program(
   };
 
   debug!("run_program: joining wasm + ipc futures");
-  join(wasm_future, ipc_future).await;
+  let (result, ()) = join(wasm_future, ipc_future).await;
   debug!("run_program: join completed, cleaning up");
 
   *crate::platform::display::LCD_BUFFER.lock().unwrap() = None;
-  stack_event_handle.send(StackEvent::Popped);
-  info!("run_program: session complete");
+  result.map_err(|err| err.to_string())
 }
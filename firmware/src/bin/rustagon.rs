@@ -14,7 +14,7 @@
 #![recursion_limit = "256"]
 
 use alloc::{borrow::ToOwned as _, string::ToString as _, sync::Arc};
-use app::platform::HexpansionHandle;
+use app::platform::{HexpansionHandle, WallClockHandle};
 use core::{net::Ipv4Addr, str::FromStr};
 use embassy_executor::Spawner;
 use embassy_sync::blocking_mutex::raw::{CriticalSectionRawMutex, NoopRawMutex};
@@ -206,7 +206,9 @@ async fn main(spawner: Spawner) {
 
   // Create and initialize WiFi manager
   let wifi_manager = HardwareWifiManager::new();
-  wifi_manager.spawn_connection_task(spawner, config_handle.clone(), controller, stack, ap_ip);
+  // Set over SNTP by the connection task once we're online
+  let wall_clock = WallClockHandle::new();
+  wifi_manager.spawn_connection_task(spawner, config_handle.clone(), controller, stack, ap_ip, wall_clock.clone());
 
   spawner.spawn(net_task(runner).expect("spawn net_task"));
 
@@ -272,7 +274,8 @@ async fn main(spawner: Spawner) {
     storage.clone(),
     config_handle.clone(),
     storage_formatter.clone(),
-  );
+  )
+  .with_wall_clock(wall_clock);
 
   let _ = platform.led_manager().request(LedRequest::Breathe(LedState { r: 255, g: 0, b: 0 }));
 
@@ -283,13 +286,27 @@ async fn main(spawner: Spawner) {
   let host_receiver = host_ipc_channel.receiver();
 
   let storage_2nd_core = storage.clone();
+  let led_2nd_core = platform.led_manager();
+  let crash_log_2nd_core = platform.crash_log();
+  let wall_clock_2nd_core = platform.wall_clock();
 
   esp_rtos::start_second_core(peripherals.CPU_CTRL, sw_int.software_interrupt1, app_core_stack, move || {
     static EXECUTOR: StaticCell<esp_rtos::embassy::Executor> = StaticCell::new();
     let executor = EXECUTOR.init(esp_rtos::embassy::Executor::new());
 
     executor.run(|spawner| {
-      spawner.spawn(second_core_task(storage_2nd_core, display_2nd_core, wasm_sender, host_receiver).expect("spawn second_core_task"))
+      spawner.spawn(
+        second_core_task(
+          storage_2nd_core,
+          display_2nd_core,
+          led_2nd_core,
+          crash_log_2nd_core,
+          wall_clock_2nd_core,
+          wasm_sender,
+          host_receiver,
+        )
+        .expect("spawn second_core_task"),
+      )
     });
   });
 
@@ -312,6 +329,9 @@ async fn main(spawner: Spawner) {
   let tcp_client = firmware::platform::tcp::HardwareTcpClient::new(stack);
   let platform = platform.with_tcp_client(app::platform::TcpHandle::new(Arc::new(tcp_client)));
 
+  let udp_client = firmware::platform::udp::HardwareUdpClient::new(stack);
+  let platform = platform.with_udp_client(app::platform::UdpHandle::new(Arc::new(udp_client)));
+
   // Stack signal — IPC handler sends events, menu runner consumes
   let stack_event_handle = app::menu::state::create_stack_event_handle();
   let stack_event_for_menu = stack_event_handle.clone();
@@ -1,4 +1,4 @@
-pub use app::platform::display::{DisplayError, DisplayHandle, DisplayManager};
+pub use app::platform::display::{DisplayError, DisplayHandle, DisplayManager, FrameRegion};
 
 use crate::{
   d_i2c::*,
@@ -14,7 +14,7 @@ use aw9523b::Pin;
 use core::fmt;
 use core::ptr;
 use core::slice::{from_raw_parts, from_raw_parts_mut};
-use core::sync::atomic::{AtomicU32, Ordering};
+use core::sync::atomic::{AtomicBool, AtomicU8, AtomicU32, Ordering};
 use display_interface::{DataFormat, WriteOnlyDataCommand};
 use display_renderer::LcdState;
 use embassy_sync::{blocking_mutex::raw::CriticalSectionRawMutex, signal::Signal};
@@ -51,6 +51,53 @@ pub static mut SPI_DISPLAY_INTERFACE: *mut SPIInterface<SpiExclusiveDevice<'_>,
 /// doesn't saturate core 0 with 115 KB copies at full frame rate.
 static LAST_SCREEN_UPDATE: AtomicU32 = AtomicU32::new(0);
 
+/// Set when a raw frame was left out of the snapshot by the throttle. Partial
+/// updates drawn over that frame can't be composited into the snapshot, so
+/// they set [`SNAPSHOT_BEHIND`] and the next whole frame is copied regardless.
+static SNAPSHOT_SKIPPED: AtomicBool = AtomicBool::new(false);
+static SNAPSHOT_BEHIND: AtomicBool = AtomicBool::new(false);
+
+/// Who may write to the LCD: `lcd_task` drawing its own screens, nobody while
+/// it is parked at `LcdScreen::Blank`, or a raw frame being written from the
+/// WASM core. Each side claims it before touching `SPI_DISPLAY_INTERFACE` and
+/// holds it for the whole write.
+static LCD_OWNER: AtomicU8 = AtomicU8::new(LCD_TASK);
+const LCD_TASK: u8 = 0;
+const LCD_FREE: u8 = 1;
+const LCD_RAW: u8 = 2;
+
+/// The LCD, claimed for a raw frame until dropped.
+struct RawWrite;
+
+impl RawWrite {
+  /// Claim the LCD if `lcd_task` is parked and it is free. Fails straight away
+  /// otherwise rather than holding up the WASM core; the runner sends the
+  /// update again before the next tick.
+  fn claim() -> Result<Self, DisplayError> {
+    LCD_OWNER
+      .compare_exchange(LCD_FREE, LCD_RAW, Ordering::Acquire, Ordering::Relaxed)
+      .map(|_| RawWrite)
+      .map_err(|_| DisplayError::SignalBusy)
+  }
+}
+
+impl Drop for RawWrite {
+  fn drop(&mut self) {
+    LCD_OWNER.store(LCD_FREE, Ordering::Release);
+  }
+}
+
+/// Take the LCD back for `lcd_task` after it was parked, waiting for a raw
+/// frame being written to finish.
+async fn claim_for_lcd_task() {
+  while LCD_OWNER
+    .compare_exchange(LCD_FREE, LCD_TASK, Ordering::Acquire, Ordering::Relaxed)
+    .is_err()
+  {
+    sleep(1).await;
+  }
+}
+
 pub struct HardwareDisplayManager {
   signal: &'static LcdSignal,
 }
@@ -93,6 +140,7 @@ impl DisplayManager for HardwareDisplayManager {
     if buffer.len() != FRAME_BYTES {
       return Err(DisplayError::InvalidFrame);
     }
+    let _lcd = RawWrite::claim()?;
 
     // Direct, blocking SPI write from the calling core (core 1 for WASM). The
     // SPI bus is the bottleneck (~11.5 ms per frame at 80 MHz), and writing
@@ -100,10 +148,9 @@ impl DisplayManager for HardwareDisplayManager {
     // path — the pre-abstraction implementation was measured to be the fastest.
     //
     // SAFETY: `SPI_DISPLAY_INTERFACE` is set up once by `lcd_task` before any
-    // WASM app runs. It is only written from core 1 while `lcd_task` is parked
-    // at `LcdScreen::Blank` (WASM sessions blank the screen before the guest
-    // starts), so the two never write the LCD concurrently in practice — the
-    // same pattern the original WASM host used.
+    // WASM app runs. `_lcd` holds `LCD_OWNER` until this function returns, and
+    // `lcd_task` only draws (into `BUFFER` as well as to the LCD) while it
+    // holds it, so the two never use the interface or `BUFFER` concurrently.
     let interface: &mut DisplayInterface = unsafe { core::mem::transmute(SPI_DISPLAY_INTERFACE) };
 
     Command::ColumnAddressSet(0, SCREEN_WIDTH as u16 - 1).send(interface).ok();
@@ -115,11 +162,51 @@ impl DisplayManager for HardwareDisplayManager {
     let now = Instant::now().duration_since_epoch().as_millis() as u32;
     let last = LAST_SCREEN_UPDATE.load(Ordering::Relaxed);
 
-    if now.wrapping_sub(last) > 250 {
+    if now.wrapping_sub(last) > 250 || SNAPSHOT_BEHIND.load(Ordering::Relaxed) {
       LAST_SCREEN_UPDATE.store(now, Ordering::Relaxed);
+      SNAPSHOT_SKIPPED.store(false, Ordering::Relaxed);
+      SNAPSHOT_BEHIND.store(false, Ordering::Relaxed);
 
       let raw_buffer = unsafe { from_raw_parts_mut(BUFFER, (SCREEN_WIDTH * SCREEN_HEIGHT * 2) as usize) };
       raw_buffer.copy_from_slice(buffer);
+    } else {
+      SNAPSHOT_SKIPPED.store(true, Ordering::Relaxed);
+    }
+
+    Ok(())
+  }
+
+  fn signal_raw_region(&self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    region.check(buffer, stride)?;
+    let _lcd = RawWrite::claim()?;
+
+    // Same direct write as `signal_raw_frame`, with the GC9A01 address window
+    // narrowed to the region. RAM writes continue across data transfers until
+    // the next command, so padded rows can be sent one at a time.
+    //
+    // SAFETY: see `signal_raw_frame`.
+    let interface: &mut DisplayInterface = unsafe { core::mem::transmute(SPI_DISPLAY_INTERFACE) };
+
+    Command::ColumnAddressSet(region.x, region.x + region.width - 1)
+      .send(interface)
+      .ok();
+    Command::RowAddressSet(region.y, region.y + region.height - 1).send(interface).ok();
+    Command::MemoryWrite.send(interface).ok();
+    if stride == region.row_bytes() {
+      interface.send_data(DataFormat::U8(&buffer[..stride * region.height as usize])).ok();
+    } else {
+      for row in region.rows(buffer, stride) {
+        interface.send_data(DataFormat::U8(row)).ok();
+      }
+    }
+
+    // Regions are small, so they always go into the snapshot — unless it is
+    // missing the frame they were drawn over.
+    if SNAPSHOT_SKIPPED.load(Ordering::Relaxed) {
+      SNAPSHOT_BEHIND.store(true, Ordering::Relaxed);
+    } else {
+      let raw_buffer = unsafe { from_raw_parts_mut(BUFFER, FRAME_BYTES) };
+      region.composite(raw_buffer, buffer, stride);
     }
 
     Ok(())
@@ -171,9 +258,16 @@ pub async fn lcd_task(sys_bus: MaskedI2cBus, signal: &'static LcdSignal) {
   let now = Instant::now().duration_since_epoch().as_millis() as i32;
   let mut state = LcdState::new(LcdScreen::Blank, now);
 
+  // Whether the LCD was handed to raw frames at `LcdScreen::Blank`.
+  let mut parked = false;
   'await_signal: loop {
+    let screen = signal.wait().await;
+    if parked {
+      claim_for_lcd_task().await;
+      parked = false;
+    }
     let now = Instant::now().duration_since_epoch().as_millis() as i32;
-    state.update(signal.wait().await, now);
+    state.update(screen, now);
 
     loop {
       let now = Instant::now().duration_since_epoch().as_millis() as i32;
@@ -185,6 +279,9 @@ pub async fn lcd_task(sys_bus: MaskedI2cBus, signal: &'static LcdSignal) {
       state.notification_cleanup(now);
 
       if let LcdScreen::Blank = state.screen {
+        // Hands the LCD to raw frames until the next screen arrives.
+        LCD_OWNER.store(LCD_FREE, Ordering::Release);
+        parked = true;
         continue 'await_signal;
       }
 
@@ -7,7 +7,7 @@ use super::system::SystemHandle;
 use super::wifi::WiFiHandle;
 use crate::utils::ota::Ota;
 use alloc::sync::Arc;
-use app::platform::{HexpansionHandle, HttpClientHandle, Platform, TcpHandle};
+use app::platform::{CrashLogHandle, HexpansionHandle, HttpClientHandle, Platform, TcpHandle, UdpHandle, WallClockHandle};
 use app::types::OtaError;
 use core::fmt;
 use embassy_executor::Spawner;
@@ -30,6 +30,9 @@ pub struct HardwarePlatform {
   storage_formatter: HardwareStorageManager,
   http_client: Option<HttpClientHandle>,
   tcp_client: Option<TcpHandle>,
+  udp_client: Option<UdpHandle>,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
 }
 
 impl HardwarePlatform {
@@ -58,6 +61,9 @@ impl HardwarePlatform {
       storage_formatter,
       http_client: None,
       tcp_client: None,
+      udp_client: None,
+      crash_log: CrashLogHandle::new(),
+      wall_clock: WallClockHandle::new(),
     }
   }
 
@@ -70,12 +76,34 @@ impl HardwarePlatform {
     self.tcp_client = Some(client);
     self
   }
+
+  pub fn with_udp_client(mut self, client: UdpHandle) -> Self {
+    self.udp_client = Some(client);
+    self
+  }
+
+  /// Share a clock created before the platform, e.g. with the WiFi task that
+  /// synchronizes it.
+  pub fn with_wall_clock(mut self, wall_clock: WallClockHandle) -> Self {
+    self.wall_clock = wall_clock;
+    self
+  }
 }
 
 const OTA_0_OFFSET: u32 = partition_offset!("ota_0");
 const OTA_1_OFFSET: u32 = partition_offset!("ota_1");
 const OTA_OFFSETS: [u32; 2] = [OTA_0_OFFSET, OTA_1_OFFSET];
 
+/// Fill `dest` from the hardware TRNG. Free-standing so the WASM host on the
+/// second core can use it without a platform handle.
+pub fn fill_entropy(dest: &mut [u8]) {
+  let rng = esp_hal::rng::Rng::new();
+  for chunk in dest.chunks_mut(4) {
+    let v = rng.random().to_le_bytes();
+    chunk.copy_from_slice(&v[..chunk.len()]);
+  }
+}
+
 impl Platform for HardwarePlatform {
   fn display_manager(&self) -> DisplayHandle {
     self.display.clone()
@@ -104,23 +132,28 @@ impl Platform for HardwarePlatform {
   fn tcp_client(&self) -> Option<TcpHandle> {
     self.tcp_client.clone()
   }
+  fn udp_client(&self) -> Option<UdpHandle> {
+    self.udp_client.clone()
+  }
   fn storage_manager(&self) -> StorageHandle {
     self.storage.clone()
   }
   fn config_manager(&self) -> ConfigHandle {
     self.config.clone()
   }
+  fn crash_log(&self) -> CrashLogHandle {
+    self.crash_log.clone()
+  }
+  fn wall_clock(&self) -> WallClockHandle {
+    self.wall_clock.clone()
+  }
 
   fn firmware_version(&self) -> u32 {
     crate::FIRMWARE_VERSION.parse().unwrap_or(0)
   }
 
   fn entropy(&self, dest: &mut [u8]) {
-    let rng = esp_hal::rng::Rng::new();
-    for chunk in dest.chunks_mut(4) {
-      let v = rng.random().to_le_bytes();
-      chunk.copy_from_slice(&v[..chunk.len()]);
-    }
+    fill_entropy(dest);
   }
 
   async fn format_storage(&self) -> Result<(), FsError> {
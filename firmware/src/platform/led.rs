@@ -10,7 +10,8 @@ use aw9523b::Pin;
 use core::fmt;
 use core::sync::atomic::{AtomicBool, Ordering};
 use display_renderer::led_effects::{
-  BreatheEffect, ChaseEffect, FireEffect, LedEffect, OffEffect, RainbowEffect, SolidEffect, SparkleEffect, TheaterChaseEffect,
+  BreatheEffect, ChaseEffect, FireEffect, LedEffect, OffEffect, PixelsEffect, RainbowEffect, SolidEffect, SparkleEffect,
+  TheaterChaseEffect,
 };
 use embassy_executor::Spawner;
 use embassy_sync::blocking_mutex::raw::CriticalSectionRawMutex;
@@ -86,6 +87,7 @@ async fn led_work_loop_task(
         LedRequest::Solid(led_state) => Box::new(SolidEffect {
           colour: display_types::LedState::new(led_state.r, led_state.g, led_state.b),
         }),
+        LedRequest::Pixels(states) => Box::new(PixelsEffect { states }),
         LedRequest::Rainbow => Box::new(RainbowEffect::new(0.1)),
         LedRequest::Breathe(led_state) => Box::new(BreatheEffect::new(
           display_types::LedState::new(led_state.r, led_state.g, led_state.b),
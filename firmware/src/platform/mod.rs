@@ -7,21 +7,25 @@ pub mod input;
 pub mod led;
 pub mod mdns;
 pub mod power;
+pub mod sntp;
 pub mod storage;
 pub mod system;
 pub mod tcp;
 pub mod traits;
+pub mod udp;
 pub mod wifi;
 
 pub use display::{lcd_task, DisplayError, DisplayHandle, DisplayManager, HardwareDisplayManager, LcdSignal};
-pub use hardware::HardwarePlatform;
+pub use hardware::{fill_entropy, HardwarePlatform};
 pub use hexpansion::HardwareHexpansionManager;
 pub use input::{HardwareInputManager, InputHandle, InputManager};
 pub use led::{HardwareLedManager, LedHandle, LedManager};
 pub use mdns::{mdns_runner, mdns_task};
 pub use power::{HardwarePowerManager, PowerError, PowerHandle, PowerManager};
+pub use sntp::sntp_task;
 pub use storage::{ConfigHandle, HardwareStorageManager, StateError, StorageHandle};
 pub use system::{HardwareSystemManager, SystemHandle};
 pub use tcp::HardwareTcpClient;
 pub use traits::Platform;
+pub use udp::HardwareUdpClient;
 pub use wifi::{HardwareWifiManager, WiFiHandle, WiFiManager, WifiDesiredState, WifiMode, WifiResult, WifiStats, WifiStatus};
@@ -0,0 +1,99 @@
+//! SNTP client — keeps the wall clock in sync while WiFi is connected in
+//! Station mode.
+
+use app::platform::{WallClockHandle, sntp};
+use core::net::SocketAddr;
+use embassy_net::{
+  Stack,
+  udp::{PacketMetadata, UdpSocket},
+};
+use embassy_time::{Duration, Instant, Timer, with_timeout};
+use embedded_nal_async::{AddrType, Dns};
+use log::{info, warn};
+
+use crate::platform::{ConfigHandle, fill_entropy};
+use crate::utils::dns::DnsResolver;
+
+/// How long to wait for the server to answer.
+const REPLY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How often to resynchronize once the clock is set. The crystal drifts by a
+/// few seconds a day, so hourly is plenty.
+const RESYNC_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How long to wait before trying again after a failed sync.
+const RETRY_INTERVAL: Duration = Duration::from_secs(30);
+
+#[derive(Debug)]
+enum SyncError {
+  Dns,
+  Socket,
+  Timeout,
+  Reply(sntp::SntpError),
+}
+
+/// A background task that sets `wall_clock` from the configured NTP server and
+/// keeps it in sync. Like `mdns_task`, it exits once the network goes down so
+/// it can be re-spawned on the next connection.
+#[embassy_executor::task]
+pub async fn sntp_task(stack: Stack<'static>, device_config: ConfigHandle, wall_clock: WallClockHandle) {
+  while stack.is_config_up() {
+    let config = device_config.get_data().await;
+    wall_clock.set_utc_offset(config.utc_offset_minutes);
+
+    let wait = match sync(stack, &config.ntp_server, &wall_clock).await {
+      Ok(()) => {
+        if let Some(now) = wall_clock.local_time() {
+          info!("SNTP: Clock set from {}, local time {now}", config.ntp_server);
+        }
+        RESYNC_INTERVAL
+      }
+      Err(err) => {
+        warn!("SNTP: Sync with {} failed: {err:?}", config.ntp_server);
+        RETRY_INTERVAL
+      }
+    };
+
+    Timer::after(wait).await;
+  }
+
+  info!("SNTP: Network down, stopping");
+}
+
+/// Ask `server` for the time once and set `wall_clock` from the answer.
+async fn sync(stack: Stack<'static>, server: &str, wall_clock: &WallClockHandle) -> Result<(), SyncError> {
+  let (host, port) = sntp::server_addr(server);
+  let ip = DnsResolver::new(stack)
+    .get_host_by_name(host, AddrType::IPv4)
+    .await
+    .map_err(|_| SyncError::Dns)?;
+
+  let mut rx_meta = [PacketMetadata::EMPTY; 2];
+  let mut tx_meta = [PacketMetadata::EMPTY; 2];
+  let mut rx_buffer = [0u8; 256];
+  let mut tx_buffer = [0u8; 128];
+  let mut socket = UdpSocket::new(stack, &mut rx_meta, &mut rx_buffer, &mut tx_meta, &mut tx_buffer);
+  // Port 0 picks an ephemeral local port.
+  socket.bind(0).map_err(|_| SyncError::Socket)?;
+
+  let mut nonce = [0u8; 8];
+  fill_entropy(&mut nonce);
+  let nonce = u64::from_le_bytes(nonce);
+
+  let sent_at = Instant::now();
+  socket
+    .send_to(&sntp::request(nonce), SocketAddr::new(ip, port))
+    .await
+    .map_err(|_| SyncError::Socket)?;
+
+  let mut reply = [0u8; 128];
+  let (len, _) = with_timeout(REPLY_TIMEOUT, socket.recv_from(&mut reply))
+    .await
+    .map_err(|_| SyncError::Timeout)?
+    .map_err(|_| SyncError::Socket)?;
+  let server_time = sntp::parse_response(&reply[..len], nonce).map_err(SyncError::Reply)?;
+
+  // Assume the reply spent half the round trip in flight.
+  wall_clock.set(server_time + sent_at.elapsed().as_micros() / 2);
+  Ok(())
+}
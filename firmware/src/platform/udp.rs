@@ -0,0 +1,241 @@
+use crate::utils::dns::DnsResolver;
+use alloc::{
+  boxed::Box,
+  string::{String, ToString},
+  sync::Arc,
+  vec,
+  vec::Vec,
+};
+use app::platform::{UdpClient, UdpEvent, UdpEventChannel};
+use core::{fmt, future::Future, net::SocketAddr, pin::Pin};
+use embassy_executor::Spawner;
+use embassy_futures::select::{Either, select};
+use embassy_net::{
+  Stack,
+  udp::{PacketMetadata, UdpSocket},
+};
+use embassy_sync::{
+  blocking_mutex::raw::CriticalSectionRawMutex,
+  channel::Channel,
+  mutex::{Mutex, MutexGuard},
+};
+use embedded_nal_async::{AddrType, Dns};
+use esp_alloc::ExternalMemory;
+use log::{info, warn};
+
+/// RX/TX buffer sizes and datagram counts for the socket.
+const TX: usize = 2048;
+const RX: usize = 2048;
+const PACKETS: usize = 8;
+
+/// Commands for the socket's pump task, which owns the `!Send` socket
+/// exclusively (see `tcp.rs` for why).
+enum UdpCommand {
+  SendTo(SocketAddr, Vec<u8>),
+  Close,
+}
+
+type CmdChannel = Channel<CriticalSectionRawMutex, UdpCommand, 8>;
+
+/// Generation counter and, while a socket is bound, the command channel of
+/// its pump, as in `tcp.rs`.
+type CmdSlot = Arc<Mutex<CriticalSectionRawMutex, (u64, Option<&'static CmdChannel>)>>;
+
+/// Buffers of the one socket the pump task pool has room for. Allocated
+/// once with the client and lent to each pump in turn, which holds the lock
+/// for as long as its socket lives.
+struct SocketBuffers {
+  rx_meta: [PacketMetadata; PACKETS],
+  tx_meta: [PacketMetadata; PACKETS],
+  rx: [u8; RX],
+  tx: [u8; TX],
+}
+
+type BufferSlot = Mutex<CriticalSectionRawMutex, SocketBuffers>;
+
+/// Embassy task that owns one UDP socket: binds it, forwards received
+/// datagrams to the event channel and executes send/close commands from the
+/// app.
+#[embassy_executor::task]
+async fn udp_pump_task(
+  stack: Stack<'static>,
+  port: u16,
+  buffers: MutexGuard<'static, CriticalSectionRawMutex, SocketBuffers>,
+  events: &'static UdpEventChannel,
+  cmd: &'static CmdChannel,
+  slot: CmdSlot,
+  generation: u64,
+) {
+  let mut buffers = buffers;
+  let SocketBuffers { rx_meta, tx_meta, rx, tx } = &mut *buffers;
+  let mut socket = UdpSocket::new(stack, rx_meta, rx, tx_meta, tx);
+  info!("udp_pump: start gen {generation}");
+
+  match socket.bind(port) {
+    Ok(()) => {
+      info!("udp: bound to port {port}");
+      events.send(UdpEvent::Bound).await;
+      let mut buf = vec![0u8; RX];
+      loop {
+        match select(socket.recv_from(&mut buf), cmd.receive()).await {
+          Either::First(Ok((n, meta))) => {
+            let datagram = UdpEvent::Datagram {
+              host: meta.endpoint.addr.to_string(),
+              port: meta.endpoint.port,
+              data: buf[..n].to_vec(),
+            };
+            // Drop datagrams the consumer has no room for rather than stall
+            // outbound sends behind them.
+            if events.try_send(datagram).is_err() {
+              warn!("udp_pump: consumer behind, dropped {n} bytes");
+            }
+          }
+          Either::First(Err(e)) => {
+            warn!("udp_pump: recv error {e:?}");
+            events.send(UdpEvent::Error).await;
+            break;
+          }
+          Either::Second(UdpCommand::SendTo(addr, data)) => {
+            if let Err(e) = socket.send_to(&data, addr).await {
+              warn!("udp_pump: send to {addr} failed: {e:?}");
+            }
+          }
+          Either::Second(UdpCommand::Close) => {
+            info!("udp_pump: close command");
+            let _ = events.try_send(UdpEvent::Closed);
+            break;
+          }
+        }
+      }
+    }
+    Err(e) => {
+      warn!("udp: bind to port {port} failed: {e:?}");
+      events.send(UdpEvent::Error).await;
+    }
+  }
+
+  let mut guard = slot.lock().await;
+  if guard.0 == generation {
+    guard.1 = None;
+  }
+  // Closes the socket before its buffers go back to the client.
+  drop(socket);
+  info!("udp_pump: task done");
+}
+
+/// Hardware UDP client wrapping the ESP32 network stack. It drives one socket
+/// at a time; binding closes the previous one.
+///
+/// Safety: as for `HardwareTcpClient`, only used from the single-core async
+/// executor.
+pub struct HardwareUdpClient {
+  stack: Stack<'static>,
+  slot: CmdSlot,
+  /// Reused by every socket, so binding allocates nothing.
+  buffers: &'static BufferSlot,
+  cmd: &'static CmdChannel,
+}
+
+impl HardwareUdpClient {
+  /// The socket buffers (about 4.5 KB of PSRAM) and command channel are
+  /// allocated here, once.
+  pub fn new(stack: Stack<'static>) -> Self {
+    let buffers = SocketBuffers {
+      rx_meta: [PacketMetadata::EMPTY; PACKETS],
+      tx_meta: [PacketMetadata::EMPTY; PACKETS],
+      rx: [0; RX],
+      tx: [0; TX],
+    };
+    Self {
+      stack,
+      slot: Arc::new(Mutex::new((0, None))),
+      buffers: Box::leak(Box::new_in(Mutex::new(buffers), ExternalMemory)),
+      cmd: Box::leak(Box::new(CmdChannel::new())),
+    }
+  }
+}
+
+// Safety: only used from a single async executor core
+unsafe impl Send for HardwareUdpClient {}
+unsafe impl Sync for HardwareUdpClient {}
+
+impl fmt::Debug for HardwareUdpClient {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    f.debug_struct("HardwareUdpClient").finish()
+  }
+}
+
+impl UdpClient for HardwareUdpClient {
+  fn bind(&self, port: u16, channel: &'static UdpEventChannel) -> Pin<Box<dyn Future<Output = ()> + 'static>> {
+    let stack = self.stack;
+    let slot = self.slot.clone();
+    let buffers = self.buffers;
+    let cmd = self.cmd;
+    Box::pin(async move {
+      // A pump still running from an earlier bind gives the buffers back
+      // once it has seen `Close`.
+      if slot.lock().await.1.is_some() {
+        cmd.send(UdpCommand::Close).await;
+      }
+      let buffers = buffers.lock().await;
+      // Whatever the previous socket left behind isn't for this one.
+      cmd.clear();
+      channel.clear();
+
+      let generation = {
+        let mut guard = slot.lock().await;
+        guard.0 = guard.0.wrapping_add(1);
+        guard.1 = Some(cmd);
+        guard.0
+      };
+
+      // Like the TCP pump, this must run on the current (non-Send) executor.
+      // The pump sends `Bound` or `Error` once it has tried to bind.
+      let spawner = unsafe { Spawner::for_current_executor() }.await;
+      match udp_pump_task(stack, port, buffers, channel, cmd, slot.clone(), generation) {
+        Ok(token) => spawner.spawn(token),
+        Err(_) => {
+          warn!("udp: failed to spawn pump task");
+          slot.lock().await.1 = None;
+          channel.send(UdpEvent::Error).await;
+        }
+      }
+    })
+  }
+
+  fn send_to(&self, host: String, port: u16, data: Vec<u8>) -> Pin<Box<dyn Future<Output = ()> + 'static>> {
+    let stack = self.stack;
+    let slot = self.slot.clone();
+    Box::pin(async move {
+      let cmd = {
+        let guard = slot.lock().await;
+        guard.1
+      };
+      let Some(cmd) = cmd else {
+        warn!("udp: send with no bound socket");
+        return;
+      };
+      let ip = match DnsResolver::new(stack).get_host_by_name(&host, AddrType::IPv4).await {
+        Ok(ip) => ip,
+        Err(_) => {
+          warn!("udp: dns failed for {host}");
+          return;
+        }
+      };
+      cmd.send(UdpCommand::SendTo(SocketAddr::new(ip, port), data)).await;
+    })
+  }
+
+  fn close(&self) -> Pin<Box<dyn Future<Output = ()> + 'static>> {
+    let slot = self.slot.clone();
+    Box::pin(async move {
+      let cmd = {
+        let guard = slot.lock().await;
+        guard.1
+      };
+      if let Some(c) = cmd {
+        c.send(UdpCommand::Close).await;
+      }
+    })
+  }
+}
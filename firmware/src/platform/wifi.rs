@@ -1,4 +1,5 @@
 use alloc::{boxed::Box, format, string::String, sync::Arc, vec::Vec};
+use app::platform::WallClockHandle;
 pub use app::platform::wifi::{WiFiHandle, WiFiManager, WifiStatus};
 pub use app::types::{WifiDesiredState, WifiMode, WifiResult};
 use core::fmt;
@@ -19,6 +20,7 @@ use log::{error, info};
 
 use crate::platform::ConfigHandle;
 use crate::platform::mdns::mdns_task;
+use crate::platform::sntp::sntp_task;
 use crate::utils::WatchedValue;
 
 #[derive(Clone, Debug, Default)]
@@ -86,9 +88,12 @@ impl HardwareWifiManager {
     controller: WifiController<'static>,
     stack: embassy_net::Stack<'static>,
     ap_ip: Ipv4Addr,
+    wall_clock: WallClockHandle,
   ) {
     let manager = self.clone();
-    spawner.spawn(wifi_connection_task(device_config, controller, stack, ap_ip, manager, spawner).expect("spawn wifi_connection_task"));
+    spawner.spawn(
+      wifi_connection_task(device_config, controller, stack, ap_ip, wall_clock, manager, spawner).expect("spawn wifi_connection_task"),
+    );
   }
 }
 
@@ -98,6 +103,7 @@ pub async fn wifi_connection_task(
   mut controller: WifiController<'static>,
   stack: embassy_net::Stack<'static>,
   ap_ip: Ipv4Addr,
+  wall_clock: WallClockHandle,
   manager: HardwareWifiManager,
   spawner: Spawner,
 ) {
@@ -257,6 +263,11 @@ pub async fn wifi_connection_task(
             if let Ok(token) = mdns_task(stack, device_name, ip_address.unwrap()) {
               spawner.spawn(token);
             }
+
+            // Set the wall clock now that we can reach the internet
+            if let Ok(token) = sntp_task(stack, device_config.clone(), wall_clock.clone()) {
+              spawner.spawn(token);
+            }
           }
 
           let connected = check_connectivity(stack).await;
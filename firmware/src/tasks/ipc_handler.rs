@@ -4,12 +4,10 @@ use crate::{
   types::*,
 };
 use app::menu::state::{StackEntryType, StackEvent, StackEventHandle};
-use app::platform::HttpEventChannel;
-use app::protocol::HttpEvent;
-use core::future::join;
-use embassy_futures::select::{select, Either};
+use app::wasm::{GuestHttp, GuestSockets};
+use embassy_futures::select::{select4, Either4};
 use log::{debug, info};
-use wasm_protocol::{HostIpcMessage as WireHostIpcMessage, WasmIpcMessage as WireWasmIpcMessage};
+use wasm_protocol::WasmIpcMessage as WireWasmIpcMessage;
 
 #[embassy_executor::task]
 pub async fn ipc_handler_task(
@@ -21,14 +19,37 @@ pub async fn ipc_handler_task(
 ) {
   info!("Starting IPC Handler Task...");
 
+  let mut sockets = GuestSockets::new(platform.tcp_client(), platform.udp_client());
+  let mut http = GuestHttp::new(platform.http_client(), host_ipc_sender);
+
   loop {
-    match select(wasm_ipc_channel.receive(), http_event_receiver.receive()).await {
-      Either::First((wasm_req_id, wasm_ipc_message)) => {
-        handle_wasm_message(wasm_req_id, wasm_ipc_message, &host_ipc_sender, &platform, &stack_event_handle).await;
+    let event = select4(
+      wasm_ipc_channel.receive(),
+      http_event_receiver.receive(),
+      sockets.next_event(),
+      http.run(),
+    )
+    .await;
+    match event {
+      Either4::First((wasm_req_id, wasm_ipc_message)) => {
+        handle_wasm_message(
+          wasm_req_id,
+          wasm_ipc_message,
+          &host_ipc_sender,
+          &platform,
+          &stack_event_handle,
+          &mut sockets,
+          &mut http,
+        )
+        .await;
       }
-      Either::Second(http_message) => {
+      Either4::Second(http_message) => {
         handle_http_event(http_message, &host_ipc_sender, &stack_event_handle).await;
       }
+      Either4::Third((socket, socket_message)) => {
+        host_ipc_sender.send((socket, HostIpcMessage::Wire(socket_message))).await;
+      }
+      Either4::Fourth(()) => {}
     }
   }
 }
@@ -39,11 +60,15 @@ async fn handle_wasm_message(
   host_ipc_sender: &HostIpcSender,
   platform: &HardwarePlatform,
   stack_event_handle: &StackEventHandle,
+  sockets: &mut GuestSockets,
+  http: &mut GuestHttp,
 ) {
   match wasm_ipc_message {
     WasmIpcMessage::Started => {}
     WasmIpcMessage::MenuAppStarted => {}
     WasmIpcMessage::Stopped => {
+      http.cancel_all();
+      sockets.close_all().await;
       stack_event_handle.send(StackEvent::Popped);
     }
     WasmIpcMessage::LcdScreen(lcd_screen) => {
@@ -51,51 +76,16 @@ async fn handle_wasm_message(
     }
     WasmIpcMessage::Wire(WireWasmIpcMessage::HttpRequest(http_request)) => {
       debug!("IPC: HttpRequest from guest id={wasm_req_id} url={}", http_request.url);
-      let http_client = match platform.http_client() {
-        Some(client) => client,
-        None => {
-          info!("IPC: no http client, sending HttpError");
-          host_ipc_sender
-            .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpError)))
-            .await;
-          return;
-        }
-      };
-
-      let channel = HttpEventChannel::new();
-      join!(http_client.request(http_request, &channel), async {
-        loop {
-          match channel.receive().await {
-            HttpEvent::Meta(meta) => {
-              debug!("IPC: forwarding meta to guest id={wasm_req_id} status={}", meta.status);
-              host_ipc_sender
-                .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpResponseMeta(meta))))
-                .await;
-            }
-            HttpEvent::Chunk(chunk) => {
-              debug!("IPC: forwarding chunk to guest id={wasm_req_id} len={}", chunk.len());
-              host_ipc_sender
-                .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpResponseBody(chunk))))
-                .await;
-            }
-            HttpEvent::Done => {
-              debug!("IPC: forwarding complete to guest id={wasm_req_id}");
-              host_ipc_sender
-                .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpResponseComplete)))
-                .await;
-              break;
-            }
-            HttpEvent::Error => {
-              info!("IPC: http error, forwarding HttpError to guest id={wasm_req_id}");
-              host_ipc_sender
-                .send((wasm_req_id, HostIpcMessage::Wire(WireHostIpcMessage::HttpError)))
-                .await;
-              break;
-            }
-          }
-        }
-      },)
-      .await;
+      if let Some((id, reply)) = http.start(wasm_req_id, http_request) {
+        info!("IPC: could not start request id={wasm_req_id}, replying {reply:?}");
+        host_ipc_sender.send((id, HostIpcMessage::Wire(reply))).await;
+      }
+    }
+    WasmIpcMessage::Wire(socket_request) => {
+      debug!("IPC: socket request from guest id={wasm_req_id}: {socket_request:?}");
+      if let Some((socket, reply)) = sockets.handle(wasm_req_id, socket_request).await {
+        host_ipc_sender.send((socket, HostIpcMessage::Wire(reply))).await;
+      }
     }
   }
 }
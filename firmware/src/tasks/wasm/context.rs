@@ -1,20 +1,40 @@
+use app::platform::led::{LedError, LedHandle};
+use app::platform::{CrashLogHandle, CrashReport, WallClockHandle};
+use app::types::{LedRequest, LedState, NUM_LEDS};
+use app::wasm::fault::GuestFault;
 use app::wasm::host::WasmHost;
 use esp_hal::{
   gpio::{AnyPin, Level, Output},
   time::Instant,
 };
 use esp_println::print;
-use log::warn;
+use wasm_protocol::Capabilities;
 
-use crate::platform::display::DisplayHandle;
+use crate::platform::display::{DisplayError, DisplayHandle, FrameRegion};
+use crate::platform::fill_entropy;
+
+/// The high-speed GPIOs of hexpansion ports 1 to 6, four per port: the only
+/// pins guests may drive. Every other pin belongs to the badge itself (the
+/// display, LEDs, I2C buses and boot button among them).
+const USER_GPIO_PINS: [u8; 24] = [
+  39, 40, 41, 42, 35, 36, 37, 38, 34, 33, 47, 48, 11, 14, 13, 12, 18, 16, 15, 17, 3, 4, 5, 6,
+];
 
 pub struct HardwareWasmHost {
   display: DisplayHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
 }
 
 impl HardwareWasmHost {
-  pub fn new(display: DisplayHandle) -> Self {
-    Self { display }
+  pub fn new(display: DisplayHandle, led: LedHandle, crash_log: CrashLogHandle, wall_clock: WallClockHandle) -> Self {
+    Self {
+      display,
+      led,
+      crash_log,
+      wall_clock,
+    }
   }
 }
 
@@ -27,17 +47,56 @@ impl WasmHost for HardwareWasmHost {
     Instant::now().duration_since_epoch().as_millis()
   }
 
-  fn set_lcd_buffer(&mut self, buffer: &[u8]) {
-    // Non-blocking: the frame is copied into a ping-pong slot and the
-    // display task (core 0) flushes it to SPI while we keep rendering.
-    if let Err(err) = self.display.signal_raw_frame(buffer) {
-      warn!("HardwareWasmHost: failed to submit LCD frame: {err:?}");
-    }
+  fn get_micros(&self) -> u64 {
+    Instant::now().duration_since_epoch().as_micros()
+  }
+
+  fn unix_time_micros(&self) -> Option<u64> {
+    self.wall_clock.unix_micros()
   }
 
-  fn set_gpio(&mut self, pin_number: u32, state: u32) {
-    let pin = unsafe { AnyPin::steal(pin_number.try_into().unwrap()) };
+  fn fill_random(&mut self, dest: &mut [u8]) {
+    fill_entropy(dest);
+  }
+
+  fn set_lcd_buffer(&mut self, buffer: &[u8]) -> Result<(), DisplayError> {
+    // Written to SPI from this core, or refused straight away while the
+    // display task (core 0) holds the LCD for a screen of its own.
+    self.display.signal_raw_frame(buffer)
+  }
+
+  fn set_lcd_region(&mut self, region: FrameRegion, buffer: &[u8], stride: usize) -> Result<(), DisplayError> {
+    self.display.signal_raw_region(region, buffer, stride)
+  }
+
+  fn set_gpio(&mut self, pin_number: u32, state: u32) -> Result<(), GuestFault> {
+    let Some(&pin_number) = USER_GPIO_PINS.iter().find(|&&pin| u32::from(pin) == pin_number) else {
+      return Err(GuestFault::BadGpioPin(pin_number));
+    };
+    // SAFETY: the user header pins aren't claimed by any driver in the
+    // firmware, so the guest is the only user of this one.
+    let pin = unsafe { AnyPin::steal(pin_number) };
     let mut output = Output::new(pin, Level::High, Default::default());
     output.set_level(if state == 0 { Level::Low } else { Level::High });
+    Ok(())
+  }
+
+  fn set_leds(&mut self, leds: &[LedState; NUM_LEDS]) -> Result<(), LedError> {
+    self.led.request(LedRequest::Pixels(*leds))
+  }
+
+  fn set_led_effect(&mut self, request: LedRequest) -> Result<(), LedError> {
+    self.led.request(request)
+  }
+
+  fn capabilities(&self) -> Capabilities {
+    Capabilities::GPIO
+      .union(Capabilities::LEDS)
+      .union(Capabilities::HTTP)
+      .union(Capabilities::SOCKETS)
+  }
+
+  fn report_crash(&mut self, report: CrashReport) {
+    self.crash_log.record(report);
   }
 }
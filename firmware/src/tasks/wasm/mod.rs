@@ -13,15 +13,34 @@ use embassy_time::{Duration, Timer};
 use esp_println::println;
 use log::{error, info};
 
-use crate::platform::StorageHandle;
 use crate::platform::display::DisplayHandle;
+use crate::platform::{LedHandle, StorageHandle};
+use app::platform::{CrashLogHandle, WallClockHandle};
 
 #[embassy_executor::task]
-pub async fn second_core_task(storage: StorageHandle, display: DisplayHandle, sender: WasmIpcSender, receiver: HostIpcReceiver) {
+pub async fn second_core_task(
+  storage: StorageHandle,
+  display: DisplayHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
+  sender: WasmIpcSender,
+  receiver: HostIpcReceiver,
+) {
   println!("Starting WASM on SECOND CORE...");
 
   loop {
-    if let Err(err) = wasm_host_loop(storage.clone(), display.clone(), sender.clone(), receiver.clone()).await {
+    if let Err(err) = wasm_host_loop(
+      storage.clone(),
+      display.clone(),
+      led.clone(),
+      crash_log.clone(),
+      wall_clock.clone(),
+      sender.clone(),
+      receiver.clone(),
+    )
+    .await
+    {
       error!("second_core_task: An error occurred: {err:?}");
     }
 
@@ -33,6 +52,9 @@ pub async fn second_core_task(storage: StorageHandle, display: DisplayHandle, se
 async fn wasm_host_loop(
   storage: StorageHandle,
   display: DisplayHandle,
+  led: LedHandle,
+  crash_log: CrashLogHandle,
+  wall_clock: WallClockHandle,
   wasm_ipc_sender: WasmIpcSender,
   host_ipc_receiver: HostIpcReceiver,
 ) -> Result<(), anyhow::Error> {
@@ -72,15 +94,16 @@ async fn wasm_host_loop(
         info!("Wasm: Started");
         print_memory_info();
 
-        let buf = storage.read_binary_chunk(filename.clone(), 0, 256 * 1024).await.unwrap();
-
-        info!("WASM: File size: {}", buf.len());
-
-        if let Err(err) = wasm::wasmi_runner(
-          HardwareWasmHost::new(display.clone()),
+        // The module is read into PSRAM a chunk at a time; one that can't be
+        // loaded ends up on the error screen like a crash.
+        let limits = wasm::WasmLimits::for_metadata(storage.read_app_metadata(&filename).await.as_ref());
+        if let Err(err) = wasm::wasmi_file_runner(
+          HardwareWasmHost::new(display.clone(), led.clone(), crash_log.clone(), wall_clock.clone()),
           wasm_ipc_sender.clone(),
           host_ipc_receiver.clone(),
-          buf,
+          storage.clone(),
+          &filename,
+          limits,
         )
         .await
         {
@@ -88,7 +111,7 @@ async fn wasm_host_loop(
         }
 
         // wasmi_runner already sends WasmIpcMessage::Stopped on
-        // completion or abort — do not send a second one here.
+        // completion, abort or error — do not send a second one here.
         info!("Wasm: Stopped");
         print_memory_info();
       }
@@ -106,10 +129,20 @@ async fn wasm_host_loop(
         info!("Wasm: Started");
         print_memory_info();
 
+        // Uploaded apps have no file name to key their data by, so they share
+        // a scratch sandbox that is wiped on every launch.
+        let app_storage = wasm::AppStorage::scratch(storage.clone());
+        if let Err(err) = app_storage.clear().await {
+          error!("WASM: Failed to clear scratch storage: {err:?}");
+        }
+
+        let limits = wasm::WasmLimits::for_module(&buffer);
         if let Err(err) = wasm::wasmi_runner(
-          HardwareWasmHost::new(display.clone()),
+          HardwareWasmHost::new(display.clone(), led.clone(), crash_log.clone(), wall_clock.clone()),
           wasm_ipc_sender.clone(),
           host_ipc_receiver.clone(),
+          Some(app_storage),
+          limits,
           buffer,
         )
         .await
@@ -118,7 +151,7 @@ async fn wasm_host_loop(
         }
 
         // wasmi_runner already sends WasmIpcMessage::Stopped on
-        // completion or abort — do not send a second one here.
+        // completion, abort or error — do not send a second one here.
         info!("Wasm: Stopped");
         print_memory_info();
       }
@@ -29,6 +29,17 @@ impl LedEffect for SolidEffect {
   }
 }
 
+/// Fixed colour per LED
+pub struct PixelsEffect {
+  pub states: [LedState; NUM_LEDS],
+}
+
+impl LedEffect for PixelsEffect {
+  fn update_and_render(&mut self, _now_ms: u64) -> [LedState; NUM_LEDS] {
+    self.states
+  }
+}
+
 /// Rainbow effect that cycles through colors
 pub struct RainbowEffect {
   offset: f32,
@@ -310,8 +310,17 @@ impl LcdState {
 
         return 1_000;
       }
-      LcdScreen::Menu { menu, selected, animation } => {
-        return self.draw_menu(display, menu, *selected, *animation, time_ms, now_ms);
+      LcdScreen::Menu {
+        menu,
+        selected,
+        animation,
+        header,
+      } => {
+        let next_frame_ms = self.draw_menu(display, menu, *selected, *animation, time_ms, now_ms);
+        if let Some(header) = header {
+          draw_menu_header(display, header);
+        }
+        return next_frame_ms;
       }
       LcdScreen::TextBuffer { lines } => {
         return self.draw_text_buffer(display, lines, now_ms);
@@ -570,6 +579,23 @@ impl LcdState {
 
 // ============================== Helpers ==============================
 
+/// Draw a `LcdScreen::Menu` header centred in the strip above the list,
+/// covering any items scrolled up into it.
+fn draw_menu_header(display: &mut impl FrameBuffer, header: &str) {
+  Rectangle::new(Point::zero(), Size::new(SCREEN_WIDTH as u32, MARGIN as u32))
+    .draw_styled(&PrimitiveStyle::with_fill(Rgb565::BLACK), display)
+    .ok();
+
+  let text_width = header.chars().count() as i32 * CHAR_WIDTH;
+  let mut text = Text::new(
+    header,
+    Point::new((SCREEN_WIDTH as i32 - text_width) / 2, MARGIN - LINE_HEIGHT - 4),
+    MonoTextStyle::new(&FONT_10X20, Rgb565::CYAN),
+  );
+  text.text_style.baseline = Baseline::Top;
+  text.draw(display).ok();
+}
+
 fn should_restart_animation(screen: &LcdScreen, new_screen: &LcdScreen) -> bool {
   match (screen, new_screen) {
     (
@@ -19,6 +19,10 @@ pub enum LcdScreen {
     /// Slide-in animation used when this menu is displayed/updated.
     #[serde(default)]
     animation: MenuAnimation,
+    /// Short status text (e.g. the time) shown above the menu; it doesn't
+    /// scroll or slide with the items.
+    #[serde(default)]
+    header: Option<String>,
   },
   /// Multi-line text buffer drawn inside a centred bordered frame. `lines`
   /// holds up to 8 lines; the line carrying `Some(cursor)` is the active
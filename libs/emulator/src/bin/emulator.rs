@@ -10,12 +10,13 @@ use crate::utils::print_memory_usage;
 use minifb::{Key, Scale, Window, WindowOptions};
 use std::future::join;
 use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::mpmc::channel;
 use std::time::SystemTime;
 use tokio::sync::RwLock;
 use tokio::task;
 use tokio::{task::yield_now, time::Duration, time::sleep};
-use wasm_protocol::{HexButton, HostIpcMessage, HttpResponseMeta, WasmIpcMessage};
+use wasm_protocol::{HexButton, HostIpcMessage, HttpResponseMeta, NUM_LEDS, WasmIpcMessage, WireFormat};
 
 pub fn __make_static<T: ?Sized>(t: &mut T) -> &'static mut T {
   unsafe { ::core::mem::transmute(t) }
@@ -24,6 +25,29 @@ pub fn __make_static<T: ?Sized>(t: &mut T) -> &'static mut T {
 const WIDTH: usize = 240;
 const HEIGHT: usize = 240;
 
+/// The LED ring stand-in is drawn just inside the edge of the round LCD.
+const LED_RING_RADIUS: f32 = 112.0;
+const LED_RADIUS: i32 = 4;
+
+/// Overlay the guest's LED ring onto a copy of the LCD frame: LED 0 at the
+/// top, continuing clockwise.
+fn draw_led_ring(frame: &mut [u32], leds: &[u32]) {
+  for (i, colour) in leds.iter().enumerate() {
+    let angle = i as f32 / leds.len() as f32 * std::f32::consts::TAU;
+    let led_x = (WIDTH as f32 / 2.0 + LED_RING_RADIUS * angle.sin()) as i32;
+    let led_y = (HEIGHT as f32 / 2.0 - LED_RING_RADIUS * angle.cos()) as i32;
+
+    for dy in -LED_RADIUS..=LED_RADIUS {
+      for dx in -LED_RADIUS..=LED_RADIUS {
+        let (x, y) = (led_x + dx, led_y + dy);
+        if dx * dx + dy * dy <= LED_RADIUS * LED_RADIUS && (0..WIDTH as i32).contains(&x) && (0..HEIGHT as i32).contains(&y) {
+          frame[y as usize * WIDTH + x as usize] = *colour;
+        }
+      }
+    }
+  }
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
   print_memory_usage();
@@ -41,25 +65,35 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
   let host_ipc_sender = _host_ipc_sender.clone();
 
+  // Set by the runner once the guest is instantiated.
+  let wire_format = Arc::new(AtomicU32::new(WireFormat::Json as u32));
+  let guest_wire_format = {
+    let wire_format = wire_format.clone();
+    move || WireFormat::from_u32(wire_format.load(Ordering::Relaxed)).unwrap_or_default()
+  };
+  let encode_format = guest_wire_format.clone();
+
   let send_host_ipc_msg = move |wasm_req_id: u32, host_ipc_msg: HostIpcMessage| {
-    // let host_msg_bytes = postcard::to_allocvec(&host_ipc_msg).unwrap();
-    let host_msg_bytes = serde_json::to_vec(&host_ipc_msg).unwrap();
-    println!("HOST: {}", std::str::from_utf8(&host_msg_bytes).unwrap());
+    println!("HOST: {host_ipc_msg:?}");
+    let host_msg_bytes = encode_format().encode(&host_ipc_msg).unwrap();
 
     host_ipc_sender.send((wasm_req_id, host_msg_bytes.clone())).unwrap();
     host_ipc_sender.send((wasm_req_id, host_msg_bytes)).unwrap();
   };
 
   let decode_wasm_ipc_msg = |wasm_msg_bytes: Vec<u8>| -> WasmIpcMessage {
-    // postcard::from_bytes(wasm_msg_bytes.as_slice()).unwrap()
-    println!("WASM: {}", std::str::from_utf8(&wasm_msg_bytes).unwrap());
-    serde_json::from_slice(wasm_msg_bytes.as_slice()).unwrap()
+    let wasm_ipc_msg = guest_wire_format().decode(&wasm_msg_bytes).unwrap();
+    println!("WASM: {wasm_ipc_msg:?}");
+    wasm_ipc_msg
   };
 
+  let leds: Arc<RwLock<Vec<u32>>> = Arc::new(RwLock::new(vec![0; NUM_LEDS]));
+
   let lcd_buffer_1 = _lcd_buffer.clone();
+  let leds_1 = leds.clone();
 
   task::spawn_blocking(|| {
-    wasmi_runner(lcd_buffer_1, wasm_ipc_sender, host_ipc_receiver);
+    wasmi_runner(lcd_buffer_1, leds_1, wire_format, wasm_ipc_sender, host_ipc_receiver);
   });
 
   let lcd_buffer_2 = _lcd_buffer.clone();
@@ -100,6 +134,16 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
 
             send_host_ipc_msg(wasm_msg_id, HostIpcMessage::HttpResponseComplete);
           }
+          // Sockets are not emulated; fail them so guests can fall back.
+          WasmIpcMessage::TcpConnect { .. } | WasmIpcMessage::UdpBind { .. } => {
+            send_host_ipc_msg(wasm_msg_id, HostIpcMessage::SocketError);
+          }
+          WasmIpcMessage::SocketSend { socket, .. } | WasmIpcMessage::SocketSendTo { socket, .. } => {
+            send_host_ipc_msg(socket, HostIpcMessage::SocketError);
+          }
+          WasmIpcMessage::SocketClose { .. } => {}
+          // The window only shows raw frames; widget screens are not emulated.
+          WasmIpcMessage::ShowScreen(_) | WasmIpcMessage::ShowFramebuffer => {}
         };
       }
 
@@ -137,9 +181,10 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         send_host_ipc_msg(0, HostIpcMessage::HexButton(HexButton::HexF));
       }
 
-      window
-        .update_with_buffer(&lcd_buffer_2.read().await, WIDTH, HEIGHT)
-        .unwrap();
+      let mut frame = lcd_buffer_2.read().await.clone();
+      draw_led_ring(&mut frame, &leds.read().await);
+
+      window.update_with_buffer(&frame, WIDTH, HEIGHT).unwrap();
 
       sleep(Duration::from_millis(20)).await;
     }
@@ -1,22 +1,36 @@
 use crate::tasks::wasm::timers::TimerRegistry;
+use display_renderer::led_effects::LedEffect;
 use std::{
+  collections::HashMap,
   sync::{
     Arc,
+    atomic::AtomicU32,
     mpmc::{Receiver, Sender},
   },
   time::Instant,
 };
 use tokio::sync::RwLock;
-use wasmi::{Caller, ResourceLimiter};
+use wasmi::{Caller, Memory, ResourceLimiter};
 use wasmi_core::LimiterError;
 
 pub struct WasmCtx {
   pub start: Instant,
   pub counter: u32,
   pub lcd_buffer: Arc<RwLock<Vec<u32>>>,
+  pub leds: Arc<RwLock<Vec<u32>>>,
+  pub led_effect: Option<Box<dyn LedEffect + Send>>,
+  /// Guest key/value storage. The emulator keeps it in memory, so values
+  /// last for one run only.
+  pub storage: HashMap<String, Vec<u8>>,
   pub timer_registry: TimerRegistry,
+  /// Set by `extern_idle` during a tick: the guest is waiting on a timer or
+  /// a host message.
+  pub idle: bool,
   pub wasm_ipc_sender: Sender<(u32, Vec<u8>)>,
   pub host_ipc_receiver: Receiver<(u32, Vec<u8>)>,
+  /// The guest's IPC encoding as a `WireFormat` value, shared with the
+  /// host side so it can encode and decode messages to match.
+  pub wire_format: Arc<AtomicU32>,
   pub limiter: MyLimiter,
 }
 
@@ -46,39 +60,44 @@ impl ResourceLimiter for MyLimiter {
   }
 }
 
+/// Guest memory access for host functions. Every method fails, trapping the
+/// guest, if the range runs past the end of its memory.
 pub trait ReadWasmBuffer {
-  fn read_memory(&self, ptr: u32, len: u32) -> Vec<u8>;
-  fn write_memory(&mut self, ptr: u32, buf: &[u8]);
+  /// Check that `len` bytes from `ptr` lie within guest memory, before
+  /// allocating anything for them.
+  fn check_memory(&self, ptr: u32, len: u32) -> Result<(), wasmi::Error>;
+  fn read_memory(&self, ptr: u32, len: u32) -> Result<Vec<u8>, wasmi::Error>;
+  fn write_memory(&mut self, ptr: u32, buf: &[u8]) -> Result<(), wasmi::Error>;
 }
 
 impl ReadWasmBuffer for Caller<'_, WasmCtx> {
-  fn read_memory(&self, ptr: u32, len: u32) -> Vec<u8> {
-    let memory = self
-      .get_export("memory")
-      .and_then(|export| export.into_memory())
-      .ok_or_else(|| wasmi::Error::new("failed to find memory export"))
-      .unwrap();
+  fn check_memory(&self, ptr: u32, len: u32) -> Result<(), wasmi::Error> {
+    let size = guest_memory(self)?.data(self).len();
+    match (ptr as usize).checked_add(len as usize) {
+      Some(end) if end <= size => Ok(()),
+      _ => Err(wasmi::Error::new(format!("{len} bytes at {ptr:#x} are outside guest memory"))),
+    }
+  }
 
+  fn read_memory(&self, ptr: u32, len: u32) -> Result<Vec<u8>, wasmi::Error> {
+    self.check_memory(ptr, len)?;
     let mut buffer = vec![0u8; len as usize];
-
-    memory
-      .read(&self, ptr as usize, &mut buffer)
-      .map_err(|_| wasmi::Error::new("failed to read memory"))
-      .unwrap();
-
-    buffer
+    guest_memory(self)?
+      .read(self, ptr as usize, &mut buffer)
+      .map_err(|_| wasmi::Error::new("failed to read memory"))?;
+    Ok(buffer)
   }
 
-  fn write_memory(&mut self, ptr: u32, buf: &[u8]) {
-    let memory = self
-      .get_export("memory")
-      .and_then(|export| export.into_memory())
-      .ok_or_else(|| wasmi::Error::new("failed to find memory export"))
-      .unwrap();
-
-    memory
-      .write(self, ptr as usize, &buf)
+  fn write_memory(&mut self, ptr: u32, buf: &[u8]) -> Result<(), wasmi::Error> {
+    guest_memory(self)?
+      .write(self, ptr as usize, buf)
       .map_err(|_| wasmi::Error::new("failed to write memory"))
-      .unwrap();
   }
 }
+
+fn guest_memory(caller: &Caller<'_, WasmCtx>) -> Result<Memory, wasmi::Error> {
+  caller
+    .get_export("memory")
+    .and_then(|export| export.into_memory())
+    .ok_or_else(|| wasmi::Error::new("failed to find memory export"))
+}
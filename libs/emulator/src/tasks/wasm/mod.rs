@@ -9,24 +9,42 @@ use crate::{
   },
 };
 use std::{
+  collections::HashMap,
   env, fs,
   ops::Sub,
   slice::from_raw_parts,
   sync::{
     Arc,
+    atomic::{AtomicU32, Ordering},
     mpmc::{Receiver, Sender},
   },
-  time::{Duration, Instant},
+  thread,
+  time::{Duration, Instant, SystemTime},
 };
+use display_renderer::led_effects::{
+  BreatheEffect, ChaseEffect, FireEffect, OffEffect, RainbowEffect, SolidEffect, SparkleEffect, TheaterChaseEffect,
+};
+use display_types::LedState;
 use tokio::sync::RwLock;
+use wasm_protocol::{
+  Capabilities, DEFAULT_STORAGE_QUOTA, HOST_ABI_VERSION, LedColour, LedEffect as GuestLedEffect, MAX_STORAGE_KEY_LEN, MAX_STORAGE_KEYS,
+  NUM_LEDS, StorageError, WIRE_FORMAT_EXPORT, WireFormat, is_valid_storage_key,
+};
 use wasmi::*;
 use zerocopy::FromBytes;
 
 const WIDTH: usize = 240;
 const HEIGHT: usize = 240;
 
+/// How often an idle guest's runner checks for host messages.
+const IDLE_POLL: Duration = Duration::from_millis(5);
+/// Longest an idle guest sleeps while an LED effect is animating.
+const LED_FRAME: Duration = Duration::from_millis(16);
+
 pub fn wasmi_runner(
   lcd_buffer: Arc<RwLock<Vec<u32>>>,
+  leds: Arc<RwLock<Vec<u32>>>,
+  wire_format: Arc<AtomicU32>,
   wasm_ipc_sender: Sender<(u32, Vec<u8>)>,
   host_ipc_receiver: Receiver<(u32, Vec<u8>)>,
 ) {
@@ -34,9 +52,14 @@ pub fn wasmi_runner(
     start: Instant::now(),
     counter: 1,
     lcd_buffer: lcd_buffer.clone(),
+    leds,
+    led_effect: None,
+    storage: HashMap::new(),
     timer_registry: TimerRegistry::new(),
+    idle: false,
     host_ipc_receiver: host_ipc_receiver.clone(),
     wasm_ipc_sender,
+    wire_format,
     limiter: MyLimiter,
   };
 
@@ -66,6 +89,20 @@ fn rgb565_to_rgb888(mut rgb565: u16) -> u32 {
   // ((red as u32) << (16 + 3)) + ((green as u32) << (8 + 2)) + ((blue as u32) << 3)
 }
 
+fn led_effect(effect: GuestLedEffect, colour: LedColour) -> Box<dyn display_renderer::led_effects::LedEffect + Send> {
+  let colour = LedState::new(colour.r, colour.g, colour.b);
+  match effect {
+    GuestLedEffect::Off => Box::new(OffEffect),
+    GuestLedEffect::Solid => Box::new(SolidEffect { colour }),
+    GuestLedEffect::Rainbow => Box::new(RainbowEffect::new(0.1)),
+    GuestLedEffect::Breathe => Box::new(BreatheEffect::new(colour, 0.001)),
+    GuestLedEffect::Chase => Box::new(ChaseEffect::new(colour, 5, 50)),
+    GuestLedEffect::Sparkle => Box::new(SparkleEffect::new(colour, 0.3, 100)),
+    GuestLedEffect::TheaterChase => Box::new(TheaterChaseEffect::new(colour, 3, 100)),
+    GuestLedEffect::Fire => Box::new(FireEffect::new(55, 120, 30)),
+  }
+}
+
 fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -> Result<(), wasmi::Error> {
   let args: Vec<String> = env::args().collect();
 
@@ -88,6 +125,27 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
 
   linker
     .func_wrap("index", "extern_write_stdout", host_println)?
+    .func_wrap(
+      "index",
+      "extern_panic",
+      |caller: Caller<'_, WasmCtx>,
+       msg_ptr: u32,
+       msg_len: u32,
+       file_ptr: u32,
+       file_len: u32,
+       line: u32|
+       -> Result<(), wasmi::Error> {
+        let message = caller.read_memory(msg_ptr, msg_len.min(256))?;
+        let file = caller.read_memory(file_ptr, file_len.min(256))?;
+        let panic = format!(
+          "App panicked at {}:{line}: {}",
+          String::from_utf8_lossy(&file),
+          String::from_utf8_lossy(&message)
+        );
+        println!("{panic}");
+        Err(wasmi::Error::new(panic))
+      },
+    )?
     .func_wrap(
       "index",
       "extern_set_gpio",
@@ -98,6 +156,28 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
     .func_wrap("index", "extern_get_millis", |caller: Caller<'_, WasmCtx>| {
       Instant::now().duration_since(caller.data().start).as_millis() as u32
     })?
+    .func_wrap("index", "extern_get_micros", |caller: Caller<'_, WasmCtx>| {
+      Instant::now().duration_since(caller.data().start).as_micros() as u64
+    })?
+    .func_wrap("index", "extern_get_unix_micros", |_caller: Caller<'_, WasmCtx>| {
+      let unix_time = SystemTime::now().duration_since(SystemTime::UNIX_EPOCH);
+      unix_time.map_or(0, |time| time.as_micros() as u64)
+    })?
+    .func_wrap(
+      "index",
+      "extern_fill_random",
+      |mut caller: Caller<'_, WasmCtx>, ptr: u32, len: u32| -> Result<(), wasmi::Error> {
+        caller.check_memory(ptr, len)?;
+        let mut bytes = vec![0; len as usize];
+        rand::fill(&mut bytes[..]);
+        caller.write_memory(ptr, &bytes)
+      },
+    )?
+    .func_wrap("index", "extern_get_abi_version", |_caller: Caller<'_, WasmCtx>| HOST_ABI_VERSION)?
+    // Storage only lasts for one run here, so it is not reported as a capability.
+    .func_wrap("index", "extern_get_capabilities", |_caller: Caller<'_, WasmCtx>| {
+      Capabilities::LEDS.union(Capabilities::HTTP).bits()
+    })?
     .func_wrap(
       "index",
       "extern_set_lcd_buffer",
@@ -124,24 +204,50 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
     )?
     .func_wrap(
       "index",
-      "extern_write_wasm_ipc_message",
-      |mut caller: Caller<'_, WasmCtx>, ptr: u32, len: u32| -> u32 {
-        let wasm_ipc_sender = &caller.data().wasm_ipc_sender;
-
-        let wasm_msg = caller.read_memory(ptr, len);
-        let wasm_msg_id = caller.data().counter + 1;
-
-        wasm_ipc_sender.try_send((wasm_msg_id, wasm_msg)).unwrap();
+      "extern_set_lcd_region",
+      |mut caller: Caller<'_, WasmCtx>,
+       x: u32,
+       y: u32,
+       width: u32,
+       height: u32,
+       ptr: u32,
+       stride: u32|
+       -> Result<(), wasmi::Error> {
+        let (x, y, width, height) = (x as usize, y as usize, width as usize, height as usize);
+        let bad_region = || wasmi::Error::new(format!("bad screen region ({width}x{height} at {x},{y})"));
+        let on_screen =
+          x.checked_add(width).is_some_and(|right| right <= WIDTH) && y.checked_add(height).is_some_and(|bottom| bottom <= HEIGHT);
+        if width == 0 || height == 0 || !on_screen || !(width * 2..=WIDTH * 2).contains(&(stride as usize)) {
+          return Err(bad_region());
+        }
 
-        caller.data_mut().counter = wasm_msg_id;
+        let rows = (0..height as u32)
+          .map(|row| {
+            let start = row
+              .checked_mul(stride)
+              .and_then(|offset| ptr.checked_add(offset))
+              .ok_or_else(bad_region)?;
+            caller.read_memory(start, width as u32 * 2)
+          })
+          .collect::<Result<Vec<Vec<u8>>, wasmi::Error>>()?;
 
-        wasm_msg_id
+        let lcd_buffer = &mut caller.data_mut().lcd_buffer.blocking_write();
+        for (row, bytes) in rows.iter().enumerate() {
+          let start = (y + row) * WIDTH + x;
+          for (pixel, b) in lcd_buffer[start..start + width].iter_mut().zip(bytes.chunks_exact(2)) {
+            *pixel = rgb565_to_rgb888(u16::from_ne_bytes([b[0], b[1]]));
+          }
+        }
+        Ok(())
       },
     )?
+    .func_wrap("index", "extern_write_wasm_ipc_message", host_write_wasm_ipc_message)?
+    // The emulator's queue is unbounded, so this never has to refuse.
+    .func_wrap("index", "extern_try_write_wasm_ipc_message", host_write_wasm_ipc_message)?
     .func_wrap(
       "index",
       "extern_read_host_ipc_message",
-      |mut caller: Caller<'_, WasmCtx>, host_msg_id_a: u32, ptr: u32| {
+      |mut caller: Caller<'_, WasmCtx>, host_msg_id_a: u32, ptr: u32| -> Result<(), wasmi::Error> {
         let host_ipc_receiver = &caller.data().host_ipc_receiver;
 
         let (host_msg_id_b, host_msg_bytes) = host_ipc_receiver.try_recv().unwrap();
@@ -150,7 +256,89 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
           panic!("Mismatched host IDs! {host_msg_id_a} {host_msg_id_b}");
         }
 
-        caller.write_memory(ptr, &host_msg_bytes);
+        caller.write_memory(ptr, &host_msg_bytes)
+      },
+    )?;
+
+  linker
+    .func_wrap(
+      "index",
+      "extern_set_led",
+      |mut caller: Caller<'_, WasmCtx>, index: u32, rgb: u32| {
+        caller.data_mut().led_effect = None;
+        if let Some(led) = caller.data().leds.blocking_write().get_mut(index as usize) {
+          *led = LedColour::from_packed(rgb).to_packed();
+        }
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_set_leds",
+      |mut caller: Caller<'_, WasmCtx>, ptr: u32| -> Result<(), wasmi::Error> {
+        let rgb = caller.read_memory(ptr, (NUM_LEDS * 3) as u32)?;
+        caller.data_mut().led_effect = None;
+        let leds = &mut caller.data().leds.blocking_write();
+        for (led, chunk) in leds.iter_mut().zip(rgb.chunks_exact(3)) {
+          *led = LedColour::new(chunk[0], chunk[1], chunk[2]).to_packed();
+        }
+        Ok(())
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_set_led_effect",
+      |mut caller: Caller<'_, WasmCtx>, effect: u32, rgb: u32| match GuestLedEffect::from_u32(effect) {
+        Some(effect) => caller.data_mut().led_effect = Some(led_effect(effect, LedColour::from_packed(rgb))),
+        None => println!("set_led_effect: unknown effect {effect}"),
+      },
+    )?;
+
+  linker
+    .func_wrap(
+      "index",
+      "extern_storage_read",
+      |mut caller: Caller<'_, WasmCtx>, key_ptr: u32, key_len: u32, buf_ptr: u32, buf_len: u32| -> Result<i32, wasmi::Error> {
+        let Some(key) = storage_key(&caller, key_ptr, key_len)? else {
+          return Ok(StorageError::InvalidKey as i32);
+        };
+        let Some(value) = caller.data().storage.get(&key).cloned() else {
+          return Ok(StorageError::NotFound as i32);
+        };
+        caller.write_memory(buf_ptr, &value[..value.len().min(buf_len as usize)])?;
+        Ok(value.len() as i32)
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_storage_write",
+      |mut caller: Caller<'_, WasmCtx>, key_ptr: u32, key_len: u32, buf_ptr: u32, buf_len: u32| -> Result<i32, wasmi::Error> {
+        let Some(key) = storage_key(&caller, key_ptr, key_len)? else {
+          return Ok(StorageError::InvalidKey as i32);
+        };
+        // The same quota as the device, counting every value but the one
+        // being replaced.
+        let storage = &caller.data().storage;
+        let others = storage.iter().filter(|(stored, _)| **stored != key);
+        let (used, keys) = others.fold((0usize, 0usize), |(bytes, keys), (_, value)| (bytes + value.len(), keys + 1));
+        if keys >= MAX_STORAGE_KEYS || used + buf_len as usize > DEFAULT_STORAGE_QUOTA as usize {
+          return Ok(StorageError::QuotaExceeded as i32);
+        }
+        let value = caller.read_memory(buf_ptr, buf_len)?;
+        caller.data_mut().storage.insert(key, value);
+        Ok(0)
+      },
+    )?
+    .func_wrap(
+      "index",
+      "extern_storage_delete",
+      |mut caller: Caller<'_, WasmCtx>, key_ptr: u32, key_len: u32| -> Result<i32, wasmi::Error> {
+        let Some(key) = storage_key(&caller, key_ptr, key_len)? else {
+          return Ok(StorageError::InvalidKey as i32);
+        };
+        Ok(match caller.data_mut().storage.remove(&key) {
+          Some(_) => 0,
+          None => StorageError::NotFound as i32,
+        })
       },
     )?;
 
@@ -158,6 +346,16 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
 
   let instance = linker.instantiate_and_start(&mut store, &module)?;
 
+  // Guests built before postcard support don't export a format and speak JSON.
+  let wire_format = match instance.get_typed_func::<(), u32>(&store, WIRE_FORMAT_EXPORT) {
+    Ok(func) => {
+      WireFormat::from_u32(func.call(&mut store, ())?).ok_or_else(|| wasmi::Error::new("App needs unsupported wire format"))?
+    }
+    Err(_) => WireFormat::Json,
+  };
+  println!("Wire format: {wire_format:?}");
+  store.data().wire_format.store(wire_format as u32, Ordering::Relaxed);
+
   print_memory_usage();
 
   let wasm_main = instance
@@ -182,6 +380,15 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
 
     let now = Instant::now();
 
+    let ctx = store.data_mut();
+    if let Some(effect) = ctx.led_effect.as_mut() {
+      let states = effect.update_and_render(now.duration_since(ctx.start).as_millis() as u64);
+      let leds = &mut ctx.leds.blocking_write();
+      for (led, state) in leds.iter_mut().zip(states) {
+        *led = LedColour::new(state.r, state.g, state.b).to_packed();
+      }
+    }
+
     if now - last_print > Duration::from_millis(1_000) {
       // print_memory_usage();
       let bytes = get_memory_usage.call(&mut store, ())?;
@@ -189,6 +396,7 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
       last_print = now;
     }
 
+    store.data_mut().idle = false;
     if tick
       .call(&mut store, (host_msg_id, host_msg_length))
       .map_err(|err| wasmi::Error::new(format!("Error Calling tick: {err}")))?
@@ -196,6 +404,10 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
     {
       break;
     }
+
+    if store.data().idle {
+      wait_for_event(store.data(), &host_ipc_receiver);
+    }
   }
 
   println!("==== Program Complete ====");
@@ -203,6 +415,31 @@ fn run_program(wasm_ctx: WasmCtx, host_ipc_receiver: Receiver<(u32, Vec<u8>)>) -
   Ok(())
 }
 
+/// Sleep while the guest is idle: until its earliest timer expires, a host
+/// message arrives, or the LED effect is due its next frame.
+fn wait_for_event(ctx: &WasmCtx, host_ipc_receiver: &Receiver<(u32, Vec<u8>)>) {
+  let effect_frame = ctx.led_effect.as_ref().map(|_| SystemTime::now() + LED_FRAME);
+  let until = match (ctx.timer_registry.next_expiry(), effect_frame) {
+    (Some(timer), Some(frame)) => Some(timer.min(frame)),
+    (timer, frame) => timer.or(frame),
+  };
+  while host_ipc_receiver.is_empty() && until.is_none_or(|until| SystemTime::now() < until) {
+    thread::sleep(IDLE_POLL);
+  }
+}
+
+fn host_write_wasm_ipc_message(mut caller: Caller<'_, WasmCtx>, ptr: u32, len: u32) -> Result<u32, wasmi::Error> {
+  let wasm_msg = caller.read_memory(ptr, len)?;
+  let wasm_ipc_sender = &caller.data().wasm_ipc_sender;
+  let wasm_msg_id = caller.data().counter + 1;
+
+  wasm_ipc_sender.try_send((wasm_msg_id, wasm_msg)).unwrap();
+
+  caller.data_mut().counter = wasm_msg_id;
+
+  Ok(wasm_msg_id)
+}
+
 // Define a host function that can read strings from wasm memory
 fn host_println(caller: Caller<'_, WasmCtx>, ptr: i32, len: u32) -> Result<(), wasmi::Error> {
   // Get access to the wasm linear memory
@@ -223,3 +460,12 @@ fn host_println(caller: Caller<'_, WasmCtx>, ptr: i32, len: u32) -> Result<(), w
 
   Ok(())
 }
+
+/// The storage key at `ptr`, or `None` if it is not a valid key. Fails if it
+/// lies outside guest memory.
+fn storage_key(caller: &Caller<'_, WasmCtx>, ptr: u32, len: u32) -> Result<Option<String>, wasmi::Error> {
+  if len as usize > MAX_STORAGE_KEY_LEN {
+    return Ok(None);
+  }
+  Ok(String::from_utf8(caller.read_memory(ptr, len)?).ok().filter(|key| is_valid_storage_key(key)))
+}
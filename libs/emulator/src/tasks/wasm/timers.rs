@@ -43,6 +43,11 @@ impl TimerRegistry {
   pub fn cancel(&mut self, timer_id: i32) {
     self.timers.remove(&timer_id);
   }
+
+  /// When the earliest registered timer expires, if there are any.
+  pub fn next_expiry(&self) -> Option<SystemTime> {
+    self.timers.values().copied().min()
+  }
 }
 
 pub fn add_timers_to_linker(linker: &mut Linker<WasmCtx>) -> Result<(), Error> {
@@ -59,10 +64,12 @@ pub fn add_timers_to_linker(linker: &mut Linker<WasmCtx>) -> Result<(), Error> {
     .func_wrap(
       "index",
       "extern_check_timer",
-      |caller: Caller<'_, WasmCtx>, timer_id: i32| -> i32 {
+      |mut caller: Caller<'_, WasmCtx>, timer_id: i32| -> i32 {
         let expired = caller.data().timer_registry.check(timer_id);
         if expired == 1 {
           // println!("check_timer: timer_id={timer_id} EXPIRED");
+          // Drop it, as the firmware does, so it no longer wakes an idle guest.
+          caller.data_mut().timer_registry.cancel(timer_id);
         }
         expired
       },
@@ -74,7 +81,10 @@ pub fn add_timers_to_linker(linker: &mut Linker<WasmCtx>) -> Result<(), Error> {
         caller.data_mut().timer_registry.cancel(timer_id);
         // println!("cancel_timer: timer_id={timer_id}");
       },
-    )?;
+    )?
+    .func_wrap("index", "extern_idle", |mut caller: Caller<'_, WasmCtx>| {
+      caller.data_mut().idle = true;
+    })?;
 
   Ok(())
 }
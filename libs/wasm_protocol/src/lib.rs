@@ -60,6 +60,48 @@ pub enum HexButton {
 }
 
 impl HexButton {
+  /// Number of physical buttons (each has a press and a release event).
+  pub const COUNT: usize = 23;
+
+  /// Every button, by its press event, in declaration order.
+  pub const ALL: [HexButton; HexButton::COUNT] = [
+    HexButton::Up,
+    HexButton::Right,
+    HexButton::Fire,
+    HexButton::Down,
+    HexButton::Left,
+    HexButton::HexA,
+    HexButton::HexB,
+    HexButton::HexC,
+    HexButton::HexD,
+    HexButton::HexE,
+    HexButton::HexF,
+    HexButton::Touch01,
+    HexButton::Touch02,
+    HexButton::Touch03,
+    HexButton::Touch04,
+    HexButton::Touch05,
+    HexButton::Touch06,
+    HexButton::Touch07,
+    HexButton::Touch08,
+    HexButton::Touch09,
+    HexButton::Touch10,
+    HexButton::Touch11,
+    HexButton::Touch12,
+  ];
+
+  /// Whether this is a `*Released` event.
+  pub const fn is_release(self) -> bool {
+    self as usize >= HexButton::COUNT
+  }
+
+  /// Position of the button in [`HexButton::ALL`], the same for its press and
+  /// release events. The release variants are declared in the same order as
+  /// the presses, straight after them.
+  pub const fn index(self) -> usize {
+    self as usize % HexButton::COUNT
+  }
+
   /// Returns the release event for this button. Press events map to their
   /// `*Released` counterpart; release events map to themselves (idempotent),
   /// so producers can call this on any event they hold.
@@ -93,6 +135,370 @@ impl HexButton {
   }
 }
 
+// ================================ Keyboard ================================
+
+/// A key on a keyboard hexpansion (or the desktop keyboard). Variants are
+/// only ever appended, since their order is part of the wire format.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum KeyCode {
+  A,
+  B,
+  C,
+  D,
+  E,
+  F,
+  G,
+  H,
+  I,
+  J,
+  K,
+  L,
+  M,
+  N,
+  O,
+  P,
+  Q,
+  R,
+  S,
+  T,
+  U,
+  V,
+  W,
+  X,
+  Y,
+  Z,
+  Digit0,
+  Digit1,
+  Digit2,
+  Digit3,
+  Digit4,
+  Digit5,
+  Digit6,
+  Digit7,
+  Digit8,
+  Digit9,
+  Enter,
+  Escape,
+  Backspace,
+  Tab,
+  Space,
+  Delete,
+  Up,
+  Down,
+  Left,
+  Right,
+  F1,
+  F2,
+  F3,
+  F4,
+  F5,
+  F6,
+  F7,
+  F8,
+  F9,
+  F10,
+  F11,
+  F12,
+  Comma,
+  Period,
+  Slash,
+  Semicolon,
+  Quote,
+  Minus,
+  Equals,
+  Backtick,
+  Backslash,
+  LBracket,
+  RBracket,
+  Shift,
+  Ctrl,
+  Alt,
+  CapsLock,
+  Home,
+  End,
+  PageUp,
+  PageDown,
+}
+
+/// Map a key's unshifted character to its shifted equivalent for keys that
+/// shift to a symbol (digits and punctuation). Letters shift to their
+/// uppercase form (handled in [`KeyCode::to_char`]), modifiers never produce a
+/// character.
+pub const SHIFTED_SYMBOL_MAP: &[(KeyCode, char)] = &[
+  (KeyCode::Digit1, '!'),
+  (KeyCode::Digit2, '@'),
+  (KeyCode::Digit3, '#'),
+  (KeyCode::Digit4, '$'),
+  (KeyCode::Digit5, '%'),
+  (KeyCode::Digit6, '^'),
+  (KeyCode::Digit7, '&'),
+  (KeyCode::Digit8, '*'),
+  (KeyCode::Digit9, '('),
+  (KeyCode::Digit0, ')'),
+  (KeyCode::Minus, '_'),
+  (KeyCode::Backtick, '~'),
+  (KeyCode::Comma, '<'),
+  (KeyCode::Period, '>'),
+  (KeyCode::Slash, '?'),
+  (KeyCode::Semicolon, ':'),
+  (KeyCode::Quote, '"'),
+  (KeyCode::Equals, '+'),
+  (KeyCode::Backslash, '|'),
+  (KeyCode::LBracket, '{'),
+  (KeyCode::RBracket, '}'),
+];
+
+impl KeyCode {
+  /// Convert a key to the character it produces on the given keyboard layout.
+  ///
+  /// Pass the caller's current shift state (tracked from `KeyCode::Shift`
+  /// press/release events). When `shifted` is true, letters become uppercase
+  /// and symbol keys (digits, punctuation) produce their shifted equivalent.
+  /// Returns `None` for keys that do not produce a character (modifiers,
+  /// navigation, editing keys, function keys).
+  pub fn to_char(self, shifted: bool) -> Option<char> {
+    match self {
+      KeyCode::A => Some('a'),
+      KeyCode::B => Some('b'),
+      KeyCode::C => Some('c'),
+      KeyCode::D => Some('d'),
+      KeyCode::E => Some('e'),
+      KeyCode::F => Some('f'),
+      KeyCode::G => Some('g'),
+      KeyCode::H => Some('h'),
+      KeyCode::I => Some('i'),
+      KeyCode::J => Some('j'),
+      KeyCode::K => Some('k'),
+      KeyCode::L => Some('l'),
+      KeyCode::M => Some('m'),
+      KeyCode::N => Some('n'),
+      KeyCode::O => Some('o'),
+      KeyCode::P => Some('p'),
+      KeyCode::Q => Some('q'),
+      KeyCode::R => Some('r'),
+      KeyCode::S => Some('s'),
+      KeyCode::T => Some('t'),
+      KeyCode::U => Some('u'),
+      KeyCode::V => Some('v'),
+      KeyCode::W => Some('w'),
+      KeyCode::X => Some('x'),
+      KeyCode::Y => Some('y'),
+      KeyCode::Z => Some('z'),
+      KeyCode::Digit0 => Some('0'),
+      KeyCode::Digit1 => Some('1'),
+      KeyCode::Digit2 => Some('2'),
+      KeyCode::Digit3 => Some('3'),
+      KeyCode::Digit4 => Some('4'),
+      KeyCode::Digit5 => Some('5'),
+      KeyCode::Digit6 => Some('6'),
+      KeyCode::Digit7 => Some('7'),
+      KeyCode::Digit8 => Some('8'),
+      KeyCode::Digit9 => Some('9'),
+      KeyCode::Comma => Some(','),
+      KeyCode::Period => Some('.'),
+      KeyCode::Slash => Some('/'),
+      KeyCode::Semicolon => Some(';'),
+      KeyCode::Quote => Some('\''),
+      KeyCode::Minus => Some('-'),
+      KeyCode::Equals => Some('='),
+      KeyCode::Backtick => Some('`'),
+      KeyCode::Backslash => Some('\\'),
+      KeyCode::LBracket => Some('['),
+      KeyCode::RBracket => Some(']'),
+      _ => return None,
+    }
+    .map(|ch| {
+      if shifted {
+        // Letters shift to uppercase; symbol keys shift via the symbol map.
+        // `SHIFTED_SYMBOL_MAP` covers digits and punctuation, so anything not
+        // in it (i.e. letters) uppercases.
+        SHIFTED_SYMBOL_MAP
+          .iter()
+          .find(|(code, _)| *code == self)
+          .map(|(_, ch)| *ch)
+          .unwrap_or_else(|| ch.to_ascii_uppercase())
+      } else {
+        ch
+      }
+    })
+  }
+
+  /// Map a directional/confirm key to its unified `HexButton` equivalent so
+  /// keyboard arrow and Enter keys are indistinguishable from physical hex
+  /// buttons. Returns `None` for keys that remain plain keyboard events.
+  pub fn to_hex_button(self) -> Option<HexButton> {
+    match self {
+      KeyCode::Up => Some(HexButton::Up),
+      KeyCode::Down => Some(HexButton::Down),
+      KeyCode::Left => Some(HexButton::Left),
+      KeyCode::Right => Some(HexButton::Right),
+      KeyCode::Enter => Some(HexButton::Fire),
+      _ => None,
+    }
+  }
+}
+
+/// Modifier keys held (or, for Caps Lock, toggled on) when a key event
+/// happened.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub struct KeyModifiers(pub u8);
+
+impl KeyModifiers {
+  pub const NONE: KeyModifiers = KeyModifiers(0);
+  pub const SHIFT: KeyModifiers = KeyModifiers(1 << 0);
+  pub const CTRL: KeyModifiers = KeyModifiers(1 << 1);
+  pub const ALT: KeyModifiers = KeyModifiers(1 << 2);
+  pub const CAPS_LOCK: KeyModifiers = KeyModifiers(1 << 3);
+
+  pub const fn contains(self, other: KeyModifiers) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  /// The modifiers after `code` was pressed or released. Shift, Ctrl and Alt
+  /// are held; Caps Lock toggles on each press.
+  pub const fn update(self, code: KeyCode, pressed: bool) -> KeyModifiers {
+    let held = match code {
+      KeyCode::Shift => KeyModifiers::SHIFT,
+      KeyCode::Ctrl => KeyModifiers::CTRL,
+      KeyCode::Alt => KeyModifiers::ALT,
+      KeyCode::CapsLock if pressed => return KeyModifiers(self.0 ^ KeyModifiers::CAPS_LOCK.0),
+      _ => return self,
+    };
+    if pressed {
+      KeyModifiers(self.0 | held.0)
+    } else {
+      KeyModifiers(self.0 & !held.0)
+    }
+  }
+}
+
+/// A key going down or up on a keyboard. Arrows and Enter are not sent as key
+/// events: like the badge buttons they arrive as [`HostIpcMessage::HexButton`].
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct KeyEvent {
+  /// Hexpansion port the keyboard is plugged into (0 on the desktop build).
+  pub port: u8,
+  pub code: KeyCode,
+  pub pressed: bool,
+  /// Modifiers in effect after this event.
+  pub modifiers: KeyModifiers,
+}
+
+// ================================ LEDs ================================
+
+/// Number of RGB LEDs on the badge's ring.
+pub const NUM_LEDS: usize = 12;
+
+/// An RGB colour for a single ring LED. Crosses the host ABI packed as
+/// `0x00RRGGBB` in a `u32`, or as `r, g, b` byte triples in a buffer.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct LedColour {
+  pub r: u8,
+  pub g: u8,
+  pub b: u8,
+}
+
+impl LedColour {
+  pub const OFF: LedColour = LedColour::new(0, 0, 0);
+
+  pub const fn new(r: u8, g: u8, b: u8) -> Self {
+    Self { r, g, b }
+  }
+
+  pub const fn to_packed(self) -> u32 {
+    (self.r as u32) << 16 | (self.g as u32) << 8 | self.b as u32
+  }
+
+  pub const fn from_packed(rgb: u32) -> Self {
+    Self::new((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+  }
+}
+
+/// Built-in LED ring effects a guest can select with `extern_set_led_effect`.
+/// The discriminants are the ABI values; effects that take no colour ignore
+/// the colour argument.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(u32)]
+pub enum LedEffect {
+  Off = 0,
+  Solid = 1,
+  Rainbow = 2,
+  Breathe = 3,
+  Chase = 4,
+  Sparkle = 5,
+  TheaterChase = 6,
+  Fire = 7,
+}
+
+impl LedEffect {
+  pub const fn from_u32(value: u32) -> Option<Self> {
+    match value {
+      0 => Some(Self::Off),
+      1 => Some(Self::Solid),
+      2 => Some(Self::Rainbow),
+      3 => Some(Self::Breathe),
+      4 => Some(Self::Chase),
+      5 => Some(Self::Sparkle),
+      6 => Some(Self::TheaterChase),
+      7 => Some(Self::Fire),
+      _ => None,
+    }
+  }
+}
+
+// ================================ Storage ================================
+
+/// Longest key accepted by the per-app storage imports, in bytes.
+pub const MAX_STORAGE_KEY_LEN: usize = 32;
+
+/// Default per-app storage quota, in bytes of stored values. Every host
+/// enforces it, so an app that fits in the emulator fits on the device.
+pub const DEFAULT_STORAGE_QUOTA: u32 = 16 * 1024;
+
+/// Maximum number of keys a single app may store.
+pub const MAX_STORAGE_KEYS: usize = 32;
+
+/// Failure codes returned (as negative `i32`s) by the `extern_storage_*`
+/// imports. Non-negative return values are successes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[repr(i32)]
+pub enum StorageError {
+  /// No value is stored under the key.
+  NotFound = -1,
+  /// The key is empty, too long, or contains characters other than ASCII
+  /// letters, digits, `-`, `_` and `.` (or starts with `.`).
+  InvalidKey = -2,
+  /// The write would take the app past its storage quota.
+  QuotaExceeded = -3,
+  /// The underlying filesystem failed.
+  Io = -4,
+  /// This host has no persistent storage for the running app.
+  Unavailable = -5,
+}
+
+impl StorageError {
+  pub const fn from_i32(value: i32) -> Option<Self> {
+    match value {
+      -1 => Some(Self::NotFound),
+      -2 => Some(Self::InvalidKey),
+      -3 => Some(Self::QuotaExceeded),
+      -4 => Some(Self::Io),
+      -5 => Some(Self::Unavailable),
+      _ => None,
+    }
+  }
+}
+
+/// Whether `key` is acceptable to the per-app storage imports. Keys are single
+/// path components, so a guest cannot address anything outside its own
+/// directory.
+pub fn is_valid_storage_key(key: &str) -> bool {
+  !key.is_empty()
+    && key.len() <= MAX_STORAGE_KEY_LEN
+    && !key.starts_with('.')
+    && key.bytes().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'_' | b'.'))
+}
+
 // ================================ HTTP types ================================
 
 #[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
@@ -149,12 +555,43 @@ impl HttpResponseMeta {
   }
 }
 
+// ================================ Sockets ================================
+//
+// A socket is named by the id of the message that opened it (`TcpConnect` or
+// `UdpBind`). Everything the host reports about the socket is sent with that
+// id, and later requests refer to it as `socket`.
+
+/// A UDP datagram, received from or sent to `host:port`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Datagram {
+  pub host: String,
+  pub port: u16,
+  pub data: Vec<u8>,
+}
+
 // ================================ WASM IPC ================================
 
 /// Messages sent from a WASM guest to the host over the wire.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub enum WasmIpcMessage {
   HttpRequest(HttpRequest),
+  /// Open a TCP stream. Answered with `SocketConnected` or `SocketError`.
+  TcpConnect { host: String, port: u16 },
+  /// Bind a UDP socket to a local port (0 for any). Answered with
+  /// `SocketConnected` or `SocketError`.
+  UdpBind { port: u16 },
+  /// Write bytes to an open TCP stream.
+  SocketSend { socket: u32, data: Vec<u8> },
+  /// Send a datagram from a bound UDP socket.
+  SocketSendTo { socket: u32, datagram: Datagram },
+  /// Close a TCP stream or UDP socket. Answered with `SocketClosed`.
+  SocketClose { socket: u32 },
+  /// Have the host draw `screen` with its own renderer, as it draws the
+  /// built-in apps. Switches the app to widget mode, in which its raw frames
+  /// are ignored, until `ShowFramebuffer`. Needs [`WIDGETS_ABI_VERSION`].
+  ShowScreen(Widget),
+  /// Leave widget mode: blank the screen and show the app's raw frames again.
+  ShowFramebuffer,
 }
 
 /// Messages sent from the host to a WASM guest over the wire.
@@ -165,4 +602,203 @@ pub enum HostIpcMessage {
   HttpResponseMeta(HttpResponseMeta),
   HttpResponseBody(Vec<u8>),
   HttpResponseComplete,
+  /// The socket is connected (TCP) or bound (UDP).
+  SocketConnected,
+  /// Bytes received on a TCP stream.
+  SocketData(Vec<u8>),
+  /// A datagram received on a UDP socket.
+  SocketDatagram(Datagram),
+  /// The socket was closed, by the peer or by `SocketClose`. No further
+  /// messages are sent for it.
+  SocketClosed,
+  /// The socket could not be opened, or failed. No further messages are sent
+  /// for it.
+  SocketError,
+  /// The request was refused because the host already has as many of its
+  /// kind in flight as it allows. The guest may retry once one finishes.
+  /// Only sent to guests built for [`BUSY_ABI_VERSION`] or later.
+  Busy,
+  /// A key on a keyboard went down or up. Only sent to guests built for
+  /// [`KEY_EVENTS_ABI_VERSION`] or later.
+  Key(KeyEvent),
 }
+
+// ================================ Wire format ================================
+
+/// Name of the optional guest export (`fn() -> u32`) declaring which
+/// [`WireFormat`] the guest speaks. Guests built before it existed do not
+/// export it and are spoken to in JSON.
+pub const WIRE_FORMAT_EXPORT: &str = "ipc_wire_format";
+
+/// Encoding of `WasmIpcMessage`/`HostIpcMessage` on the guest boundary.
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WireFormat {
+  /// `serde_json`. Kept so existing `.wsm` files keep working.
+  #[default]
+  Json = 0,
+  /// `postcard`: compact, and byte buffers stay bytes.
+  Postcard = 1,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WireError {
+  Encode,
+  Decode,
+  /// Support for this format was not compiled in.
+  Unsupported,
+}
+
+impl WireFormat {
+  pub const fn from_u32(value: u32) -> Option<Self> {
+    match value {
+      0 => Some(Self::Json),
+      1 => Some(Self::Postcard),
+      _ => None,
+    }
+  }
+
+  pub fn encode<T: Serialize>(self, value: &T) -> Result<Vec<u8>, WireError> {
+    match self {
+      #[cfg(feature = "json")]
+      Self::Json => serde_json::to_vec(value).map_err(|_| WireError::Encode),
+      #[cfg(feature = "postcard")]
+      Self::Postcard => postcard::to_allocvec(value).map_err(|_| WireError::Encode),
+      #[allow(unreachable_patterns)]
+      _ => Err(WireError::Unsupported),
+    }
+  }
+
+  pub fn decode<'a, T: Deserialize<'a>>(self, bytes: &'a [u8]) -> Result<T, WireError> {
+    match self {
+      #[cfg(feature = "json")]
+      Self::Json => serde_json::from_slice(bytes).map_err(|_| WireError::Decode),
+      #[cfg(feature = "postcard")]
+      Self::Postcard => postcard::from_bytes(bytes).map_err(|_| WireError::Decode),
+      #[allow(unreachable_patterns)]
+      _ => Err(WireError::Unsupported),
+    }
+  }
+}
+
+// ================================ Host ABI ================================
+
+/// Import module every host function is registered under.
+pub const HOST_IMPORT_MODULE: &str = "index";
+
+/// Version of the host import ABI. Bumped whenever an import or a message the
+/// host sends unprompted is added; a shipped import never changes signature or
+/// meaning, so a host runs every guest built for its version or earlier.
+pub const HOST_ABI_VERSION: u32 = 9;
+
+/// First ABI version whose guests can decode [`HostIpcMessage::Busy`]. Older
+/// guests would fail on the unknown variant, so hosts do not send it to them.
+pub const BUSY_ABI_VERSION: u32 = 4;
+
+/// First ABI version whose guests can decode [`HostIpcMessage::Key`]. Older
+/// guests would fail on the unknown variant, so hosts do not send it to them.
+pub const KEY_EVENTS_ABI_VERSION: u32 = 6;
+
+/// First ABI version whose hosts understand [`WasmIpcMessage::ShowScreen`]
+/// and [`WasmIpcMessage::ShowFramebuffer`]. Older hosts stop a guest that
+/// sends a message they can't decode.
+pub const WIDGETS_ABI_VERSION: u32 = 9;
+
+/// Name of the custom section in which a guest declares, as a little-endian
+/// `u32`, the host ABI version it was built for. The host reads it before
+/// instantiating the module, so an app built for newer firmware can be
+/// rejected with a readable message instead of a link error.
+pub const ABI_VERSION_SECTION: &str = "badge_abi_version";
+
+/// Every host import, with the ABI version that introduced it.
+pub const HOST_IMPORTS: &[(&str, u32)] = &[
+  ("extern_write_stdout", 1),
+  ("extern_set_gpio", 1),
+  ("extern_set_lcd_buffer", 1),
+  ("extern_register_timer", 1),
+  ("extern_check_timer", 1),
+  ("extern_cancel_timer", 1),
+  ("extern_get_millis", 1),
+  ("extern_write_wasm_ipc_message", 1),
+  ("extern_read_host_ipc_message", 1),
+  ("extern_set_led", 2),
+  ("extern_set_leds", 2),
+  ("extern_set_led_effect", 2),
+  ("extern_storage_read", 2),
+  ("extern_storage_write", 2),
+  ("extern_storage_delete", 2),
+  ("extern_get_abi_version", 2),
+  ("extern_get_capabilities", 2),
+  ("extern_idle", 3),
+  ("extern_try_write_wasm_ipc_message", 4),
+  ("extern_panic", 5),
+  ("extern_set_lcd_region", 7),
+  ("extern_get_micros", 8),
+  ("extern_get_unix_micros", 8),
+  ("extern_fill_random", 8),
+];
+
+/// The ABI version that introduced the host import `module::name`, or `None`
+/// if no host version provides it.
+pub fn import_abi_version(module: &str, name: &str) -> Option<u32> {
+  if module != HOST_IMPORT_MODULE {
+    return None;
+  }
+  HOST_IMPORTS
+    .iter()
+    .find(|(import, _)| *import == name)
+    .map(|(_, version)| *version)
+}
+
+/// Optional features a host backs, as reported by `extern_get_capabilities`.
+/// The imports for a feature always link, so guests use this to tell a real
+/// implementation from a stub (e.g. GPIO on the desktop build).
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Capabilities(pub u32);
+
+impl Capabilities {
+  pub const NONE: Capabilities = Capabilities(0);
+  /// `extern_set_gpio` drives real pins.
+  pub const GPIO: Capabilities = Capabilities(1 << 0);
+  /// The LED ring imports drive real (or emulated) LEDs.
+  pub const LEDS: Capabilities = Capabilities(1 << 1);
+  /// The `extern_storage_*` imports persist values across launches.
+  pub const STORAGE: Capabilities = Capabilities(1 << 2);
+  /// HTTP requests over IPC reach the network.
+  pub const HTTP: Capabilities = Capabilities(1 << 3);
+  /// The TCP and UDP socket messages are supported.
+  pub const SOCKETS: Capabilities = Capabilities(1 << 4);
+
+  /// Every capability with a short name, for listing an app's permissions.
+  pub const NAMED: [(Capabilities, &'static str); 5] = [
+    (Self::GPIO, "GPIO"),
+    (Self::LEDS, "LEDs"),
+    (Self::STORAGE, "Storage"),
+    (Self::HTTP, "HTTP"),
+    (Self::SOCKETS, "Sockets"),
+  ];
+
+  pub const fn bits(self) -> u32 {
+    self.0
+  }
+
+  pub const fn contains(self, other: Capabilities) -> bool {
+    self.0 & other.0 == other.0
+  }
+
+  pub const fn union(self, other: Capabilities) -> Capabilities {
+    Capabilities(self.0 | other.0)
+  }
+}
+
+// ================================ App metadata ================================
+
+mod metadata;
+
+pub use metadata::*;
+
+// ================================ Widgets ================================
+
+mod widgets;
+
+pub use widgets::*;
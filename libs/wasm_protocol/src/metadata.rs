@@ -0,0 +1,438 @@
+//! App metadata: the display name, version, author, description, icon, ABI
+//! version, permissions and resource limits a guest declares in the [`APP_METADATA_SECTION`]
+//! custom section, so a `.wsm` file can be described without running it.
+//!
+//! The section starts with a format byte ([`METADATA_FORMAT`]) followed by
+//! fields, each a tag byte, a little-endian `u16` length and that many bytes.
+//! Readers skip tags they don't know, so fields can be added without breaking
+//! older firmware. Guests build the section at compile time with
+//! [`AppInfo::encode`]; hosts read it back with [`AppMetadata::decode`].
+
+use crate::{Capabilities, HOST_ABI_VERSION};
+use alloc::{string::String, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+/// Name of the custom section holding a guest's [`AppMetadata`].
+pub const APP_METADATA_SECTION: &str = "badge_app_metadata";
+
+/// Version of the section layout, stored in its first byte.
+pub const METADATA_FORMAT: u8 = 1;
+
+/// Hosts ignore metadata sections larger than this.
+pub const MAX_METADATA_LEN: usize = 4096;
+
+/// Width and height of an app icon, in pixels.
+pub const ICON_SIZE: usize = 20;
+
+/// An app icon is RGB565, big-endian, row by row, like the SDK's `Canvas`.
+pub const ICON_BYTES: usize = ICON_SIZE * ICON_SIZE * 2;
+
+const TAG_NAME: u8 = 1;
+const TAG_VERSION: u8 = 2;
+const TAG_AUTHOR: u8 = 3;
+const TAG_DESCRIPTION: u8 = 4;
+const TAG_ABI_VERSION: u8 = 5;
+const TAG_PERMISSIONS: u8 = 6;
+const TAG_ICON: u8 = 7;
+const TAG_LIMITS: u8 = 8;
+
+/// Encoded size of [`AppLimits`].
+const LIMITS_BYTES: usize = 12;
+
+/// Tag and length in front of every field.
+const FIELD_HEADER: usize = 3;
+
+/// The metadata a guest declares, in a form that can be encoded in a `const`
+/// (see the SDK's `app_metadata!`).
+#[derive(Debug, Clone, Copy)]
+pub struct AppInfo<'a> {
+  pub name: &'a str,
+  pub version: &'a str,
+  pub author: &'a str,
+  pub description: &'a str,
+  pub icon: Option<&'a [u8; ICON_BYTES]>,
+  /// The host ABI version the app was built for.
+  pub abi_version: u32,
+  /// What the app wants to use, e.g. [`Capabilities::HTTP`].
+  pub permissions: Capabilities,
+  pub limits: AppLimits,
+}
+
+/// Resources an app needs that differ from the host's defaults, e.g. a game
+/// with large level data. A zero field keeps the host's default; hosts cap
+/// every request at their own maximum.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AppLimits {
+  /// Largest linear memory, in KiB.
+  pub memory_kb: u32,
+  /// Largest element count of any table.
+  pub table_elements: u32,
+  /// Fuel for each `tick`, roughly one unit per interpreted instruction.
+  pub fuel_per_tick: u32,
+}
+
+impl AppLimits {
+  pub const DEFAULT: AppLimits = AppLimits {
+    memory_kb: 0,
+    table_elements: 0,
+    fuel_per_tick: 0,
+  };
+
+  pub const fn is_default(&self) -> bool {
+    self.memory_kb == 0 && self.table_elements == 0 && self.fuel_per_tick == 0
+  }
+
+  const fn encode(&self) -> [u8; LIMITS_BYTES] {
+    let mut out = [0u8; LIMITS_BYTES];
+    let fields = [self.memory_kb, self.table_elements, self.fuel_per_tick];
+    let mut i = 0;
+    while i < LIMITS_BYTES {
+      out[i] = fields[i / 4].to_le_bytes()[i % 4];
+      i += 1;
+    }
+    out
+  }
+
+  fn decode(data: &[u8]) -> Result<Self, MetadataError> {
+    let data: &[u8; LIMITS_BYTES] = data.try_into().map_err(|_| MetadataError::Malformed)?;
+    let field = |i: usize| u32::from_le_bytes([data[i * 4], data[i * 4 + 1], data[i * 4 + 2], data[i * 4 + 3]]);
+    Ok(Self {
+      memory_kb: field(0),
+      table_elements: field(1),
+      fuel_per_tick: field(2),
+    })
+  }
+}
+
+impl AppInfo<'static> {
+  pub const DEFAULT: AppInfo<'static> = AppInfo {
+    name: "",
+    version: "",
+    author: "",
+    description: "",
+    icon: None,
+    abi_version: HOST_ABI_VERSION,
+    permissions: Capabilities::NONE,
+    limits: AppLimits::DEFAULT,
+  };
+}
+
+impl AppInfo<'_> {
+  /// Size of the encoded section.
+  pub const fn encoded_len(&self) -> usize {
+    let mut len = 1 + 6 * FIELD_HEADER + 8;
+    len += self.name.len() + self.version.len() + self.author.len() + self.description.len();
+    if self.icon.is_some() {
+      len += FIELD_HEADER + ICON_BYTES;
+    }
+    if !self.limits.is_default() {
+      len += FIELD_HEADER + LIMITS_BYTES;
+    }
+    len
+  }
+
+  /// The section contents. `N` must be [`encoded_len`](Self::encoded_len).
+  pub const fn encode<const N: usize>(&self) -> [u8; N] {
+    assert!(N == self.encoded_len(), "app metadata length mismatch");
+    let mut out = [0u8; N];
+    out[0] = METADATA_FORMAT;
+    let mut pos = 1;
+    pos = put_field(&mut out, pos, TAG_NAME, self.name.as_bytes());
+    pos = put_field(&mut out, pos, TAG_VERSION, self.version.as_bytes());
+    pos = put_field(&mut out, pos, TAG_AUTHOR, self.author.as_bytes());
+    pos = put_field(&mut out, pos, TAG_DESCRIPTION, self.description.as_bytes());
+    pos = put_field(&mut out, pos, TAG_ABI_VERSION, &self.abi_version.to_le_bytes());
+    pos = put_field(&mut out, pos, TAG_PERMISSIONS, &self.permissions.bits().to_le_bytes());
+    if let Some(icon) = self.icon {
+      pos = put_field(&mut out, pos, TAG_ICON, icon);
+    }
+    if !self.limits.is_default() {
+      put_field(&mut out, pos, TAG_LIMITS, &self.limits.encode());
+    }
+    out
+  }
+}
+
+const fn put_field(out: &mut [u8], pos: usize, tag: u8, data: &[u8]) -> usize {
+  assert!(data.len() <= u16::MAX as usize, "app metadata field too long");
+  let len = (data.len() as u16).to_le_bytes();
+  out[pos] = tag;
+  out[pos + 1] = len[0];
+  out[pos + 2] = len[1];
+  let mut i = 0;
+  while i < data.len() {
+    out[pos + FIELD_HEADER + i] = data[i];
+    i += 1;
+  }
+  pos + FIELD_HEADER + data.len()
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataError {
+  /// Written by a newer SDK in a layout this host doesn't know.
+  UnsupportedFormat(u8),
+  /// Truncated, or a field has the wrong size or isn't UTF-8.
+  Malformed,
+}
+
+/// A guest's metadata as read by the host. Also how it appears in the web
+/// API's file listing and the App Store manifest.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AppMetadata {
+  pub name: String,
+  pub version: String,
+  pub author: String,
+  pub description: String,
+  /// [`ICON_BYTES`] of RGB565; hex in JSON.
+  #[serde(default, skip_serializing_if = "Option::is_none", with = "hex_icon")]
+  pub icon: Option<Vec<u8>>,
+  pub abi_version: u32,
+  /// [`Capabilities`] bits the app asks for.
+  pub permissions: u32,
+  #[serde(default, skip_serializing_if = "AppLimits::is_default")]
+  pub limits: AppLimits,
+}
+
+impl AppMetadata {
+  /// Parse the contents of an [`APP_METADATA_SECTION`].
+  pub fn decode(bytes: &[u8]) -> Result<Self, MetadataError> {
+    let (&format, mut fields) = bytes.split_first().ok_or(MetadataError::Malformed)?;
+    if format != METADATA_FORMAT {
+      return Err(MetadataError::UnsupportedFormat(format));
+    }
+
+    // Apps that leave a field out of `app_metadata!` get its default.
+    let mut metadata = AppMetadata {
+      name: String::new(),
+      version: String::new(),
+      author: String::new(),
+      description: String::new(),
+      icon: None,
+      abi_version: 1,
+      permissions: 0,
+      limits: AppLimits::DEFAULT,
+    };
+    while !fields.is_empty() {
+      if fields.len() < FIELD_HEADER {
+        return Err(MetadataError::Malformed);
+      }
+      let tag = fields[0];
+      let len = u16::from_le_bytes([fields[1], fields[2]]) as usize;
+      let data = fields.get(FIELD_HEADER..FIELD_HEADER + len).ok_or(MetadataError::Malformed)?;
+      match tag {
+        TAG_NAME => metadata.name = text(data)?,
+        TAG_VERSION => metadata.version = text(data)?,
+        TAG_AUTHOR => metadata.author = text(data)?,
+        TAG_DESCRIPTION => metadata.description = text(data)?,
+        TAG_ABI_VERSION => metadata.abi_version = u32::from_le_bytes(data.try_into().map_err(|_| MetadataError::Malformed)?),
+        TAG_PERMISSIONS => metadata.permissions = u32::from_le_bytes(data.try_into().map_err(|_| MetadataError::Malformed)?),
+        TAG_ICON if data.len() == ICON_BYTES => metadata.icon = Some(data.into()),
+        TAG_ICON => return Err(MetadataError::Malformed),
+        TAG_LIMITS => metadata.limits = AppLimits::decode(data)?,
+        _ => {}
+      }
+      fields = &fields[FIELD_HEADER + len..];
+    }
+    Ok(metadata)
+  }
+
+  pub fn permissions(&self) -> Capabilities {
+    Capabilities(self.permissions)
+  }
+}
+
+fn text(data: &[u8]) -> Result<String, MetadataError> {
+  core::str::from_utf8(data).map(String::from).map_err(|_| MetadataError::Malformed)
+}
+
+mod hex_icon {
+  use alloc::{string::String, vec::Vec};
+  use serde::{Deserialize, Deserializer, Serializer, de::Error};
+
+  const DIGITS: &[u8; 16] = b"0123456789abcdef";
+
+  pub fn serialize<S: Serializer>(icon: &Option<Vec<u8>>, serializer: S) -> Result<S::Ok, S::Error> {
+    match icon {
+      Some(icon) => {
+        let hex: String = icon
+          .iter()
+          .flat_map(|byte| [DIGITS[(byte >> 4) as usize] as char, DIGITS[(byte & 0xF) as usize] as char])
+          .collect();
+        serializer.serialize_some(&hex)
+      }
+      None => serializer.serialize_none(),
+    }
+  }
+
+  pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<u8>>, D::Error> {
+    let Some(hex) = Option::<String>::deserialize(deserializer)? else {
+      return Ok(None);
+    };
+    let hex = hex.as_bytes();
+    if hex.len() % 2 != 0 {
+      return Err(D::Error::custom("icon is not hex"));
+    }
+    let nibble = |digit: u8| (digit as char).to_digit(16).ok_or_else(|| D::Error::custom("icon is not hex"));
+    (0..hex.len())
+      .step_by(2)
+      .map(|i| Ok(((nibble(hex[i])? << 4) | nibble(hex[i + 1])?) as u8))
+      .collect::<Result<_, _>>()
+      .map(Some)
+  }
+}
+
+// ================================ Module sections ================================
+
+/// Section id of custom sections.
+pub const CUSTOM_SECTION_ID: u8 = 0;
+
+/// The magic number and version every module starts with.
+pub const MODULE_HEADER: [u8; 8] = *b"\0asm\x01\0\0\0";
+
+/// The id and size of one section of a module, parsed from its first bytes
+/// so a host can skip from section to section without loading the module.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SectionHeader {
+  pub id: u8,
+  /// Bytes taken by the id and size.
+  pub header_len: usize,
+  /// Bytes of payload after the header.
+  pub size: usize,
+}
+
+impl SectionHeader {
+  /// `None` if `bytes` doesn't start with a whole header.
+  pub fn parse(bytes: &[u8]) -> Option<Self> {
+    let (&id, rest) = bytes.split_first()?;
+    let (size, size_len) = read_leb_u32(rest)?;
+    Some(Self {
+      id,
+      header_len: 1 + size_len,
+      size: size as usize,
+    })
+  }
+}
+
+/// The name at the start of a custom section's payload, with the number of
+/// bytes it takes.
+pub fn custom_section_name(payload: &[u8]) -> Option<(&str, usize)> {
+  let (len, len_len) = read_leb_u32(payload)?;
+  let name = payload.get(len_len..len_len + len as usize)?;
+  Some((core::str::from_utf8(name).ok()?, len_len + len as usize))
+}
+
+/// The contents of the custom section `name` in a whole module.
+pub fn find_custom_section<'a>(module: &'a [u8], name: &str) -> Option<&'a [u8]> {
+  let mut rest = module.strip_prefix(&MODULE_HEADER)?;
+  while !rest.is_empty() {
+    let header = SectionHeader::parse(rest)?;
+    let payload = rest.get(header.header_len..header.header_len.checked_add(header.size)?)?;
+    if header.id == CUSTOM_SECTION_ID
+      && let Some((section_name, name_len)) = custom_section_name(payload)
+      && section_name == name
+    {
+      return Some(&payload[name_len..]);
+    }
+    rest = &rest[header.header_len + header.size..];
+  }
+  None
+}
+
+/// An unsigned LEB128 number, with the number of bytes it takes.
+fn read_leb_u32(bytes: &[u8]) -> Option<(u32, usize)> {
+  let mut value = 0u32;
+  for (i, &byte) in bytes.iter().take(5).enumerate() {
+    value |= ((byte & 0x7F) as u32) << (7 * i);
+    if byte & 0x80 == 0 {
+      return Some((value, i + 1));
+    }
+  }
+  None
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+  use alloc::vec;
+
+  const INFO: AppInfo<'static> = AppInfo {
+    name: "Snake",
+    version: "1.2.0",
+    author: "Rustagon",
+    description: "Eat the apples",
+    permissions: Capabilities::STORAGE,
+    ..AppInfo::DEFAULT
+  };
+
+  #[test]
+  fn metadata_round_trips() {
+    const ENCODED: [u8; INFO.encoded_len()] = INFO.encode();
+    let metadata = AppMetadata::decode(&ENCODED).unwrap();
+    assert_eq!(metadata.name, "Snake");
+    assert_eq!(metadata.description, "Eat the apples");
+    assert_eq!(metadata.abi_version, HOST_ABI_VERSION);
+    assert!(metadata.permissions().contains(Capabilities::STORAGE));
+    assert_eq!(metadata.icon, None);
+
+    let icon = [0xA5; ICON_BYTES];
+    let with_icon = AppInfo { icon: Some(&icon), ..INFO };
+    let encoded: [u8; INFO.encoded_len() + FIELD_HEADER + ICON_BYTES] = with_icon.encode();
+    assert_eq!(AppMetadata::decode(&encoded).unwrap().icon.as_deref(), Some(&icon[..]));
+
+    let limits = AppLimits {
+      memory_kb: 3072,
+      fuel_per_tick: 50_000_000,
+      ..AppLimits::DEFAULT
+    };
+    let with_limits = AppInfo { limits, ..INFO };
+    let encoded: [u8; INFO.encoded_len() + FIELD_HEADER + LIMITS_BYTES] = with_limits.encode();
+    assert_eq!(AppMetadata::decode(&encoded).unwrap().limits, limits);
+    assert_eq!(metadata.limits, AppLimits::DEFAULT);
+  }
+
+  #[test]
+  fn unknown_fields_are_skipped_and_bad_ones_rejected() {
+    let mut encoded = vec![METADATA_FORMAT, 99, 2, 0, 1, 2, TAG_NAME, 1, 0, b'x'];
+    assert_eq!(AppMetadata::decode(&encoded).unwrap().name, "x");
+
+    encoded.truncate(8);
+    assert_eq!(AppMetadata::decode(&encoded), Err(MetadataError::Malformed));
+    assert_eq!(AppMetadata::decode(&[2]), Err(MetadataError::UnsupportedFormat(2)));
+    assert_eq!(
+      AppMetadata::decode(&[METADATA_FORMAT, TAG_ICON, 1, 0, 0]),
+      Err(MetadataError::Malformed)
+    );
+  }
+
+  #[test]
+  fn custom_sections_are_found_by_name() {
+    let mut module = MODULE_HEADER.to_vec();
+    // A type section, then custom sections "a" and "badge_app_metadata".
+    module.extend([1, 1, 0]);
+    module.extend([CUSTOM_SECTION_ID, 3, 1, b'a', 7]);
+    module.extend([CUSTOM_SECTION_ID, 20, 18]);
+    module.extend(APP_METADATA_SECTION.as_bytes());
+    module.push(METADATA_FORMAT);
+
+    assert_eq!(find_custom_section(&module, "a"), Some(&[7][..]));
+    assert_eq!(find_custom_section(&module, APP_METADATA_SECTION), Some(&[METADATA_FORMAT][..]));
+    assert_eq!(find_custom_section(&module, "name"), None);
+    assert_eq!(find_custom_section(&module[..module.len() - 1], APP_METADATA_SECTION), None);
+
+    let mut huge = MODULE_HEADER.to_vec();
+    huge.extend([CUSTOM_SECTION_ID, 0xFF, 0xFF, 0xFF, 0xFF, 0x0F]);
+    assert_eq!(find_custom_section(&huge, "a"), None);
+  }
+
+  #[cfg(feature = "json")]
+  #[test]
+  fn icons_are_hex_in_json() {
+    let metadata = AppMetadata {
+      icon: Some(vec![0x0F, 0xA0]),
+      ..AppMetadata::decode(&[METADATA_FORMAT]).unwrap()
+    };
+    let json = serde_json::to_string(&metadata).unwrap();
+    assert!(json.contains(r#""icon":"0fa0""#), "{json}");
+    assert_eq!(serde_json::from_str::<AppMetadata>(&json).unwrap(), metadata);
+  }
+}
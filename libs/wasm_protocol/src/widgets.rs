@@ -0,0 +1,72 @@
+//! Screens a guest can have the host draw with
+//! [`WasmIpcMessage::ShowScreen`](crate::WasmIpcMessage::ShowScreen).
+//!
+//! These mirror the host renderer's `LcdScreen` but are part of the guest
+//! ABI, so they only change with [`HOST_ABI_VERSION`](crate::HOST_ABI_VERSION)
+//! however the renderer's own screens evolve. Hosts convert them to
+//! `LcdScreen` before drawing. There is no `Blank`: the display is only
+//! handed to the guest's raw frames with `ShowFramebuffer`.
+
+use alloc::{string::String, vec::Vec};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Widget {
+  Splash,
+  Headline(Icon40, String),
+  Progress(String),
+  /// Step `.0` of `.1`.
+  BoundedProgress(u32, u32),
+  Menu {
+    menu: Vec<MenuLine>,
+    selected: u32,
+    animation: MenuAnimation,
+    /// Short status text shown above the menu; it doesn't scroll or slide
+    /// with the items.
+    header: Option<String>,
+  },
+  /// Up to 8 lines in a centred bordered frame. The line with a cursor is
+  /// the selected one.
+  TextBuffer {
+    lines: Vec<TextBufferLine>,
+  },
+  /// Slides in over the current screen and away again.
+  Notification(Icon40, String),
+}
+
+/// Slide-in animation for a [`Widget::Menu`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum MenuAnimation {
+  FromLeft,
+  #[default]
+  FromRight,
+  None,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MenuLine(pub Icon20, pub String);
+
+/// One line of a [`Widget::TextBuffer`].
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TextBufferLine {
+  pub text: String,
+  /// Byte index of the blinking cursor within `text`.
+  pub cursor: Option<u32>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Icon20 {
+  Home,
+  Config,
+  Wifi,
+  File,
+  Info,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Icon40 {
+  Info,
+  Warn,
+  Error,
+  Wifi,
+}
@@ -0,0 +1,38 @@
+//! Converts `assets/sprites/*.png` into sprite sheets in `$OUT_DIR/sprites`,
+//! for `include_sprite!`. A size before the extension cuts the image into
+//! frames: `ship.16x16.png` becomes the sheet `ship` of 16x16 frames.
+
+use sprite_tool::{Options, convert, parse_size};
+use std::env;
+use std::fs;
+use std::path::Path;
+
+const SPRITE_DIR: &str = "assets/sprites";
+
+fn main() {
+  println!("cargo:rerun-if-changed={SPRITE_DIR}");
+
+  let out_dir = Path::new(&env::var("OUT_DIR").unwrap()).join("sprites");
+  fs::create_dir_all(&out_dir).unwrap_or_else(|err| panic!("failed to create '{}': {err}", out_dir.display()));
+
+  for entry in fs::read_dir(SPRITE_DIR).unwrap_or_else(|err| panic!("failed to read dir '{SPRITE_DIR}': {err}")) {
+    let path = entry.expect("failed to read dir entry").path();
+    let file_name = path.file_name().and_then(|name| name.to_str()).unwrap_or_default();
+    let Some(stem) = file_name.strip_suffix(".png") else {
+      continue;
+    };
+    let (name, frame) = match stem.rsplit_once('.') {
+      Some((name, size)) => (name, Some(parse_size(size))),
+      None => (stem, None),
+    };
+    let frame = frame.transpose().unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+
+    let png = fs::read(&path).unwrap_or_else(|err| panic!("failed to read '{}': {err}", path.display()));
+    let options = Options {
+      frame,
+      ..Options::default()
+    };
+    let sheet = convert(&png, &options).unwrap_or_else(|err| panic!("{}: {err}", path.display()));
+    fs::write(out_dir.join(format!("{name}.spr")), sheet).unwrap_or_else(|err| panic!("failed to write sprite '{name}': {err}"));
+  }
+}
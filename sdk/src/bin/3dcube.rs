@@ -15,7 +15,6 @@ use crate::lib::{
   fmt::{print_str, print_u32},
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::{get_millis, print_line},
-  protocol::extern_set_lcd_buffer,
   tasks::{spawn, yield_now},
   trig::{fast_cos, fast_sin, fast_sqrt},
 };
@@ -189,7 +188,7 @@ fn wasm_main() {
         canvas.draw_line(proj[a], proj[b], Rgb565::WHITE);
       }
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       yield_now().await;
     }
@@ -9,7 +9,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -19,8 +18,9 @@ use crate::lib::{
   fmt,
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::get_millis,
-  protocol::{HexButton, HostIpcMessage, extern_set_lcd_buffer},
-  tasks::{HOST_IPC_CHANNEL, spawn, yield_now},
+  input::Buttons,
+  protocol::HexButton,
+  tasks::{spawn, yield_now},
   trig::{fast_cos, fast_sin, fast_sqrt},
 };
 use alloc::boxed::Box;
@@ -139,36 +139,37 @@ fn wasm_main() {
     let mut manual_light_angle_v = 0.0f32;
 
     canvas.clear(Rgb565::BLACK);
-    unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+    canvas.present();
 
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "subscriber");
+    let mut buttons = Buttons::new().with_repeat(300, 80);
 
     'running: loop {
       // Handle events
-      if let Some((_, host_ipc_msg)) = subscriber.try_next_message_pure() {
-        match host_ipc_msg {
-          HostIpcMessage::HexButton(hex_button) => match hex_button {
-            HexButton::Up => {
-              manual_light_angle_v += 0.1;
-              println_light(&manual_light_angle_v);
-            }
-            HexButton::Right => {
-              manual_light_angle_h += 0.2;
-            }
-            HexButton::Fire => {
-              auto_rotate = !auto_rotate;
-            }
-            HexButton::Down => {
-              manual_light_angle_v -= 0.1;
-              println_light(&manual_light_angle_v);
-            }
-            HexButton::Left => {
-              manual_light_angle_h -= 0.2;
-            }
-            _ => break 'running,
-          },
-          _ => {}
-        }
+      buttons.update();
+      if buttons.just_pressed_or_repeated(HexButton::Up) {
+        manual_light_angle_v += 0.1;
+        println_light(&manual_light_angle_v);
+      }
+      if buttons.just_pressed_or_repeated(HexButton::Right) {
+        manual_light_angle_h += 0.2;
+      }
+      if buttons.just_pressed(HexButton::Fire) {
+        auto_rotate = !auto_rotate;
+      }
+      if buttons.just_pressed_or_repeated(HexButton::Down) {
+        manual_light_angle_v -= 0.1;
+        println_light(&manual_light_angle_v);
+      }
+      if buttons.just_pressed_or_repeated(HexButton::Left) {
+        manual_light_angle_h -= 0.2;
+      }
+      // Any other button quits.
+      let steering = [HexButton::Up, HexButton::Right, HexButton::Fire, HexButton::Down, HexButton::Left];
+      if HexButton::ALL
+        .iter()
+        .any(|&button| buttons.just_pressed(button) && !steering.contains(&button))
+      {
+        break 'running;
       }
 
       // Light direction
@@ -269,7 +270,7 @@ fn wasm_main() {
       let py = icy - (lz / llen * ir as f32) as i32;
       canvas.draw_line(Point::new(icx, icy), Point::new(px, py), Rgb565::YELLOW);
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       yield_now().await;
     }
@@ -13,7 +13,6 @@ use crate::lib::{
   fmt::{print_str, print_u32},
   gfx::{Canvas, Point, Rect, Rgb565},
   helper::{get_millis, print_line},
-  protocol::extern_set_lcd_buffer,
   tasks::{HOST_IPC_CHANNEL, spawn, yield_now},
 };
 use alloc::{boxed::Box, format, string::ToString};
@@ -69,7 +68,7 @@ fn wasm_main() {
       canvas.draw_rect(Rect::new(79, 15 + i, 34, 34), Rgb565::BLUE);
       canvas.draw_text(&str, 6, 5 + i, Rgb565::WHITE, 2);
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       yield_now().await;
     }
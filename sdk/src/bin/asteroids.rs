@@ -12,7 +12,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -22,8 +21,10 @@ use crate::lib::{
   fmt::{append_str, append_u32},
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::get_millis,
-  protocol::{HexButton, HostIpcMessage, extern_set_lcd_buffer},
-  tasks::{HOST_IPC_CHANNEL, spawn, yield_now},
+  input::Buttons,
+  protocol::HexButton,
+  random::Rng,
+  tasks::{spawn, yield_now},
   trig::{fast_cos, fast_sin, fast_sqrt},
 };
 use alloc::boxed::Box;
@@ -61,23 +62,6 @@ const S_MENU: u8 = 0;
 const S_PLAY: u8 = 1;
 const S_GAMEOVER: u8 = 2;
 
-// ── RNG (simple LCG, same pattern as the Tetris demo) ──────────
-struct Rng {
-  state: u32,
-}
-impl Rng {
-  fn next(&mut self) -> u32 {
-    self.state = self.state.wrapping_mul(1103515245).wrapping_add(12345);
-    self.state
-  }
-  fn range(&mut self, n: usize) -> usize {
-    self.next() as usize % n
-  }
-  fn float(&mut self) -> f32 {
-    (self.next() >> 8) as f32 / ((1u32 << 24) as f32 - 1.0)
-  }
-}
-
 // ── Helpers ─────────────────────────────────────────────────────
 fn wrap(v: f32, max: f32) -> f32 {
   let mut r = v % max;
@@ -316,13 +300,11 @@ struct GameState {
 
 impl GameState {
   fn new() -> Self {
-    let seed = get_millis();
-    let mut rng = Rng { state: seed };
+    let mut rng = Rng::from_entropy();
 
-    // Deterministic star field
     let mut stars = [(0i32, 0i32); NUM_STARS];
     for i in 0..NUM_STARS {
-      stars[i] = (rng.range(SCREEN_WIDTH) as i32, rng.range(SCREEN_HEIGHT) as i32);
+      stars[i] = (rng.range(0, SCREEN_WIDTH as i32), rng.range(0, SCREEN_HEIGHT as i32));
     }
 
     let mut gs = GameState {
@@ -344,10 +326,10 @@ impl GameState {
     self.asteroids.clear();
     let base_speed = 20.0 + 5.0 * self.level as f32;
     for _ in 0..count {
-      let x = self.rng.float() * SCREEN_WIDTH as f32;
-      let y = self.rng.float() * SCREEN_HEIGHT as f32;
-      let angle = self.rng.float() * TWO_PI;
-      let spd = base_speed + self.rng.float() * 15.0;
+      let x = self.rng.next_f32() * SCREEN_WIDTH as f32;
+      let y = self.rng.next_f32() * SCREEN_HEIGHT as f32;
+      let angle = self.rng.next_f32() * TWO_PI;
+      let spd = base_speed + self.rng.next_f32() * 15.0;
       self.asteroids.push(Asteroid::new(
         &mut self.rng,
         x,
@@ -407,7 +389,7 @@ fn wasm_main() {
     let mut buf = Box::new([0x00u8; SCREEN_WIDTH * SCREEN_HEIGHT * 2]);
     let mut canvas = Canvas::new(&mut buf[..], SCREEN_WIDTH, SCREEN_HEIGHT);
     let mut state = GameState::new();
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "asteroids: subscriber");
+    let mut buttons = Buttons::new();
     let mut last_frame = get_millis();
 
     loop {
@@ -419,35 +401,18 @@ fn wasm_main() {
       let dt = delta as f32 * 0.001;
 
       // ── Input ──────────────────────────────────────────────
-      loop {
-        if let Some((_, msg)) = subscriber.try_next_message_pure() {
-          if let HostIpcMessage::HexButton(btn) = msg {
-            if state.state != S_PLAY {
-              // Any button starts the game from menu / game over
-              state.start_game();
-            } else {
-              match btn {
-                HexButton::Left => state.ship.rot = -1,
-                HexButton::LeftReleased => {
-                  if state.ship.rot == -1 {
-                    state.ship.rot = 0;
-                  }
-                }
-                HexButton::Right => state.ship.rot = 1,
-                HexButton::RightReleased => {
-                  if state.ship.rot == 1 {
-                    state.ship.rot = 0;
-                  }
-                }
-                HexButton::Up => state.ship.thrusting = true,
-                HexButton::UpReleased => state.ship.thrusting = false,
-                HexButton::Fire => state.ship.try_fire(&mut state.bullets),
-                _ => {}
-              }
-            }
-          }
-        } else {
-          break;
+      buttons.update();
+      if state.state != S_PLAY {
+        // Any button starts the game from menu / game over
+        if buttons.any_just_pressed() {
+          state.start_game();
+        }
+      } else {
+        // Holding both rotate buttons cancels out.
+        state.ship.rot = buttons.is_down(HexButton::Right) as i32 - buttons.is_down(HexButton::Left) as i32;
+        state.ship.thrusting = buttons.is_down(HexButton::Up);
+        if buttons.just_pressed(HexButton::Fire) {
+          state.ship.try_fire(&mut state.bullets);
         }
       }
 
@@ -499,9 +464,9 @@ fn wasm_main() {
             };
             // Split into two smaller asteroids
             if ast.size < 2 {
-              let sp = 25.0 + state.rng.float() * 20.0;
+              let sp = 25.0 + state.rng.next_f32() * 20.0;
               for _ in 0..2 {
-                let a = state.rng.float() * TWO_PI;
+                let a = state.rng.next_f32() * TWO_PI;
                 state.asteroids.push(Asteroid::new(
                   &mut state.rng,
                   ast.x,
@@ -579,9 +544,7 @@ fn wasm_main() {
         canvas.draw_text("PRESS ANY BUTTON", 36, 160, Rgb565::YELLOW, 1);
       }
 
-      unsafe {
-        extern_set_lcd_buffer(canvas.as_ptr());
-      }
+      canvas.present();
 
       yield_now().await;
     }
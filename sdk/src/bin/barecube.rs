@@ -11,7 +11,7 @@ use alloc::boxed::Box;
 use core::f32::consts::PI;
 use lib::{
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
-  protocol::{extern_get_millis, extern_set_lcd_buffer},
+  protocol::extern_get_millis,
   trig::{fast_cos, fast_sin},
 };
 
@@ -179,7 +179,7 @@ fn wasm_main() {
     }
 
     // Update the display
-    unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+    canvas.present();
   }
 }
 
@@ -9,8 +9,9 @@ use crate::lib::{
   fmt::print_str,
   gfx::{Canvas, Rgb565},
   helper::print_line,
-  protocol::{HexButton, HostIpcMessage, extern_set_lcd_buffer},
-  tasks::{get_next_host_message, spawn},
+  input::{ButtonEvent, Buttons},
+  protocol::HexButton,
+  tasks::spawn,
 };
 use alloc::boxed::Box;
 
@@ -56,9 +57,11 @@ fn wasm_main() {
 
     print_line("Buffer created\n");
 
+    let mut buttons = Buttons::new();
+
     loop {
-      match get_next_host_message().await.1 {
-        HostIpcMessage::HexButton(hex) => {
+      match buttons.next_event().await {
+        ButtonEvent::Pressed(hex) => {
           print_str("HEX BUTTON: ");
           print_line(hex_button_name(hex));
           print_line("\n");
@@ -80,7 +83,7 @@ fn wasm_main() {
 
           canvas.draw_text(text, 20, 110, Rgb565::WHITE, 2);
 
-          unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+          canvas.present();
         }
         _ => {}
       }
@@ -10,7 +10,6 @@ extern crate alloc;
 use crate::lib::{
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::get_millis,
-  protocol::extern_set_lcd_buffer,
   tasks::{spawn, yield_now},
   trig::{fast_cos, fast_sin},
 };
@@ -182,7 +181,7 @@ fn wasm_main() {
       }
 
       // Update the display
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       // Yield to allow other tasks to run
       yield_now().await;
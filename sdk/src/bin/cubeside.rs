@@ -10,7 +10,6 @@ extern crate alloc;
 use crate::lib::{
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::get_millis,
-  protocol::extern_set_lcd_buffer,
   tasks::{spawn, yield_now},
   trig::{fast_cos, fast_sin, fast_sqrt},
 };
@@ -225,7 +224,7 @@ fn wasm_main() {
         canvas.draw_line(projected[*start_idx], projected[*end_idx], Rgb565::WHITE);
       }
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
       yield_now().await;
     }
   })());
@@ -10,11 +10,19 @@ extern crate alloc;
 use crate::lib::{
   gfx::{Canvas, Rgb565},
   http::make_http_request,
-  protocol::{HttpRequest, extern_set_lcd_buffer},
+  protocol::{Capabilities, HttpRequest},
   sleep::sleep,
   tasks::spawn,
 };
-use alloc::{boxed::Box, string::ToString};
+use alloc::{boxed::Box, format, string::ToString};
+
+lib::app_metadata! {
+  name: "Fetch",
+  version: "1.0.0",
+  author: "Rustagon",
+  description: "Shows a web page's text",
+  permissions: Capabilities::HTTP,
+}
 
 #[unsafe(no_mangle)]
 fn tick(host_msg_id: u32, host_msg_size: u32) -> bool {
@@ -29,9 +37,13 @@ fn wasm_main() {
 
     let resp = make_http_request(HttpRequest::new("http://firmware.rustagon.chrisdell.info".to_string())).await;
 
-    canvas.draw_text(&resp.body, 0, 0, Rgb565::WHITE, 1);
+    match resp.as_ref().map(|resp| resp.text()) {
+      Ok(Ok(text)) => canvas.draw_text(text, 0, 0, Rgb565::WHITE, 1),
+      Ok(Err(_)) => canvas.draw_text("Response is not text", 0, 0, Rgb565::RED, 1),
+      Err(err) => canvas.draw_text(&format!("Request failed: {err:?}"), 0, 0, Rgb565::RED, 1),
+    }
 
-    unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+    canvas.present();
 
     sleep(2_000).await;
   })());
@@ -10,7 +10,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -20,12 +19,26 @@ use crate::lib::{
   fmt::{append_str, append_u32},
   gfx::{text_width, Canvas, Point, Rect, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::get_millis,
-  protocol::{extern_set_lcd_buffer, HexButton, HostIpcMessage},
-  tasks::{spawn, yield_now, HOST_IPC_CHANNEL},
+  input::Buttons,
+  protocol::{Capabilities, HexButton},
+  random::Rng,
+  storage,
+  tasks::{spawn, yield_now},
   trig::{fast_cos, fast_sin},
 };
 use alloc::{boxed::Box, vec::Vec};
 
+lib::app_metadata! {
+  name: "Flappy",
+  version: "1.0.0",
+  author: "Rustagon",
+  description: "Flap between the pipes",
+  permissions: Capabilities::STORAGE,
+}
+
+// Persisted in the app's storage sandbox so the best score survives reboots.
+const BEST_KEY: &str = "best";
+
 // ── Geometry ────────────────────────────────────────────────────────
 const SW: i32 = SCREEN_WIDTH as i32;
 const SH: i32 = SCREEN_HEIGHT as i32;
@@ -68,30 +81,6 @@ const S_MENU: u8 = 0;
 const S_PLAY: u8 = 1;
 const S_GAMEOVER: u8 = 2;
 
-// ── RNG (xorshift32, same as the shooter demo) ─────────────────────
-struct Rng {
-  state: u32,
-}
-
-impl Rng {
-  fn new(seed: u32) -> Self {
-    Rng { state: seed | 1 }
-  }
-
-  fn next(&mut self) -> u32 {
-    let mut x = self.state;
-    x ^= x << 13;
-    x ^= x >> 17;
-    x ^= x << 5;
-    self.state = x;
-    x
-  }
-
-  fn range(&mut self, lo: i32, hi: i32) -> i32 {
-    lo + (self.next() as i32 % (hi - lo + 1))
-  }
-}
-
 // ── Entities ────────────────────────────────────────────────────────
 struct Pipe {
   x: f32,     // left edge
@@ -127,16 +116,15 @@ struct Game {
 
 impl Game {
   fn new() -> Self {
-    let seed = get_millis();
     Game {
       bird: Bird {
         y: SH as f32 / 2.0,
         vy: 0.0,
       },
       pipes: Vec::with_capacity(6),
-      rng: Rng::new(seed),
+      rng: Rng::from_entropy(),
       score: 0,
-      best: 0,
+      best: storage::read_u32(BEST_KEY).unwrap_or(0),
       state: S_MENU,
       pipe_timer: FIRST_PIPE_DELAY,
     }
@@ -153,12 +141,13 @@ impl Game {
   fn game_over(&mut self) {
     if self.score > self.best {
       self.best = self.score;
+      let _ = storage::write_u32(BEST_KEY, self.best);
     }
     self.state = S_GAMEOVER;
   }
 
   fn spawn_pipe(&mut self) {
-    let gap_y = self.rng.range(78, GROUND_Y as i32 - 55) as f32;
+    let gap_y = self.rng.range(78, GROUND_Y as i32 - 54) as f32;
     self.pipes.push(Pipe {
       x: SW as f32,
       gap_y,
@@ -340,7 +329,7 @@ fn wasm_main() {
     let mut canvas = Canvas::new(&mut buf[..], SCREEN_WIDTH, SCREEN_HEIGHT);
 
     let mut game = Game::new();
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "flappy: subscriber");
+    let mut buttons = Buttons::new();
     let mut last_frame = get_millis();
 
     loop {
@@ -352,19 +341,11 @@ fn wasm_main() {
       let t = now as f32 * 0.001;
 
       // ── Input ──────────────────────────────────────────────
-      loop {
-        if let Some((_, msg)) = subscriber.try_next_message_pure() {
-          if let HostIpcMessage::HexButton(btn) = msg {
-            match btn {
-              HexButton::Fire | HexButton::Up => match game.state {
-                S_MENU | S_GAMEOVER => game.start(),
-                _ => game.bird.flap(),
-              },
-              _ => {}
-            }
-          }
-        } else {
-          break;
+      buttons.update();
+      if buttons.just_pressed(HexButton::Fire) || buttons.just_pressed(HexButton::Up) {
+        match game.state {
+          S_MENU | S_GAMEOVER => game.start(),
+          _ => game.bird.flap(),
         }
       }
 
@@ -437,9 +418,7 @@ fn wasm_main() {
         _ => {}
       }
 
-      unsafe {
-        extern_set_lcd_buffer(canvas.as_ptr());
-      }
+      canvas.present();
 
       yield_now().await;
     }
@@ -11,7 +11,6 @@ use crate::lib::{
   fmt::{print_str, print_u32},
   gfx::{Canvas, Point, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::{get_millis, print_line},
-  protocol::extern_set_lcd_buffer,
   tasks::{spawn, yield_now},
 };
 use alloc::boxed::Box;
@@ -56,7 +55,7 @@ fn wasm_main() {
       canvas.draw_line(Point::new(0, i), Point::new(SCREEN_WIDTH as i32 - 1, i), Rgb565::WHITE);
       canvas.draw_text("Hello Oggcamp 2026", 0, i - 10, Rgb565::WHITE, 2);
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       yield_now().await;
     }
@@ -0,0 +1,262 @@
+// A small side-scrolling platformer, showing off sprite sheets and tilemaps.
+//
+// Controls:
+//   Left / Right  — walk
+//   Fire (or Up)  — jump
+//
+// Run right collecting coins. The level and hero are PNGs from
+// `sdk/assets/sprites`, converted at build time and drawn with
+// `Canvas::draw_tilemap` and `Canvas::draw_frame`.
+
+#![no_std]
+#![no_main]
+
+extern crate sdk;
+use sdk as lib;
+
+extern crate alloc;
+
+use crate::lib::{
+  fmt::{append_str, append_u32},
+  gfx::{Canvas, Flip, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH, SpriteSheet, Tilemap},
+  helper::{get_millis, print_line},
+  include_sprite,
+  input::Buttons,
+  protocol::HexButton,
+  tasks::{spawn, yield_now},
+};
+use alloc::{boxed::Box, vec::Vec};
+
+// ── Sprites ─────────────────────────────────────────────────────────
+// Tiles: 0 grass, 1 dirt, 2 brick, 3 stone, 4 coin, 5 bush, 6 cloud.
+static TILES: SpriteSheet = include_sprite!("tiles");
+// Hero frames, facing right: 0 standing, 1–2 walking, 3 jumping.
+static HERO: SpriteSheet = include_sprite!("hero");
+
+const TILE_COIN: u8 = 4;
+const TILE: i32 = 16;
+
+// ── Level ───────────────────────────────────────────────────────────
+const COLS: usize = 48;
+const ROWS: usize = 15;
+
+// One character per tile: `#` grass, `d` dirt, `B` brick, `S` stone,
+// `o` coin, `b` bush, `c` cloud, `.` empty.
+const LEVEL: &[u8; COLS * ROWS] = b"\
+................................................\
+..cc..........cc..............cc.........cc.....\
+.................................ooo............\
+..........ooo...................BBBBB...........\
+.........BBBBB..........c.......................\
+...........................o..............ooo...\
+......................S...SSS............SSSSS..\
+....................S.S.........................\
+..............o...S.S.S.......o.o...............\
+.............SSS....S.S........................o\
+.....o.o.............S.......BB...BB..........SS\
+..b.........b.....b........b..........b....b..SS\
+#########....#############....######....########\
+ddddddddd....ddddddddddddd....dddddd....dddddddd\
+ddddddddd....ddddddddddddd....dddddd....dddddddd";
+
+/// The level as tile indices for [`Tilemap`].
+fn level_cells() -> Vec<u8> {
+  LEVEL
+    .iter()
+    .map(|c| match c {
+      b'#' => 0,
+      b'd' => 1,
+      b'B' => 2,
+      b'S' => 3,
+      b'o' => TILE_COIN,
+      b'b' => 5,
+      b'c' => 6,
+      _ => Tilemap::EMPTY,
+    })
+    .collect()
+}
+
+/// Grass, dirt, brick and stone block the hero; the rest is scenery.
+fn is_solid(tile: Option<u8>) -> bool {
+  matches!(tile, Some(0..=3))
+}
+
+// ── Colors ──────────────────────────────────────────────────────────
+const CLR_SKY: Rgb565 = Rgb565::from_rgb888(100, 170, 255);
+const CLR_HUD: Rgb565 = Rgb565::WHITE;
+
+// ── Hero ────────────────────────────────────────────────────────────
+const WALK_SPEED: f32 = 90.0; // px/sec
+const JUMP_SPEED: f32 = 280.0;
+const GRAVITY: f32 = 700.0;
+const MAX_FALL: f32 = 400.0;
+// The hitbox inside the 16x16 frame, a little narrower than the art.
+const HIT_X: i32 = 3;
+const HIT_W: i32 = 10;
+const HIT_H: i32 = 16;
+
+const START_X: f32 = 24.0;
+const START_Y: f32 = 176.0;
+
+struct Hero {
+  x: f32,
+  y: f32,
+  vx: f32,
+  vy: f32,
+  on_ground: bool,
+  facing_left: bool,
+}
+
+impl Hero {
+  fn new() -> Self {
+    Hero {
+      x: START_X,
+      y: START_Y,
+      vx: 0.0,
+      vy: 0.0,
+      on_ground: false,
+      facing_left: false,
+    }
+  }
+
+  /// Whether the hitbox at `(x, y)` overlaps a solid tile.
+  fn collides(map: &Tilemap, x: f32, y: f32) -> bool {
+    let left = x as i32 + HIT_X;
+    let right = left + HIT_W - 1;
+    let top = y as i32;
+    let bottom = top + HIT_H - 1;
+    // The hitbox is no bigger than a tile, so its corners are enough.
+    is_solid(map.tile_at_pixel(left, top))
+      || is_solid(map.tile_at_pixel(right, top))
+      || is_solid(map.tile_at_pixel(left, bottom))
+      || is_solid(map.tile_at_pixel(right, bottom))
+  }
+
+  fn update(&mut self, map: &Tilemap, dt: f32, left: bool, right: bool, jump: bool) {
+    self.vx = 0.0;
+    if left {
+      self.vx -= WALK_SPEED;
+      self.facing_left = true;
+    }
+    if right {
+      self.vx += WALK_SPEED;
+      self.facing_left = false;
+    }
+    if jump && self.on_ground {
+      self.vy = -JUMP_SPEED;
+    }
+    self.vy = (self.vy + GRAVITY * dt).min(MAX_FALL);
+
+    // Move one axis at a time, backing out of walls pixel by pixel
+    let step_x = self.vx * dt;
+    if !Self::collides(map, self.x + step_x, self.y) {
+      self.x += step_x;
+    } else {
+      let dir = if step_x > 0.0 { 1.0 } else { -1.0 };
+      while !Self::collides(map, self.x + dir, self.y) {
+        self.x += dir;
+      }
+    }
+    let max_x = (map.columns() as i32 * TILE - TILE) as f32;
+    self.x = self.x.clamp(0.0, max_x);
+
+    let step_y = self.vy * dt;
+    self.on_ground = false;
+    if !Self::collides(map, self.x, self.y + step_y) {
+      self.y += step_y;
+    } else {
+      let dir = if step_y > 0.0 { 1.0 } else { -1.0 };
+      while !Self::collides(map, self.x, self.y + dir) {
+        self.y += dir;
+      }
+      self.on_ground = dir > 0.0;
+      self.vy = 0.0;
+    }
+  }
+
+  fn frame(&self, now: u32) -> usize {
+    if !self.on_ground {
+      3
+    } else if self.vx != 0.0 {
+      1 + (now / 120 % 2) as usize
+    } else {
+      0
+    }
+  }
+}
+
+// ── Entry point ─────────────────────────────────────────────────────
+#[unsafe(no_mangle)]
+fn tick(host_msg_id: u32, host_msg_size: u32) -> bool {
+  lib::tasks::runtime_tick(host_msg_id, host_msg_size)
+}
+
+#[unsafe(no_mangle)]
+fn wasm_main() {
+  spawn((async move || {
+    let mut buf = Box::new([0x00u8; SCREEN_WIDTH * SCREEN_HEIGHT * 2]);
+    let mut canvas = Canvas::new(&mut buf[..], SCREEN_WIDTH, SCREEN_HEIGHT);
+
+    print_line("Platformer starting\n");
+
+    let mut cells = level_cells();
+    let mut hero = Hero::new();
+    let mut coins = 0u32;
+    let mut buttons = Buttons::new();
+    let mut last_frame = get_millis();
+
+    loop {
+      let now = get_millis();
+      let delta = now.wrapping_sub(last_frame).min(50);
+      last_frame = now;
+      let dt = delta as f32 * 0.001;
+
+      // ── Input and physics ────────────────────────────────────────
+      buttons.update();
+      let jump = buttons.just_pressed(HexButton::Fire) || buttons.just_pressed(HexButton::Up);
+      hero.update(
+        &Tilemap::new(&TILES, &cells, COLS),
+        dt,
+        buttons.is_down(HexButton::Left),
+        buttons.is_down(HexButton::Right),
+        jump,
+      );
+
+      // Fell down a gap: back to the start
+      if hero.y > (ROWS as i32 * TILE) as f32 {
+        hero = Hero::new();
+      }
+
+      // Pick up the coin under the hero's middle
+      let col = (hero.x as i32 + TILE / 2) / TILE;
+      let row = (hero.y as i32 + TILE / 2).div_euclid(TILE);
+      if (0..ROWS as i32).contains(&row) {
+        let cell = &mut cells[row as usize * COLS + col as usize];
+        if *cell == TILE_COIN {
+          *cell = Tilemap::EMPTY;
+          coins += 1;
+        }
+      }
+
+      // ── Render ───────────────────────────────────────────────────
+      // Keep the hero a third of the way across the screen
+      let max_scroll = COLS as i32 * TILE - SCREEN_WIDTH as i32;
+      let scroll_x = (hero.x as i32 - SCREEN_WIDTH as i32 / 3).clamp(0, max_scroll);
+
+      canvas.clear(CLR_SKY);
+      canvas.draw_tilemap(&Tilemap::new(&TILES, &cells, COLS), scroll_x, 0);
+      let flip = if hero.facing_left { Flip::HORIZONTAL } else { Flip::NONE };
+      canvas.draw_frame(&HERO, hero.frame(now), hero.x as i32 - scroll_x, hero.y as i32, flip);
+
+      let mut text = [0u8; 16];
+      let mut len = 0;
+      append_str(&mut text, &mut len, "COINS ");
+      append_u32(&mut text, &mut len, coins);
+      canvas.draw_text(core::str::from_utf8(&text[..len]).unwrap(), 5, 5, CLR_HUD, 1);
+
+      canvas.present();
+
+      yield_now().await;
+    }
+  })());
+}
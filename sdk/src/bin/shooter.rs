@@ -13,7 +13,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -21,10 +20,13 @@ extern crate alloc;
 
 use crate::lib::{
   fmt::{append_str, append_u32},
-  gfx::{Canvas, Point, Rect, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
+  gfx::{Canvas, Flip, Point, Rect, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH, SpriteSheet},
   helper::{get_millis, print_line},
-  protocol::{HexButton, HostIpcMessage, extern_set_lcd_buffer},
-  tasks::{HOST_IPC_CHANNEL, spawn, yield_now},
+  include_sprite,
+  input::Buttons,
+  protocol::HexButton,
+  random::Rng,
+  tasks::{spawn, yield_now},
 };
 use alloc::boxed::Box;
 use alloc::vec::Vec;
@@ -35,18 +37,18 @@ const SH: i32 = SCREEN_HEIGHT as i32;
 
 // ── Colors ──────────────────────────────────────────────────────────
 const CLR_BG: Rgb565 = Rgb565::BLACK;
-const CLR_SHIP: Rgb565 = Rgb565::from_rgb888(80, 200, 255);
-const CLR_SHIP_LIGHT: Rgb565 = Rgb565::from_rgb888(160, 230, 255);
 const CLR_BULLET: Rgb565 = Rgb565::YELLOW;
-const CLR_ENEMY_S: Rgb565 = Rgb565::from_rgb888(200, 200, 200); // small — white/grey
-const CLR_ENEMY_M: Rgb565 = Rgb565::from_rgb888(255, 120, 60); // medium — orange
-const CLR_ENEMY_H: Rgb565 = Rgb565::from_rgb888(255, 80, 80); // heavy — red
-const CLR_ENEMY_S2: Rgb565 = Rgb565::from_rgb888(180, 255, 100); // small tier-2 — green
 const CLR_HUD: Rgb565 = Rgb565::WHITE;
 
+// ── Sprites ─────────────────────────────────────────────────────────
+// Frames: 0 level, 1 banking right (flipped for left).
+static SHIP_SPRITE: SpriteSheet = include_sprite!("ship");
+// Two animation frames per enemy type, in `EnemyType` order.
+static ENEMY_SPRITES: SpriteSheet = include_sprite!("enemies");
+
 // ── Ship ────────────────────────────────────────────────────────────
 const SHIP_W: f32 = 14.0;
-const SHIP_H: f32 = 20.0;
+const SHIP_H: f32 = 16.0;
 const SHIP_RADIUS: f32 = 10.0;
 const SHIP_SPEED: f32 = 130.0; // px/sec
 const SHIP_FIRE_CD: u32 = 240; // ms between shots
@@ -64,35 +66,6 @@ const ENEMY_H_RADIUS: f32 = 13.0;
 // ── Starfield ───────────────────────────────────────────────────────
 const NUM_STARS: usize = 48;
 
-// ── RNG ─────────────────────────────────────────────────────────────
-struct Rng {
-  state: u32,
-}
-
-impl Rng {
-  fn new(seed: u32) -> Self {
-    Rng { state: seed | 1 }
-  }
-
-  fn next(&mut self) -> u32 {
-    // xorshift32
-    let mut x = self.state;
-    x ^= x << 13;
-    x ^= x >> 17;
-    x ^= x << 5;
-    self.state = x;
-    x
-  }
-
-  fn range(&mut self, n: usize) -> usize {
-    (self.next() as usize) % n
-  }
-
-  fn float(&mut self) -> f32 {
-    (self.next() >> 8) as f32 / ((1u32 << 24) as f32 - 1.0)
-  }
-}
-
 // ── Ship ────────────────────────────────────────────────────────────
 struct Ship {
   x: f32,
@@ -195,28 +168,17 @@ impl Ship {
   }
 
   fn draw(&self, canvas: &mut Canvas) {
-    // Main body — a rounded triangle / chevron pointing up
-    let cx = self.x as i32;
-    let cy = self.y as i32;
-    let r = SHIP_RADIUS as i32;
-
-    // Wings
-    let wing_l = Point::new(cx - 10, cy + 6);
-    let wing_r = Point::new(cx + 10, cy + 6);
-    let body_t = Point::new(cx, cy - r);
-    let body_l = Point::new(cx - 7, cy + 4);
-    let body_r = Point::new(cx + 7, cy + 4);
-
-    // Ship body (filled polygon via two triangles)
-    canvas.fill_triangle(body_t, body_r, Point::new(cx, cy + 2), CLR_SHIP);
-    canvas.fill_triangle(body_t, Point::new(cx, cy + 2), body_l, CLR_SHIP);
-    // Wings
-    canvas.fill_triangle(wing_l, wing_r, Point::new(cx, cy + 2), CLR_SHIP);
-    // Wing tips
-    canvas.fill_circle(wing_l, 3, CLR_SHIP_LIGHT);
-    canvas.fill_circle(wing_r, 3, CLR_SHIP_LIGHT);
-    // Cockpit
-    canvas.fill_circle(Point::new(cx, cy - 4), 4, CLR_SHIP_LIGHT);
+    // Bank into the turn while strafing
+    let (frame, flip) = if self.vx > 0.0 {
+      (1, Flip::NONE)
+    } else if self.vx < 0.0 {
+      (1, Flip::HORIZONTAL)
+    } else {
+      (0, Flip::NONE)
+    };
+    let w = SHIP_SPRITE.frame_width() as i32;
+    let h = SHIP_SPRITE.frame_height() as i32;
+    canvas.draw_frame(&SHIP_SPRITE, frame, self.x as i32 - w / 2, self.y as i32 - h / 2, flip);
   }
 }
 
@@ -261,7 +223,7 @@ impl Enemy {
       radius: ENEMY_S_RADIUS,
       etype: EnemyType::Small,
       hp: 1,
-      zigzag_phase: rng.float() * 6.2831853,
+      zigzag_phase: rng.next_f32() * 6.2831853,
     }
   }
 
@@ -274,7 +236,7 @@ impl Enemy {
       radius: ENEMY_M_RADIUS,
       etype: EnemyType::Medium,
       hp: 2,
-      zigzag_phase: rng.float() * 6.2831853,
+      zigzag_phase: rng.next_f32() * 6.2831853,
     }
   }
 
@@ -287,7 +249,7 @@ impl Enemy {
       radius: ENEMY_H_RADIUS,
       etype: EnemyType::Heavy,
       hp: 3,
-      zigzag_phase: rng.float() * 6.2831853,
+      zigzag_phase: rng.next_f32() * 6.2831853,
     }
   }
 
@@ -300,18 +262,19 @@ impl Enemy {
       radius: ENEMY_S_RADIUS,
       etype: EnemyType::Small2,
       hp: 1,
-      zigzag_phase: rng.float() * 6.2831853,
+      zigzag_phase: rng.next_f32() * 6.2831853,
     };
     e.vx *= 0.0; // zigzag handled in update
     e
   }
 
-  fn color(&self) -> Rgb565 {
+  /// First of the type's two frames in `ENEMY_SPRITES`.
+  fn first_frame(&self) -> usize {
     match self.etype {
-      EnemyType::Small => CLR_ENEMY_S,
-      EnemyType::Medium => CLR_ENEMY_M,
-      EnemyType::Heavy => CLR_ENEMY_H,
-      EnemyType::Small2 => CLR_ENEMY_S2,
+      EnemyType::Small => 0,
+      EnemyType::Medium => 2,
+      EnemyType::Heavy => 4,
+      EnemyType::Small2 => 6,
     }
   }
 
@@ -324,15 +287,16 @@ impl Enemy {
     self.y += self.vy * dt;
   }
 
-  fn draw(&self, canvas: &mut Canvas) {
+  fn draw(&self, canvas: &mut Canvas, t: f32) {
     let cx = self.x as i32;
     let cy = self.y as i32;
     let r = self.radius as i32;
-    let inner = self.color();
 
-    // Main body
-    canvas.fill_circle(Point::new(cx, cy), r, inner);
-    canvas.draw_circle(Point::new(cx, cy), r, Rgb565::BLACK);
+    // Main body, flickering between its two frames
+    let frame = self.first_frame() + ((t * 4.0 + self.zigzag_phase) as usize & 1);
+    let w = ENEMY_SPRITES.frame_width() as i32;
+    let h = ENEMY_SPRITES.frame_height() as i32;
+    canvas.draw_frame(&ENEMY_SPRITES, frame, cx - w / 2, cy - h / 2, Flip::NONE);
 
     // Hit indicators — draw segments
     let seg_angle = 2.0943951; // 120 degrees
@@ -349,7 +313,7 @@ impl Enemy {
 fn init_stars(rng: &mut Rng) -> [(i32, i32, u8); NUM_STARS] {
   let mut stars = [(0i32, 0i32, 0u8); NUM_STARS];
   for i in 0..NUM_STARS {
-    stars[i] = (rng.range(SCREEN_WIDTH) as i32, rng.range(SCREEN_HEIGHT) as i32, rng.range(3) as u8);
+    stars[i] = (rng.range(0, SW), rng.range(0, SH), rng.below(3) as u8);
   }
   stars
 }
@@ -389,8 +353,7 @@ struct GameState {
 
 impl GameState {
   fn new() -> Self {
-    let seed = get_millis();
-    let mut rng = Rng::new(seed);
+    let mut rng = Rng::from_entropy();
     let stars = init_stars(&mut rng);
     let gs = GameState {
       ship: Ship::new(),
@@ -405,7 +368,7 @@ impl GameState {
       spawn_timer: 0,
       enemies_to_spawn: 0,
       enemies_spawned: 0,
-      wave_start: seed,
+      wave_start: get_millis(),
     };
     gs
   }
@@ -444,9 +407,9 @@ impl GameState {
 
   fn spawn_enemy(&mut self, level: u32) {
     let speed = 30.0 + level as f32 * 4.0;
-    let x = self.rng.float() * (SW as f32 - 40.0) + 20.0;
+    let x = self.rng.next_f32() * (SW as f32 - 40.0) + 20.0;
     // Weighted random type
-    let roll = self.rng.range(100);
+    let roll = self.rng.below(100);
     let enemy = if roll < 60 {
       // 60% small
       Enemy::new_small(&mut self.rng, x, -20.0, 0.0, speed)
@@ -517,12 +480,8 @@ fn wasm_main() {
     print_line("Shooter starting\n");
 
     let mut state = GameState::new();
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "shooter: subscriber");
+    let mut buttons = Buttons::new();
     let mut last_frame = get_millis();
-    let mut ship_left = false;
-    let mut ship_right = false;
-    let mut ship_up = false;
-    let mut ship_down = false;
 
     loop {
       let now = get_millis();
@@ -533,32 +492,14 @@ fn wasm_main() {
       let t = now as f32 * 0.001;
 
       // ── Input ────────────────────────────────────────────────────
-      loop {
-        if let Some((_, msg)) = subscriber.try_next_message_pure() {
-          if let HostIpcMessage::HexButton(btn) = msg {
-            match btn {
-              HexButton::Left => ship_left = true,
-              HexButton::LeftReleased => ship_left = false,
-              HexButton::Right => ship_right = true,
-              HexButton::RightReleased => ship_right = false,
-              HexButton::Up => ship_up = true,
-              HexButton::UpReleased => ship_up = false,
-              HexButton::Down => ship_down = true,
-              HexButton::DownReleased => ship_down = false,
-              HexButton::Fire => {
-                if state.state != S_PLAY {
-                  if state.state == S_GAMEOVER || state.state == S_MENU {
-                    state.start_game();
-                  }
-                } else {
-                  state.ship.try_fire(&mut state.bullets);
-                }
-              }
-              _ => {}
-            }
+      buttons.update();
+      if buttons.just_pressed(HexButton::Fire) {
+        if state.state != S_PLAY {
+          if state.state == S_GAMEOVER || state.state == S_MENU {
+            state.start_game();
           }
         } else {
-          break;
+          state.ship.try_fire(&mut state.bullets);
         }
       }
 
@@ -569,7 +510,13 @@ fn wasm_main() {
         }
         S_PLAY => {
           // Ship
-          state.ship.update(delta, ship_left, ship_right, ship_up, ship_down);
+          state.ship.update(
+            delta,
+            buttons.is_down(HexButton::Left),
+            buttons.is_down(HexButton::Right),
+            buttons.is_down(HexButton::Up),
+            buttons.is_down(HexButton::Down),
+          );
 
           // Bullets — update, cull off-screen
           let mut i = 0;
@@ -692,7 +639,7 @@ fn wasm_main() {
 
         // Enemies
         for e in &state.enemies {
-          e.draw(&mut canvas);
+          e.draw(&mut canvas, t);
         }
 
         // Bullets
@@ -732,9 +679,7 @@ fn wasm_main() {
         }
       }
 
-      unsafe {
-        extern_set_lcd_buffer(canvas.as_ptr());
-      }
+      canvas.present();
 
       yield_now().await;
     }
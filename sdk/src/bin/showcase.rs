@@ -2,7 +2,8 @@
 //
 // Cycles through animated scenes exercising every primitive on a 240x240
 // RGB565 canvas: palette/pixels, lines, rects, circles, triangles, text,
-// and a combined "everything" screen. No embedded_graphics is used here.
+// and a combined "everything" screen. Built with the `embedded-graphics`
+// feature, a last scene draws on the same canvas through embedded_graphics.
 //
 // Controls:
 //   Up / Down - previous / next scene
@@ -12,7 +13,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -22,8 +22,9 @@ use crate::lib::{
   fmt,
   gfx::{Canvas, Point, Rect, Rgb565, text_height, text_width},
   helper::get_millis,
-  protocol::{HexButton, HostIpcMessage, extern_set_lcd_buffer},
-  tasks::{HOST_IPC_CHANNEL, spawn, yield_now},
+  input::Buttons,
+  protocol::HexButton,
+  tasks::{spawn, yield_now},
 };
 use alloc::boxed::Box;
 
@@ -44,9 +45,11 @@ enum Scene {
   Triangles,
   Text,
   All,
+  #[cfg(feature = "embedded-graphics")]
+  EmbeddedGraphics,
 }
 
-const SCENES: [Scene; 7] = [
+const SCENES: &[Scene] = &[
   Scene::Palette,
   Scene::Lines,
   Scene::Rects,
@@ -54,6 +57,8 @@ const SCENES: [Scene; 7] = [
   Scene::Triangles,
   Scene::Text,
   Scene::All,
+  #[cfg(feature = "embedded-graphics")]
+  Scene::EmbeddedGraphics,
 ];
 
 fn scene_name(scene: Scene) -> &'static str {
@@ -65,6 +70,8 @@ fn scene_name(scene: Scene) -> &'static str {
     Scene::Triangles => "Triangles",
     Scene::Text => "Text",
     Scene::All => "All",
+    #[cfg(feature = "embedded-graphics")]
+    Scene::EmbeddedGraphics => "e-g",
   }
 }
 
@@ -112,7 +119,7 @@ fn wasm_main() {
     let mut buf = Box::new([0u8; SCREEN_W * SCREEN_H * 2]);
     let mut canvas = Canvas::new(&mut buf[..], SCREEN_W, SCREEN_H);
 
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "subscriber");
+    let mut buttons = Buttons::new();
 
     let mut scene_idx = 0usize;
     let mut paused = false;
@@ -124,21 +131,17 @@ fn wasm_main() {
 
     loop {
       // Button input
-      if let Some((_, msg)) = subscriber.try_next_message_pure() {
-        if let HostIpcMessage::HexButton(btn) = msg {
-          match btn {
-            HexButton::Up => {
-              scene_idx = (scene_idx + SCENES.len() - 1) % SCENES.len();
-              scene_start = get_millis();
-            }
-            HexButton::Down => {
-              scene_idx = (scene_idx + 1) % SCENES.len();
-              scene_start = get_millis();
-            }
-            HexButton::Fire => paused = !paused,
-            _ => {}
-          }
-        }
+      buttons.update();
+      if buttons.just_pressed(HexButton::Up) {
+        scene_idx = (scene_idx + SCENES.len() - 1) % SCENES.len();
+        scene_start = get_millis();
+      }
+      if buttons.just_pressed(HexButton::Down) {
+        scene_idx = (scene_idx + 1) % SCENES.len();
+        scene_start = get_millis();
+      }
+      if buttons.just_pressed(HexButton::Fire) {
+        paused = !paused;
       }
 
       // Auto-advance scenes
@@ -178,7 +181,7 @@ fn wasm_main() {
       canvas.fill_rect(Rect::new(0, canvas.height() as i32 - 12, canvas.width() as i32, 12), Rgb565::BLACK);
       canvas.draw_text(status, 4, canvas.height() as i32 - 11, Rgb565::WHITE, 1);
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
       yield_now().await;
     }
   })());
@@ -193,6 +196,8 @@ fn draw_scene(canvas: &mut Canvas, scene: Scene, frame: u32) {
     Scene::Triangles => draw_triangles(canvas, frame),
     Scene::Text => draw_text_scene(canvas, frame),
     Scene::All => draw_all(canvas, frame),
+    #[cfg(feature = "embedded-graphics")]
+    Scene::EmbeddedGraphics => eg_scene::draw(canvas, frame),
   }
 }
 
@@ -534,3 +539,108 @@ fn draw_all(canvas: &mut Canvas, frame: u32) {
 
   centered_text(canvas, "gfx demo", 120, 2, Rgb565::WHITE);
 }
+
+// ---------------------------------------------------------------------------
+// Scene: embedded_graphics on the same canvas
+// ---------------------------------------------------------------------------
+
+#[cfg(feature = "embedded-graphics")]
+mod eg_scene {
+  use crate::lib::gfx::{
+    Canvas,
+    embedded_graphics::{
+      Drawable,
+      draw_target::DrawTargetExt,
+      geometry::{AngleUnit, Point, Size},
+      image::{Image, ImageRawBE},
+      mono_font::{
+        MonoTextStyle,
+        ascii::{FONT_6X10, FONT_10X20},
+      },
+      pixelcolor::{Rgb565, RgbColor, WebColors},
+      primitives::{Arc, Circle, Ellipse, Polyline, Primitive, PrimitiveStyle, PrimitiveStyleBuilder, Rectangle, RoundedRectangle},
+      text::{Alignment, Text},
+    },
+  };
+
+  /// 4x4 big-endian RGB565 tile for the image demo.
+  const TILE: [u8; 32] = [
+    0xF8, 0x00, 0xFF, 0xFF, 0xF8, 0x00, 0xFF, 0xFF, //
+    0xFF, 0xFF, 0x00, 0x1F, 0xFF, 0xFF, 0x00, 0x1F, //
+    0xF8, 0x00, 0xFF, 0xFF, 0xF8, 0x00, 0xFF, 0xFF, //
+    0xFF, 0xFF, 0x00, 0x1F, 0xFF, 0xFF, 0x00, 0x1F, //
+  ];
+
+  pub fn draw(canvas: &mut Canvas, frame: u32) {
+    canvas.clear(crate::lib::gfx::Rgb565::BLACK);
+    // Drawing into a Canvas cannot fail.
+    let _ = draw_scene(canvas, frame);
+  }
+
+  fn draw_scene(canvas: &mut Canvas, frame: u32) -> Result<(), core::convert::Infallible> {
+    // Mono fonts, aligned text
+    Text::with_alignment(
+      "embedded-graphics",
+      Point::new(120, 26),
+      MonoTextStyle::new(&FONT_10X20, Rgb565::YELLOW),
+      Alignment::Center,
+    )
+    .draw(canvas)?;
+    Text::with_alignment(
+      "DrawTarget for sdk::gfx::Canvas",
+      Point::new(120, 42),
+      MonoTextStyle::new(&FONT_6X10, Rgb565::CSS_LIGHT_GRAY),
+      Alignment::Center,
+    )
+    .draw(canvas)?;
+
+    // Styled primitives
+    let panel = PrimitiveStyleBuilder::new()
+      .stroke_color(Rgb565::WHITE)
+      .stroke_width(2)
+      .fill_color(Rgb565::CSS_DARK_SLATE_BLUE)
+      .build();
+    RoundedRectangle::with_equal_corners(Rectangle::new(Point::new(20, 54), Size::new(200, 70)), Size::new(12, 12))
+      .into_styled(panel)
+      .draw(canvas)?;
+
+    let sweep = (frame % 72) as f32 * 5.0;
+    Arc::new(Point::new(34, 62), 54, 90.0.deg(), sweep.deg())
+      .into_styled(PrimitiveStyle::with_stroke(Rgb565::CSS_ORANGE, 5))
+      .draw(canvas)?;
+    Ellipse::new(Point::new(100, 68), Size::new(50, 40))
+      .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_TEAL))
+      .draw(canvas)?;
+    let zigzag = [
+      Point::new(164, 110),
+      Point::new(176, 70),
+      Point::new(188, 110),
+      Point::new(200, 70),
+      Point::new(208, 90),
+    ];
+    Polyline::new(&zigzag)
+      .into_styled(PrimitiveStyle::with_stroke(Rgb565::GREEN, 3))
+      .draw(canvas)?;
+
+    // Images
+    let tile = ImageRawBE::<Rgb565>::new(&TILE, 4);
+    for i in 0..8 {
+      Image::new(&tile, Point::new(24 + i * 24, 134)).draw(canvas)?;
+    }
+
+    // Clipping: a big circle drawn through a window that slides with the frame
+    let x = (frame % 160) as i32;
+    let window = Rectangle::new(Point::new(20 + x / 2, 150), Size::new(120, 60));
+    let mut clipped = canvas.clipped(&window);
+    Circle::with_center(Point::new(120, 200), 100)
+      .into_styled(PrimitiveStyle::with_stroke(Rgb565::CSS_HOT_PINK, 6))
+      .draw(&mut clipped)?;
+    Circle::with_center(Point::new(120, 200), 60)
+      .into_styled(PrimitiveStyle::with_fill(Rgb565::CSS_DEEP_SKY_BLUE))
+      .draw(&mut clipped)?;
+    window
+      .into_styled(PrimitiveStyle::with_stroke(Rgb565::CSS_DIM_GRAY, 1))
+      .draw(canvas)?;
+    Ok(())
+  }
+}
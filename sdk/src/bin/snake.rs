@@ -7,7 +7,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -15,12 +14,26 @@ extern crate alloc;
 
 use crate::lib::{
   gfx::{Canvas, Point, Rect, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
-  helper::get_millis,
-  protocol::{extern_set_lcd_buffer, HexButton, HostIpcMessage},
-  tasks::{spawn, yield_now, HOST_IPC_CHANNEL},
+  helper::{get_millis, set_led_effect, set_leds},
+  input::Buttons,
+  protocol::{Capabilities, HexButton, LedColour, LedEffect, NUM_LEDS},
+  random::Rng,
+  storage,
+  tasks::{spawn, yield_now},
 };
 use alloc::{boxed::Box, vec, vec::Vec};
 
+lib::app_metadata! {
+  name: "Snake",
+  version: "1.0.0",
+  author: "Rustagon",
+  description: "Eat the food, miss your tail",
+  permissions: Capabilities::LEDS.union(Capabilities::STORAGE),
+}
+
+// Key the high score is persisted under in the app's storage sandbox.
+const BEST_KEY: &str = "best";
+
 const GRID_W: usize = 20;
 const GRID_H: usize = 20;
 const CELL: i32 = 12;
@@ -69,7 +82,9 @@ impl Snake {
 struct Game {
   snake: Snake,
   food: Cell,
+  rng: Rng,
   score: u32,
+  best: u32,
   over: bool,
 }
 
@@ -78,21 +93,24 @@ impl Game {
     Game {
       snake: Snake::new(),
       food: Cell { x: 0, y: 0 },
+      rng: Rng::from_entropy(),
       score: 0,
+      best: storage::read_u32(BEST_KEY).unwrap_or(0),
       over: false,
     }
   }
 
+  /// Put the food on a random cell the snake isn't on.
   fn place_food(&mut self) {
-    for y in 0..GRID_H {
-      for x in 0..GRID_W {
-        let cx = x as i32 * CELL;
-        let cy = y as i32 * CELL;
-        if !self.snake.body.iter().any(|c| c.x == cx && c.y == cy) {
-          self.food = Cell { x: cx, y: cy };
-          return;
-        }
-      }
+    let mut free = (0..GRID_W * GRID_H)
+      .map(|i| Cell {
+        x: (i % GRID_W) as i32 * CELL,
+        y: (i / GRID_W) as i32 * CELL,
+      })
+      .filter(|cell| !self.snake.body.contains(cell));
+    let pick = self.rng.below(free.clone().count() as u32);
+    if let Some(cell) = free.nth(pick as usize) {
+      self.food = cell;
     }
   }
 
@@ -107,6 +125,10 @@ impl Game {
     };
     if self.collides(&new_head) {
       self.over = true;
+      if self.score > self.best {
+        self.best = self.score;
+        let _ = storage::write_u32(BEST_KEY, self.best);
+      }
       return false;
     }
     self.snake.body.insert(0, new_head);
@@ -215,9 +237,30 @@ fn render(game: &Game, canvas: &mut Canvas) {
   if game.over {
     canvas.draw_text("GAME OVER", (SCREEN_WIDTH as i32 - 162) / 2, 80, Rgb565::RED, 2);
     canvas.draw_text("Fire to restart", (SCREEN_WIDTH as i32 - 162) / 2, 100, Rgb565::WHITE, 1);
+
+    len = 0;
+    lib::fmt::append_str(&mut buf, &mut len, "BEST: ");
+    lib::fmt::append_u32(&mut buf, &mut len, game.best);
+    if let Ok(text) = core::str::from_utf8(&buf[..len]) {
+      canvas.draw_text(text, (SCREEN_WIDTH as i32 - 162) / 2, 112, Rgb565::YELLOW, 1);
+    }
   }
 }
 
+/// Show the score on the LED ring (one green LED per point), or pulse red
+/// once the game is over.
+fn update_leds(game: &Game) {
+  if game.over {
+    set_led_effect(LedEffect::Breathe, LedColour::new(255, 0, 0));
+    return;
+  }
+  let mut leds = [LedColour::OFF; NUM_LEDS];
+  for led in leds.iter_mut().take(game.score as usize) {
+    *led = LedColour::new(0, 255, 0);
+  }
+  set_leds(&leds);
+}
+
 #[unsafe(no_mangle)]
 fn tick(host_msg_id: u32, host_msg_size: u32) -> bool {
   lib::tasks::runtime_tick(host_msg_id, host_msg_size)
@@ -230,14 +273,16 @@ fn wasm_main() {
     let mut canvas = Canvas::new(&mut buf[..], SCREEN_WIDTH, SCREEN_HEIGHT);
 
     let mut game = Game::new();
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "snake: subscriber");
+    let mut buttons = Buttons::new();
     let mut start = get_millis();
+    let mut shown = (u32::MAX, false);
 
     loop {
       let now = get_millis();
 
-      if let Some((_, msg)) = subscriber.try_next_message_pure() {
-        if let HostIpcMessage::HexButton(btn) = msg {
+      buttons.update();
+      for btn in [HexButton::Left, HexButton::Right, HexButton::Up, HexButton::Down, HexButton::Fire] {
+        if buttons.just_pressed(btn) {
           handle_input(&mut game, btn);
         }
       }
@@ -248,8 +293,13 @@ fn wasm_main() {
         game.step();
       }
 
+      if shown != (game.score, game.over) {
+        shown = (game.score, game.over);
+        update_leds(&game);
+      }
+
       render(&game, &mut canvas);
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       yield_now().await;
     }
@@ -7,7 +7,6 @@
 #![no_std]
 #![no_main]
 
-#[macro_use]
 extern crate sdk;
 use sdk as lib;
 
@@ -16,11 +15,25 @@ extern crate alloc;
 use crate::lib::{
   gfx::{Canvas, Point, Rect, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH},
   helper::print_line,
-  protocol::{HexButton, HostIpcMessage, extern_set_lcd_buffer},
-  tasks::{HOST_IPC_CHANNEL, spawn, yield_now},
+  input::Buttons,
+  protocol::{Capabilities, HexButton},
+  random::Rng,
+  storage,
+  tasks::{spawn, yield_now},
 };
 use alloc::boxed::Box;
 
+lib::app_metadata! {
+  name: "Tetris",
+  version: "1.0.0",
+  author: "Rustagon",
+  description: "Stack the falling blocks, clear the lines",
+  permissions: Capabilities::STORAGE,
+}
+
+// Key the high score is persisted under in the app's storage sandbox.
+const BEST_KEY: &str = "best";
+
 // --- Board geometry ---
 const BOARD_W: usize = 10;
 const BOARD_H: usize = 20;
@@ -63,22 +76,6 @@ const PALETTE: [Rgb565; 8] = [
   Rgb565::RED,     // 7: Z
 ];
 
-// --- Random number generator (simple LCG) ---
-struct Rng {
-  state: u32,
-}
-
-impl Rng {
-  fn next(&mut self) -> u32 {
-    self.state = self.state.wrapping_mul(1103515245).wrapping_add(12345);
-    self.state
-  }
-
-  fn range(&mut self, max: usize) -> usize {
-    (self.next() as usize) % max
-  }
-}
-
 // --- Collision and placement helpers ---
 
 /// Rotate a 4x4 shape 90 degrees clockwise in place.
@@ -169,6 +166,7 @@ struct GameState {
   last_drop: u32,
   drop_interval_ms: u32,
   score: u32,
+  best: u32,
   lines: u32,
   level: u32,
   game_over: bool,
@@ -176,8 +174,7 @@ struct GameState {
 
 impl GameState {
   fn new() -> Self {
-    let seed = crate::lib::helper::get_millis();
-    let mut rng = Rng { state: seed };
+    let mut rng = Rng::from_entropy();
     let (shape, color) = Self::random_piece(&mut rng);
     let (next_shape, next_color) = Self::random_piece(&mut rng);
     let mut state = Self {
@@ -189,9 +186,10 @@ impl GameState {
       piece_color: color,
       next_shape,
       next_color,
-      last_drop: seed,
+      last_drop: crate::lib::helper::get_millis(),
       drop_interval_ms: 800,
       score: 0,
+      best: storage::read_u32(BEST_KEY).unwrap_or(0),
       lines: 0,
       level: 0,
       game_over: false,
@@ -201,7 +199,7 @@ impl GameState {
   }
 
   fn random_piece(rng: &mut Rng) -> (Shape, u8) {
-    let idx = rng.range(PIECES.len());
+    let idx = rng.below(PIECES.len() as u32) as usize;
     (PIECES[idx].0, PIECES[idx].1)
   }
 
@@ -215,6 +213,10 @@ impl GameState {
     self.next_color = color;
     if !can_place(&self.board, &self.piece_shape, self.piece_x, self.piece_y) {
       self.game_over = true;
+      if self.score > self.best {
+        self.best = self.score;
+        let _ = storage::write_u32(BEST_KEY, self.best);
+      }
     }
   }
 
@@ -348,6 +350,13 @@ impl GameState {
     let lines_text = core::str::from_utf8(&buf[..len]).unwrap();
     canvas.draw_text(lines_text, ui_x, BOARD_Y + 25, Rgb565::WHITE, 1);
 
+    // Best
+    len = 0;
+    crate::lib::fmt::append_str(&mut buf, &mut len, "BEST ");
+    crate::lib::fmt::append_u32(&mut buf, &mut len, self.best);
+    let best_text = core::str::from_utf8(&buf[..len]).unwrap();
+    canvas.draw_text(best_text, ui_x, BOARD_Y + 38, Rgb565::YELLOW, 1);
+
     // Next piece label
     canvas.draw_text("NEXT", ui_x, BOARD_Y + 50, Rgb565::WHITE, 1);
 
@@ -372,7 +381,8 @@ fn wasm_main() {
     print_line("Tetris starting\n");
 
     let mut state = GameState::new();
-    let mut subscriber = log_error!(HOST_IPC_CHANNEL.subscriber(), "tetris: subscriber");
+    // Holding Left, Right or Down keeps moving the piece.
+    let mut buttons = Buttons::new().with_repeat(250, 60);
 
     let mut game_over_start: u32 = 0;
 
@@ -381,28 +391,22 @@ fn wasm_main() {
 
       // Process input
       if !state.game_over {
-        if let Some((_, msg)) = subscriber.try_next_message_pure() {
-          match msg {
-            HostIpcMessage::HexButton(HexButton::Left) => {
-              state.try_move(-1, 0);
-            }
-            HostIpcMessage::HexButton(HexButton::Right) => {
-              state.try_move(1, 0);
-            }
-            HostIpcMessage::HexButton(HexButton::Up) => {
-              state.try_rotate();
-            }
-            HostIpcMessage::HexButton(HexButton::Down) => {
-              if !state.try_move(0, 1) {
-                state.lock_piece();
-              }
-            }
-            HostIpcMessage::HexButton(HexButton::Fire) => {
-              state.hard_drop();
-              state.lock_piece();
-            }
-            _ => {}
-          }
+        buttons.update();
+        if buttons.just_pressed_or_repeated(HexButton::Left) {
+          state.try_move(-1, 0);
+        }
+        if buttons.just_pressed_or_repeated(HexButton::Right) {
+          state.try_move(1, 0);
+        }
+        if buttons.just_pressed(HexButton::Up) {
+          state.try_rotate();
+        }
+        if buttons.just_pressed_or_repeated(HexButton::Down) && !state.try_move(0, 1) {
+          state.lock_piece();
+        }
+        if buttons.just_pressed(HexButton::Fire) {
+          state.hard_drop();
+          state.lock_piece();
         }
 
         // Update
@@ -431,7 +435,7 @@ fn wasm_main() {
         canvas.draw_text("GAME OVER", (SCREEN_WIDTH as i32 - 162) / 2, 108, Rgb565::RED, 3);
       }
 
-      unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+      canvas.present();
 
       yield_now().await;
     }
@@ -0,0 +1,112 @@
+// Demo the host-drawn screens: a menu, a progress bar, a text frame and a
+// notification, with a detour back to raw pixels.
+
+#![no_std]
+#![no_main]
+
+use sdk as lib;
+
+extern crate alloc;
+
+use crate::lib::{
+  gfx::{Canvas, Rgb565},
+  input::{ButtonEvent, Buttons},
+  protocol::HexButton,
+  screen::{self, Icon20, Icon40, MenuAnimation, MenuLine, TextBufferLine, Widget},
+  sleep::sleep,
+  tasks::spawn,
+};
+use alloc::{string::ToString, vec, vec::Vec};
+
+lib::app_metadata! {
+  name: "Widgets",
+  version: "1.0.0",
+  author: "Rustagon",
+  description: "Menus and notifications drawn by the host",
+}
+
+const ITEMS: [(Icon20, &str); 4] = [
+  (Icon20::File, "Progress"),
+  (Icon20::Info, "Notes"),
+  (Icon20::Config, "Notify"),
+  (Icon20::Home, "Pixels"),
+];
+
+fn menu(selected: u32) -> Widget {
+  Widget::Menu {
+    menu: ITEMS.iter().map(|&(icon, label)| MenuLine(icon, label.to_string())).collect(),
+    selected,
+    animation: MenuAnimation::FromRight,
+    header: Some("Widgets".to_string()),
+  }
+}
+
+fn notes() -> Widget {
+  let lines: Vec<TextBufferLine> = ["Drawn by the host,", "not by the app."]
+    .iter()
+    .map(|text| TextBufferLine {
+      text: text.to_string(),
+      cursor: None,
+    })
+    .collect();
+  Widget::TextBuffer { lines }
+}
+
+/// Wait for a press of any button.
+async fn any_press(buttons: &mut Buttons) {
+  while !matches!(buttons.next_event().await, ButtonEvent::Pressed(_)) {}
+}
+
+#[unsafe(no_mangle)]
+fn tick(host_msg_id: u32, host_msg_size: u32) -> bool {
+  lib::tasks::runtime_tick(host_msg_id, host_msg_size)
+}
+
+#[unsafe(no_mangle)]
+fn wasm_main() {
+  spawn((async || {
+    let mut buttons = Buttons::new();
+    let mut selected = 0;
+
+    screen::show(menu(selected)).await;
+
+    loop {
+      let ButtonEvent::Pressed(button) = buttons.next_event().await else {
+        continue;
+      };
+      match button {
+        HexButton::Up => selected = (selected + ITEMS.len() as u32 - 1) % ITEMS.len() as u32,
+        HexButton::Down => selected = (selected + 1) % ITEMS.len() as u32,
+        HexButton::Fire => match selected {
+          0 => {
+            for step in 0..=20 {
+              screen::show(Widget::BoundedProgress(step, 20)).await;
+              sleep(100).await;
+            }
+          }
+          1 => {
+            screen::show(notes()).await;
+            any_press(&mut buttons).await;
+          }
+          2 => {
+            // Slides over the menu and back out; showing the menu again now
+            // would cut it short.
+            screen::show(Widget::Notification(Icon40::Info, "Hello!".to_string())).await;
+            continue;
+          }
+          _ => {
+            screen::show_framebuffer().await;
+            let mut buf = vec![0u8; 240 * 240 * 2];
+            let mut canvas = Canvas::new(&mut buf[..], 240, 240);
+            canvas.clear(Rgb565::BLACK);
+            canvas.draw_text("Raw pixels again", 24, 112, Rgb565::WHITE, 2);
+            canvas.present();
+            any_press(&mut buttons).await;
+          }
+        },
+        _ => continue,
+      }
+      screen::show(menu(selected)).await;
+    }
+  })());
+}
@@ -4,7 +4,10 @@
 //! Only `core` is used — no `embedded_graphics`, no `libm`, no `alloc`. The
 //! framebuffer layout matches what the host LCD expects (RGB565, big-endian
 //! bytes — the same layout the host LCD uses), so a canvas can be handed
-//! straight to `extern_set_lcd_buffer`.
+//! straight to `extern_set_lcd_buffer`, or to `extern_set_lcd_region` for
+//! just the part that changed (see [`Canvas::present`]).
+
+use crate::protocol::{extern_set_lcd_buffer, extern_set_lcd_region};
 
 /// Logical screen width in pixels.
 pub const SCREEN_WIDTH: usize = 240;
@@ -43,6 +46,11 @@ impl Rgb565 {
     Rgb565(((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3))
   }
 
+  /// A color from its raw 16-bit RGB565 value.
+  pub const fn from_raw(raw: u16) -> Rgb565 {
+    Rgb565(raw)
+  }
+
   /// The raw 16-bit RGB565 value.
   pub const fn raw(self) -> u16 {
     self.0
@@ -111,11 +119,16 @@ impl Rect {
 /// A drawable framebuffer of RGB565 pixels in big-endian byte order.
 ///
 /// All drawing is clipped to the canvas bounds; out-of-bounds pixels are
-/// silently ignored.
+/// silently ignored. The canvas keeps the bounding box of everything drawn
+/// since the last [`present`](Canvas::present), so presenting only sends the
+/// pixels that may have changed.
 pub struct Canvas<'a> {
   buf: &'a mut [u8],
   w: usize,
   h: usize,
+  /// Changed pixels since the last `present`, as `[x0, y0, x1, y1)` in canvas
+  /// coordinates, or `None` if nothing was drawn.
+  dirty: Option<[usize; 4]>,
 }
 
 impl<'a> Canvas<'a> {
@@ -126,7 +139,9 @@ impl<'a> Canvas<'a> {
   /// Panics if `buf.len() < w * h * 2`.
   pub fn new(buf: &'a mut [u8], w: usize, h: usize) -> Canvas<'a> {
     assert!(buf.len() >= w * h * 2, "canvas buffer too small");
-    Canvas { buf, w, h }
+    // The screen hasn't seen this buffer yet, so the first present sends it all.
+    let dirty = if w > 0 && h > 0 { Some([0, 0, w, h]) } else { None };
+    Canvas { buf, w, h, dirty }
   }
 
   pub fn width(&self) -> usize {
@@ -142,8 +157,66 @@ impl<'a> Canvas<'a> {
     self.buf.as_ptr()
   }
 
+  /// The area drawn since the last [`present`](Canvas::present), if any.
+  pub fn dirty_rect(&self) -> Option<Rect> {
+    self
+      .dirty
+      .map(|[x0, y0, x1, y1]| Rect::new(x0 as i32, y0 as i32, (x1 - x0) as i32, (y1 - y0) as i32))
+  }
+
+  /// Mark the whole canvas as changed, e.g. after writing to the buffer
+  /// directly rather than through the drawing methods.
+  pub fn invalidate(&mut self) {
+    self.mark_dirty(0, 0, self.w as i32, self.h as i32);
+  }
+
+  /// Send what was drawn since the last call to the screen.
+  ///
+  /// Only the bounding box of the changes is sent (with
+  /// `extern_set_lcd_region`), so a frame that moves a sprite or updates a
+  /// score costs a fraction of a full 115 KB frame. Does nothing if nothing
+  /// was drawn. The canvas must be screen-sized, as with
+  /// `extern_set_lcd_buffer`.
+  pub fn present(&mut self) {
+    let Some([x0, y0, x1, y1]) = self.dirty.take() else {
+      return;
+    };
+    if x0 == 0 && y0 == 0 && x1 == SCREEN_WIDTH && y1 == SCREEN_HEIGHT && self.w == SCREEN_WIDTH {
+      unsafe { extern_set_lcd_buffer(self.buf.as_ptr()) };
+      return;
+    }
+    let start = (y0 * self.w + x0) * 2;
+    unsafe {
+      extern_set_lcd_region(
+        x0 as u32,
+        y0 as u32,
+        (x1 - x0) as u32,
+        (y1 - y0) as u32,
+        self.buf[start..].as_ptr(),
+        (self.w * 2) as u32,
+      )
+    };
+  }
+
+  /// Grow the dirty area to cover `[x0, x1) x [y0, y1)`, clipped to the canvas.
+  #[inline]
+  pub(super) fn mark_dirty(&mut self, x0: i32, y0: i32, x1: i32, y1: i32) {
+    let x0 = x0.max(0) as usize;
+    let y0 = y0.max(0) as usize;
+    let x1 = x1.min(self.w as i32).max(0) as usize;
+    let y1 = y1.min(self.h as i32).max(0) as usize;
+    if x0 >= x1 || y0 >= y1 {
+      return;
+    }
+    self.dirty = Some(match self.dirty {
+      Some([dx0, dy0, dx1, dy1]) => [dx0.min(x0), dy0.min(y0), dx1.max(x1), dy1.max(y1)],
+      None => [x0, y0, x1, y1],
+    });
+  }
+
   /// Fill the whole canvas with `color`.
   pub fn clear(&mut self, color: Rgb565) {
+    self.invalidate();
     let hi = (color.0 >> 8) as u8;
     let lo = (color.0 & 0xFF) as u8;
     let mut i = 0;
@@ -157,6 +230,14 @@ impl<'a> Canvas<'a> {
   /// Set a single pixel (no-op if out of bounds).
   #[inline]
   pub fn set_pixel(&mut self, x: i32, y: i32, color: Rgb565) {
+    self.mark_dirty(x, y, x + 1, y + 1);
+    self.plot(x, y, color);
+  }
+
+  /// [`set_pixel`](Canvas::set_pixel) for callers that have already marked
+  /// the area dirty.
+  #[inline]
+  pub(super) fn plot(&mut self, x: i32, y: i32, color: Rgb565) {
     if x < 0 || y < 0 || x >= self.w as i32 || y >= self.h as i32 {
       return;
     }
@@ -176,6 +257,7 @@ impl<'a> Canvas<'a> {
 
   /// Bresenham line from `p0` to `p1`.
   pub fn draw_line(&mut self, p0: Point, p1: Point, color: Rgb565) {
+    self.mark_dirty(p0.x.min(p1.x), p0.y.min(p1.y), p0.x.max(p1.x) + 1, p0.y.max(p1.y) + 1);
     let mut x0 = p0.x;
     let mut y0 = p0.y;
     let x1 = p1.x;
@@ -186,7 +268,7 @@ impl<'a> Canvas<'a> {
     let sy = if y0 < y1 { 1 } else { -1 };
     let mut err = dx + dy;
     loop {
-      self.set_pixel(x0, y0, color);
+      self.plot(x0, y0, color);
       if x0 == x1 && y0 == y1 {
         break;
       }
@@ -224,6 +306,7 @@ impl<'a> Canvas<'a> {
     if x0 >= x1 || y0 >= y1 {
       return;
     }
+    self.mark_dirty(x0 as i32, y0 as i32, x1 as i32, y1 as i32);
     let hi = (color.0 >> 8) as u8;
     let lo = (color.0 & 0xFF) as u8;
     for y in y0..y1 {
@@ -246,18 +329,19 @@ impl<'a> Canvas<'a> {
       self.set_pixel(c.x, c.y, color);
       return;
     }
+    self.mark_dirty(c.x - radius, c.y - radius, c.x + radius + 1, c.y + radius + 1);
     let mut x = radius;
     let mut y = 0;
     let mut err = 1 - radius;
     while x >= y {
-      self.set_pixel(c.x + x, c.y + y, color);
-      self.set_pixel(c.x + y, c.y + x, color);
-      self.set_pixel(c.x - y, c.y + x, color);
-      self.set_pixel(c.x - x, c.y + y, color);
-      self.set_pixel(c.x - x, c.y - y, color);
-      self.set_pixel(c.x - y, c.y - x, color);
-      self.set_pixel(c.x + y, c.y - x, color);
-      self.set_pixel(c.x + x, c.y - y, color);
+      self.plot(c.x + x, c.y + y, color);
+      self.plot(c.x + y, c.y + x, color);
+      self.plot(c.x - y, c.y + x, color);
+      self.plot(c.x - x, c.y + y, color);
+      self.plot(c.x - x, c.y - y, color);
+      self.plot(c.x - y, c.y - x, color);
+      self.plot(c.x + y, c.y - x, color);
+      self.plot(c.x + x, c.y - y, color);
       y += 1;
       if err < 0 {
         err += 2 * y + 1;
@@ -293,10 +377,11 @@ impl<'a> Canvas<'a> {
     let max_x = a.x.max(b.x).max(c.x);
     let min_y = a.y.min(b.y).min(c.y);
     let max_y = a.y.max(b.y).max(c.y);
+    self.mark_dirty(min_x, min_y, max_x + 1, max_y + 1);
     for y in min_y..=max_y {
       for x in min_x..=max_x {
         if point_in_triangle(x, y, a, b, c) {
-          self.set_pixel(x, y, color);
+          self.plot(x, y, color);
         }
       }
     }
@@ -311,6 +396,7 @@ impl<'a> Canvas<'a> {
     if x0 >= x1 || y0 >= y1 {
       return;
     }
+    self.mark_dirty(x0 as i32, y0 as i32, x1 as i32, y1 as i32);
     for yy in y0..y1 {
       let src_row = (yy as i32 - y) as usize;
       let mut si = (src_row * w + (x0 as i32 - x) as usize) * 2;
@@ -0,0 +1,62 @@
+//! `embedded_graphics` support for [`Canvas`] (the `embedded-graphics`
+//! feature).
+//!
+//! `Canvas` implements [`DrawTarget`], so everything in the embedded-graphics
+//! ecosystem draws onto it: mono fonts, styled primitives, images, and
+//! clipping through `DrawTargetExt::clipped`. Drawing this way marks the
+//! canvas dirty like its own methods do, so [`Canvas::present`] still sends
+//! only what changed.
+
+use super::canvas::{Canvas, Rect, Rgb565};
+use core::convert::Infallible;
+use embedded_graphics::{
+  Pixel,
+  draw_target::DrawTarget,
+  geometry::{OriginDimensions, Size},
+  pixelcolor::{self, IntoStorage, raw::RawU16},
+  primitives::Rectangle,
+};
+
+impl From<pixelcolor::Rgb565> for Rgb565 {
+  fn from(color: pixelcolor::Rgb565) -> Rgb565 {
+    Rgb565::from_raw(color.into_storage())
+  }
+}
+
+impl From<Rgb565> for pixelcolor::Rgb565 {
+  fn from(color: Rgb565) -> pixelcolor::Rgb565 {
+    RawU16::new(color.raw()).into()
+  }
+}
+
+impl OriginDimensions for Canvas<'_> {
+  fn size(&self) -> Size {
+    Size::new(self.width() as u32, self.height() as u32)
+  }
+}
+
+impl DrawTarget for Canvas<'_> {
+  type Color = pixelcolor::Rgb565;
+  type Error = Infallible;
+
+  fn draw_iter<I>(&mut self, pixels: I) -> Result<(), Self::Error>
+  where
+    I: IntoIterator<Item = Pixel<Self::Color>>,
+  {
+    for Pixel(point, color) in pixels {
+      self.set_pixel(point.x, point.y, color.into());
+    }
+    Ok(())
+  }
+
+  fn fill_solid(&mut self, area: &Rectangle, color: Self::Color) -> Result<(), Self::Error> {
+    let rect = Rect::new(area.top_left.x, area.top_left.y, area.size.width as i32, area.size.height as i32);
+    self.fill_rect(rect, color.into());
+    Ok(())
+  }
+
+  fn clear(&mut self, color: Self::Color) -> Result<(), Self::Error> {
+    Canvas::clear(self, color.into());
+    Ok(())
+  }
+}
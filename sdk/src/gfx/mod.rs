@@ -2,14 +2,19 @@
 //!
 //! A `core`-only replacement for the `embedded_graphics` surface the SDK
 //! currently uses: a raw RGB565 framebuffer ([`Canvas`]) with pixel, line,
-//! rectangle, circle, triangle, blit and bitmap-text drawing. No `alloc`, no
-//! external crates — the whole module is self-contained, so it keeps working
-//! even if `embedded-graphics` (and friends) are eventually dropped from
-//! `sdk/Cargo.toml`.
+//! rectangle, circle, triangle, blit and bitmap-text drawing, plus sprite
+//! sheets and tilemaps built from PNGs at compile time ([`SpriteSheet`]). No
+//! `alloc`, no external crates — the whole module is self-contained, so it
+//! keeps working even if `embedded-graphics` (and friends) are eventually
+//! dropped from `sdk/Cargo.toml`.
+//!
+//! With the `embedded-graphics` feature, [`Canvas`] is also an
+//! `embedded_graphics` `DrawTarget` (re-exported as [`embedded_graphics`]),
+//! for apps that want its fonts, styled primitives or images.
 //!
 //! The framebuffer is RGB565 in big-endian byte order — identical to the
-//! layout the host LCD expects — so a canvas can be handed straight to
-//! `extern_set_lcd_buffer`:
+//! layout the host LCD expects. [`Canvas::present`] sends the area drawn
+//! since the last frame, or the whole buffer when everything changed:
 //!
 //! ```ignore
 //! let buf = Box::new([0u8; 240 * 240 * 2]);
@@ -17,11 +22,18 @@
 //! canvas.clear(Rgb565::BLACK);
 //! canvas.fill_circle(Point::new(120, 120), 40, Rgb565::RED);
 //! canvas.draw_text("hello", 8, 8, Rgb565::WHITE, 2);
-//! unsafe { extern_set_lcd_buffer(canvas.as_ptr()) };
+//! canvas.present();
 //! ```
 
 mod canvas;
+#[cfg(feature = "embedded-graphics")]
+mod draw_target;
 mod font;
+mod sprite;
+
+#[cfg(feature = "embedded-graphics")]
+pub use embedded_graphics;
 
 pub use canvas::{Canvas, Point, Rect, Rgb565, SCREEN_HEIGHT, SCREEN_WIDTH};
 pub use font::{text_height, text_width};
+pub use sprite::{Flip, SpriteSheet, Tilemap};
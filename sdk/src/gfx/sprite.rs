@@ -0,0 +1,351 @@
+//! Sprite sheets and tilemaps.
+//!
+//! Sheets are images converted at build time by `tools/sprite-tool` (see its
+//! docs for the byte format): RGB565 or palettized pixels, cut into equal
+//! frames, with optional transparency. PNGs in `sdk/assets/sprites` are
+//! converted by the SDK's build script and embedded with [`include_sprite!`]:
+//!
+//! ```ignore
+//! static SHIP: SpriteSheet = include_sprite!("ship");
+//!
+//! canvas.draw_frame(&SHIP, frame, x, y, Flip::HORIZONTAL);
+//! ```
+//!
+//! A [`Tilemap`] draws a grid of a sheet's frames, e.g. a scrolling level.
+
+use super::canvas::{Canvas, Rect, Rgb565};
+
+const MAGIC: &[u8; 4] = b"SPR1";
+const HEADER_LEN: usize = 18;
+
+const FORMAT_RGB565: u8 = 0;
+const FORMAT_INDEXED4: u8 = 1;
+const FORMAT_INDEXED8: u8 = 2;
+
+const FLAG_TRANSPARENT: u8 = 1;
+
+/// Embed the sprite sheet converted from `sdk/assets/sprites/<name>[.WxH].png`.
+///
+/// Expands to a constant [`SpriteSheet`], so it can initialise a `static`.
+#[macro_export]
+macro_rules! include_sprite {
+  ($name:literal) => {
+    match $crate::gfx::SpriteSheet::new(include_bytes!(concat!(env!("OUT_DIR"), "/sprites/", $name, ".spr"))) {
+      Some(sheet) => sheet,
+      None => panic!("malformed sprite sheet"),
+    }
+  };
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+enum PixelFormat {
+  Rgb565,
+  Indexed4,
+  Indexed8,
+}
+
+/// An image cut into equal frames, read straight from the converted bytes.
+#[derive(Clone, Copy, Debug)]
+pub struct SpriteSheet<'a> {
+  format: PixelFormat,
+  width: usize,
+  height: usize,
+  frame_w: usize,
+  frame_h: usize,
+  /// The RGB565 color or palette index of transparent pixels.
+  key: Option<u16>,
+  palette: &'a [u8],
+  pixels: &'a [u8],
+}
+
+impl<'a> SpriteSheet<'a> {
+  /// Parse a converted sheet, or `None` if `data` is not a valid one: its
+  /// frames must be non-empty and fit in the image, and every opaque pixel
+  /// of a palettized sheet must index its palette.
+  pub const fn new(data: &'a [u8]) -> Option<SpriteSheet<'a>> {
+    if data.len() < HEADER_LEN || data[0] != MAGIC[0] || data[1] != MAGIC[1] || data[2] != MAGIC[2] || data[3] != MAGIC[3] {
+      return None;
+    }
+    let format = match data[4] {
+      FORMAT_RGB565 => PixelFormat::Rgb565,
+      FORMAT_INDEXED4 => PixelFormat::Indexed4,
+      FORMAT_INDEXED8 => PixelFormat::Indexed8,
+      _ => return None,
+    };
+    let width = read_u16(data, 6) as usize;
+    let height = read_u16(data, 8) as usize;
+    let frame_w = read_u16(data, 10) as usize;
+    let frame_h = read_u16(data, 12) as usize;
+    let key = if data[5] & FLAG_TRANSPARENT != 0 {
+      Some(read_u16(data, 14))
+    } else {
+      None
+    };
+    let palette_len = read_u16(data, 16) as usize * 2;
+
+    let row_bytes = match format {
+      PixelFormat::Rgb565 => width * 2,
+      PixelFormat::Indexed4 => width.div_ceil(2),
+      PixelFormat::Indexed8 => width,
+    };
+    if frame_w == 0 || frame_h == 0 || frame_w > width || frame_h > height {
+      return None;
+    }
+    if data.len() < HEADER_LEN + palette_len + row_bytes * height {
+      return None;
+    }
+    let (_, rest) = data.split_at(HEADER_LEN);
+    let (palette, pixels) = rest.split_at(palette_len);
+    if !indices_in_palette(format, width, height, key, palette_len / 2, pixels) {
+      return None;
+    }
+    Some(SpriteSheet {
+      format,
+      width,
+      height,
+      frame_w,
+      frame_h,
+      key,
+      palette,
+      pixels,
+    })
+  }
+
+  pub fn width(&self) -> usize {
+    self.width
+  }
+
+  pub fn height(&self) -> usize {
+    self.height
+  }
+
+  pub fn frame_width(&self) -> usize {
+    self.frame_w
+  }
+
+  pub fn frame_height(&self) -> usize {
+    self.frame_h
+  }
+
+  /// Frames per row of the sheet.
+  pub fn columns(&self) -> usize {
+    self.width / self.frame_w
+  }
+
+  pub fn frame_count(&self) -> usize {
+    self.columns() * (self.height / self.frame_h)
+  }
+
+  /// Where frame `index` sits in the sheet, counting left to right, then top
+  /// to bottom.
+  pub fn frame(&self, index: usize) -> Rect {
+    let col = index % self.columns();
+    let row = index / self.columns();
+    Rect::new(
+      (col * self.frame_w) as i32,
+      (row * self.frame_h) as i32,
+      self.frame_w as i32,
+      self.frame_h as i32,
+    )
+  }
+
+  /// The color at `(x, y)` of the sheet, or `None` if it is transparent.
+  /// `(x, y)` must be inside the sheet.
+  #[inline]
+  fn pixel(&self, x: usize, y: usize) -> Option<Rgb565> {
+    let raw = match self.format {
+      PixelFormat::Rgb565 => {
+        let i = (y * self.width + x) * 2;
+        let raw = ((self.pixels[i] as u16) << 8) | self.pixels[i + 1] as u16;
+        if Some(raw) == self.key {
+          return None;
+        }
+        return Some(Rgb565::from_raw(raw));
+      }
+      PixelFormat::Indexed4 => {
+        let byte = self.pixels[y * self.width.div_ceil(2) + x / 2];
+        if x % 2 == 0 { byte >> 4 } else { byte & 0x0F }
+      }
+      PixelFormat::Indexed8 => self.pixels[y * self.width + x],
+    };
+    if Some(raw as u16) == self.key {
+      return None;
+    }
+    let i = raw as usize * 2;
+    Some(Rgb565::from_raw(((self.palette[i] as u16) << 8) | self.palette[i + 1] as u16))
+  }
+}
+
+/// Whether every pixel of a `width` x `height` sheet that isn't the
+/// transparent `key` is below `palette_entries`. Always true for RGB565.
+const fn indices_in_palette(
+  format: PixelFormat,
+  width: usize,
+  height: usize,
+  key: Option<u16>,
+  palette_entries: usize,
+  pixels: &[u8],
+) -> bool {
+  if matches!(format, PixelFormat::Rgb565) {
+    return true;
+  }
+  let mut y = 0;
+  while y < height {
+    let mut x = 0;
+    while x < width {
+      let index = match format {
+        PixelFormat::Indexed4 => {
+          let byte = pixels[y * width.div_ceil(2) + x / 2];
+          if x % 2 == 0 { byte >> 4 } else { byte & 0x0F }
+        }
+        _ => pixels[y * width + x],
+      };
+      let transparent = match key {
+        Some(key) => key == index as u16,
+        None => false,
+      };
+      if !transparent && index as usize >= palette_entries {
+        return false;
+      }
+      x += 1;
+    }
+    y += 1;
+  }
+  true
+}
+
+const fn read_u16(data: &[u8], offset: usize) -> u16 {
+  u16::from_le_bytes([data[offset], data[offset + 1]])
+}
+
+/// Mirroring applied when drawing a sprite.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub struct Flip {
+  pub horizontal: bool,
+  pub vertical: bool,
+}
+
+impl Flip {
+  pub const NONE: Flip = Flip {
+    horizontal: false,
+    vertical: false,
+  };
+  pub const HORIZONTAL: Flip = Flip {
+    horizontal: true,
+    vertical: false,
+  };
+  pub const VERTICAL: Flip = Flip {
+    horizontal: false,
+    vertical: true,
+  };
+  pub const BOTH: Flip = Flip {
+    horizontal: true,
+    vertical: true,
+  };
+}
+
+/// A grid of tiles drawn from a sheet's frames, stored row by row as frame
+/// indices. [`Tilemap::EMPTY`] leaves a cell undrawn.
+#[derive(Clone, Copy, Debug)]
+pub struct Tilemap<'a> {
+  tiles: &'a SpriteSheet<'a>,
+  cells: &'a [u8],
+  columns: usize,
+}
+
+impl<'a> Tilemap<'a> {
+  /// Cell value for "no tile".
+  pub const EMPTY: u8 = 0xFF;
+
+  /// A map `columns` cells wide; `cells.len() / columns` rows tall.
+  pub const fn new(tiles: &'a SpriteSheet<'a>, cells: &'a [u8], columns: usize) -> Tilemap<'a> {
+    Tilemap { tiles, cells, columns }
+  }
+
+  pub fn columns(&self) -> usize {
+    self.columns
+  }
+
+  pub fn rows(&self) -> usize {
+    self.cells.len() / self.columns
+  }
+
+  /// The tile at cell `(col, row)`, or `None` if the cell is empty or outside
+  /// the map. Handy for collision checks.
+  pub fn tile_at(&self, col: i32, row: i32) -> Option<u8> {
+    if col < 0 || row < 0 || col as usize >= self.columns || row as usize >= self.rows() {
+      return None;
+    }
+    match self.cells[row as usize * self.columns + col as usize] {
+      Self::EMPTY => None,
+      tile => Some(tile),
+    }
+  }
+
+  /// The tile under pixel `(x, y)` of the map.
+  pub fn tile_at_pixel(&self, x: i32, y: i32) -> Option<u8> {
+    self.tile_at(x.div_euclid(self.tiles.frame_w as i32), y.div_euclid(self.tiles.frame_h as i32))
+  }
+}
+
+impl<'a> Canvas<'a> {
+  /// Draw the `src` area of `sheet` with its top-left corner at `(x, y)`,
+  /// skipping transparent pixels. `src` is clipped to the sheet.
+  pub fn draw_sprite(&mut self, sheet: &SpriteSheet, src: Rect, x: i32, y: i32, flip: Flip) {
+    let sx0 = src.x.max(0);
+    let sy0 = src.y.max(0);
+    let sx1 = (src.x + src.w).min(sheet.width as i32);
+    let sy1 = (src.y + src.h).min(sheet.height as i32);
+    if sx0 >= sx1 || sy0 >= sy1 {
+      return;
+    }
+    let (w, h) = (sx1 - sx0, sy1 - sy0);
+    // Only the on-screen part of the destination is visited.
+    let dx0 = (-x).max(0);
+    let dy0 = (-y).max(0);
+    let dx1 = w.min(self.width() as i32 - x);
+    let dy1 = h.min(self.height() as i32 - y);
+    if dx0 >= dx1 || dy0 >= dy1 {
+      return;
+    }
+    self.mark_dirty(x + dx0, y + dy0, x + dx1, y + dy1);
+    for dy in dy0..dy1 {
+      let sy = sy0 + if flip.vertical { h - 1 - dy } else { dy };
+      for dx in dx0..dx1 {
+        let sx = sx0 + if flip.horizontal { w - 1 - dx } else { dx };
+        if let Some(color) = sheet.pixel(sx as usize, sy as usize) {
+          self.plot(x + dx, y + dy, color);
+        }
+      }
+    }
+  }
+
+  /// Draw frame `index` of `sheet` with its top-left corner at `(x, y)`.
+  pub fn draw_frame(&mut self, sheet: &SpriteSheet, index: usize, x: i32, y: i32, flip: Flip) {
+    self.draw_sprite(sheet, sheet.frame(index), x, y, flip);
+  }
+
+  /// Draw the part of `map` seen through the canvas when its top-left corner
+  /// is scrolled to `(scroll_x, scroll_y)` of the map.
+  pub fn draw_tilemap(&mut self, map: &Tilemap, scroll_x: i32, scroll_y: i32) {
+    let tile_w = map.tiles.frame_w as i32;
+    let tile_h = map.tiles.frame_h as i32;
+    let first_col = scroll_x.div_euclid(tile_w);
+    let first_row = scroll_y.div_euclid(tile_h);
+    let last_col = (scroll_x + self.width() as i32 - 1).div_euclid(tile_w);
+    let last_row = (scroll_y + self.height() as i32 - 1).div_euclid(tile_h);
+    for row in first_row..=last_row {
+      for col in first_col..=last_col {
+        if let Some(tile) = map.tile_at(col, row) {
+          self.draw_frame(
+            map.tiles,
+            tile as usize,
+            col * tile_w - scroll_x,
+            row * tile_h - scroll_y,
+            Flip::NONE,
+          );
+        }
+      }
+    }
+  }
+}
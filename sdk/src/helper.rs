@@ -1,7 +1,10 @@
 use crate::protocol::{
-  HostIpcMessage, WasmIpcMessage, extern_get_millis, extern_read_host_ipc_message, extern_set_gpio, extern_set_lcd_buffer,
-  extern_write_stdout, extern_write_wasm_ipc_message,
+  Capabilities, HOST_ABI_VERSION, HostIpcMessage, LedColour, LedEffect, NUM_LEDS, WasmIpcMessage, WireFormat, extern_get_abi_version,
+  extern_get_capabilities, extern_get_micros, extern_get_millis, extern_get_unix_micros, extern_read_host_ipc_message, extern_set_gpio,
+  extern_set_lcd_buffer, extern_set_led, extern_set_led_effect, extern_set_leds, extern_try_write_wasm_ipc_message, extern_write_stdout,
+  extern_write_wasm_ipc_message,
 };
+use crate::tasks::yield_now;
 use alloc::vec;
 
 #[macro_export]
@@ -58,27 +61,122 @@ pub fn set_lcd_buffer(buf: *const u8) {
   unsafe { extern_set_lcd_buffer(buf) };
 }
 
+/// Drive a hexpansion high-speed GPIO low (`val` 0) or high. On the badge any
+/// other pin traps the app; see `Capabilities::GPIO` for hosts without pins.
 pub fn set_gpio(pin: i32, val: i32) {
   unsafe { extern_set_gpio(pin, val) };
 }
 
+/// Milliseconds since the host started. Wraps after about 49 days; use
+/// [`get_micros`] where that matters.
 pub fn get_millis() -> u32 {
   unsafe { extern_get_millis() }
 }
 
+/// Microseconds since the host started. Never wraps or goes backwards.
+pub fn get_micros() -> u64 {
+  unsafe { extern_get_micros() }
+}
+
+/// The current UNIX time in microseconds, or `None` if the host doesn't know
+/// the time of day yet (the badge learns it once online).
+pub fn unix_time_micros() -> Option<u64> {
+  match unsafe { extern_get_unix_micros() } {
+    0 => None,
+    micros => Some(micros),
+  }
+}
+
+/// Set one ring LED. The host sends all LED changes made during a `tick` to
+/// the ring together, and ignores out-of-range indices.
+pub fn set_led(index: u32, colour: LedColour) {
+  unsafe { extern_set_led(index, colour.to_packed()) };
+}
+
+/// Set every ring LED at once, LED 0 first.
+pub fn set_leds(leds: &[LedColour; NUM_LEDS]) {
+  let mut rgb = [0u8; NUM_LEDS * 3];
+  for (chunk, led) in rgb.chunks_exact_mut(3).zip(leds) {
+    chunk.copy_from_slice(&[led.r, led.g, led.b]);
+  }
+  unsafe { extern_set_leds(rgb.as_ptr()) };
+}
+
+/// Hand the ring over to one of the host's built-in animated effects.
+/// `colour` is ignored by effects with their own palette (Rainbow, Fire, Off).
+pub fn set_led_effect(effect: LedEffect, colour: LedColour) {
+  unsafe { extern_set_led_effect(effect as u32, colour.to_packed()) };
+}
+
+/// Host ABI version this SDK was built against, declared in a custom section
+/// so firmware too old to run the app can say so before loading it.
+#[used]
+#[unsafe(link_section = "badge_abi_version")]
+static ABI_VERSION: [u8; 4] = HOST_ABI_VERSION.to_le_bytes();
+
+/// Describe the app for the Files and App Store screens and the web UI, in a
+/// custom section the host reads without running the app. Every field is
+/// optional; `abi_version` defaults to the SDK's `HOST_ABI_VERSION`. The icon
+/// is 20x20 RGB565, big-endian like the `Canvas` framebuffer. `limits` asks
+/// for more (or less) memory or fuel per tick than the host's defaults.
+///
+/// ```ignore
+/// app_metadata! {
+///   name: "Snake",
+///   version: "1.0.0",
+///   author: "Rustagon",
+///   description: "Eat the apples, miss your tail",
+///   icon: Some(include_bytes!("../../assets/snake_icon.raw")),
+///   permissions: Capabilities::STORAGE,
+///   limits: AppLimits { memory_kb: 3072, ..AppLimits::DEFAULT },
+/// }
+/// ```
+#[macro_export]
+macro_rules! app_metadata {
+  ($($field:ident: $value:expr),* $(,)?) => {
+    const _: () = {
+      const INFO: $crate::protocol::AppInfo<'static> = $crate::protocol::AppInfo {
+        $($field: $value,)*
+        ..$crate::protocol::AppInfo::DEFAULT
+      };
+      // The section name must match `wasm_protocol::APP_METADATA_SECTION`.
+      #[used]
+      #[unsafe(link_section = "badge_app_metadata")]
+      static APP_METADATA: [u8; INFO.encoded_len()] = INFO.encode();
+    };
+  };
+}
+
+/// ABI version of the host running the app.
+pub fn host_abi_version() -> u32 {
+  unsafe { extern_get_abi_version() }
+}
+
+/// Optional features the host backs, e.g. whether `set_gpio` drives real pins
+/// or storage survives a restart.
+pub fn host_capabilities() -> Capabilities {
+  Capabilities(unsafe { extern_get_capabilities() })
+}
+
+/// Wire format this SDK speaks to the host.
+const WIRE_FORMAT: WireFormat = WireFormat::Postcard;
+
+/// Declares [`WIRE_FORMAT`] to the host. The symbol name must match
+/// `wasm_protocol::WIRE_FORMAT_EXPORT`; apps built without it are spoken to
+/// in JSON.
+#[unsafe(no_mangle)]
+pub extern "C" fn ipc_wire_format() -> u32 {
+  WIRE_FORMAT as u32
+}
+
 pub fn receive_host_ipc_message(host_msg_id: u32, host_msg_size: u32) -> HostIpcMessage {
   let mut host_msg_bytes = vec![0u8; host_msg_size as usize];
 
   unsafe { extern_read_host_ipc_message(host_msg_id, host_msg_bytes.as_mut_ptr()) };
 
-  // match postcard::from_bytes::<HostIpcMessage>(&host_msg_bytes) {
-  //   Ok(host_msg) => host_msg,
-  //   Err(err) => print_and_panic!("tick: Error receiving message: {err}"),
-  // }
-
-  let host_msg = match serde_json_core::from_slice::<HostIpcMessage>(&host_msg_bytes) {
-    Ok((host_msg, _)) => host_msg,
-    Err(err) => print_and_panic!("tick: Error receiving message: {err}"),
+  let host_msg = match WIRE_FORMAT.decode::<HostIpcMessage>(&host_msg_bytes) {
+    Ok(host_msg) => host_msg,
+    Err(err) => print_and_panic!("tick: Error receiving message: {err:?}"),
   };
 
   debug_print!("tick: received host msg id={host_msg_id} size={host_msg_size}: {host_msg:?}");
@@ -86,10 +184,23 @@ pub fn receive_host_ipc_message(host_msg_id: u32, host_msg_size: u32) -> HostIpc
   host_msg
 }
 
+/// Send a message to the host, returning its id. The host stops the app if
+/// its queue is full; async code should use [`queue_wasm_ipc_message`].
 pub fn send_wasm_ipc_message(wasm_ipc_message: WasmIpcMessage) -> u32 {
-  // let wasm_msg_bytes = postcard::to_allocvec(&wasm_ipc_message).unwrap();
-
-  let wasm_msg_bytes = serde_json::to_vec(&wasm_ipc_message).unwrap();
+  let wasm_msg_bytes = log_error!(WIRE_FORMAT.encode(&wasm_ipc_message), "send_wasm_ipc_message");
 
   unsafe { extern_write_wasm_ipc_message(wasm_msg_bytes.as_ptr(), wasm_msg_bytes.len() as u32) }
 }
+
+/// Send a message to the host, waiting a tick at a time while its queue is
+/// full. Returns the message's id.
+pub async fn queue_wasm_ipc_message(wasm_ipc_message: WasmIpcMessage) -> u32 {
+  let wasm_msg_bytes = log_error!(WIRE_FORMAT.encode(&wasm_ipc_message), "queue_wasm_ipc_message");
+
+  loop {
+    match unsafe { extern_try_write_wasm_ipc_message(wasm_msg_bytes.as_ptr(), wasm_msg_bytes.len() as u32) } {
+      0 => yield_now().await,
+      id => return id,
+    }
+  }
+}
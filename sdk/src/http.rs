@@ -1,61 +1,160 @@
 extern crate alloc;
 
-use crate::helper::send_wasm_ipc_message;
+use crate::helper::queue_wasm_ipc_message;
 use crate::protocol::{HostIpcMessage, HttpRequest, HttpResponseMeta, WasmIpcMessage};
-use crate::tasks::get_next_host_message;
-use alloc::string::{String, ToString as _};
+use crate::sleep::Sleep;
+use crate::tasks::{HostIpcSubscriber, subscribe_host_messages};
 use alloc::vec::Vec;
+use core::future::{Future, poll_fn};
+use core::pin::{Pin, pin};
 use core::str::from_utf8;
+use core::task::{Context, Poll, ready};
+use embassy_sync::pubsub::WaitResult;
+use futures_core::Stream;
+
+/// Timeout used by [`make_http_request`].
+pub const DEFAULT_TIMEOUT_MS: u32 = 30_000;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum HttpError {
+  /// The host could not make the request, or it failed part-way through.
+  Failed,
+  /// The response did not complete within the timeout.
+  TimedOut,
+  /// Part of the response was dropped because the app did not read it in
+  /// time.
+  Lagged,
+  /// The body is not valid UTF-8 (from [`HttpResponse::text`]).
+  InvalidUtf8,
+  /// The host already has as many requests from this app in flight as it
+  /// allows, or too many tasks are waiting on host messages. Retry later.
+  Busy,
+}
 
 pub struct HttpResponse {
   pub meta: HttpResponseMeta,
-  pub body: String,
+  pub body: Vec<u8>,
 }
 
-pub async fn make_http_request(req: HttpRequest) -> HttpResponse {
-  let req_id = send_wasm_ipc_message(WasmIpcMessage::HttpRequest(req));
-  debug_print!("make_http_request: sent request, req_id={req_id}");
+impl HttpResponse {
+  /// The body as text.
+  pub fn text(&self) -> Result<&str, HttpError> {
+    from_utf8(&self.body).map_err(|_| HttpError::InvalidUtf8)
+  }
+}
 
-  let mut response_meta: Option<HttpResponseMeta> = None;
-  let mut response_body: Vec<u8> = Vec::new();
+/// A response whose body is read a chunk at a time as the host receives it,
+/// so large or binary downloads never have to fit in memory at once. Read it
+/// with [`next_chunk`](Self::next_chunk), or as a [`Stream`] of chunks that
+/// ends after the last one or the first error.
+pub struct HttpResponseStream {
+  req_id: u32,
+  host_messages: HostIpcSubscriber,
+  /// Fires once the whole request has timed out.
+  timeout: Sleep,
+  meta: HttpResponseMeta,
+  done: bool,
+}
 
-  loop {
-    match get_next_host_message().await {
-      (res_id, HostIpcMessage::HttpResponseMeta(meta)) => {
-        debug_print!("make_http_request: got meta res_id={res_id} req_id={req_id} status={}", meta.status);
-        if res_id != req_id {
-          continue;
-        }
+impl HttpResponseStream {
+  pub fn meta(&self) -> &HttpResponseMeta {
+    &self.meta
+  }
 
-        response_meta = Some(meta);
-      }
-      (res_id, HostIpcMessage::HttpResponseBody(body)) => {
-        debug_print!("make_http_request: got body res_id={res_id} req_id={req_id} len={}", body.len());
-        if res_id != req_id {
-          continue;
-        }
+  /// The next chunk of the body, or `None` once it is complete.
+  pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, HttpError> {
+    poll_fn(|cx| Pin::new(&mut *self).poll_next(cx)).await.transpose()
+  }
 
-        response_body.extend(body);
-      }
-      (res_id, HostIpcMessage::HttpResponseComplete) => {
-        debug_print!("make_http_request: got complete res_id={res_id} req_id={req_id}");
-        if res_id != req_id {
-          continue;
+  /// Read the rest of the body into memory.
+  pub async fn collect(mut self) -> Result<HttpResponse, HttpError> {
+    let mut body = Vec::new();
+    while let Some(chunk) = self.next_chunk().await? {
+      body.extend(chunk);
+    }
+    Ok(HttpResponse { meta: self.meta, body })
+  }
+
+  fn fail(&mut self, err: HttpError) -> HttpError {
+    self.done = true;
+    err
+  }
+}
+
+impl Stream for HttpResponseStream {
+  type Item = Result<Vec<u8>, HttpError>;
+
+  fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+    let this = self.get_mut();
+    while !this.done {
+      match ready!(poll_for_request(&mut this.host_messages, this.req_id, &mut this.timeout, cx)) {
+        Ok(HostIpcMessage::HttpResponseBody(chunk)) => return Poll::Ready(Some(Ok(chunk))),
+        Ok(HostIpcMessage::HttpResponseComplete) => this.done = true,
+        Ok(HostIpcMessage::HttpError) => return Poll::Ready(Some(Err(this.fail(HttpError::Failed)))),
+        Ok(_other) => {
+          debug_print!("HttpResponseStream: unexpected message for req_id={}: {_other:?}", this.req_id);
         }
+        Err(err) => return Poll::Ready(Some(Err(this.fail(err)))),
+      }
+    }
+    Poll::Ready(None)
+  }
+}
+
+/// Send `req` and wait for the response headers. The whole response must
+/// arrive within `timeout_ms`.
+pub async fn stream_http_request(req: HttpRequest, timeout_ms: u32) -> Result<HttpResponseStream, HttpError> {
+  let mut host_messages = subscribe_host_messages().ok_or(HttpError::Busy)?;
+  let mut timeout = Sleep::new(timeout_ms);
+  let req_id = queue_wasm_ipc_message(WasmIpcMessage::HttpRequest(req)).await;
+  debug_print!("stream_http_request: sent request, req_id={req_id}");
 
-        return HttpResponse {
-          meta: response_meta.unwrap(),
-          body: match from_utf8(&response_body) {
-            Ok(body) => body.to_string(),
-            Err(err) => {
-              print_and_panic!("make_http_request: Could not decode body: {err:?}")
-            }
-          },
-        };
+  loop {
+    match poll_fn(|cx| poll_for_request(&mut host_messages, req_id, &mut timeout, cx)).await? {
+      HostIpcMessage::HttpResponseMeta(meta) => {
+        debug_print!("stream_http_request: got meta req_id={req_id} status={}", meta.status);
+        return Ok(HttpResponseStream {
+          req_id,
+          host_messages,
+          timeout,
+          meta,
+          done: false,
+        });
       }
-      (_res_id, _other) => {
-        debug_print!("make_http_request: got unexpected message res_id={_res_id}: {_other:?}");
+      HostIpcMessage::HttpError => return Err(HttpError::Failed),
+      HostIpcMessage::Busy => return Err(HttpError::Busy),
+      _other => {
+        debug_print!("stream_http_request: unexpected message for req_id={req_id}: {_other:?}");
       }
     }
   }
 }
+
+/// Send `req` and read the whole response into memory, giving up after
+/// [`DEFAULT_TIMEOUT_MS`].
+pub async fn make_http_request(req: HttpRequest) -> Result<HttpResponse, HttpError> {
+  make_http_request_with_timeout(req, DEFAULT_TIMEOUT_MS).await
+}
+
+pub async fn make_http_request_with_timeout(req: HttpRequest, timeout_ms: u32) -> Result<HttpResponse, HttpError> {
+  stream_http_request(req, timeout_ms).await?.collect().await
+}
+
+/// The next host message for request `req_id`. While there is none, the
+/// task's waker is registered with the host message channel and the timeout,
+/// so the app can go idle until a message arrives or the request times out.
+fn poll_for_request(
+  host_messages: &mut HostIpcSubscriber,
+  req_id: u32,
+  timeout: &mut Sleep,
+  cx: &mut Context<'_>,
+) -> Poll<Result<HostIpcMessage, HttpError>> {
+  loop {
+    match pin!(host_messages.next_message()).poll(cx) {
+      Poll::Ready(WaitResult::Message((id, msg))) if id == req_id => return Poll::Ready(Ok(msg)),
+      Poll::Ready(WaitResult::Message(_)) => {}
+      Poll::Ready(WaitResult::Lagged(_)) => return Poll::Ready(Err(HttpError::Lagged)),
+      Poll::Pending => return Pin::new(timeout).poll(cx).map(|()| Err(HttpError::TimedOut)),
+    }
+  }
+}
@@ -0,0 +1,235 @@
+//! Button and keyboard input.
+//!
+//! [`Buttons`] tracks all 23 [`HexButton`]s for games: call
+//! [`Buttons::update`] once per frame and ask which buttons are held or
+//! changed since the last frame, or `await` [`Buttons::next_event`] for
+//! event-driven apps. Held buttons can auto-repeat.
+//!
+//! Keyboard input comes from a keyboard hexpansion on the badge or the
+//! computer's keyboard in the desktop build. Every key press and release
+//! arrives as a [`HostIpcMessage::Key`]. Arrows and Enter are the exception:
+//! they arrive as `HexButton` presses, like the badge's own buttons. Escape and
+//! Tab arrive both ways (as `HexF`/`HexE` and as key events).
+
+use crate::helper::get_millis;
+pub use crate::protocol::HexButton;
+use crate::protocol::HostIpcMessage;
+pub use crate::protocol::{KeyCode, KeyEvent, KeyModifiers};
+use crate::sleep::sleep;
+use crate::tasks::{HostIpcSubscriber, get_next_host_message, subscribe_host_messages};
+use core::future::{Future, poll_fn};
+use core::pin::pin;
+use core::task::Poll;
+
+/// Auto-repeat timing for held buttons.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Repeat {
+  /// How long a button is held before it first repeats.
+  pub delay_ms: u32,
+  /// Time between repeats after that.
+  pub interval_ms: u32,
+}
+
+/// A change to a button, from [`Buttons::next_event`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ButtonEvent {
+  Pressed(HexButton),
+  /// The button has been held long enough to repeat (see [`Repeat`]).
+  Repeated(HexButton),
+  Released(HexButton),
+}
+
+/// The state of every [`HexButton`], built from the host's button messages.
+///
+/// Buttons are always named by their press variant (`HexButton::Left`, not
+/// `LeftReleased`). A `Buttons` holds one of the app's host message
+/// subscriptions for as long as it lives.
+pub struct Buttons {
+  subscriber: HostIpcSubscriber,
+  repeat: Option<Repeat>,
+  /// One bit per button, by [`HexButton::index`].
+  down: u32,
+  pressed: u32,
+  released: u32,
+  repeated: u32,
+  /// When each held button next repeats, in host milliseconds.
+  next_repeat: [u32; HexButton::COUNT],
+}
+
+impl Buttons {
+  /// Start tracking buttons, without auto-repeat. Panics if the app already
+  /// holds every host message subscription.
+  pub fn new() -> Self {
+    let Some(subscriber) = subscribe_host_messages() else {
+      panic!("Buttons::new: no host message subscriber left");
+    };
+    Self {
+      subscriber,
+      repeat: None,
+      down: 0,
+      pressed: 0,
+      released: 0,
+      repeated: 0,
+      next_repeat: [0; HexButton::COUNT],
+    }
+  }
+
+  /// Repeat held buttons: first after `delay_ms`, then every `interval_ms`.
+  pub fn with_repeat(mut self, delay_ms: u32, interval_ms: u32) -> Self {
+    self.repeat = Some(Repeat { delay_ms, interval_ms });
+    self
+  }
+
+  /// Take in the button messages that arrived since the last call, starting a
+  /// new frame. A press and release that both arrived since then still count
+  /// as [`just_pressed`](Self::just_pressed), so short taps are not lost.
+  pub fn update(&mut self) {
+    self.pressed = 0;
+    self.released = 0;
+    self.repeated = 0;
+    let now = get_millis();
+    while let Some((_, message)) = self.subscriber.try_next_message_pure() {
+      self.apply(message, now);
+    }
+    while let Some(button) = self.due_repeat(now) {
+      self.repeated |= 1 << button.index();
+    }
+  }
+
+  /// Whether `button` is held down.
+  pub fn is_down(&self, button: HexButton) -> bool {
+    self.down & (1 << button.index()) != 0
+  }
+
+  /// Whether `button` was pressed this frame.
+  pub fn just_pressed(&self, button: HexButton) -> bool {
+    self.pressed & (1 << button.index()) != 0
+  }
+
+  /// Whether `button` was released this frame.
+  pub fn just_released(&self, button: HexButton) -> bool {
+    self.released & (1 << button.index()) != 0
+  }
+
+  /// Whether `button` was pressed or auto-repeated this frame, for actions
+  /// that should repeat while the button is held (moving a cursor or piece).
+  pub fn just_pressed_or_repeated(&self, button: HexButton) -> bool {
+    (self.pressed | self.repeated) & (1 << button.index()) != 0
+  }
+
+  /// Whether any button was pressed this frame.
+  pub fn any_just_pressed(&self) -> bool {
+    self.pressed != 0
+  }
+
+  /// Wait for the next press, release or auto-repeat. Events are not seen by
+  /// [`update`](Self::update), so use one or the other.
+  pub async fn next_event(&mut self) -> ButtonEvent {
+    loop {
+      let now = get_millis();
+      if let Some(button) = self.due_repeat(now) {
+        return ButtonEvent::Repeated(button);
+      }
+      let message = match self.next_repeat_in(now) {
+        None => Some(self.subscriber.next_message_pure().await),
+        Some(wait_ms) => {
+          let mut message = pin!(self.subscriber.next_message_pure());
+          let mut timeout = pin!(sleep(wait_ms));
+          poll_fn(|cx| match message.as_mut().poll(cx) {
+            Poll::Ready(message) => Poll::Ready(Some(message)),
+            Poll::Pending => timeout.as_mut().poll(cx).map(|()| None),
+          })
+          .await
+        }
+      };
+      if let Some(event) = message.and_then(|(_, message)| self.apply(message, get_millis())) {
+        return event;
+      }
+    }
+  }
+
+  fn apply(&mut self, message: HostIpcMessage, now: u32) -> Option<ButtonEvent> {
+    let HostIpcMessage::HexButton(event) = message else {
+      return None;
+    };
+    let index = event.index();
+    let button = HexButton::ALL[index];
+    if event.is_release() {
+      self.down &= !(1 << index);
+      self.released |= 1 << index;
+      Some(ButtonEvent::Released(button))
+    } else {
+      self.down |= 1 << index;
+      self.pressed |= 1 << index;
+      if let Some(repeat) = self.repeat {
+        self.next_repeat[index] = now.wrapping_add(repeat.delay_ms);
+      }
+      Some(ButtonEvent::Pressed(button))
+    }
+  }
+
+  /// A held button whose repeat is due at `now`, scheduling its next one.
+  fn due_repeat(&mut self, now: u32) -> Option<HexButton> {
+    let repeat = self.repeat?;
+    let index = (0..HexButton::COUNT).find(|&index| self.down & (1 << index) != 0 && is_due(self.next_repeat[index], now))?;
+    // Catch up from `now`, not the missed deadline, so a stalled frame
+    // repeats once rather than in a burst.
+    self.next_repeat[index] = now.wrapping_add(repeat.interval_ms.max(1));
+    Some(HexButton::ALL[index])
+  }
+
+  /// Milliseconds until the next held button repeats, if any will.
+  fn next_repeat_in(&self, now: u32) -> Option<u32> {
+    if self.repeat.is_none() {
+      return None;
+    }
+    (0..HexButton::COUNT)
+      .filter(|&index| self.down & (1 << index) != 0)
+      .map(|index| self.next_repeat[index].wrapping_sub(now))
+      .min()
+  }
+}
+
+impl Default for Buttons {
+  fn default() -> Self {
+    Self::new()
+  }
+}
+
+/// Whether a deadline of `at` has passed at `now`, allowing for the host
+/// clock wrapping.
+fn is_due(at: u32, now: u32) -> bool {
+  now.wrapping_sub(at) as i32 >= 0
+}
+
+/// Wait for the next key event.
+pub async fn next_key() -> KeyEvent {
+  loop {
+    if let HostIpcMessage::Key(event) = get_next_host_message().await.1 {
+      return event;
+    }
+  }
+}
+
+/// The character a key press types, using the same layout as the badge's
+/// built-in apps. `None` for releases and for keys that do not type anything
+/// (modifiers, Backspace, Escape, function keys).
+pub fn key_char(event: &KeyEvent) -> Option<char> {
+  if !event.pressed {
+    return None;
+  }
+  let ch = match event.code {
+    KeyCode::Space => ' ',
+    KeyCode::Tab => '\t',
+    code => code.to_char(event.modifiers.contains(KeyModifiers::SHIFT))?,
+  };
+  // Caps Lock flips letters only, so Shift still types lowercase under it.
+  if event.modifiers.contains(KeyModifiers::CAPS_LOCK) && ch.is_ascii_alphabetic() {
+    return Some(if ch.is_ascii_uppercase() {
+      ch.to_ascii_lowercase()
+    } else {
+      ch.to_ascii_uppercase()
+    });
+  }
+  Some(ch)
+}
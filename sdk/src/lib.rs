@@ -8,9 +8,14 @@ pub mod allocator;
 pub mod fmt;
 pub mod gfx;
 pub mod http;
+pub mod input;
+pub mod net;
 pub mod panic;
 pub mod protocol;
+pub mod random;
+pub mod screen;
 pub mod sleep;
+pub mod storage;
 pub mod tasks;
 pub mod trig;
 
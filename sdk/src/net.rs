@@ -0,0 +1,174 @@
+//! Raw TCP streams and UDP sockets, opened by the host on the app's behalf.
+//!
+//! Check for [`Capabilities::SOCKETS`](crate::protocol::Capabilities) with
+//! [`host_capabilities`](crate::helper::host_capabilities) first: hosts
+//! without socket support stop an app that sends socket messages. An app may
+//! have one TCP stream and one UDP socket open at a time; anything still open
+//! when the app exits is closed by the host.
+
+use crate::helper::queue_wasm_ipc_message;
+use crate::protocol::{HostIpcMessage, WasmIpcMessage};
+use crate::tasks::{HostIpcSubscriber, subscribe_host_messages};
+use alloc::string::String;
+use alloc::vec::Vec;
+
+pub use crate::protocol::Datagram;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SocketError {
+  /// The host could not open the socket (no network, DNS failure, refused,
+  /// or one is already open), or it failed later.
+  Failed,
+  /// The socket is closed.
+  Closed,
+  /// Too many tasks are already waiting on host messages.
+  Busy,
+}
+
+/// State shared by both socket kinds: the id of the message that opened the
+/// socket, which the host tags everything about it with.
+struct Socket {
+  id: u32,
+  host_messages: HostIpcSubscriber,
+  open: bool,
+}
+
+impl Socket {
+  /// Send `open` and wait for the host to accept it.
+  async fn open(open: WasmIpcMessage) -> Result<Self, SocketError> {
+    let host_messages = subscribe_host_messages().ok_or(SocketError::Busy)?;
+    let id = queue_wasm_ipc_message(open).await;
+    let mut socket = Socket {
+      id,
+      host_messages,
+      open: true,
+    };
+    match socket.next().await {
+      HostIpcMessage::SocketConnected => Ok(socket),
+      _ => Err(SocketError::Failed),
+    }
+  }
+
+  async fn send(&self, msg: WasmIpcMessage) -> Result<(), SocketError> {
+    if !self.open {
+      return Err(SocketError::Closed);
+    }
+    queue_wasm_ipc_message(msg).await;
+    Ok(())
+  }
+
+  /// The next message about this socket, marking it closed if it is the last.
+  async fn next(&mut self) -> HostIpcMessage {
+    loop {
+      let (id, msg) = self.host_messages.next_message_pure().await;
+      if id != self.id {
+        continue;
+      }
+      if matches!(msg, HostIpcMessage::SocketClosed | HostIpcMessage::SocketError) {
+        self.open = false;
+      }
+      return msg;
+    }
+  }
+
+  async fn close(mut self) {
+    if !self.open {
+      return;
+    }
+    queue_wasm_ipc_message(WasmIpcMessage::SocketClose { socket: self.id }).await;
+    while self.open {
+      self.next().await;
+    }
+  }
+}
+
+/// A TCP connection.
+pub struct TcpStream {
+  socket: Socket,
+}
+
+impl TcpStream {
+  /// Connect to `host:port`. `host` may be a name or an IP address.
+  pub async fn connect(host: &str, port: u16) -> Result<TcpStream, SocketError> {
+    let socket = Socket::open(WasmIpcMessage::TcpConnect {
+      host: String::from(host),
+      port,
+    })
+    .await?;
+    Ok(TcpStream { socket })
+  }
+
+  /// Queue `data` to be written to the stream.
+  pub async fn send(&self, data: &[u8]) -> Result<(), SocketError> {
+    self
+      .socket
+      .send(WasmIpcMessage::SocketSend {
+        socket: self.socket.id,
+        data: data.to_vec(),
+      })
+      .await
+  }
+
+  /// The next chunk of received bytes, or `None` once the peer has closed
+  /// the stream.
+  pub async fn recv(&mut self) -> Result<Option<Vec<u8>>, SocketError> {
+    while self.socket.open {
+      match self.socket.next().await {
+        HostIpcMessage::SocketData(data) => return Ok(Some(data)),
+        HostIpcMessage::SocketClosed => return Ok(None),
+        HostIpcMessage::SocketError => return Err(SocketError::Failed),
+        _ => {}
+      }
+    }
+    Ok(None)
+  }
+
+  pub async fn close(self) {
+    self.socket.close().await
+  }
+}
+
+/// A bound UDP socket.
+pub struct UdpSocket {
+  socket: Socket,
+}
+
+impl UdpSocket {
+  /// Bind to local `port`, or any free port if it is 0.
+  pub async fn bind(port: u16) -> Result<UdpSocket, SocketError> {
+    let socket = Socket::open(WasmIpcMessage::UdpBind { port }).await?;
+    Ok(UdpSocket { socket })
+  }
+
+  /// Send `data` as one datagram to `host:port`.
+  pub async fn send_to(&self, host: &str, port: u16, data: &[u8]) -> Result<(), SocketError> {
+    self
+      .socket
+      .send(WasmIpcMessage::SocketSendTo {
+        socket: self.socket.id,
+        datagram: Datagram {
+          host: String::from(host),
+          port,
+          data: data.to_vec(),
+        },
+      })
+      .await
+  }
+
+  /// The next datagram received. Datagrams that arrive while the app is not
+  /// keeping up are dropped.
+  pub async fn recv_from(&mut self) -> Result<Datagram, SocketError> {
+    while self.socket.open {
+      match self.socket.next().await {
+        HostIpcMessage::SocketDatagram(datagram) => return Ok(datagram),
+        HostIpcMessage::SocketError => return Err(SocketError::Failed),
+        _ => {}
+      }
+    }
+    Err(SocketError::Closed)
+  }
+
+  pub async fn close(self) {
+    self.socket.close().await
+  }
+}
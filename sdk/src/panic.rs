@@ -1,8 +1,15 @@
+use crate::protocol::extern_panic;
 use core::panic::PanicInfo;
 
 // Minimal non-formatting panic handler. Keeps the guest small by avoiding
-// core::fmt machinery on the panic path (std's panic handler is not linked).
+// core::fmt machinery on the panic path (std's panic handler is not linked),
+// so only static messages (`panic!("...")`, `unwrap` on `None`, etc.) reach
+// the host; formatted ones are reported by location alone. `extern_panic`
+// stops the app and shows the message on its error screen.
 #[panic_handler]
-fn panic(_info: &PanicInfo<'_>) -> ! {
+fn panic(info: &PanicInfo<'_>) -> ! {
+  let message = info.message().as_str().unwrap_or("");
+  let (file, line) = info.location().map_or(("", 0), |location| (location.file(), location.line()));
+  unsafe { extern_panic(message.as_ptr(), message.len() as u32, file.as_ptr(), file.len() as u32, line) };
   loop {}
 }
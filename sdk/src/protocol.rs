@@ -3,16 +3,39 @@ extern crate core;
 
 #[link(wasm_import_module = "index")]
 unsafe extern "C" {
+  pub fn extern_get_abi_version() -> u32;
+  pub fn extern_get_capabilities() -> u32;
+
   pub fn extern_write_stdout(str: *const u8, len: u32) -> ();
+  pub fn extern_panic(msg: *const u8, msg_len: u32, file: *const u8, file_len: u32, line: u32) -> ();
   pub fn extern_set_gpio(pin: i32, val: i32) -> ();
   pub fn extern_set_lcd_buffer(buf: *const u8) -> ();
+  /// Update a `w` x `h` area at `(x, y)` of the screen from `buf`, whose rows
+  /// are `stride` bytes apart (at most one screen row, 480). Needs host ABI 7.
+  pub fn extern_set_lcd_region(x: u32, y: u32, w: u32, h: u32, buf: *const u8, stride: u32) -> ();
 
   pub fn extern_register_timer(ms: u32) -> i32;
   pub fn extern_check_timer(id: i32) -> i32;
+  pub fn extern_cancel_timer(id: i32) -> ();
+  pub fn extern_idle() -> ();
 
   pub fn extern_get_millis() -> u32;
+  /// Microseconds since boot. Needs host ABI 8, like the two below.
+  pub fn extern_get_micros() -> u64;
+  /// Microseconds since the UNIX epoch, or 0 if the host doesn't know the time.
+  pub fn extern_get_unix_micros() -> u64;
+  pub fn extern_fill_random(buf: *mut u8, len: u32) -> ();
+
+  pub fn extern_set_led(index: u32, rgb: u32) -> ();
+  pub fn extern_set_leds(buf: *const u8) -> ();
+  pub fn extern_set_led_effect(effect: u32, rgb: u32) -> ();
+
+  pub fn extern_storage_read(key: *const u8, key_len: u32, buf: *mut u8, buf_len: u32) -> i32;
+  pub fn extern_storage_write(key: *const u8, key_len: u32, buf: *const u8, buf_len: u32) -> i32;
+  pub fn extern_storage_delete(key: *const u8, key_len: u32) -> i32;
 
   pub fn extern_write_wasm_ipc_message(buf: *const u8, len: u32) -> u32;
+  pub fn extern_try_write_wasm_ipc_message(buf: *const u8, len: u32) -> u32;
   pub fn extern_read_host_ipc_message(host_msg_id: u32, buf: *const u8) -> ();
 }
 
@@ -0,0 +1,78 @@
+//! Random numbers.
+//!
+//! [`fill_random`] reads the host's entropy source (the badge's hardware RNG,
+//! the OS on desktop). Each call goes through the host, so games seed an
+//! [`Rng`] from it once and draw from that:
+//!
+//! ```ignore
+//! let mut rng = Rng::from_entropy();
+//! let x = rng.range(10, 230);
+//! ```
+
+use crate::protocol::extern_fill_random;
+
+/// Fill `buf` with random bytes from the host. Needs host ABI 8.
+pub fn fill_random(buf: &mut [u8]) {
+  unsafe { extern_fill_random(buf.as_mut_ptr(), buf.len() as u32) };
+}
+
+/// A small, fast pseudo-random generator (xorshift64*). Fine for games, not
+/// for keys or anything secret.
+#[derive(Debug, Clone)]
+pub struct Rng {
+  state: u64,
+}
+
+impl Rng {
+  /// A generator that always produces the same numbers for the same `seed`,
+  /// e.g. to replay a level.
+  pub const fn new(seed: u64) -> Rng {
+    // An all-zero state would only ever produce zeros.
+    Rng {
+      state: if seed == 0 { 0x9E37_79B9_7F4A_7C15 } else { seed },
+    }
+  }
+
+  /// A generator seeded from the host's entropy source.
+  pub fn from_entropy() -> Rng {
+    let mut seed = [0u8; 8];
+    fill_random(&mut seed);
+    Rng::new(u64::from_le_bytes(seed))
+  }
+
+  pub fn next_u64(&mut self) -> u64 {
+    let mut x = self.state;
+    x ^= x >> 12;
+    x ^= x << 25;
+    x ^= x >> 27;
+    self.state = x;
+    x.wrapping_mul(0x2545_F491_4F6C_DD1D)
+  }
+
+  pub fn next_u32(&mut self) -> u32 {
+    (self.next_u64() >> 32) as u32
+  }
+
+  /// A number in `0..n`, or 0 if `n` is 0.
+  pub fn below(&mut self, n: u32) -> u32 {
+    ((self.next_u32() as u64 * n as u64) >> 32) as u32
+  }
+
+  /// A number in `lo..hi`, or `lo` if the range is empty.
+  pub fn range(&mut self, lo: i32, hi: i32) -> i32 {
+    if hi <= lo {
+      return lo;
+    }
+    lo.wrapping_add(self.below(hi.wrapping_sub(lo) as u32) as i32)
+  }
+
+  /// A number in `0.0..1.0`.
+  pub fn next_f32(&mut self) -> f32 {
+    (self.next_u32() >> 8) as f32 * (1.0 / (1u32 << 24) as f32)
+  }
+
+  /// `true` with probability `p` (0.0 to 1.0).
+  pub fn chance(&mut self, p: f32) -> bool {
+    self.next_f32() < p
+  }
+}
@@ -0,0 +1,37 @@
+//! Built-in screens, drawn by the host.
+//!
+//! Instead of drawing pixels, an app can hand the host a [`Widget`] — a
+//! menu, a headline, a progress bar, a text frame or a notification — and
+//! have it drawn, animations included, exactly like the badge's own apps.
+//! That needs no font or canvas, so a small utility stays a few KB:
+//!
+//! ```ignore
+//! screen::show(Widget::Menu {
+//!   menu: vec![MenuLine(Icon20::Info, "About".into())],
+//!   selected: 0,
+//!   animation: MenuAnimation::FromRight,
+//!   header: None,
+//! })
+//! .await;
+//! ```
+//!
+//! Showing a screen switches the app to widget mode, in which the host
+//! ignores its raw frames (`set_lcd_buffer`, `Canvas::present`).
+//! [`show_framebuffer`] switches back. Needs host ABI 9
+//! ([`WIDGETS_ABI_VERSION`](crate::protocol::WIDGETS_ABI_VERSION)).
+
+use crate::helper::queue_wasm_ipc_message;
+use crate::protocol::WasmIpcMessage;
+
+pub use crate::protocol::{Icon20, Icon40, MenuAnimation, MenuLine, TextBufferLine, Widget};
+
+/// Have the host draw `widget`, replacing whatever it showed before. A
+/// `Notification` slides in over the current screen and away again.
+pub async fn show(widget: Widget) {
+  queue_wasm_ipc_message(WasmIpcMessage::ShowScreen(widget)).await;
+}
+
+/// Leave widget mode. The screen is blanked until the app's next frame.
+pub async fn show_framebuffer() {
+  queue_wasm_ipc_message(WasmIpcMessage::ShowFramebuffer).await;
+}
@@ -1,16 +1,16 @@
-use crate::protocol::{extern_check_timer, extern_register_timer};
+use crate::protocol::{extern_cancel_timer, extern_check_timer, extern_register_timer};
 use core::future::Future;
 use core::pin::Pin;
 use core::task::{Context, Poll};
 
-struct Sleep {
+pub(crate) struct Sleep {
   timer_id: i32,
   registered: bool,
   duration_ms: u32,
 }
 
 impl Sleep {
-  fn new(ms: u32) -> Self {
+  pub(crate) fn new(ms: u32) -> Self {
     Self {
       timer_id: -1,
       registered: false,
@@ -22,7 +22,7 @@ impl Sleep {
 impl Future for Sleep {
   type Output = ();
 
-  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Self::Output> {
+  fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<Self::Output> {
     // Register timer on first poll
     if !self.registered {
       self.timer_id = unsafe { extern_register_timer(self.duration_ms) };
@@ -33,15 +33,26 @@ impl Future for Sleep {
     let expired = unsafe { extern_check_timer(self.timer_id) };
 
     if expired == 1 {
+      // The host drops expired timers once they have been checked.
+      self.registered = false;
       Poll::Ready(())
     } else {
-      // Wake the waker so we get polled again
-      cx.waker().wake_by_ref();
+      // No wake: the host runs the next tick once the timer expires.
       Poll::Pending
     }
   }
 }
 
+impl Drop for Sleep {
+  /// Cancel the timer of a sleep abandoned early, so the host does not keep
+  /// waking the app for it.
+  fn drop(&mut self) {
+    if self.registered {
+      unsafe { extern_cancel_timer(self.timer_id) };
+    }
+  }
+}
+
 pub async fn sleep(ms: u32) {
   Sleep::new(ms).await
 }
@@ -0,0 +1,47 @@
+//! Per-app persistent key/value storage.
+//!
+//! Values live on the badge filesystem in a directory private to the app, so
+//! they survive reboots but cannot be seen by other apps. Keys are short
+//! names (see [`is_valid_storage_key`]); values are raw bytes, capped by a
+//! per-app quota enforced by the host.
+
+use crate::protocol::{StorageError, extern_storage_delete, extern_storage_read, extern_storage_write};
+use alloc::vec::Vec;
+
+pub use crate::protocol::{MAX_STORAGE_KEY_LEN, is_valid_storage_key};
+
+/// Read the value stored under `key`.
+pub fn read(key: &str) -> Result<Vec<u8>, StorageError> {
+  // Ask for the size first, then read into an exactly-sized buffer.
+  let len = check(unsafe { extern_storage_read(key.as_ptr(), key.len() as u32, core::ptr::null_mut(), 0) })?;
+  let mut buf = Vec::with_capacity(len as usize);
+  let read = check(unsafe { extern_storage_read(key.as_ptr(), key.len() as u32, buf.as_mut_ptr(), len) })?;
+  unsafe { buf.set_len(read.min(len) as usize) };
+  Ok(buf)
+}
+
+/// Store `value` under `key`, replacing any previous value.
+pub fn write(key: &str, value: &[u8]) -> Result<(), StorageError> {
+  check(unsafe { extern_storage_write(key.as_ptr(), key.len() as u32, value.as_ptr(), value.len() as u32) }).map(|_| ())
+}
+
+/// Remove `key`. Deleting a key that was never written returns
+/// `StorageError::NotFound`.
+pub fn delete(key: &str) -> Result<(), StorageError> {
+  check(unsafe { extern_storage_delete(key.as_ptr(), key.len() as u32) }).map(|_| ())
+}
+
+/// Read a little-endian `u32`, e.g. a high score. Missing or malformed values
+/// read as `None`.
+pub fn read_u32(key: &str) -> Option<u32> {
+  let bytes = read(key).ok()?;
+  Some(u32::from_le_bytes(bytes.get(..4)?.try_into().ok()?))
+}
+
+pub fn write_u32(key: &str, value: u32) -> Result<(), StorageError> {
+  write(key, &value.to_le_bytes())
+}
+
+fn check(ret: i32) -> Result<u32, StorageError> {
+  if ret >= 0 { Ok(ret as u32) } else { Err(StorageError::from_i32(ret).unwrap_or(StorageError::Io)) }
+}
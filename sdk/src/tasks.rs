@@ -1,14 +1,15 @@
 use crate::helper::receive_host_ipc_message;
-use crate::protocol::HostIpcMessage;
+use crate::protocol::{HostIpcMessage, extern_idle};
 use alloc::boxed::Box;
 use alloc::vec::Vec;
 use core::cell::{RefCell, UnsafeCell};
 use core::future::Future;
 use core::ops::Deref;
 use core::pin::Pin;
+use core::sync::atomic::{AtomicBool, Ordering};
 use core::task::{Context, Poll, RawWaker, RawWakerVTable, Waker};
 use embassy_sync::blocking_mutex::raw::NoopRawMutex;
-use embassy_sync::pubsub::{PubSubBehavior, PubSubChannel};
+use embassy_sync::pubsub::{PubSubBehavior, PubSubChannel, Subscriber};
 
 extern crate alloc;
 extern crate core;
@@ -17,6 +18,11 @@ type BoxFuture = Pin<Box<dyn Future<Output = ()>>>;
 
 type HostIpcChannel = PubSubChannel<NoopRawMutex, (u32, HostIpcMessage), 10, 3, 1>;
 
+/// A standing subscription to host messages. Unlike
+/// [`get_next_host_message`], it also queues messages published while its
+/// owner is busy elsewhere, so nothing sent between two reads is lost.
+pub type HostIpcSubscriber = Subscriber<'static, NoopRawMutex, (u32, HostIpcMessage), 10, 3, 1>;
+
 /// Single-threaded `static` wrapper. wasm32-unknown-unknown runs on exactly
 /// one thread, so `!Sync` inner types (RefCell, the NoopRawMutex pubsub
 /// channel) are sound behind a plain `static`. All access goes through the
@@ -43,6 +49,11 @@ static TASK_QUEUE: SyncUnsafe<RefCell<Option<TaskQueue>>> = SyncUnsafe::new(RefC
 
 pub static HOST_IPC_CHANNEL: SyncUnsafe<HostIpcChannel> = SyncUnsafe::new(HostIpcChannel::new());
 
+/// Set when a task asks to be polled again. If no task does during a tick,
+/// every task is waiting on a host timer or message, and the host is told it
+/// can sleep until one arrives.
+static WOKEN: AtomicBool = AtomicBool::new(false);
+
 /// Simple fixed-capacity FIFO queue (ring buffer) for spawned tasks.
 struct TaskQueue {
   buf: Vec<Option<BoxFuture>>,
@@ -108,6 +119,9 @@ where
 /// Execute one round of polling all pending tasks
 /// Returns true if there are no more tasks to run
 ///
+/// Calls `extern_idle` when no task woke itself, so tasks that only wait on
+/// `sleep` or host messages do not keep the badge spinning.
+///
 /// Exported as `tick` by each app binary (`#[no_mangle] fn tick`), which
 /// either calls this directly or provides its own minimal tick. Kept
 /// non-`no_mangle` so apps that define a custom tick (e.g. `barefill`) don't
@@ -129,6 +143,7 @@ pub fn runtime_tick(host_msg_id: u32, host_msg_size: u32) -> bool {
   };
 
   let count = queue.len();
+  WOKEN.store(false, Ordering::Relaxed);
 
   for _ in 0..count {
     if let Some(mut task) = queue.pop_front() {
@@ -147,17 +162,27 @@ pub fn runtime_tick(host_msg_id: u32, host_msg_size: u32) -> bool {
     }
   }
 
+  if !queue.is_empty() && !WOKEN.load(Ordering::Relaxed) {
+    unsafe { extern_idle() };
+  }
+
   queue.is_empty()
 }
 
-/// Create a no-op waker (we don't need wake notifications in this simple model)
+/// Create a waker that only records that it was woken. Every task is polled
+/// on every tick anyway; wakes just tell `runtime_tick` whether the app is
+/// busy.
 fn create_waker() -> Waker {
   unsafe fn clone(_: *const ()) -> RawWaker {
     RawWaker::new(core::ptr::null(), &VTABLE)
   }
 
-  unsafe fn wake(_: *const ()) {}
-  unsafe fn wake_by_ref(_: *const ()) {}
+  unsafe fn wake(_: *const ()) {
+    WOKEN.store(true, Ordering::Relaxed);
+  }
+  unsafe fn wake_by_ref(_: *const ()) {
+    WOKEN.store(true, Ordering::Relaxed);
+  }
   unsafe fn drop(_: *const ()) {}
 
   static VTABLE: RawWakerVTable = RawWakerVTable::new(clone, wake, wake_by_ref, drop);
@@ -177,11 +202,12 @@ pub struct YieldNow {
 impl Future for YieldNow {
   type Output = ();
 
-  fn poll(mut self: Pin<&mut Self>, _cx: &mut Context<'_>) -> Poll<()> {
+  fn poll(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<()> {
     if self.yielded {
       Poll::Ready(())
     } else {
       self.yielded = true;
+      cx.waker().wake_by_ref();
       Poll::Pending
     }
   }
@@ -195,3 +221,8 @@ pub async fn get_next_host_message() -> (u32, HostIpcMessage) {
 
   subscriber.next_message_pure().await
 }
+
+/// Subscribe to host messages, or `None` if every subscriber slot is taken.
+pub fn subscribe_host_messages() -> Option<HostIpcSubscriber> {
+  HOST_IPC_CHANNEL.subscriber().ok()
+}
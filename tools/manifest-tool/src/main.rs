@@ -1,6 +1,17 @@
+use serde::Serialize;
 use std::env;
 use std::fs;
 use std::path::Path;
+use wasm_protocol::{find_custom_section, AppMetadata, APP_METADATA_SECTION};
+
+/// One app in `manifest.json`, as the App Store reads it.
+#[derive(Serialize)]
+struct AppEntry {
+  name: String,
+  size: u64,
+  #[serde(skip_serializing_if = "Option::is_none")]
+  app: Option<AppMetadata>,
+}
 
 fn main() {
   let dir = env::args().nth(1).unwrap_or_else(|| {
@@ -9,34 +20,39 @@ fn main() {
   });
 
   let path = Path::new(&dir);
-  let mut entries: Vec<(String, u64)> = fs::read_dir(path)
+  let mut entries: Vec<AppEntry> = fs::read_dir(path)
     .unwrap_or_else(|err| panic!("failed to read dir '{dir}': {err}"))
     .filter_map(|entry| {
       let entry = entry.expect("failed to read dir entry");
       let name = entry.file_name().to_string_lossy().into_owned();
       if name.ends_with(".wsm") {
-        let size = entry.metadata().expect("failed to stat").len();
-        Some((name, size))
+        let module = fs::read(entry.path()).unwrap_or_else(|err| panic!("failed to read '{name}': {err}"));
+        let app = read_metadata(&name, &module);
+        Some(AppEntry {
+          name,
+          size: module.len() as u64,
+          app,
+        })
       } else {
         None
       }
     })
     .collect();
 
-  entries.sort();
-
-  let manifest = format!(
-    "[{}]",
-    entries
-      .iter()
-      .map(|(name, size)| format!(r#"{{"name":"{}","size":{}}}"#, escape(name), size))
-      .collect::<Vec<_>>()
-      .join(",")
-  );
+  entries.sort_by(|a, b| a.name.cmp(&b.name));
 
+  let manifest = serde_json::to_string(&entries).expect("failed to encode manifest");
   fs::write(path.join("manifest.json"), manifest).unwrap_or_else(|err| panic!("failed to write manifest: {err}"));
 }
 
-fn escape(s: &str) -> String {
-  s.replace('\\', "\\\\").replace('"', "\\\"")
+/// The app's metadata section, if it has a readable one.
+fn read_metadata(name: &str, module: &[u8]) -> Option<AppMetadata> {
+  let section = find_custom_section(module, APP_METADATA_SECTION)?;
+  match AppMetadata::decode(section) {
+    Ok(metadata) => Some(metadata),
+    Err(err) => {
+      eprintln!("warning: {name}: unreadable app metadata: {err:?}");
+      None
+    }
+  }
 }
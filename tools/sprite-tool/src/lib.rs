@@ -0,0 +1,280 @@
+//! Converts PNG images into sprite sheets for `sdk::gfx::SpriteSheet`.
+//!
+//! A sheet is one image cut into equal frames (animation frames, or the tiles
+//! of a tileset), stored as RGB565 or as a palette of RGB565 colors with 4- or
+//! 8-bit indices. Pixels with less than half alpha, or of the key color, are
+//! transparent.
+//!
+//! # Format
+//!
+//! All integers are little-endian.
+//!
+//! | Offset | Size | Field |
+//! |---|---|---|
+//! | 0 | 4 | Magic `SPR1` |
+//! | 4 | 1 | Pixel format: 0 = RGB565, 1 = 4-bit indexed, 2 = 8-bit indexed |
+//! | 5 | 1 | Flags: bit 0 = has transparent pixels |
+//! | 6 | 2 | Sheet width |
+//! | 8 | 2 | Sheet height |
+//! | 10 | 2 | Frame width |
+//! | 12 | 2 | Frame height |
+//! | 14 | 2 | Transparent key: an RGB565 color, or a palette index |
+//! | 16 | 2 | Palette length (0 for RGB565) |
+//! | 18 | 2 × len | Palette, RGB565 big-endian |
+//!
+//! Pixel rows follow, top to bottom: RGB565 big-endian (the LCD's layout), one
+//! byte per pixel, or two pixels per byte (high nibble first, each row padded
+//! to a whole byte). Indexed sheets with transparency reserve index 0 for it.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::io::Cursor;
+
+pub const MAGIC: &[u8; 4] = b"SPR1";
+pub const HEADER_LEN: usize = 18;
+
+pub const FORMAT_RGB565: u8 = 0;
+pub const FORMAT_INDEXED4: u8 = 1;
+pub const FORMAT_INDEXED8: u8 = 2;
+
+pub const FLAG_TRANSPARENT: u8 = 1;
+
+/// How pixels are stored.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Format {
+  /// Two bytes per pixel; any number of colors.
+  Rgb565,
+  /// A palette of up to 256 colors (16 or fewer use 4-bit indices).
+  Indexed,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Options {
+  /// Frame size; the whole image is one frame if unset.
+  pub frame: Option<(u16, u16)>,
+  /// Pixel storage; the smallest that fits the image's colors if unset.
+  pub format: Option<Format>,
+  /// An opaque color to treat as transparent, for images without alpha.
+  pub key: Option<[u8; 3]>,
+}
+
+#[derive(Debug)]
+pub enum Error {
+  Png(png::DecodingError),
+  /// The image is too large for the format's 16-bit dimensions.
+  TooLarge,
+  /// The frame size is zero or does not divide the image.
+  BadFrame {
+    width: u16,
+    height: u16,
+  },
+  /// `Format::Indexed` was asked for, but the image has over 256 colors.
+  TooManyColors(usize),
+  /// Every RGB565 color is used, leaving none to mark transparency.
+  NoFreeKey,
+}
+
+impl fmt::Display for Error {
+  fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+    match self {
+      Self::Png(err) => write!(f, "failed to decode PNG: {err}"),
+      Self::TooLarge => f.write_str("image is larger than 65535 pixels across"),
+      Self::BadFrame { width, height } => write!(f, "frame size {width}x{height} does not divide the image"),
+      Self::TooManyColors(count) => write!(f, "image has {count} colors, too many for a palette"),
+      Self::NoFreeKey => f.write_str("image uses every RGB565 color, so none is left for transparency"),
+    }
+  }
+}
+
+impl std::error::Error for Error {}
+
+impl From<png::DecodingError> for Error {
+  fn from(err: png::DecodingError) -> Self {
+    Self::Png(err)
+  }
+}
+
+/// Convert a PNG file's contents into a sprite sheet.
+pub fn convert(png_bytes: &[u8], options: &Options) -> Result<Vec<u8>, Error> {
+  let mut decoder = png::Decoder::new(Cursor::new(png_bytes));
+  decoder.set_transformations(png::Transformations::normalize_to_color8());
+  let mut reader = decoder.read_info()?;
+  let mut buf = vec![0; reader.output_buffer_size().ok_or(Error::TooLarge)?];
+  let info = reader.next_frame(&mut buf)?;
+  let pixels = &buf[..info.buffer_size()];
+
+  let rgba: Vec<[u8; 4]> = match info.color_type {
+    png::ColorType::Rgba => pixels.as_chunks::<4>().0.to_vec(),
+    png::ColorType::Rgb => pixels.as_chunks::<3>().0.iter().map(|&[r, g, b]| [r, g, b, 255]).collect(),
+    png::ColorType::GrayscaleAlpha => pixels.as_chunks::<2>().0.iter().map(|&[v, a]| [v, v, v, a]).collect(),
+    // Indexed images are expanded to RGB(A) by `normalize_to_color8`.
+    png::ColorType::Grayscale | png::ColorType::Indexed => pixels.iter().map(|&v| [v, v, v, 255]).collect(),
+  };
+  let width = u16::try_from(info.width).map_err(|_| Error::TooLarge)?;
+  let height = u16::try_from(info.height).map_err(|_| Error::TooLarge)?;
+  encode(width, height, &rgba, options)
+}
+
+/// Encode `width` x `height` RGBA pixels, row by row, as a sprite sheet.
+pub fn encode(width: u16, height: u16, rgba: &[[u8; 4]], options: &Options) -> Result<Vec<u8>, Error> {
+  let (frame_w, frame_h) = options.frame.unwrap_or((width, height));
+  if frame_w == 0 || frame_h == 0 || !width.is_multiple_of(frame_w) || !height.is_multiple_of(frame_h) {
+    return Err(Error::BadFrame {
+      width: frame_w,
+      height: frame_h,
+    });
+  }
+
+  // `None` marks a transparent pixel.
+  let colors: Vec<Option<u16>> = rgba
+    .iter()
+    .map(|&[r, g, b, a]| (a >= 128 && options.key != Some([r, g, b])).then(|| rgb565(r, g, b)))
+    .collect();
+  let transparent = colors.iter().any(Option::is_none);
+
+  // Distinct colors in first-seen order, after index 0 if that marks
+  // transparency.
+  let mut palette: Vec<u16> = Vec::new();
+  let mut index: HashMap<u16, usize> = HashMap::new();
+  for &color in colors.iter().flatten() {
+    index.entry(color).or_insert_with(|| {
+      palette.push(color);
+      palette.len() - 1 + usize::from(transparent)
+    });
+  }
+  let palette_len = palette.len() + usize::from(transparent);
+
+  let format = match options.format {
+    Some(Format::Indexed) if palette_len > 256 => return Err(Error::TooManyColors(palette.len())),
+    Some(Format::Indexed) => Format::Indexed,
+    Some(Format::Rgb565) => Format::Rgb565,
+    None if palette_len <= 256 => Format::Indexed,
+    None => Format::Rgb565,
+  };
+
+  let mut out = Vec::new();
+  out.extend_from_slice(MAGIC);
+  match format {
+    Format::Rgb565 => {
+      let key = if transparent { free_color(&colors)? } else { 0 };
+      write_header(&mut out, FORMAT_RGB565, transparent, [width, height, frame_w, frame_h, key, 0]);
+      for color in &colors {
+        out.extend_from_slice(&color.unwrap_or(key).to_be_bytes());
+      }
+    }
+    Format::Indexed => {
+      let mut entries = Vec::with_capacity(palette_len);
+      if transparent {
+        entries.push(0);
+      }
+      entries.extend_from_slice(&palette);
+      let pixel_format = if entries.len() <= 16 { FORMAT_INDEXED4 } else { FORMAT_INDEXED8 };
+      write_header(
+        &mut out,
+        pixel_format,
+        transparent,
+        [width, height, frame_w, frame_h, 0, entries.len() as u16],
+      );
+      for entry in &entries {
+        out.extend_from_slice(&entry.to_be_bytes());
+      }
+      let indices: Vec<u8> = colors.iter().map(|color| color.map_or(0, |color| index[&color] as u8)).collect();
+      for row in indices.chunks(width as usize) {
+        if pixel_format == FORMAT_INDEXED8 {
+          out.extend_from_slice(row);
+        } else {
+          out.extend(row.chunks(2).map(|pair| pair[0] << 4 | pair.get(1).copied().unwrap_or(0)));
+        }
+      }
+    }
+  }
+  Ok(out)
+}
+
+/// A frame size written `WxH`, e.g. `16x16`.
+pub fn parse_size(size: &str) -> Result<(u16, u16), String> {
+  let parsed = size.split_once('x').and_then(|(w, h)| Some((w.parse().ok()?, h.parse().ok()?)));
+  parsed.ok_or_else(|| format!("bad size '{size}', expected WxH"))
+}
+
+fn write_header(out: &mut Vec<u8>, format: u8, transparent: bool, fields: [u16; 6]) {
+  out.push(format);
+  out.push(if transparent { FLAG_TRANSPARENT } else { 0 });
+  for field in fields {
+    out.extend_from_slice(&field.to_le_bytes());
+  }
+}
+
+/// An RGB565 color the image doesn't use, preferring magenta.
+fn free_color(colors: &[Option<u16>]) -> Result<u16, Error> {
+  let mut used = vec![false; 1 << 16];
+  for color in colors.iter().flatten() {
+    used[*color as usize] = true;
+  }
+  let magenta = rgb565(255, 0, 255);
+  if !used[magenta as usize] {
+    return Ok(magenta);
+  }
+  (0..=u16::MAX).find(|&color| !used[color as usize]).ok_or(Error::NoFreeKey)
+}
+
+pub fn rgb565(r: u8, g: u8, b: u8) -> u16 {
+  ((r as u16 >> 3) << 11) | ((g as u16 >> 2) << 5) | (b as u16 >> 3)
+}
+
+#[cfg(test)]
+mod tests {
+  use super::*;
+
+  const CLEAR: [u8; 4] = [0, 0, 0, 0];
+  const RED: [u8; 4] = [255, 0, 0, 255];
+  const BLUE: [u8; 4] = [0, 0, 255, 255];
+
+  fn header_field(sheet: &[u8], offset: usize) -> u16 {
+    u16::from_le_bytes([sheet[offset], sheet[offset + 1]])
+  }
+
+  #[test]
+  fn few_colors_pack_into_nibbles_with_index_0_transparent() {
+    let options = Options {
+      frame: Some((1, 1)),
+      ..Options::default()
+    };
+    let sheet = encode(3, 1, &[RED, CLEAR, BLUE], &options).unwrap();
+    assert_eq!(&sheet[..4], MAGIC);
+    assert_eq!(sheet[4], FORMAT_INDEXED4);
+    assert_eq!(sheet[5], FLAG_TRANSPARENT);
+    assert_eq!(header_field(&sheet, 10), 1);
+    assert_eq!(header_field(&sheet, 16), 3);
+    let palette = &sheet[HEADER_LEN..HEADER_LEN + 6];
+    assert_eq!(palette, [0, 0, 0xF8, 0x00, 0x00, 0x1F]);
+    // Red, transparent, blue, padding.
+    assert_eq!(&sheet[HEADER_LEN + 6..], [0x10, 0x20]);
+  }
+
+  #[test]
+  fn rgb565_sheets_mark_transparency_with_an_unused_color() {
+    let options = Options {
+      format: Some(Format::Rgb565),
+      key: Some([0, 0, 255]),
+      ..Options::default()
+    };
+    let sheet = encode(2, 1, &[RED, BLUE], &options).unwrap();
+    assert_eq!(sheet[4], FORMAT_RGB565);
+    let key = header_field(&sheet, 14);
+    assert_eq!(key, rgb565(255, 0, 255));
+    assert_eq!(&sheet[HEADER_LEN..], [0xF8, 0x00, (key >> 8) as u8, key as u8]);
+  }
+
+  #[test]
+  fn frames_must_divide_the_image() {
+    let options = Options {
+      frame: Some((2, 1)),
+      ..Options::default()
+    };
+    assert!(matches!(
+      encode(3, 1, &[RED; 3], &options),
+      Err(Error::BadFrame { width: 2, height: 1 })
+    ));
+  }
+}
@@ -0,0 +1,55 @@
+use sprite_tool::{convert, parse_size, Format, Options};
+use std::env;
+use std::fs;
+use std::process::exit;
+
+const USAGE: &str = "usage: sprite-tool <input.png> <output.spr> [--frame WxH] [--format rgb565|indexed] [--key RRGGBB]";
+
+fn main() {
+  let args: Vec<String> = env::args().skip(1).collect();
+  let (input, output, options) = parse_args(&args).unwrap_or_else(|err| {
+    eprintln!("{err}\n{USAGE}");
+    exit(2);
+  });
+
+  let png = fs::read(input).unwrap_or_else(|err| panic!("failed to read '{input}': {err}"));
+  let sheet = convert(&png, &options).unwrap_or_else(|err| {
+    eprintln!("{input}: {err}");
+    exit(1);
+  });
+  fs::write(output, &sheet).unwrap_or_else(|err| panic!("failed to write '{output}': {err}"));
+  println!("{input} -> {output} ({} bytes)", sheet.len());
+}
+
+fn parse_args(args: &[String]) -> Result<(&str, &str, Options), String> {
+  let mut paths = Vec::new();
+  let mut options = Options::default();
+  let mut args = args.iter();
+  while let Some(arg) = args.next() {
+    let mut value = || args.next().ok_or_else(|| format!("{arg} needs a value"));
+    match arg.as_str() {
+      "--frame" => options.frame = Some(parse_size(value()?)?),
+      "--format" => {
+        options.format = Some(match value()?.as_str() {
+          "rgb565" => Format::Rgb565,
+          "indexed" => Format::Indexed,
+          other => return Err(format!("unknown format '{other}'")),
+        })
+      }
+      "--key" => options.key = Some(parse_color(value()?)?),
+      _ => paths.push(arg.as_str()),
+    }
+  }
+  match paths[..] {
+    [input, output] => Ok((input, output, options)),
+    _ => Err("expected an input and an output path".into()),
+  }
+}
+
+/// `RRGGBB` hex, with or without a leading `#`.
+fn parse_color(color: &str) -> Result<[u8; 3], String> {
+  let hex = color.trim_start_matches('#');
+  let value = u32::from_str_radix(hex, 16).ok().filter(|_| hex.len() == 6);
+  let value = value.ok_or_else(|| format!("bad color '{color}', expected RRGGBB"))?;
+  Ok([(value >> 16) as u8, (value >> 8) as u8, value as u8])
+}
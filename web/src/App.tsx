@@ -5,6 +5,7 @@ import "./App.scss";
 import { ConfirmDialogProvider } from "./components/ConfirmDialog/index.tsx";
 import { NavBar } from "./components/NavBar/index.tsx";
 import { ConfigRoute } from "./routes/config.tsx";
+import { CrashRoute } from "./routes/crash.tsx";
 import { EmulatorRoute } from "./routes/emulator.tsx";
 import { FilesRoute } from "./routes/files.tsx";
 import { IndexRoute } from "./routes/index.tsx";
@@ -48,6 +49,11 @@ const Routes: readonly RouteDefinition[] = [
     path: "/wifi",
     component: WifiRoute,
   },
+  {
+    label: "Crash",
+    path: "/crash",
+    component: CrashRoute,
+  },
 ];
 
 export function App() {
@@ -1,6 +1,8 @@
 import * as v from "valibot";
 import { sleep } from "../core/index.ts";
 import {
+  CrashReport,
+  CrashReportSchema,
   DeviceApi,
   DeviceConfig,
   DeviceConfigSchema,
@@ -85,6 +87,14 @@ export class BadgeDeviceApi implements DeviceApi {
     return v.parse(v.array(WifiResultSchema), await res.json());
   }
 
+  public async getCrashReport(): Promise<CrashReport | null> {
+    const res = await fetch(`${this.baseUrl}crash`);
+    if (res.status !== 200) {
+      throw new Error(await res.text());
+    }
+    return v.parse(v.nullable(CrashReportSchema), await res.json());
+  }
+
   public async sendMessage(message: DeviceMessage) {
     while (this.ws?.readyState !== WebSocket.OPEN) {
       await sleep(1000);
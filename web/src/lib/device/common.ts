@@ -28,13 +28,48 @@ export const DeviceConfigSchema = v.object({
     ssid: v.pipe(v.string(), v.minLength(1), v.title("SSID")),
     pass: v.pipe(v.string(), v.minLength(0), v.title("Password"), v.metadata(FieldMetadata({ password: true }))),
   })),
+  ntp_server: v.pipe(
+    v.string(),
+    v.minLength(1),
+    v.title("NTP Server"),
+    v.description("Where the clock is set from in Station mode, e.g. `pool.ntp.org` or `192.168.1.10:123`"),
+  ),
+  // Edited in a text field, so accept the string it produces
+  utc_offset_minutes: v.pipe(
+    v.union([v.number(), v.string()]),
+    v.transform(Number),
+    v.integer(),
+    v.minValue(-720),
+    v.maxValue(840),
+    v.title("UTC Offset (minutes)"),
+    v.description("Local timezone, in minutes ahead of UTC, e.g. `60` for Central European Time"),
+  ),
 });
 
 export type DeviceConfig = v.InferInput<typeof DeviceConfigSchema>;
 
+/** What a `.wsm` app declares about itself in its metadata section. */
+export const AppMetadataSchema = v.object({
+  name: v.string(),
+  version: v.string(),
+  author: v.string(),
+  description: v.string(),
+  /** 20x20 RGB565 pixels, big-endian, as hex. */
+  icon: v.optional(v.string()),
+  abi_version: v.number(),
+  /** `Capabilities` bits, see `APP_PERMISSIONS`. */
+  permissions: v.number(),
+});
+
+export type AppMetadata = v.InferInput<typeof AppMetadataSchema>;
+
+/** Names of the `Capabilities` bits an app can ask for. */
+export const APP_PERMISSIONS = ["GPIO", "LEDs", "Storage", "HTTP", "Sockets"] as const;
+
 export const DeviceFileSchema = v.object({
   name: v.string(),
   size: v.number(),
+  app: v.optional(AppMetadataSchema),
 });
 
 export type DeviceFile = v.InferInput<typeof DeviceFileSchema>;
@@ -47,6 +82,16 @@ export const WifiResultSchema = v.object({
 
 export type WifiResult = v.InferInput<typeof WifiResultSchema>;
 
+/** Why the last app stopped abnormally, from `GET /api/crash`. */
+export const CrashReportSchema = v.object({
+  /** The reason shown on the badge's error screen. */
+  reason: v.string(),
+  /** The last lines the app printed, oldest first. */
+  stdout: v.array(v.string()),
+});
+
+export type CrashReport = v.InferInput<typeof CrashReportSchema>;
+
 export interface HexButtonMessage {
   HexButton: HexButton;
 }
@@ -70,6 +115,9 @@ export interface DeviceApi {
 
   scanWifiNetworks(): Promise<readonly WifiResult[]>;
 
+  /** `null` if no app has crashed since the badge started. */
+  getCrashReport(): Promise<CrashReport | null>;
+
   sendMessage(message: DeviceMessage): Promise<void>;
   sendFile(buffer: Uint8Array): Promise<void>;
 
@@ -1,5 +1,6 @@
 import * as v from "valibot";
 import {
+  CrashReport,
   DeviceApi,
   DeviceConfig,
   DeviceConfigSchema,
@@ -29,6 +30,8 @@ export class DummyDeviceApi implements DeviceApi {
         ssid: "cccc",
         pass: "dddd",
       }],
+      ntp_server: "pool.ntp.org",
+      utc_offset_minutes: 0,
     };
   }
 
@@ -60,6 +63,13 @@ export class DummyDeviceApi implements DeviceApi {
     ];
   }
 
+  public async getCrashReport(): Promise<CrashReport | null> {
+    return {
+      reason: "App panicked at src/bin/snake.rs:42: index out of bounds",
+      stdout: ["score: 12", "score: 13"],
+    };
+  }
+
   public async sendMessage(message: DeviceMessage) {
     console.log("DummyDeviceApi.sendMessage:", message);
   }
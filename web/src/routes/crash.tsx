@@ -0,0 +1,35 @@
+import { Button, Card } from "@components";
+import { GlobalDeviceApi } from "@lib";
+import { createResource, Show } from "solid-js";
+
+export function CrashRoute() {
+  const api = GlobalDeviceApi;
+
+  const [crashReport, { refetch }] = createResource(() => api.getCrashReport());
+
+  return (
+    <div class="grid">
+      <div class="g-col-12">
+        <Card colour="danger">
+          <Card.Header text="Last Crash" />
+          <Card.Body>
+            <Show when={crashReport()} fallback={crashReport.loading ? "Loading..." : "No app has crashed since the badge started."}>
+              {(crashReport) => (
+                <div class="d-flex flex-column gap-2">
+                  <div class="fw-bold">{crashReport().reason}</div>
+                  <Show when={crashReport().stdout.length > 0} fallback="The app printed nothing before it stopped.">
+                    <div>Last output:</div>
+                    <pre class="mb-0">{crashReport().stdout.join("\n")}</pre>
+                  </Show>
+                </div>
+              )}
+            </Show>
+          </Card.Body>
+          <Card.Footer>
+            <Button colour="info" on:click={() => refetch()}>Refresh</Button>
+          </Card.Footer>
+        </Card>
+      </div>
+    </div>
+  );
+}
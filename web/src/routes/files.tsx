@@ -1,10 +1,36 @@
 import { Button, Card } from "@components";
-import { downloadFile, GlobalDeviceApi, uploadFile } from "@lib";
+import { APP_PERMISSIONS, type AppMetadata, downloadFile, GlobalDeviceApi, uploadFile } from "@lib";
 import { useNavigate } from "@solidjs/router";
 import { createSignal } from "solid-js";
 import { createResource, For, Show } from "solid-js";
 import { Suspense } from "solid-js/web";
 
+const ICON_SIZE = 20;
+
+/** Draws an app's RGB565 icon. */
+function AppIcon(props: { hex: string }) {
+  const draw = (canvas: HTMLCanvasElement) => {
+    const context = canvas.getContext("2d");
+    if (!context) return;
+
+    const image = context.createImageData(ICON_SIZE, ICON_SIZE);
+    for (let i = 0; i < ICON_SIZE * ICON_SIZE; i++) {
+      const pixel = parseInt(props.hex.slice(i * 4, i * 4 + 4), 16);
+      image.data[i * 4] = ((pixel >> 11) & 0x1f) * 255 / 31;
+      image.data[i * 4 + 1] = ((pixel >> 5) & 0x3f) * 255 / 63;
+      image.data[i * 4 + 2] = (pixel & 0x1f) * 255 / 31;
+      image.data[i * 4 + 3] = 255;
+    }
+    context.putImageData(image, 0, 0);
+  };
+
+  return <canvas class="Files__fileicon me-2" ref={draw} width={ICON_SIZE} height={ICON_SIZE} />;
+}
+
+function permissionNames(app: AppMetadata) {
+  return APP_PERMISSIONS.filter((_, bit) => app.permissions & (1 << bit)).join(", ");
+}
+
 export function FilesRoute() {
   const navigate = useNavigate();
   const api = GlobalDeviceApi;
@@ -54,9 +80,34 @@ export function FilesRoute() {
                         classList={{ selected: file.name === selected() }}
                         on:click={() => onFileClick(file.name)}
                       >
-                        <div class="Files__filename g-col-12 g-col-lg-4">{file.name}</div>
+                        <div class="Files__filename g-col-12 g-col-lg-4 d-flex align-items-center">
+                          <Show when={file.app?.icon}>{(icon) => <AppIcon hex={icon()} />}</Show>
+                          {file.app?.name || file.name}
+                        </div>
                         <div class="Files__filesize g-col-12 g-col-lg-4">{file.size} bytes</div>
+                        <Show when={file.app}>
+                          {(app) => (
+                            <div class="Files__fileapp g-col-12 g-col-lg-4">
+                              {[app().version && `v${app().version}`, app().author && `by ${app().author}`]
+                                .filter(Boolean)
+                                .join(" ")}
+                            </div>
+                          )}
+                        </Show>
 
+                        <Show when={file.name === selected() && file.app}>
+                          {(app) => (
+                            <div class="Files__filedetails g-col-12">
+                              <Show when={app().description}>
+                                <div>{app().description}</div>
+                              </Show>
+                              <div>
+                                {file.name} · ABI v{app().abi_version}
+                                <Show when={permissionNames(app())}>{(names) => <> · Uses {names()}</>}</Show>
+                              </div>
+                            </div>
+                          )}
+                        </Show>
                         <Show when={file.name === selected()}>
                           <div class="g-col-12 d-flex gap-1">
                             <Button colour="danger" on:click={() => onDeleteFile()}>Delete</Button>